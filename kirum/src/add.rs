@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context, anyhow};
+use libkirum::word::{Edge, Etymology, PartOfSpeech};
+use serde_json::Value;
+use crate::{concepts::slug, entries::{RawLexicalEntry, WordGraph, Generate}, files::{add_tree_file, handle_directory, read_transform_files, read_tree_files},
+    format::FileFormat, transaction::WriteTransaction};
+
+/// The fields of a new entry, as given on the command line
+#[derive(Default, Debug, Clone)]
+pub struct NewEntry {
+    pub id: String,
+    pub word: Option<String>,
+    pub definition: String,
+    pub language: Option<String>,
+    pub part_of_speech: Option<PartOfSpeech>,
+    pub word_type: Option<String>,
+    pub etymons: Vec<String>,
+    pub transforms: Vec<String>,
+    pub generate: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl NewEntry {
+    fn into_raw(self) -> RawLexicalEntry {
+        let etymology = if self.etymons.is_empty() {
+            None
+        } else {
+            let transforms = if self.transforms.is_empty() {None} else {Some(self.transforms)};
+            Some(Etymology { etymons: self.etymons.into_iter()
+                .map(|etymon| Edge { etymon, transforms: transforms.clone(), ..Default::default() }).collect() })
+        };
+        RawLexicalEntry {
+            word: self.word.map(|w| w.into()),
+            word_type: self.word_type,
+            language: self.language,
+            definition: self.definition.into(),
+            part_of_speech: self.part_of_speech,
+            etymology,
+            tags: if self.tags.is_empty() {None} else {Some(self.tags)},
+            generate: self.generate.map(Generate::Type),
+            ..Default::default()
+        }
+    }
+}
+
+/// Add a new entry to a tree file in the project.
+/// The entry is written to `file` if it's set; otherwise it's added to the tree file with the most words in the same language,
+/// or a new file named after the language.
+/// The ID must be new, and every etymon and transform must already exist in the project.
+pub fn add_entry(directory: Option<String>, file: Option<String>, entry: NewEntry) -> Result<String> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    let project = handle_directory(&dir)?;
+    let words = read_tree_files(&project.graphs, false).context("error reading existing tree files")?;
+    if words.contains_key(&entry.id) {
+        return Err(anyhow!("a word with ID '{}' already exists", entry.id));
+    }
+    if let Some(missing) = entry.etymons.iter().find(|e| !words.contains_key(*e)) {
+        return Err(anyhow!("etymon '{}' does not exist", missing));
+    }
+    if !entry.transforms.is_empty() {
+        if entry.etymons.is_empty() {
+            return Err(anyhow!("transforms can only be added along with an etymon"));
+        }
+        let transforms = read_transform_files(&project.transforms, false).context("error reading existing etymology files")?;
+        if let Some(missing) = entry.transforms.iter().find(|t| !transforms.contains_key(*t)) {
+            return Err(anyhow!("transform '{}' does not exist", missing));
+        }
+    }
+
+    let tree_dir = Path::new(&dir).join("tree");
+    let target = match file {
+        Some(name) => tree_dir.join(name),
+        None => pick_file(&project.graphs, entry.language.as_deref())?
+            .unwrap_or(tree_dir.join(format!("{}.json", entry.language.as_deref().map(slug).unwrap_or("words".to_string()))))
+    };
+    let id = entry.id.clone();
+    let raw = entry.into_raw();
+
+    let mut tx = WriteTransaction::new();
+    if target.exists() {
+        let format = FileFormat::from_path(&target);
+        let existing = std::fs::read_to_string(&target).context(format!("error reading {}", target.display()))?;
+        let mut parsed: Value = format.parse_value(&existing).context(format!("error parsing {}", target.display()))?;
+        let words = parsed.as_object_mut().ok_or(anyhow!("{} is not a tree file", target.display()))?
+        .entry("words").or_insert(Value::Object(Default::default()))
+        .as_object_mut().ok_or(anyhow!("the words in {} are not a map", target.display()))?;
+        words.insert(id.clone(), serde_json::to_value(raw)?);
+        let updated = format.write_value(&parsed).context(format!("error creating {:?} data", format))?;
+        tx.write(&target, updated).context(format!("error writing {}", target.display()))?;
+    } else {
+        tx.create_dir_all(&tree_dir)?;
+        let name = target.file_name().ok_or(anyhow!("invalid file name {}", target.display()))?.to_string_lossy().to_string();
+        let graph = WordGraph { words: [(id.clone(), raw)].into() };
+        add_tree_file(&mut tx, &dir, &name, graph)?;
+    }
+    tx.commit();
+    Ok(format!("added {} to {}", id, target.display()))
+}
+
+/// find the tree file with the most words in the given language
+fn pick_file(files: &[PathBuf], language: Option<&str>) -> Result<Option<PathBuf>> {
+    let language = match language {
+        Some(lang) => lang,
+        None => return Ok(None)
+    };
+    let mut best: Option<(usize, PathBuf)> = None;
+    for path in files {
+        let words = read_tree_files(&vec![path.clone()], false)?;
+        let count = words.values().filter(|w| w.language.as_deref() == Some(language)).count();
+        if count > 0 && best.as_ref().is_none_or(|(most, _)| count > *most) {
+            best = Some((count, path.clone()));
+        }
+    }
+    Ok(best.map(|(_, path)| path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use crate::files::read_and_compute;
+    use super::{add_entry, NewEntry};
+
+
+    #[test]
+    fn test_add_entry() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_add")?;
+        let entry = NewEntry { id: "latin_derived".to_string(), definition: "a new word".to_string(), language: Some("Latin".to_string()),
+            etymons: vec!["latin_verb".to_string()], transforms: vec!["latin-from-verb".to_string()], ..Default::default() };
+        let msg = add_entry(Some(dir.clone()), None, entry.clone())?;
+        assert!(msg.ends_with("test_der.json"));
+
+        let computed = read_and_compute(Some(dir.clone()))?;
+        let added = computed.get_by_id("latin_derived").unwrap();
+        assert_eq!(added.word, computed.get_by_id("latin_example").unwrap().word);
+
+        assert!(add_entry(Some(dir.clone()), None, entry.clone()).is_err());
+        let missing = NewEntry { id: "other".to_string(), etymons: vec!["nothing".to_string()], ..Default::default() };
+        assert!(add_entry(Some(dir.clone()), None, missing).is_err());
+
+        let new_lang = NewEntry { id: "gothic".to_string(), word: Some("kirum".to_string()), language: Some("Old Gothic".to_string()), ..Default::default() };
+        add_entry(Some(dir.clone()), None, new_lang)?;
+        assert!(Path::new(&dir).join("tree/old-gothic.json").exists());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{Result, Context, anyhow};
+use libkirum::{kirum::LanguageTree, lemma::Lemma};
+use serde_json::{Map, Value};
+use crate::{files::{handle_directory, read_and_compute}, format::FileFormat, transaction::WriteTransaction};
+
+/// Write the computed word of each entry back into the tree files, so generated and derived forms are frozen and can be edited by hand.
+/// Words without etymons get their `word` field set. Derived words, including derivatives defined inside another word,
+/// get their `word_override` field set instead, as a `word` field is replaced by the word derived from the etymology.
+/// If ids is not empty, only those words are written. Only the tree files that change are re-written, in their original format.
+/// Keys keep the order they're written in, and a new `word` or `word_override` field is added after the other fields of its entry.
+/// Returns a summary of the changes.
+pub fn bake(directory: &str, ids: &[String]) -> Result<String> {
+    let computed = read_and_compute(Some(directory.to_string()))?;
+    if let Some(missing) = ids.iter().find(|id| computed.get_by_id(id).is_none()) {
+        return Err(anyhow!("no word with ID '{}' found in project", missing));
+    }
+    let selected = |id: &str| ids.is_empty() || ids.iter().any(|i| i == id);
+
+    let mut tx = WriteTransaction::new();
+    let mut baked = 0;
+    let mut written = 0;
+    for path in handle_directory(directory)?.graphs {
+        let format = FileFormat::from_path(&path);
+        let raw = std::fs::read_to_string(&path).context(format!("error reading {}", path.display()))?;
+        let mut value = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+        let mut changed = 0;
+        if let Some(words) = value.get_mut("words").and_then(Value::as_object_mut) {
+            for (id, entry) in words.iter_mut() {
+                changed += bake_entry(&computed, id, entry, &selected)?;
+            }
+        }
+        if changed > 0 {
+            let data = format.write_value(&value).context(format!("error creating {:?} data", format))?;
+            tx.write(&path, data).context(format!("error writing {}", path.display()))?;
+            debug!("baked {} words into {}", changed, path.display());
+            baked += changed;
+            written += 1;
+        }
+    }
+    tx.commit();
+    Ok(format!("baked {} words into {} files", baked, written))
+}
+
+/// write the computed word into an entry and any derivatives defined inside it. Returns the number of words changed.
+fn bake_entry(computed: &LanguageTree, id: &str, entry: &mut Value, selected: &impl Fn(&str) -> bool) -> Result<usize> {
+    let derived = entry.pointer("/etymology/etymons").and_then(Value::as_array).is_some_and(|e| !e.is_empty());
+    let mut changed = 0;
+    if let (true, Some(map)) = (selected(id), entry.as_object_mut()) {
+        if set_word(computed, id, map, if derived {"word_override"} else {"word"})? {
+            changed += 1;
+        }
+    }
+    Ok(changed + bake_derivatives(computed, id, entry, selected)?)
+}
+
+/// write the computed word into the derivatives defined inside an entry, recursively.
+/// These are always derived from the entry, so the word is written to `word_override`.
+fn bake_derivatives(computed: &LanguageTree, id: &str, entry: &mut Value, selected: &impl Fn(&str) -> bool) -> Result<usize> {
+    let mut changed = 0;
+    let derivatives = entry.get_mut("derivatives").and_then(Value::as_array_mut).into_iter().flatten();
+    for (count, der) in derivatives.enumerate() {
+        let der_id = der.get("id").and_then(Value::as_str).map(String::from)
+        .unwrap_or(format!("{}-autoderive-{}", id, count));
+        let lexis = match der.get_mut("lexis") {
+            Some(lexis) => lexis,
+            None => continue
+        };
+        if let (true, Some(map)) = (selected(&der_id), lexis.as_object_mut()) {
+            if set_word(computed, &der_id, map, "word_override")? {
+                changed += 1;
+            }
+        }
+        changed += bake_derivatives(computed, &der_id, lexis, selected)?;
+    }
+    Ok(changed)
+}
+
+/// set the field to the computed word, returning true if the entry changed
+fn set_word(computed: &LanguageTree, id: &str, entry: &mut Map<String, Value>, field: &str) -> Result<bool> {
+    let word = match computed.get_by_id(id).and_then(|l| l.word) {
+        Some(word) => word,
+        None => return Ok(false)
+    };
+    let existing: Option<Lemma> = entry.get(field).cloned().and_then(|v| serde_json::from_value(v).ok());
+    if existing.is_some_and(|e| e.string_without_sep() == word.string_without_sep()) {
+        return Ok(false);
+    }
+    entry.insert(field.to_string(), serde_json::to_value(&word).context(format!("error serializing word for '{}'", id))?);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use crate::files::read_and_compute;
+    use super::bake;
+
+
+    #[test]
+    fn test_bake() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_bake")?;
+        let before = read_and_compute(Some(dir.clone()))?;
+        let msg = bake(&dir, &[])?;
+        assert_eq!(msg, "baked 3 words into 2 files");
+
+        let raw = std::fs::read_to_string(Path::new(&dir).join("tree/test_der.json"))?;
+        let tree: serde_json::Value = serde_json::from_str(&raw)?;
+        let example = before.get_by_id("latin_example").unwrap().word.unwrap().string_without_sep();
+        assert_eq!(tree["words"]["latin_example"]["word_override"], example.as_str());
+        assert!(tree["words"]["latin_example"]["derivatives"][0]["lexis"]["word_override"].is_string());
+        // the keys keep their order, with the new field last
+        let keys: Vec<&String> = tree["words"]["latin_example"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["type", "language", "definition", "part_of_speech", "etymology", "archaic", "tags", "derivatives", "word_override"]);
+
+        let after = read_and_compute(Some(dir.clone()))?;
+        for id in ["latin_verb", "latin_example", "latin_example-autoderive-0", "daughter-gen-hsshmplh"] {
+            assert_eq!(after.get_by_id(id).unwrap().word, before.get_by_id(id).unwrap().word);
+        }
+        assert_eq!(bake(&dir, &[])?, "baked 0 words into 0 files");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bake_selected() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_bake_selected")?;
+        assert_eq!(bake(&dir, &[String::from("latin_example")])?, "baked 1 words into 1 files");
+        assert!(bake(&dir, &[String::from("missing")]).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
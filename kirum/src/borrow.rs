@@ -0,0 +1,117 @@
+use std::{collections::{HashMap, HashSet}, path::Path};
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::Lexis, word::{Edge, Etymology}};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use crate::{concepts::slug, entries::{RawLexicalEntry, WordGraph}, transaction::WriteTransaction,
+    files::{add_tree_file, find_transforms, handle_directory, read_and_compute, read_project_globals, read_transform_files}};
+
+/// The tag added to every borrowed word
+pub const LOANWORD_TAG: &str = "loanword";
+
+/// Borrow a random sample of words from one language into another, writing them to a new file in the tree directory of the project.
+/// Each loanword is derived from the original word through the adaptation transforms, with its etymology marked as borrowed,
+/// so the borrowed form is computed along with the rest of the project. Words already borrowed into the language are skipped.
+/// The sample uses the seed from globals.json if seed isn't set, so the same words are borrowed on every run.
+pub fn borrow(directory: Option<String>, from: &str, to: &str, count: usize, adapt: Vec<String>, seed: Option<u64>) -> Result<String> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    // check the transforms before computing the project, as the loanwords can't be computed without them
+    find_transforms(&adapt, &read_transform_files(&handle_directory(&dir)?.transforms, false)?)?;
+    let computed = read_and_compute(Some(dir.clone()))?;
+
+    let borrowed: HashSet<String> = computed.to_vec_etymons(|l| l.language == to).into_iter()
+    .flat_map(|(_, ety)| ety.etymons.into_iter().filter(|e| e.borrowed.unwrap_or_default()).map(|e| e.etymon))
+    .collect();
+    let mut candidates: Vec<Lexis> = computed.iter()
+    .filter(|l| l.language == from && l.word.is_some() && !borrowed.contains(&l.id)).cloned().collect();
+    if candidates.is_empty() {
+        return Err(anyhow!("no words in {} that haven't already been borrowed into {}", from, to));
+    }
+    // the tree isn't in a stable order, so sort before sampling
+    candidates.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut rng = match seed.or(read_project_globals(Some(dir.clone()))?.seed) {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy()
+    };
+
+    let mut words: HashMap<String, RawLexicalEntry> = HashMap::new();
+    for source in candidates.choose_multiple(&mut rng, count) {
+        let id = format!("{}-loan-{}", slug(to), source.id);
+        if computed.get_by_id(&id).is_some() {
+            return Err(anyhow!("a word with ID '{}' already exists in the project", id));
+        }
+        let base: RawLexicalEntry = source.clone().into();
+        let edge = Edge { etymon: source.id.clone(), transforms: if adapt.is_empty() {None} else {Some(adapt.clone())},
+            borrowed: Some(true), ..Default::default() };
+        words.insert(id, RawLexicalEntry {
+            language: Some(to.to_string()),
+            definition: base.definition,
+            part_of_speech: base.part_of_speech,
+            word_type: base.word_type,
+            semantic_field: base.semantic_field,
+            tags: Some(vec![LOANWORD_TAG.to_string()]),
+            etymology: Some(Etymology { etymons: vec![edge] }),
+            ..Default::default()
+        });
+    }
+
+    // each run gets its own file, so earlier loanwords aren't overwritten
+    let stem = format!("loans-{}-{}", slug(from), slug(to));
+    let tree_dir = Path::new(&dir).join("tree");
+    let file_name = (1..).map(|n| if n == 1 {format!("{}.json", stem)} else {format!("{}-{}.json", stem, n)})
+    .find(|name| !tree_dir.join(name).exists()).unwrap_or_default();
+    let count = words.len();
+    let mut tx = WriteTransaction::new();
+    tx.create_dir_all(&tree_dir)?;
+    add_tree_file(&mut tx, &dir, &file_name, WordGraph { words })?;
+    tx.commit();
+    Ok(format!("borrowed {} words from {} into {} in {}", count, from, to, tree_dir.join(file_name).display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use crate::files::read_and_compute;
+    use super::borrow;
+
+
+    #[test]
+    fn test_borrow() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_borrow")?;
+        let msg = borrow(Some(dir.clone()), "Latin", "Old French", 1, vec!["of-from-latin".to_string()], Some(3))?;
+        assert!(msg.starts_with("borrowed 1 words from Latin into Old French"), "{}", msg);
+        assert!(Path::new(&dir).join("tree/loans-latin-old-french.json").exists());
+        borrow(Some(dir.clone()), "Latin", "Old French", 5, vec!["of-from-latin".to_string()], Some(3))?;
+        assert!(Path::new(&dir).join("tree/loans-latin-old-french-2.json").exists());
+
+        let computed = read_and_compute(Some(dir.clone()))?;
+        let loan = computed.get_by_id("old-french-loan-latin_example").unwrap();
+        assert_eq!(loan.word.unwrap().string_without_sep(), "essemple");
+        assert_eq!(loan.tags, vec!["loanword".to_string()]);
+        let ety = computed.to_vec_etymons(|l| l.id == "old-french-loan-latin_example");
+        assert_eq!(ety[0].1.etymons[0].borrowed, Some(true));
+        assert!(computed.get_by_id("old-french-loan-latin_verb").is_some());
+
+        // every Latin word has been borrowed
+        assert!(borrow(Some(dir.clone()), "Latin", "Old French", 1, Vec::new(), None).is_err());
+        assert!(borrow(Some(dir.clone()), "Old French", "Latin", 1, vec!["missing".to_string()], None).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_borrow_skips_language_pair() -> Result<()> {
+        let dir = copy_project("language_pairs", "kirum_test_borrow_pairs")?;
+        // with the default precedence, the language pair transforms would apply on top of the adaptation
+        let globals = Path::new(&dir).join("globals.json");
+        std::fs::write(&globals, std::fs::read_to_string(&globals)?.replace(r#""transform_precedence": "override","#, ""))?;
+        borrow(Some(dir.clone()), "Old Exemplum", "New Exemplum", 1, vec!["irregular-shift".to_string()], Some(1))?;
+
+        let computed = read_and_compute(Some(dir.clone()))?;
+        assert_eq!(computed.get_by_id("derived").unwrap().word.unwrap().string_without_sep(), "kirūa");
+        assert_eq!(computed.get_by_id("new-exemplum-loan-root").unwrap().word.unwrap().string_without_sep(), "ciraua");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use anyhow::{Result, Context, anyhow};
+use libkirum::{kirum::Lexis, lexicon::{ComputedLexicon, SharedLexicon}};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use crate::{cli::ExplainFormat, explain::render_explain, family::render_family, files::{read_and_compute, read_lexicon}, watch::on_change};
+
+/// how often the browser checks for a recomputed snapshot while waiting for a key press
+const REFRESH: Duration = Duration::from_millis(250);
+
+/// The state of the lexicon browser
+pub struct Browser {
+    lexicon: SharedLexicon,
+    /// the snapshot the words are read from, until a newer one is swapped in
+    tree: Arc<ComputedLexicon>,
+    /// the error from the last recompute, if it failed
+    reload_error: Arc<Mutex<Option<String>>>,
+    /// every word in the tree, sorted by language and word
+    words: Vec<Lexis>,
+    /// the search string typed by the user
+    query: String,
+    /// indexes into `words` of the words that match the query
+    matches: Vec<usize>,
+    list: ListState,
+    /// the number of lines the detail pane is scrolled by
+    scroll: u16,
+    quit: bool
+}
+
+impl Browser {
+    pub fn new(lexicon: SharedLexicon) -> Browser {
+        let tree = lexicon.snapshot();
+        let words = sorted_words(&tree);
+        let mut browser = Browser { lexicon, tree, reload_error: Arc::new(Mutex::new(None)), words, query: String::new(), matches: Vec::new(),
+            list: ListState::default(), scroll: 0, quit: false };
+        browser.search();
+        browser
+    }
+
+    /// switch to the current snapshot if a new one was swapped in, keeping the query and the selected word
+    pub fn refresh(&mut self) {
+        let current = self.lexicon.snapshot();
+        if Arc::ptr_eq(&current, &self.tree) {
+            return;
+        }
+        let selected = self.selected().map(|lex| lex.id.clone());
+        let scroll = self.scroll;
+        self.tree = current;
+        self.words = sorted_words(&self.tree);
+        self.search();
+        if let Some(pos) = selected.and_then(|id| self.matches.iter().position(|idx| self.words[*idx].id == id)) {
+            self.list.select(Some(pos));
+            self.scroll = scroll;
+        }
+    }
+
+    /// update the list of matching words after the query changes.
+    /// A word matches if its ID, word, language or definition contains the query.
+    fn search(&mut self) {
+        let query = self.query.to_lowercase();
+        self.matches = self.words.iter().enumerate().filter(|(_, lex)| {
+            query.is_empty() || lex.id.to_lowercase().contains(&query) || display_word(lex).to_lowercase().contains(&query)
+            || lex.language.to_lowercase().contains(&query) || lex.definition.to_lowercase().contains(&query)
+        }).map(|(idx, _)| idx).collect();
+        self.list.select(if self.matches.is_empty() {None} else {Some(0)});
+        self.scroll = 0;
+    }
+
+    /// the currently selected word
+    pub fn selected(&self) -> Option<&Lexis> {
+        self.list.selected().and_then(|s| self.matches.get(s)).map(|idx| &self.words[*idx])
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + by).clamp(0, self.matches.len() as isize - 1);
+        self.list.select(Some(next as usize));
+        self.scroll = 0;
+    }
+
+    /// update the browser state from a key press
+    pub fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Esc if self.query.is_empty() => self.quit = true,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.search();
+            },
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.search();
+            },
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.search();
+            },
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Home => self.move_selection(isize::MIN / 2),
+            KeyCode::End => self.move_selection(isize::MAX / 2),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            _ => {}
+        }
+    }
+
+    /// the lines shown in the detail pane for the selected word: its definition, etymology and descendants
+    pub fn detail(&self) -> Vec<String> {
+        let lex = match self.selected() {
+            Some(lex) => lex,
+            None => return vec![String::from("no matching words")]
+        };
+        let mut lines = vec![display_word(lex)];
+        let mut info: Vec<String> = Vec::new();
+        if !lex.language.is_empty() {
+            info.push(lex.language.clone());
+        }
+        if let Some(pos) = &lex.pos {
+            info.push(pos.to_string());
+        }
+        if let Some(ipa) = &lex.ipa {
+            info.push(format!("/{}/", ipa));
+        }
+        if lex.archaic {
+            info.push(String::from("archaic"));
+        }
+        if lex.reconstructed {
+            info.push(String::from("reconstructed"));
+        }
+        lines.push(info.join(", "));
+        lines.push(String::new());
+        lines.push(lex.definition.clone());
+        lines.push(String::new());
+        lines.push(String::from("Etymology"));
+        match render_explain(&self.tree, &lex.id, ExplainFormat::Text, false, false) {
+            Ok(trace) => lines.extend(trace.lines().map(|l| l.to_string())),
+            Err(e) => lines.push(format!("could not explain word: {}", e))
+        }
+        if !self.tree.descendants(&lex.id).is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Descendants"));
+            match render_family(&self.tree, &lex.id) {
+                Ok(family) => lines.extend(family.lines().map(|l| l.to_string())),
+                Err(e) => lines.push(format!("could not render descendants: {}", e))
+            }
+        }
+        lines
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1), Constraint::Length(1)]).split(frame.area());
+        let panes = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)]).split(rows[1]);
+
+        let search = Paragraph::new(self.query.as_str())
+        .block(Block::default().borders(Borders::ALL).title(format!("Search ({} of {} words)", self.matches.len(), self.words.len())));
+        frame.render_widget(search, rows[0]);
+
+        let items: Vec<ListItem> = self.matches.iter().map(|idx| {
+            let lex = &self.words[*idx];
+            ListItem::new(Line::from(vec![Span::raw(display_word(lex)), Span::styled(format!("  {}", lex.language), Style::default().add_modifier(Modifier::DIM))]))
+        }).collect();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Words"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, panes[0], &mut self.list);
+
+        let detail_lines: Vec<Line> = self.detail().into_iter().map(Line::from).collect();
+        let detail = Paragraph::new(detail_lines).block(Block::default().borders(Borders::ALL).title("Entry"))
+        .wrap(Wrap { trim: false }).scroll((self.scroll, 0));
+        frame.render_widget(detail, panes[1]);
+
+        let reload_error = self.reload_error.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let help = match reload_error {
+            Some(e) => Paragraph::new(format!("could not reload project: {}", e)).style(Style::default().add_modifier(Modifier::BOLD)),
+            None => Paragraph::new("type to search  ↑/↓ select  PgUp/PgDn scroll entry  Esc clear/quit")
+                .style(Style::default().add_modifier(Modifier::DIM))
+        };
+        frame.render_widget(help, rows[2]);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            self.refresh();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(REFRESH)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key.code, key.modifiers);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the word as shown in the browser, or the ID if the word has none
+fn display_word(lex: &Lexis) -> String {
+    match &lex.word {
+        Some(w) => w.string_without_sep(),
+        None => format!("[{}]", lex.id)
+    }
+}
+
+/// every word in the snapshot, sorted by language and word
+fn sorted_words(tree: &ComputedLexicon) -> Vec<Lexis> {
+    let mut words = tree.to_vec();
+    words.sort_by(|a, b| a.language.cmp(&b.language).then(display_word(a).cmp(&display_word(b))));
+    words
+}
+
+/// Open an interactive terminal browser for the words in a project.
+/// The project is recomputed in the background whenever a project file changes, and the browser switches to the new words once they're ready.
+pub fn browse(directory: Option<String>) -> Result<String> {
+    let dir = directory.clone().ok_or(anyhow!("must specify a project directory"))?;
+    let lexicon = SharedLexicon::new(ComputedLexicon::from_computed(read_and_compute(directory)?));
+    let mut browser = Browser::new(lexicon.clone());
+
+    let reload_error = browser.reload_error.clone();
+    std::thread::spawn(move || {
+        let res = on_change(&dir, None, || {
+            let reload_dir = dir.clone();
+            let res = lexicon.recompute(move || read_lexicon(&reload_dir)).join();
+            let error = match res {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(format!("{:#}", e)),
+                Err(_) => Some(String::from("recompute panicked"))
+            };
+            *reload_error.lock().unwrap_or_else(|e| e.into_inner()) = error;
+        });
+        if let Err(e) = res {
+            *reload_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(format!("{:#}", e));
+        }
+    });
+
+    let mut terminal = ratatui::try_init().context("could not start terminal browser")?;
+    // log output would be drawn over the browser, so it's turned off until the browser closes
+    let log_level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    log::set_max_level(log_level);
+    result?;
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+    use libkirum::lexicon::SharedLexicon;
+    use crate::{files::read_lexicon, test_util::copy_project};
+    use super::Browser;
+
+    fn browser(dir: &str) -> Result<Browser> {
+        Ok(Browser::new(SharedLexicon::new(read_lexicon(dir)?)))
+    }
+
+    fn type_str(browser: &mut Browser, value: &str) {
+        for c in value.chars() {
+            browser.handle_key(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    #[test]
+    fn test_browser_search() -> Result<()> {
+        let mut browser = browser("src/test_files/test_der")?;
+        let total = browser.matches.len();
+        assert!(total > 1);
+
+        type_str(&mut browser, "instance");
+        assert_eq!(browser.selected().unwrap().id, "latin_example");
+        browser.handle_key(KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(browser.selected().unwrap().id, "latin_example");
+
+        let detail = browser.detail();
+        assert!(detail.contains(&String::from("Etymology")));
+        assert!(detail.iter().any(|l| l.contains("from latin_verb")));
+
+        browser.handle_key(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(browser.matches.len(), total);
+        assert!(!browser.quit);
+        browser.handle_key(KeyCode::Esc, KeyModifiers::NONE);
+        assert!(browser.quit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_browser_no_matches() -> Result<()> {
+        let mut browser = browser("src/test_files/test_der")?;
+        type_str(&mut browser, "zzzzzz");
+        assert!(browser.selected().is_none());
+        assert_eq!(browser.detail(), vec![String::from("no matching words")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_browser_refresh() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_browser_refresh")?;
+        let lexicon = SharedLexicon::new(read_lexicon(&dir)?);
+        let mut browser = Browser::new(lexicon.clone());
+        type_str(&mut browser, "example");
+        assert_eq!(browser.selected().unwrap().id, "latin_example");
+
+        let tree_file = std::path::Path::new(&dir).join("tree/test_der.json");
+        let raw = std::fs::read_to_string(&tree_file)?;
+        std::fs::write(&tree_file, raw.replace("an instance", "a specimen"))?;
+        let reload_dir = dir.clone();
+        lexicon.recompute(move || read_lexicon(&reload_dir)).join().unwrap()?;
+
+        assert!(!browser.detail().iter().any(|l| l.contains("specimen")));
+        browser.refresh();
+        assert_eq!(browser.query, "example");
+        assert_eq!(browser.selected().unwrap().id, "latin_example");
+        assert!(browser.detail().iter().any(|l| l.contains("specimen")));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
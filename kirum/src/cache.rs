@@ -0,0 +1,173 @@
+use std::{collections::{hash_map::DefaultHasher, BTreeSet}, hash::{Hash, Hasher}, path::{Path, PathBuf}};
+
+use anyhow::{Result, Context, anyhow};
+use libkirum::kirum::{ComputedGraph, LanguageTree};
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::{format::FileFormat, files::{handle_directory, read_and_compute, read_from_files, transform_error_context, Project}, progress};
+
+/// The name of the cache file, written to the root of the project directory
+pub const CACHE_FILE: &str = ".kirum-cache.json";
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// hash of the kirum version, the project files, and any environment variables they reference
+    key: String,
+    graph: ComputedGraph
+}
+
+/// Like read_and_compute(), but if cache is set, the computed tree is stored in a cache file in the project directory.
+/// If none of the project files have changed since the cache was written, the computed tree is read from the cache
+/// instead of running compute_lexicon() again. Words generated from phonetic rules stay the same while the cache is used.
+pub fn read_and_compute_cached(directory: Option<String>, cache: bool) -> Result<LanguageTree> {
+    if !cache {
+        return read_and_compute(directory);
+    }
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    let project = handle_directory(&dir)?;
+    let key = cache_key(&project)?;
+    let cache_path = Path::new(&dir).join(CACHE_FILE);
+
+    let mut tree = read_from_files(project.clone())?;
+    if let Some(cached) = read_cache(&cache_path, &key) {
+        if tree.restore_computed(cached) {
+            debug!("using computed tree from {}", cache_path.display());
+            return Ok(tree);
+        }
+        warn!("cache file {} does not match the project, recomputing", cache_path.display());
+    }
+
+    info!("rendering tree...");
+    tree.on_progress(progress::bar("computing words"));
+    tree.compute_lexicon().map_err(|e| transform_error_context(e, &project))?;
+    let data = serde_json::to_string(&CacheFile { key, graph: tree.computed_graph() }).context("error creating cache data")?;
+    // a cache that can't be written only makes the next run slower
+    if let Err(e) = std::fs::write(&cache_path, data) {
+        warn!("could not write cache file {}: {}", cache_path.display(), e);
+    }
+    Ok(tree)
+}
+
+/// read the computed graph from the cache file, if it exists and was written from the same project files
+fn read_cache(path: &Path, key: &str) -> Option<ComputedGraph> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<CacheFile>(&raw) {
+        Ok(cached) if cached.key == key => Some(cached.graph),
+        Ok(_) => {
+            debug!("project files changed since {} was written", path.display());
+            None
+        },
+        Err(e) => {
+            warn!("could not read cache file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// hash everything that can change the computed tree: the kirum version, the path and contents of every project file,
+/// the contents of any Rhai scripts used by transforms, and the value of any environment variables referenced in the files
+fn cache_key(project: &Project) -> Result<String> {
+    let mut files: Vec<&PathBuf> = project.graphs.iter().chain(project.transforms.iter())
+        .chain(project.phonetic_rules.iter().flatten()).chain(project.globals.iter()).chain(project.taxonomy.iter()).collect();
+    files.sort();
+    let env_ref = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)")?;
+
+    let mut hasher = DefaultHasher::new();
+    let mut scripts = BTreeSet::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    for path in files {
+        let raw = std::fs::read_to_string(path).context(format!("error reading {}", path.display()))?;
+        path.hash(&mut hasher);
+        raw.hash(&mut hasher);
+        for var in env_ref.captures_iter(&raw) {
+            var[1].hash(&mut hasher);
+            std::env::var(&var[1]).ok().hash(&mut hasher);
+        }
+        // a file that doesn't parse will fail to compute anyway
+        if let Ok(value) = FileFormat::from_path(path).parse_value(&raw) {
+            find_scripts(&value, &mut scripts);
+        }
+    }
+    // scripts are read relative to the working directory when the transform runs; a missing script is hashed as empty,
+    // so creating it later invalidates the cache
+    for script in scripts {
+        script.hash(&mut hasher);
+        std::fs::read_to_string(&script).ok().hash(&mut hasher);
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// collect the file of every rhai_script transform in a project file
+fn find_scripts(value: &Value, scripts: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(file)) = map.get("rhai_script").and_then(|script| script.get("file")) {
+                scripts.insert(file.clone());
+            }
+            map.values().for_each(|child| find_scripts(child, scripts));
+        },
+        Value::Array(items) => items.iter().for_each(|item| find_scripts(item, scripts)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use super::{read_and_compute_cached, CACHE_FILE};
+
+
+    #[test]
+    fn test_cache() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_cache")?;
+        let cache_path = Path::new(&dir).join(CACHE_FILE);
+        let computed = read_and_compute_cached(Some(dir.clone()), true)?;
+        assert!(cache_path.exists());
+        let word = computed.get_by_id("latin_example").unwrap().word.unwrap().string_without_sep();
+
+        // change the cached word, so we can tell the cache was used
+        let mut cached: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&cache_path)?)?;
+        let idx = cached["graph"]["words"].as_array().unwrap().iter().position(|l| l["id"] == "latin_example").unwrap();
+        cached["graph"]["letters"][idx] = serde_json::json!(["c", "a", "ch", "ed"]);
+        std::fs::write(&cache_path, cached.to_string())?;
+        let from_cache = read_and_compute_cached(Some(dir.clone()), true)?;
+        assert_eq!(from_cache.get_by_id("latin_example").unwrap().word, Some(vec!["c", "a", "ch", "ed"].into()));
+        assert_eq!(from_cache.etymons("latin_example")[0].id, "latin_verb");
+
+        // any change to the project files invalidates the cache
+        let tree_file = Path::new(&dir).join("tree/test_der.json");
+        let raw = std::fs::read_to_string(&tree_file)?;
+        std::fs::write(&tree_file, format!("{}\n", raw))?;
+        let recomputed = read_and_compute_cached(Some(dir.clone()), true)?;
+        assert_eq!(recomputed.get_by_id("latin_example").unwrap().word.unwrap().string_without_sep(), word);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_rhai_script() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_cache_rhai")?;
+        let script = Path::new(&dir).join("suffix.rhai");
+        std::fs::write(&script, "lemma_string + \"x\"")?;
+        let ety_path = Path::new(&dir).join("etymology/ety.json");
+        let mut ety: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&ety_path)?)?;
+        ety["transforms"]["latin-from-verb"]["transforms"].as_array_mut().unwrap()
+            .push(serde_json::json!({"rhai_script": {"file": script.to_string_lossy()}}));
+        std::fs::write(&ety_path, ety.to_string())?;
+
+        let computed = read_and_compute_cached(Some(dir.clone()), true)?;
+        let word = computed.get_by_id("latin_example").unwrap().word.unwrap().string_without_sep();
+        assert!(word.ends_with('x'), "{}", word);
+
+        // editing the script, without touching the project files, invalidates the cache
+        std::fs::write(&script, "lemma_string + \"y\"")?;
+        let recomputed = read_and_compute_cached(Some(dir.clone()), true)?;
+        let updated = recomputed.get_by_id("latin_example").unwrap().word.unwrap().string_without_sep();
+        assert_eq!(updated, format!("{}y", word.strip_suffix('x').unwrap()));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
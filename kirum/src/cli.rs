@@ -14,11 +14,105 @@ pub struct Args {
     #[clap(short, long, default_value_t=false)]
     /// Do not print any log output
     pub quiet: bool,
+    /// Write logs to the given file as JSON lines, instead of the console
+    #[clap(long, value_parser)]
+    pub log_file: Option<String>,
+    /// Set the log level for a single module, in the form module=level, i.e. `libkirum::kirum=trace`.
+    /// Can be specified multiple times.
+    #[clap(long, value_parser)]
+    pub log_filter: Vec<String>,
 
     #[clap(subcommand)]
     pub command: Commands
 }
 
+/// The options for rendering a lexicon, shared by the render and watch commands
+#[derive(clap::Args, Clone)]
+pub struct RenderArgs {
+    /// path to a directory to read in all transform and graph files.
+    /// Can be specified instead of -g -d
+    #[clap(short, long, value_parser)]
+    pub directory: Option<String>,
+    /// TOML file that will be used to resolve template variables in definition fields.
+    /// Template variables can be written into Lexis definition fields using {{handlebars_variables}}
+    #[clap(short, long, value_parser)]
+    pub variables: Option<String>,
+    /// For words with definitions in multiple languages, the language code of the definition to render.
+    /// If a word has no definition in that language, the default definition is used.
+    #[clap(long, value_parser)]
+    pub gloss_lang: Option<String>,
+    /// Render words in the named orthography, as defined in the language settings of globals.json.
+    /// Words in languages without that orthography are rendered unchanged.
+    #[clap(long, value_parser)]
+    pub orthography: Option<String>,
+    /// Only render words in the given semantic field, or any of its subdomains
+    #[clap(short, long, value_parser)]
+    pub semantic_field: Option<String>,
+    /// Only render words in this language. Can be specified multiple times to render several languages
+    #[clap(long, value_parser)]
+    pub language: Vec<String>,
+    /// Only render words with this tag. Can be specified multiple times to render words with any of the tags
+    #[clap(long, value_parser)]
+    pub tag: Vec<String>,
+    /// Only render words with this part of speech. Can be specified multiple times
+    #[clap(long, value_parser)]
+    pub pos: Vec<libkirum::word::PartOfSpeech>,
+    /// Only render archaic words
+    #[clap(long, default_value_t=false, conflicts_with = "no_archaic")]
+    pub archaic: bool,
+    /// Leave out archaic words
+    #[clap(long, default_value_t=false)]
+    pub no_archaic: bool,
+    /// Only render words with the given historical metadata, in the form key=value.
+    /// Can be specified multiple times, and words must match every value
+    #[clap(long, value_parser)]
+    pub metadata: Vec<String>,
+    /// Add a header to the output with the project name, git revision, word count, kirum version and seed,
+    /// so the output can be traced back to the project state it was rendered from
+    #[clap(long, default_value_t=false)]
+    pub header: bool,
+    /// Render words with the draft status, along with the editorial notes of every word
+    #[clap(long, default_value_t=false)]
+    pub include_drafts: bool,
+    /// Store the computed tree in a cache file in the project directory, and reuse it while the project files are unchanged
+    #[clap(long, default_value_t=false)]
+    pub cache: bool,
+    /// Instead of rendering the lexicon, apply the named transform to the word given with --word, and print the word before and after each step.
+    /// Can be specified multiple times to apply transforms in order. The project files are not changed.
+    #[clap(long, value_parser, requires = "word", conflicts_with = "dry_run_edge")]
+    pub dry_run_transform: Vec<String>,
+    /// Instead of rendering the lexicon, apply every transform between an etymon and a word derived from it, including language pair transforms,
+    /// and print the word before and after each step. Applied to the etymon, or to the word given with --word.
+    #[clap(long, num_args = 2, value_names = ["ETYMON", "ID"])]
+    pub dry_run_edge: Option<Vec<String>>,
+    /// The word used by --dry-run-transform and --dry-run-edge: either the ID of a word in the project, or a word to transform
+    #[clap(long, value_parser)]
+    pub word: Option<String>,
+    /// Print a trace of how the word with this ID was derived to stderr, including every transform that was considered,
+    /// whether its conditional matched, and the word after each transform function. Can be specified multiple times.
+    /// With -vv, the same is logged for every word.
+    #[clap(long, value_parser)]
+    pub explain: Vec<String>,
+    /// Render each language, or the words starting with each letter, into a separate file in the directory set with --split-dir.
+    /// Files are named after the group, i.e. `old-french.html`
+    #[clap(long, value_enum, requires = "split_dir")]
+    pub split_by: Option<SplitBy>,
+    /// The directory to write files to with --split-by
+    #[clap(long, value_parser, requires = "split_by")]
+    pub split_dir: Option<String>,
+    /// The order words are rendered in. `alpha` sorts by word, using the collation of each language.
+    /// JSON output is keyed by ID, and is always sorted by ID
+    #[clap(long, value_enum, default_value_t=SortOrder::Alpha)]
+    pub sort: SortOrder,
+    /// Group words with the same value of a field together, ordered by the value. Words keep the --sort order within each group
+    #[clap(long, value_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// The output format. Not needed with --dry-run-transform or --dry-run-edge
+    #[clap(subcommand)]
+    pub command: Option<Format>
+}
+
 #[derive(clap::Subcommand, Clone)]
 pub enum Commands{
     /// Create a new language project with the specified name
@@ -30,27 +124,249 @@ pub enum Commands{
         /// path to a directory to read in all transform and graph files
         #[clap(short, long, value_parser)]
         directory: Option<String>,
+        /// Only include words in the given semantic field, or any of its subdomains
+        #[clap(short, long, value_parser)]
+        semantic_field: Option<String>,
+        /// Report the phonological distance between two languages, based on the differences between their cognates
+        #[clap(long, num_args = 2, value_names = ["LANGUAGE", "OTHER_LANGUAGE"])]
+        distance: Option<Vec<String>>,
+        /// Report how often each segment is used in each language, including at the start and end of words
+        #[clap(long, default_value_t=false)]
+        segments: bool,
+        /// Report how often each bigram, trigram and consonant cluster is used in each language
+        #[clap(long, default_value_t=false)]
+        ngrams: bool,
+        /// Report a histogram of word lengths in each language, in both segments and syllables
+        #[clap(long, default_value_t=false)]
+        lengths: bool,
+        /// Store the computed tree in a cache file in the project directory, and reuse it while the project files are unchanged
+        #[clap(long, default_value_t=false)]
+        cache: bool,
+    },
+    /// Browse the computed words in an interactive terminal interface, with search and etymology views
+    Browse {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+    },
+    /// Print a tree of a word and all of its descendants
+    Family {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The ID of the root word
+        id: String,
+    },
+    /// Print a trace of how a word was derived, including each etymon and the result of every transform
+    Explain {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The ID of the word
+        id: String,
+        /// The output format of the trace
+        #[clap(short, long, value_enum, default_value_t=ExplainFormat::Text)]
+        format: ExplainFormat,
+        /// List every transform that changed each word, and whether it came from the edge, a language pair, or the global transforms
+        #[clap(long, action)]
+        show_applied: bool,
+        /// Show the word after each function of every transform
+        #[clap(long, action)]
+        show_functions: bool,
+    },
+    /// Check the language project for likely mistakes
+    Lint {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// Also warn about root words with no etymology that nothing derives from
+        #[clap(long, default_value_t=false)]
+        unused_roots: bool,
+        /// Warn about any language where more than this percentage of the words are generated from phonetic rules
+        #[clap(long, value_name = "PERCENT")]
+        max_generated: Option<f64>,
+    },
+    /// Serve a web viewer and read-only JSON API for the project over HTTP, recomputing the language when project files change
+    Serve {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The address to listen on
+        #[clap(short, long, default_value="127.0.0.1")]
+        address: String,
+        /// The port to listen on
+        #[clap(short, long, default_value_t=8080)]
+        port: u16,
+    },
+    /// Run a language server for editing project files, with diagnostics, go-to-definition and completion of word IDs and transform names
+    Lsp,
+    /// Add a new word to a tree file in the project
+    Add {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The tree file to add the word to. By default, the word is added to the file with the most words in the same language
+        #[clap(short, long, value_parser)]
+        file: Option<String>,
+        /// The ID of the new word
+        #[clap(long, value_parser)]
+        id: String,
+        /// The word itself. Can be left out for words derived from an etymon or generated from phonetic rules
+        #[clap(short, long, value_parser)]
+        word: Option<String>,
+        /// The definition of the word
+        #[clap(long, default_value="")]
+        definition: String,
+        /// The language of the word
+        #[clap(short='n', long, value_parser)]
+        language: Option<String>,
+        /// The part of speech of the word
+        #[clap(short, long, value_parser)]
+        pos: Option<libkirum::word::PartOfSpeech>,
+        /// The lexis type of the word
+        #[clap(short='t', long="type", value_parser)]
+        word_type: Option<String>,
+        /// The ID of an etymon of the word. Can be specified multiple times
+        #[clap(short, long, value_parser)]
+        etymon: Vec<String>,
+        /// A transform applied between each etymon and the word. Can be specified multiple times
+        #[clap(short='x', long, value_parser)]
+        transform: Vec<String>,
+        /// Generate the word from the given lexis type in the phonetic rules
+        #[clap(short, long, value_parser)]
+        generate: Option<String>,
+        /// A tag to add to the word. Can be specified multiple times
+        #[clap(long, value_parser)]
+        tag: Vec<String>,
+    },
+    /// Remove a word from the project. By default, words derived from it are kept, with the removed word taken out of their etymology
+    Rm {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The ID of the word to remove
+        id: String,
+        /// Also remove every word derived from the removed word
+        #[clap(long, default_value_t=false, conflicts_with="reparent")]
+        drop: bool,
+        /// Derive words from the etymons of the removed word instead, applying the transforms of both etymologies in order
+        #[clap(long, default_value_t=false)]
+        reparent: bool,
+    },
+    /// Rename a word ID, updating every etymon, derivative and conditional that refers to it
+    Mv {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The current ID of the word
+        old: String,
+        /// The new ID of the word
+        new: String,
+    },
+    /// Write the computed word of each entry into its tree file, so generated and derived forms can be frozen and edited by hand.
+    /// Words with etymons get the `word_override` field, so their etymology is kept.
+    Bake {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The IDs of the words to write. If none are given, every word is written
+        ids: Vec<String>,
+    },
+    /// Run the transform tests in the tests directory of the project. Each test applies a list of transforms to a word,
+    /// and checks the result against the expected word.
+    Test {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// Only run tests with a name containing this string
+        filter: Option<String>,
+        /// Instead of the transform tests, compare every computed word with the snapshot.json file in the project,
+        /// and print the words that changed
+        #[clap(long, default_value_t=false)]
+        snapshot: bool,
+        /// Write the snapshot.json file from the current words, instead of comparing them
+        #[clap(long, default_value_t=false, requires = "snapshot")]
+        update: bool,
+    },
+    /// Find words matching a search expression, such as `language=Latin and tags has archaic and word ends "um"`.
+    /// Fields are compared with `=`, `!=`, `~` (contains), `has` (one of the values is equal), `starts`, `ends` and `matches` (a regex),
+    /// and comparisons can be combined with `and`, `or`, `not` and parentheses.
+    /// Historical metadata can be searched with `meta.<key>`.
+    #[clap(verbatim_doc_comment)]
+    Search {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The search expression
+        query: String,
+        /// The output format of the matching words
+        #[clap(short, long, value_enum, default_value_t=SearchFormat::Line)]
+        format: SearchFormat,
+    },
+    /// Merge the tree and etymology files of another project into this one
+    Merge {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The project to merge from
+        other: String,
+        /// What to do with words and transforms that exist in both projects
+        #[clap(long, value_enum, default_value_t=MergeStrategy::Skip)]
+        on_conflict: MergeStrategy,
+        /// The prefix added to colliding IDs when renaming. Defaults to the name of the other project, followed by a dash
+        #[clap(long, value_parser)]
+        prefix: Option<String>,
+    },
+    /// Rewrite the tree, etymology and phonetics files of a project with sorted keys and consistent indentation
+    Fmt {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// Don't write any files, and fail if any file isn't formatted
+        #[clap(long, default_value_t=false)]
+        check: bool,
     },
     /// Print a graphviz representation of the language
     Graphviz{
         /// path to a directory to read in all transform and graph files
         #[clap(short, long, value_parser)]
         directory: Option<String>,
+        /// Highlight words that will never have a word in red, and roots that nothing derives from in gray
+        #[clap(long, default_value_t=false)]
+        highlight: bool,
+        /// Store the computed tree in a cache file in the project directory, and reuse it while the project files are unchanged
+        #[clap(long, default_value_t=false)]
+        cache: bool,
     },
 
     /// Render a lexicon from an existing set of graph files and transformations
-    Render{
-        /// path to a directory to read in all transform and graph files.
-        /// Can be specified instead of -g -d
-        #[clap(short, long, value_parser)]
-        directory: Option<String>,
-        /// TOML file that will be used to resolve template variables in definition fields.
-        /// Template variables can be written into Lexis definition fields using {{handlebars_variables}}
-        #[clap(short, long, value_parser)]
-        variables: Option<String>,
+    Render(RenderArgs),
 
+    /// Watch a project for changes, and re-render the lexicon every time a file changes
+    Watch(RenderArgs),
+
+    /// Print a shell completion script for kirum
+    Completions {
+        /// The shell to generate completions for
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Write man pages for kirum and all of its subcommands to a directory
+    Manpages {
+        /// The directory to write man pages to
+        directory: String,
+    },
+    /// Print a JSON Schema for a type of project file, for validation and completion in editors
+    Schema {
+        /// The type of project file
+        #[clap(value_enum)]
+        file: SchemaFile,
+    },
+
+    /// Edit an existing language project
+    Edit {
         #[clap(subcommand)]
-        command: Format
+        command: Edit
     },
 
     /// Generate a language tree from another source
@@ -64,7 +380,7 @@ pub enum Commands{
     #[clap(verbatim_doc_comment)]
     Ingest {
         /// Override a default ingest value that will be applied to all ingested words, specified in key=value form.
-        /// Possible key values: word, type, language, pos, archaic, tag, generate
+        /// Possible key values: word, type, language, pos, archaic, tag, generate, noun_class
         #[clap(short, long, value_parser, verbatim_doc_comment)]
         overrides: Option<Vec<String>>,
         /// Path to a directory to read in all transform and graph files. Can be used instead of -t or -g
@@ -88,9 +404,72 @@ pub enum Ingest {
     Lines {
         /// a newline-delimited list of words to ingest
         file: String,
+    },
+    /// Derive a language tree from a LIFT XML file, as exported by FieldWorks and other lexicography tools
+    Lift {
+        /// LIFT file to ingest
+        file: String,
+    },
+    /// Derive a language tree from a PolyGlot .pgd file, or the PGDictionary.xml file extracted from one
+    Polyglot {
+        /// PolyGlot file to ingest
+        file: String,
     }
 }
 
+#[derive(clap::Subcommand, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum Edit {
+    /// Rename a language everywhere it appears in the project, including tree files, match expressions and globals
+    RenameLanguage {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The current name of the language
+        old: String,
+        /// The new name of the language
+        new: String,
+    },
+    /// Rename a lexis type everywhere it appears in the project, including tree files, match expressions and globals
+    RenameType {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The current lexis type
+        old: String,
+        /// The new lexis type
+        new: String,
+    },
+    /// Rename a tag everywhere it appears in the project, including tree files, match expressions and globals
+    RenameTag {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The current tag
+        old: String,
+        /// The new tag
+        new: String,
+    },
+    /// Change the fields of a single entry, re-writing only the tree file that defines it
+    Entry {
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The ID of the word to edit
+        id: String,
+        /// Set a field of the entry, in key=value form. Lists, maps and booleans are read as JSON, and a value of null removes the field.
+        /// Can be specified multiple times
+        #[clap(short, long, value_parser, verbatim_doc_comment)]
+        set: Vec<String>,
+        /// Add a tag to the entry. Can be specified multiple times
+        #[clap(long, value_parser)]
+        add_tag: Vec<String>,
+        /// Remove a tag from the entry. Can be specified multiple times
+        #[clap(long, value_parser)]
+        remove_tag: Vec<String>,
+    },
+}
+
 #[derive(clap::Subcommand, Clone)]
 pub enum Generate{
     /// Generate a daughter language from an existing language in a graph.
@@ -102,20 +481,171 @@ pub enum Generate{
         #[clap(short='e', long, value_parser)]
         daughter_etymology: String,
         /// the ancestor language as specified in the "language" field of the graph definition.
-        #[clap(short, long, value_parser)]
-        ancestor: String,
+        #[clap(short, long, value_parser, required_unless_present = "where")]
+        ancestor: Option<String>,
+        /// Only create daughter words from words that match a search expression, as used by `kirum search`,
+        /// i.e. "pos=noun and archaic=false". If an ancestor is also set, words must be in the ancestor language.
+        #[clap(short, long = "where", value_parser)]
+        r#where: Option<String>,
         /// The name of the daughter language. This will become the "language" field in the daughter Lexis
         #[clap(short, long, value_parser)]
         name: String,
         /// Output file to write the new language file to. If group_by is set, this is used as the directory prefix.
-        #[clap(short, long, value_parser)]
-        output: String,
+        #[clap(short, long, value_parser, required_unless_present = "in_place")]
+        output: Option<String>,
         /// group output into different files
         #[clap(short='b', long, value_enum)]
-        group_by: Option<SeparateValues>
+        group_by: Option<SeparateValues>,
+        /// Add the daughter words to the tree directory of the project, instead of writing them to an output file.
+        /// If the daughter transforms aren't already part of the project, they're copied into the etymology directory.
+        #[clap(long, action, conflicts_with = "output")]
+        in_place: bool
+    },
+    /// Create draft entries for every concept in a standard concept list, such as the Swadesh list.
+    /// Entries are written to a new file in the tree directory, with the definition filled in and the word left empty,
+    /// or generated from phonetic rules if --generate is set.
+    Concepts{
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The concept list to create entries for
+        #[clap(short, long, value_enum)]
+        list: ConceptList,
+        /// The language of the new entries
+        #[clap(short='n', long, value_parser)]
+        language: String,
+        /// Generate each word from the given lexis type in the phonetic rules, instead of leaving it empty
+        #[clap(short, long, value_parser)]
+        generate: Option<String>,
+    },
+    /// Borrow a random sample of words from one language into another as loanwords.
+    /// Loanwords are written to a new file in the tree directory, and derived from the original words through the adaptation transforms.
+    Borrow{
+        /// path to a directory to read in all transform and graph files
+        #[clap(short, long, value_parser)]
+        directory: Option<String>,
+        /// The language to borrow words from
+        #[clap(short, long, value_parser)]
+        from: String,
+        /// The language to borrow words into
+        #[clap(short, long, value_parser)]
+        to: String,
+        /// The number of words to borrow
+        #[clap(short, long, value_parser, default_value_t=10)]
+        count: usize,
+        /// A transform from the etymology files that adapts the words to the new language. Can be specified multiple times, and is applied in order
+        #[clap(short, long, value_parser)]
+        adapt: Vec<String>,
+        /// Seed used to choose the words. Defaults to the seed in globals.json
+        #[clap(short, long, value_parser)]
+        seed: Option<u64>,
     }
 }
 
+/// The order of rendered words
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SortOrder {
+    /// Alphabetically by word
+    Alpha,
+    /// By language, then by word
+    Language,
+    /// By part of speech, then by word. Words without a part of speech are last
+    Pos,
+    /// By ID
+    Id,
+}
+
+/// The field words are grouped by with `render --group-by`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum GroupBy {
+    Language,
+    Pos,
+    Type,
+}
+
+/// How words are grouped into files with `render --split-by`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SplitBy {
+    /// One file for each language
+    Language,
+    /// One file for each initial letter, using the collation of each language
+    Letter,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ConceptList {
+    /// The 100-word Swadesh list
+    #[clap(name = "swadesh-100")]
+    Swadesh100,
+    /// The 207-word Swadesh list
+    #[clap(name = "swadesh-207")]
+    Swadesh207,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SchemaFile {
+    /// Tree files, containing words
+    Tree,
+    /// Etymology files, containing transforms
+    Etymology,
+    /// Phonetic rule files
+    Phonetics,
+    /// The globals.json file
+    Globals,
+    /// The taxonomy.json file
+    Taxonomy,
+    /// Transform test files, in the tests directory
+    Tests,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ExplainFormat {
+    /// A human-readable trace
+    Text,
+    /// A nested JSON object, with an entry for each etymon
+    Json,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum MergeStrategy {
+    /// Keep the existing definition, and leave out the one from the other project.
+    /// Fails if other words of the other project refer to a skipped word
+    Skip,
+    /// Add a prefix to the IDs from the other project, updating every reference to them
+    Rename,
+    /// Replace the existing definition with the one from the other project
+    Overwrite,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SearchFormat {
+    /// One word per line, in aligned columns
+    Line,
+    /// A JSON object in the same structure as a tree file
+    Json,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum LineField {
+    Id,
+    Word,
+    Language,
+    Pos,
+    Gloss,
+    Type,
+    Ipa,
+    Tags,
+    Status,
+    Notes,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum SeparateValues {
     Word,
@@ -125,19 +655,82 @@ pub enum SeparateValues {
 
 #[derive(clap::Subcommand, Clone, PartialEq, PartialOrd)]
 pub enum Format{
-     /// Print one word per line
-    Line,
-    // Print language in CSV format
-    //Csv,
+     /// Print one word per line, in aligned columns
+    Line{
+        /// Don't shorten long glosses to fit the terminal width
+        #[clap(short, long, action)]
+        wide: bool,
+        /// The fields to print, in order
+        #[clap(short, long, value_enum, value_delimiter=',')]
+        fields: Vec<LineField>,
+        /// Color the output. By default, output is colored when printed to a terminal, unless NO_COLOR is set
+        #[clap(long, value_enum, default_value_t=ColorChoice::Auto)]
+        color: ColorChoice,
+        /// Print each word with a format string instead of in columns, such as "{word}\t{pos}\t{definition}".
+        /// Any field of a word can be used, along with {meta.KEY}, {glosses.LANG} and {orthographies.NAME}
+        #[clap(long, value_parser, conflicts_with_all = ["wide", "fields", "color"])]
+        format: Option<String>
+    },
+    /// Print language in CSV format, with one row per word
+    Csv,
+    /// Print language in TSV format, with one row per word
+    Tsv,
     /// Print language in format specified by a handlebars template file
     Template{
         /// Path to the .hbs template file
         #[clap(short, long, value_parser)]
         template_file: String,
+        /// A directory of .hbs files to register as partials, named by their path in the directory without the extension.
+        /// i.e. `entry.hbs` can be included with `{{> entry}}`, and `sections/letter.hbs` with `{{> sections/letter}}`
+        #[clap(long, value_parser)]
+        template_dir: Option<String>,
         /// Optional rhai scripts for processing template data. See https://docs.rs/handlebars/latest/handlebars/#script-helper
         #[clap(short, long, value_parser)]
-        rhai_files: Option<Vec<String>>
+        rhai_files: Option<Vec<String>>,
+        /// Pass the template a structured object of `{languages: [{name, words}], all_words}` instead of a flat list of words
+        #[clap(short, long, default_value_t=false)]
+        grouped: bool
+    },
+    /// Print a standalone HTML dictionary, with a section for each language
+    Html{
+        /// The title of the dictionary
+        #[clap(short, long, default_value="Lexicon")]
+        title: String
+    },
+    /// Print a markdown dictionary, with a section for each language
+    Markdown{
+        /// The title of the dictionary
+        #[clap(short, long, default_value="Lexicon")]
+        title: String
+    },
+    /// Print MDF (Multi-Dictionary Formatter) records, for use with SIL Toolbox or FLEx
+    Mdf{
+        /// Only include words of the given language, as Toolbox databases hold a single language
+        #[clap(short, long, value_parser)]
+        language: Option<String>
     },
+    /// Print a LIFT XML document, for use with FieldWorks and other lexicography tools
+    Lift,
+    /// Print a GraphML document of the words and their etymologies, for use with graph tools such as Gephi or yEd
+    Graphml,
+    /// Print an XDXF dictionary, for use with offline dictionary readers such as GoldenDict
+    Xdxf{
+        /// The title of the dictionary
+        #[clap(short, long, default_value="Lexicon")]
+        title: String,
+        /// Only include words of the given language
+        #[clap(short, long, value_parser)]
+        language: Option<String>
+    },
+    /// Print a reverse index, listing the words that contain each keyword of the definitions
+    ReverseIndex,
     /// Prints a JSON object of the language
-    Json
+    Json{
+        /// Include a list of the IDs of words derived from each entry
+        #[clap(long, default_value_t=false)]
+        descendants: bool,
+        /// Include the full definition of the transforms of each etymon, as `resolved_transforms`
+        #[clap(long, default_value_t=false)]
+        transforms: bool
+    }
 }
\ No newline at end of file
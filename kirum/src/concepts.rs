@@ -0,0 +1,109 @@
+use std::{collections::HashMap, path::Path};
+use anyhow::{Result, anyhow, Context};
+use libkirum::word::{PartOfSpeech, EditorialStatus};
+use crate::{cli::ConceptList, entries::{RawLexicalEntry, WordGraph, Generate}, files::{add_tree_file, handle_directory, read_tree_files}, transaction::WriteTransaction};
+
+const SWADESH_100: &str = include_str!("concept_lists/swadesh-100.txt");
+const SWADESH_207: &str = include_str!("concept_lists/swadesh-207.txt");
+
+impl ConceptList {
+    /// the name of the list, used for the tree file name and the tag added to each entry
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConceptList::Swadesh100 => "swadesh-100",
+            ConceptList::Swadesh207 => "swadesh-207"
+        }
+    }
+
+    /// return the concepts in the list, as (definition, part of speech) pairs.
+    /// Each line of a list is a definition, optionally followed by a `|` and a part of speech.
+    fn concepts(&self) -> Vec<(String, Option<PartOfSpeech>)> {
+        let raw = match self {
+            ConceptList::Swadesh100 => SWADESH_100,
+            ConceptList::Swadesh207 => SWADESH_207
+        };
+        raw.lines().filter(|l| !l.trim().is_empty()).map(|line| {
+            match line.split_once('|') {
+                Some((def, pos)) => (def.to_string(), pos.parse().ok()),
+                None => (line.to_string(), None)
+            }
+        }).collect()
+    }
+}
+
+/// Create a skeleton entry for every concept in a list, with the definition filled in.
+/// Entries are marked as drafts; if `generate` is set, the word is generated from the named phonetic rule set,
+/// otherwise it's left empty.
+pub fn concept_entries(list: ConceptList, language: &str, generate: Option<String>) -> WordGraph {
+    let mut words: HashMap<String, RawLexicalEntry> = HashMap::new();
+    for (definition, pos) in list.concepts() {
+        let id = format!("{}-{}", slug(language), slug(&definition));
+        words.insert(id, RawLexicalEntry {
+            language: Some(language.to_string()),
+            definition: definition.into(),
+            part_of_speech: pos,
+            tags: Some(vec![list.name().to_string()]),
+            generate: generate.clone().map(Generate::Type),
+            status: Some(EditorialStatus::Draft),
+            ..Default::default()
+        });
+    }
+    WordGraph { words }
+}
+
+/// Write the entries for a concept list to a new file in the tree directory of a project.
+/// Concepts that already have an entry in the project, such as from a different concept list, are skipped.
+pub fn scaffold(directory: Option<String>, list: ConceptList, language: String, generate: Option<String>) -> Result<String> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    let file_name = format!("{}-{}.json", slug(&language), list.name());
+    let out_path = Path::new(&dir).join("tree").join(&file_name);
+    if out_path.exists() {
+        return Err(anyhow!("file {} already exists", out_path.display()));
+    }
+
+    let existing = read_tree_files(&handle_directory(&dir)?.graphs, false)
+    .context("error reading existing tree files")?;
+    let mut graph = concept_entries(list, &language, generate);
+    graph.words.retain(|id, _| !existing.contains_key(id));
+    let count = graph.words.len();
+    if count == 0 {
+        return Err(anyhow!("every concept in {} already has an entry in the project", list.name()));
+    }
+    let mut tx = WriteTransaction::new();
+    tx.create_dir_all(Path::new(&dir).join("tree"))?;
+    add_tree_file(&mut tx, &dir, &file_name, graph).context("error writing concept list")?;
+    tx.commit();
+
+    Ok(format!("created {} draft entries in {}", count, out_path.display()))
+}
+
+/// turn a definition or language name into a lowercase, hyphenated ID component
+pub fn slug(value: &str) -> String {
+    let cleaned: String = value.to_lowercase().chars()
+    .map(|c| if c.is_alphanumeric() {c} else {' '}).collect();
+    cleaned.split_whitespace().collect::<Vec<&str>>().join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::word::{PartOfSpeech, EditorialStatus};
+    use crate::cli::ConceptList;
+    use super::concept_entries;
+
+    #[test]
+    fn test_swadesh_entries() {
+        let graph = concept_entries(ConceptList::Swadesh207, "Old Kirum", Some("word".into()));
+        assert_eq!(graph.words.len(), 207);
+
+        let bark = graph.words.get("old-kirum-bark-of-a-tree").unwrap();
+        assert_eq!(bark.definition.to_string(), "bark (of a tree)");
+        assert_eq!(bark.part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(bark.status, Some(EditorialStatus::Draft));
+        assert_eq!(bark.tags, Some(vec!["swadesh-207".to_string()]));
+        assert_eq!(bark.word, None);
+
+        let short_list = concept_entries(ConceptList::Swadesh100, "Old Kirum", None);
+        assert_eq!(short_list.words.len(), 100);
+        assert_eq!(short_list.words.get("old-kirum-to-drink").unwrap().generate, None);
+    }
+}
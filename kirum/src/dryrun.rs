@@ -0,0 +1,101 @@
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::Lexis, lemma::Lemma, word::DerivationStep};
+use crate::files::{find_transforms, handle_directory, read_from_files, read_transform_files, transform_error_context};
+
+/// The transforms to apply in a dry run
+pub enum DryRun {
+    /// Transforms from the etymology files, applied in order
+    Transforms(Vec<String>),
+    /// The transforms between an etymon and a word derived from it
+    Edge{etymon: String, id: String}
+}
+
+/// Apply transforms to a single word without changing the project, and print the word before and after each step.
+/// The word is either the ID of a word in the project, in which case its computed form is used, or a word to transform.
+/// A word that isn't an ID has no language or part of speech, so transforms that match on those fields are skipped.
+pub fn dry_run(directory: &str, run: DryRun, word: Option<&str>) -> Result<String> {
+    let project = handle_directory(directory)?;
+    let mut tree = read_from_files(project.clone())?;
+    // only compute the tree if a computed word is needed, so a transform that breaks the project can still be tried on a plain word
+    if matches!(run, DryRun::Edge { .. }) || word.is_some_and(|w| tree.get_by_id(w).is_some()) {
+        tree.compute_lexicon().map_err(|e| transform_error_context(e, &project))?;
+    }
+    let literal = |word: &str| Lexis{id: word.to_string(), word: Some(word.to_string().into()), ..Default::default()};
+
+    let (before, after, steps, show_layer) = match run {
+        DryRun::Transforms(names) => {
+            let word = word.ok_or(anyhow!("must specify a word to transform with --word"))?;
+            let mut working = tree.get_by_id(word).unwrap_or_else(|| literal(word));
+            let before = working.word.clone();
+            let transforms = find_transforms(&names, &read_transform_files(&project.transforms, false)?)?;
+            let vowels = tree.vowels();
+            let mut steps = Vec::new();
+            for trans in &transforms {
+                let input = working.word.clone();
+                let applied = trans.transform_option(&mut working, &vowels)?;
+                steps.push(DerivationStep { transform: trans.name.clone(), applied, input, output: working.word.clone(), ..Default::default() });
+            }
+            (before, working.word, steps, false)
+        },
+        DryRun::Edge { etymon, id } => {
+            let mut working = match word {
+                Some(word) => tree.get_by_id(word).unwrap_or_else(|| {
+                    // keep the fields of the etymon, so transforms that match on language or part of speech still apply
+                    let etymon = tree.get_by_id(&etymon).unwrap_or_default();
+                    Lexis{word: Some(word.to_string().into()), ..etymon}
+                }),
+                None => tree.get_by_id(&etymon).ok_or(anyhow!("no word with ID '{}' found", etymon))?
+            };
+            let before = working.word.clone();
+            let steps = tree.apply_edge(&etymon, &id, &mut working)?
+                .ok_or(anyhow!("'{}' is not derived from '{}'", id, etymon))?;
+            (before, working.word, steps, true)
+        }
+    };
+
+    let mut lines = vec![format!("{} -> {}", form(&before), form(&after))];
+    for step in steps {
+        let name = if show_layer {format!("{} [{}]", step.transform, step.layer)} else {step.transform};
+        if step.applied {
+            lines.push(format!("  {}: {} -> {}", name, form(&step.input), form(&step.output)));
+        } else {
+            lines.push(format!("  {}: skipped", name));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+fn form(word: &Option<Lemma>) -> String {
+    match word {
+        Some(w) => w.string_without_sep(),
+        None => String::from("[none]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use super::{dry_run, DryRun};
+
+    #[test]
+    fn test_dry_run_transforms() -> Result<()> {
+        let out = dry_run("src/test_files/test_der", DryRun::Transforms(vec!["latin-from-verb".to_string(), "of-from-latin".to_string()]), Some("latin_verb"))?;
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec!["emere -> essemple", "  latin-from-verb: emere -> exemplum", "  of-from-latin: exemplum -> essemple"]);
+
+        let out = dry_run("src/test_files/test_der", DryRun::Transforms(vec!["latin-from-verb".to_string()]), Some("tenere"))?;
+        assert_eq!(out.lines().next(), Some("tenere -> extenplum"));
+        assert!(dry_run("src/test_files/test_der", DryRun::Transforms(vec!["missing".to_string()]), Some("tenere")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_edge() -> Result<()> {
+        let edge = || DryRun::Edge { etymon: "latin_verb".to_string(), id: "latin_example".to_string() };
+        let out = dry_run("src/test_files/test_der", edge(), None)?;
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec!["emere -> exemplum", "  latin-from-verb [edge]: emere -> exemplum"]);
+        let out = dry_run("src/test_files/test_der", edge(), Some("legere"))?;
+        assert_eq!(out.lines().next(), Some("legere -> exlegplum"));
+        assert!(dry_run("src/test_files/test_der", DryRun::Edge { etymon: "latin_example".to_string(), id: "latin_verb".to_string() }, None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use anyhow::{Result, Context, anyhow};
+use serde_json::{value::RawValue, Map, Value};
+
+use crate::{entries::WordGraph, files::{handle_directory, read_tree_files}, format::FileFormat, interpolate::from_str_with_env, transaction::WriteTransaction};
+
+/// A field of a lexis that can be renamed across a project
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenameField {
+    Language,
+    LexisType,
+    Tag
+}
+
+impl RenameField {
+    /// the JSON keys that hold values of this field, in tree files, match expressions and globals
+    fn keys(&self) -> &'static [&'static str] {
+        match self {
+            RenameField::Language => &["language", "default_language", "parent"],
+            RenameField::LexisType => &["type", "lexis_type"],
+            RenameField::Tag => &["tags"]
+        }
+    }
+}
+
+/// keys that are never rewritten; the generate field refers to phonetic rules, not the lexis type,
+/// and historical metadata is free-form user data
+const SKIPPED_KEYS: &[&str] = &["generate", "historical_metadata"];
+
+/// Rename every occurrence of a language, lexis type or tag across the tree files, etymology files and globals of a project.
+/// Returns the number of files that were changed. Changed files are re-written in their original format, keeping the order of their keys.
+pub fn rename(directory: &str, field: RenameField, old: &str, new: &str) -> Result<usize> {
+    let project = handle_directory(directory)?;
+    let mut files = project.graphs;
+    files.extend(project.transforms);
+    files.extend(project.globals);
+
+    rewrite_files(&files, old, new, |value| rename_value(value, field, old, new))
+}
+
+/// Rename a word ID, updating every reference to it across the tree files, etymology files and globals of a project:
+/// etymons, derivative IDs, and `id` fields in transform conditionals.
+/// IDs generated for derivatives of the word, such as `old-autoderive-0`, are renamed along with it.
+/// Returns the number of files that were changed. No files are changed if any of them can't be re-written.
+pub fn rename_id(directory: &str, old: &str, new: &str) -> Result<usize> {
+    let project = handle_directory(directory)?;
+    let words = read_tree_files(&project.graphs, false).context("error reading existing tree files")?;
+    if !words.contains_key(old) {
+        return Err(anyhow!("no word with ID '{}' found in tree files", old));
+    }
+    if words.contains_key(new) {
+        return Err(anyhow!("a word with ID '{}' already exists", new));
+    }
+    let mut files = project.graphs;
+    files.extend(project.transforms);
+    files.extend(project.globals);
+    rewrite_files(&files, old, new, |value| rename_id_value(value, old, new))
+}
+
+/// apply a change to every file, and write the files that changed as a single transaction. Returns the number of changed files.
+fn rewrite_files(files: &[PathBuf], old: &str, new: &str, update: impl Fn(&mut Value) -> bool) -> Result<usize> {
+    let mut tx = WriteTransaction::new();
+    let mut changed = 0;
+    for path in files {
+        if rewrite_file(&mut tx, path, &update)? {
+            debug!("renamed '{}' to '{}' in {}", old, new, path.display());
+            changed += 1;
+        }
+    }
+    tx.commit();
+    Ok(changed)
+}
+
+/// update a single project file, only writing the file if something changed
+fn rewrite_file(tx: &mut WriteTransaction, path: &Path, update: impl Fn(&mut Value) -> bool) -> Result<bool> {
+    let format = FileFormat::from_path(path);
+    let raw = std::fs::read_to_string(path).context(format!("error reading {}", path.display()))?;
+    let mut parsed: Value = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+    if !update(&mut parsed) {
+        return Ok(false)
+    }
+    let updated = format.write_value(&parsed).context(format!("error creating {:?} data", format))?;
+    tx.write(path, updated).context(format!("error writing {}", path.display()))?;
+    Ok(true)
+}
+
+/// walk a JSON value, renaming the field wherever it appears. Returns true if anything was renamed.
+fn rename_value(value: &mut Value, field: RenameField, old: &str, new: &str) -> bool {
+    let mut renamed = false;
+    match value {
+        Value::Object(map) => {
+            // the per-language settings in globals are keyed by the language name
+            if field == RenameField::Language {
+                if let Some(Value::Object(languages)) = map.get_mut("languages") {
+                    renamed |= rename_key(languages, old, new);
+                }
+                // language transforms refer to the languages they connect by name
+                if let Some(Value::Array(pairs)) = map.get_mut("language_transforms") {
+                    for pair in pairs.iter_mut().filter_map(Value::as_object_mut) {
+                        for key in ["from", "to"] {
+                            if let Some(lang) = pair.get_mut(key) {
+                                renamed |= replace_strings(lang, old, new);
+                            }
+                        }
+                    }
+                }
+            }
+            for (key, child) in map.iter_mut() {
+                if field.keys().contains(&key.as_str()) {
+                    renamed |= replace_strings(child, old, new);
+                } else if !SKIPPED_KEYS.contains(&key.as_str()) {
+                    renamed |= rename_value(child, field, old, new);
+                }
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                renamed |= rename_value(item, field, old, new);
+            }
+        },
+        _ => {}
+    }
+    renamed
+}
+
+/// rename a key of a map, keeping its place in the map. Returns true if the key was found.
+pub fn rename_key(map: &mut Map<String, Value>, old: &str, new: &str) -> bool {
+    let Some(idx) = map.keys().position(|k| k == old) else {
+        return false
+    };
+    if let Some(value) = map.shift_remove(old) {
+        map.shift_insert(idx, new.to_string(), value);
+    }
+    true
+}
+
+/// walk a JSON value, renaming a word ID wherever it's referenced. Returns true if anything was renamed.
+pub fn rename_id_value(value: &mut Value, old: &str, new: &str) -> bool {
+    let mut renamed = false;
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Object(words)) = map.get_mut("words") {
+                renamed |= rename_key(words, old, new);
+            }
+            for (key, child) in map.iter_mut() {
+                match (key.as_str(), child) {
+                    // an etymon is either an edge to another word, or a match expression in a conditional
+                    ("etymon", Value::String(etymon)) => renamed |= replace_id(etymon, old, new),
+                    ("id", child) => renamed |= replace_matching(child, &|s| replace_id(s, old, new)),
+                    (key, _) if SKIPPED_KEYS.contains(&key) => {},
+                    (_, child) => renamed |= rename_id_value(child, old, new)
+                }
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                renamed |= rename_id_value(item, old, new);
+            }
+        },
+        _ => {}
+    }
+    renamed
+}
+
+/// rename an ID, or an ID generated for one of its derivatives
+fn replace_id(id: &mut String, old: &str, new: &str) -> bool {
+    if id == old {
+        *id = new.to_string();
+        return true;
+    }
+    match id.strip_prefix(old).filter(|rest| rest.starts_with("-autoderive-")) {
+        Some(rest) => {
+            *id = format!("{}{}", new, rest);
+            true
+        },
+        None => false
+    }
+}
+
+/// replace every string equal to old in a field value. This covers plain values, lists of tags,
+/// and match expressions such as `{"match": {"oneof": ["a", "b"]}}`
+fn replace_strings(value: &mut Value, old: &str, new: &str) -> bool {
+    replace_matching(value, &|s| {
+        if s == old {
+            *s = new.to_string();
+            return true;
+        }
+        false
+    })
+}
+
+/// apply a replacement to every string in a field value, returning true if any string was replaced
+fn replace_matching(value: &mut Value, replace: &impl Fn(&mut String) -> bool) -> bool {
+    match value {
+        Value::String(s) => replace(s),
+        Value::Array(items) => items.iter_mut().fold(false, |acc, i| replace_matching(i, replace) | acc),
+        Value::Object(map) => map.values_mut().fold(false, |acc, v| replace_matching(v, replace) | acc),
+        _ => false
+    }
+}
+
+/// Changes to make to a single entry in a tree file
+#[derive(Default, Debug, Clone)]
+pub struct EntryChanges {
+    /// fields to set, in `key=value` form. A value of `null` removes the field
+    pub set: Vec<String>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+/// fields that can be written under more than one name; setting any of them replaces the others with the first name
+const FIELD_ALIASES: &[&[&str]] = &[&["part_of_speech", "pos"], &["type", "lexis_type"]];
+
+/// Edit a single entry in place. The tree file that defines the entry is found and re-written in its original format,
+/// keeping the order of its keys; no other files are touched. In JSON files, only the text of the edited entry changes,
+/// so the formatting of the rest of the file is kept as-is.
+/// Returns the path of the changed file.
+pub fn edit_entry(directory: &str, id: &str, changes: &EntryChanges) -> Result<String> {
+    let project = handle_directory(directory)?;
+    for path in project.graphs {
+        let format = FileFormat::from_path(&path);
+        let raw = std::fs::read_to_string(&path).context(format!("error reading {}", path.display()))?;
+        let mut parsed: Value = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+        let entry = match parsed.get_mut("words").and_then(|w| w.get_mut(id)).and_then(Value::as_object_mut) {
+            Some(entry) => entry,
+            None => continue
+        };
+        apply_changes(entry, changes)?;
+
+        let updated = match format {
+            FileFormat::Json => splice_json_entry(&raw, id, &parsed["words"][id]),
+            _ => format.write_value(&parsed)
+        }.context(format!("error creating {:?} data", format))?;
+        // make sure the file is still a valid tree file before writing it
+        from_str_with_env::<WordGraph>(&updated, format, true).context(format!("edited entry '{}' is invalid", id))?;
+        let mut tx = WriteTransaction::new();
+        tx.write(&path, updated).context(format!("error writing {}", path.display()))?;
+        tx.commit();
+        return Ok(path.display().to_string());
+    }
+    Err(anyhow!("no word with ID '{}' found in tree files", id))
+}
+
+/// replace the text of a single entry in a JSON tree file, indenting the new entry to match the line it starts on
+fn splice_json_entry(raw: &str, id: &str, entry: &Value) -> Result<String> {
+    let file: HashMap<String, &RawValue> = serde_json::from_str(raw)?;
+    let words: HashMap<String, &RawValue> = serde_json::from_str(file.get("words").ok_or(anyhow!("no words in tree file"))?.get())?;
+    let old = words.get(id).ok_or(anyhow!("no word with ID '{}' in tree file", id))?.get();
+    // the raw values borrow from the file contents, so their position in the file is their offset from the start
+    let start = old.as_ptr() as usize - raw.as_ptr() as usize;
+    let line_start = raw[..start].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = raw[line_start..start].chars().take_while(|c| c.is_whitespace()).collect();
+    let written = serde_json::to_string_pretty(entry)?.replace('\n', &format!("\n{}", indent));
+    Ok(format!("{}{}{}", &raw[..start], written, &raw[start + old.len()..]))
+}
+
+fn apply_changes(entry: &mut Map<String, Value>, changes: &EntryChanges) -> Result<()> {
+    for pair in &changes.set {
+        let (key, raw) = pair.split_once('=').ok_or(anyhow!("field '{}' must be in key=value form", pair))?;
+        let mut key = key.trim();
+        if let Some(aliases) = FIELD_ALIASES.iter().find(|a| a.contains(&key)) {
+            for alias in aliases.iter().skip(1) {
+                entry.shift_remove(*alias);
+            }
+            key = aliases[0];
+        }
+        // inserting an existing key replaces its value in place, so the entry keeps its key order
+        match parse_field_value(raw) {
+            Value::Null => {entry.shift_remove(key);},
+            value => {entry.insert(key.to_string(), value);}
+        }
+    }
+
+    if changes.add_tags.is_empty() && changes.remove_tags.is_empty() {
+        return Ok(())
+    }
+    let mut tags: Vec<Value> = match entry.get("tags").cloned() {
+        Some(Value::Array(tags)) => tags,
+        Some(other) => return Err(anyhow!("tags must be a list, found {}", other)),
+        None => Vec::new()
+    };
+    for tag in &changes.add_tags {
+        if !tags.iter().any(|t| t == tag.as_str()) {
+            tags.push(Value::String(tag.clone()));
+        }
+    }
+    tags.retain(|t| !changes.remove_tags.iter().any(|r| t == r.as_str()));
+    if tags.is_empty() {
+        entry.shift_remove("tags");
+    } else {
+        entry.insert("tags".to_string(), Value::Array(tags));
+    }
+    Ok(())
+}
+
+/// read a value given on the command line. Lists, maps, booleans and null are read as JSON,
+/// and anything else is a plain string, so definitions don't need to be quoted
+fn parse_field_value(raw: &str) -> Value {
+    let trimmed = raw.trim();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') || ["true", "false", "null"].contains(&trimmed) {
+        if let Ok(value) = serde_json::from_str(trimmed) {
+            return value;
+        }
+    }
+    Value::String(raw.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anyhow::Result;
+    use serde_json::{json, Value};
+    use crate::test_util::copy_project;
+    use crate::files::read_and_compute;
+    use super::{edit_entry, rename, rename_id, EntryChanges, RenameField};
+
+
+    fn read_json(path: PathBuf) -> Result<Value> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    #[test]
+    fn test_rename_language() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_rename_language")?);
+        let changed = rename(dir.to_str().unwrap(), RenameField::Language, "Old Lang", "Old Gauntlet")?;
+        assert_eq!(changed, 3);
+
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert_eq!(words["words"]["root"]["language"], "Old Gauntlet");
+        assert_eq!(words["words"]["root"]["historical_metadata"]["language"], "Old Lang");
+        assert_eq!(words["words"]["root"]["derivatives"][0]["lexis"]["language"], "New Lang");
+        let ety = read_json(dir.join("etymology/ety.json"))?;
+        assert_eq!(ety["transforms"]["to-new"]["conditional"]["language"], json!({"match": {"equals": "Old Gauntlet"}}));
+        let globals = read_json(dir.join("globals.json"))?;
+        assert_eq!(globals["default_language"], "Old Gauntlet");
+        assert!(globals["languages"].get("Old Lang").is_none());
+        assert_eq!(globals["languages"].as_object().unwrap().keys().collect::<Vec<_>>(), ["Old Gauntlet", "New Lang"]);
+        assert_eq!(globals["languages"]["Old Gauntlet"]["collation"][0], "a");
+        assert_eq!(globals["languages"]["New Lang"]["parent"], "Old Gauntlet");
+        assert_eq!(globals["transforms"][0]["conditional"]["lexis"]["language"], json!({"match": {"equals": "Old Gauntlet"}}));
+
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.to_vec().iter().filter(|l| l.language == "Old Gauntlet").count(), 2);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_language_transforms() -> Result<()> {
+        let dir = PathBuf::from(copy_project("language_pairs", "kirum_test_rename_language_pairs")?);
+        let before = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(rename(dir.to_str().unwrap(), RenameField::Language, "New Exemplum", "Later Exemplum")?, 2);
+
+        let globals = read_json(dir.join("globals.json"))?;
+        assert_eq!(globals["language_transforms"][0]["from"], "Old Exemplum");
+        assert_eq!(globals["language_transforms"][0]["to"], "Later Exemplum");
+
+        let after = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(after.len(), before.len());
+        for word in before.to_vec() {
+            let renamed = after.get_by_id(&word.id).unwrap();
+            assert_eq!(renamed.word, word.word, "{}", word.id);
+            if word.language == "New Exemplum" {
+                assert_eq!(renamed.language, "Later Exemplum");
+            }
+        }
+        assert_eq!(after.get_by_id("derived").unwrap().word.unwrap().string_without_sep(), "kirū");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_type_and_tag() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_rename_type")?);
+        let changed = rename(dir.to_str().unwrap(), RenameField::LexisType, "root", "stem")?;
+        assert_eq!(changed, 3);
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert_eq!(words["words"]["root"]["type"], "stem");
+        assert_eq!(words["words"]["generated"]["generate"]["type"], "root");
+        let ety = read_json(dir.join("etymology/ety.json"))?;
+        assert_eq!(ety["transforms"]["to-new"]["conditional"]["type"], json!({"not": {"oneof": ["stem", "affix"]}}));
+
+        let changed = rename(dir.to_str().unwrap(), RenameField::Tag, "old", "archaic")?;
+        assert_eq!(changed, 2);
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert_eq!(words["words"]["root"]["tags"], json!(["nature", "archaic"]));
+        assert_eq!(words["words"]["root"]["derivatives"][0]["lexis"]["tags"], json!(["archaic"]));
+
+        assert_eq!(rename(dir.to_str().unwrap(), RenameField::Tag, "missing", "other")?, 0);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_entry() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_edit_entry")?);
+        let before = std::fs::read_to_string(dir.join("tree/words.json"))?;
+        let changes = EntryChanges { set: vec!["definition=a walled garden".to_string(), "pos=noun".to_string(), "archaic=true".to_string()],
+            add_tags: vec!["archaic-spelling".to_string(), "nature".to_string()], remove_tags: vec!["old".to_string()] };
+        let path = edit_entry(dir.to_str().unwrap(), "root", &changes)?;
+        assert!(path.ends_with("words.json"));
+
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert_eq!(words["words"]["root"]["definition"], "a walled garden");
+        assert_eq!(words["words"]["root"]["part_of_speech"], "noun");
+        assert_eq!(words["words"]["root"]["archaic"], true);
+        assert_eq!(words["words"]["root"]["tags"], json!(["nature", "archaic-spelling"]));
+        assert_eq!(words["words"]["generated"]["definition"], "orchard");
+
+        // only the text of the edited entry changes, and it keeps its key order
+        let after = std::fs::read_to_string(dir.join("tree/words.json"))?;
+        assert!(after.starts_with(&before[..before.find("\"root\"").unwrap()]));
+        assert!(after.ends_with(&before[before.find("    \"borrowed\"").unwrap()..]), "{}", after);
+        let keys: Vec<&str> = words["words"]["root"].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["word", "type", "language", "definition", "tags", "historical_metadata", "derivatives", "part_of_speech", "archaic"]);
+
+        let remove = EntryChanges { set: vec!["part_of_speech=null".to_string()], ..Default::default() };
+        edit_entry(dir.to_str().unwrap(), "root", &remove)?;
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert!(words["words"]["root"].get("part_of_speech").is_none());
+
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert!(computed.get_by_id("root").unwrap().archaic);
+
+        let invalid = EntryChanges { set: vec!["archaic=maybe".to_string()], ..Default::default() };
+        assert!(edit_entry(dir.to_str().unwrap(), "root", &invalid).is_err());
+        let unknown = EntryChanges { set: vec!["colour=red".to_string()], ..Default::default() };
+        assert!(edit_entry(dir.to_str().unwrap(), "root", &unknown).is_err());
+        assert!(edit_entry(dir.to_str().unwrap(), "missing", &changes).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_id() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_rename_id")?);
+        let before = read_and_compute(dir.to_str().map(String::from))?;
+        let changed = rename_id(dir.to_str().unwrap(), "root", "garden")?;
+        assert_eq!(changed, 2);
+
+        let words = read_json(dir.join("tree/words.json"))?;
+        assert!(words["words"].get("root").is_none());
+        assert_eq!(words["words"]["garden"]["definition"], "garden");
+        // the renamed word keeps its place in the file
+        let ids: Vec<&String> = words["words"].as_object().unwrap().keys().collect();
+        assert_eq!(ids, ["garden", "borrowed", "generated"]);
+        assert_eq!(words["words"]["borrowed"]["etymology"]["etymons"][0]["etymon"], "garden-autoderive-0");
+        let globals = read_json(dir.join("globals.json"))?;
+        assert_eq!(globals["transforms"][1]["conditional"]["etymon"]["id"], json!({"match": {"equals": "garden"}}));
+
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.get_by_id("garden").unwrap().word, before.get_by_id("root").unwrap().word);
+        assert_eq!(computed.get_by_id("borrowed").unwrap().word, before.get_by_id("borrowed").unwrap().word);
+
+        assert!(rename_id(dir.to_str().unwrap(), "root", "other").is_err());
+        assert!(rename_id(dir.to_str().unwrap(), "garden", "generated").is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
 use anyhow::{anyhow, Result};
-use libkirum::{word::{PartOfSpeech, Etymology}, kirum::Lexis, transforms::{TransformFunc, Transform}, matching::LexisMatch, lemma::Lemma};
+use libkirum::{word::{PartOfSpeech, Etymology, Example, EditorialStatus}, kirum::{Lexis, Agglutination}, transforms::{TransformFunc, Transform}, matching::LexisMatch, lemma::Lemma, lexcreate::CreateConstraints, sorted::{sorted_map, sorted_option_map}};
 use serde::{Serialize, Deserialize};
 use serde_with::skip_serializing_none;
+use schemars::JsonSchema;
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
 /// Defines the transform structure as created by the user in JSON.
 pub struct RawTransform{
     pub transforms: Vec<TransformFunc>,
@@ -19,13 +20,14 @@ impl From<RawTransform> for Transform{
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 pub struct TransformGraph {
+    #[serde(serialize_with = "sorted_map")]
     pub transforms: HashMap<String, RawTransform>
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 /// Defines a single lexis entry as created by the user in JSON
 pub struct RawLexicalEntry {
     /// Optional word
@@ -35,9 +37,9 @@ pub struct RawLexicalEntry {
     pub word_type: Option<String>,
     /// Language name.
     pub language: Option<String>,
-    /// Word definition
+    /// Word definition. Can be a single string, or a map of glosses keyed by language code
     #[serde(default)]
-    pub definition:String,
+    pub definition: Definition,
     /// Part of speech
     #[serde(alias = "pos")]
     pub part_of_speech: Option<PartOfSpeech>,
@@ -47,28 +49,124 @@ pub struct RawLexicalEntry {
     #[serde(default = "default_archaic")]
     /// Optional user tagging
     pub archaic: bool,
+    /// Marks the word as a reconstructed form, rather than an attested one. Reconstructed forms are rendered with a `*` prefix.
+    pub reconstructed: Option<bool>,
     /// Optional tags used for user-filtering
     pub tags: Option<Vec<String>>,
     /// Optional metadata values used for filtering, and ordering.
     /// Unlike tags, historical_metadata will be copied to any derivative words, and can be used for templating, filtering, etc
+    #[serde(serialize_with = "sorted_option_map")]
     pub historical_metadata: Option<HashMap<String, String>>,
-    /// A key that tells Kirum to generate the word based on the phonetic rule set specified by the tag
-    pub generate: Option<String>,
+    /// A key that tells Kirum to generate the word based on the phonetic rule set specified by the tag.
+    /// Can also be an object with the rule set in the `type` field, along with constraints on the generated word.
+    pub generate: Option<Generate>,
     /// Words that will be added as a derivative of the enclosing Lexis; any value not specified will be taken from the enclosing entry.
-    pub derivatives: Option<Vec<Derivative>>
+    pub derivatives: Option<Vec<Derivative>>,
+    /// Optional rules for how multiple etymons are joined when the word is created via agglutination
+    pub agglutination: Option<Agglutination>,
+    /// Optional noun class or grammatical gender
+    #[serde(alias = "gender")]
+    pub noun_class: Option<String>,
+    /// An irregular form that replaces the word derived from the etymology, while keeping the etymology intact
+    pub word_override: Option<Lemma>,
+    /// Example sentences demonstrating usage of the word
+    pub examples: Option<Vec<Example>>,
+    /// Optional semantic domain of the word. If the project has a taxonomy.json file, this should be one of the domains in the taxonomy
+    pub semantic_field: Option<String>,
+    /// Optional notes for other contributors. Notes aren't rendered unless `--include-drafts` is set.
+    pub notes: Option<String>,
+    /// Optional editorial status of the word: draft, reviewed or canon. Draft words aren't rendered unless `--include-drafts` is set.
+    pub status: Option<EditorialStatus>,
+    /// IDs of words derived from this word. Only used when rendering JSON output, and ignored when reading tree files.
+    #[serde(skip_deserializing)]
+    pub descendants: Option<Vec<String>>,
+    /// IPA transcription of the word, generated from the IPA mapping in globals.json. Only used when rendering output, and ignored when reading tree files.
+    #[serde(skip_deserializing)]
+    pub ipa: Option<String>
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+/// The generate field can either be the name of a lexis type from the phonetic rules,
+/// or an object with the lexis type and additional constraints, i.e `{"type": "root", "must_contain": "r", "forbid": ["ii"], "min_len": 3}`
+pub enum Generate {
+    Type(String),
+    Constrained{
+        #[serde(rename="type")]
+        lexis_type: String,
+        #[serde(flatten)]
+        constraints: CreateConstraints
+    }
+}
+
+impl From<String> for Generate {
+    fn from(value: String) -> Self {
+        Generate::Type(value)
+    }
+}
+
+impl From<&str> for Generate {
+    fn from(value: &str) -> Self {
+        Generate::Type(value.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+/// A definition can either be a single string, or a map of definitions in different metalanguages,
+/// keyed by a language code, i.e `{"en": "garden", "de": "Garten"}`
+pub enum Definition {
+    Single(String),
+    Glosses(#[serde(serialize_with = "sorted_map")] HashMap<String, String>)
+}
+
+impl Default for Definition {
+    fn default() -> Self {
+        Definition::Single(String::new())
+    }
+}
+
+impl From<String> for Definition {
+    fn from(value: String) -> Self {
+        Definition::Single(value)
+    }
+}
+
+impl From<&str> for Definition {
+    fn from(value: &str) -> Self {
+        Definition::Single(value.to_string())
+    }
+}
+
+impl std::fmt::Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Definition::Single(def) => write!(f, "{}", def),
+            Definition::Glosses(glosses) => {
+                let mut langs: Vec<&String> = glosses.keys().collect();
+                langs.sort();
+                let rendered: Vec<String> = langs.into_iter().map(|l| format!("{}: {}", l, glosses[l])).collect();
+                write!(f, "{}", rendered.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 /// The "derivative" field is largely semantic sugar, and allows the user to
 /// define derivative words inside a given lexis entry.
 pub struct Derivative{
+    /// Optional ID for the derivative word. If not set, an ID will be generated from the ID of the enclosing lexis and the position of the derivative.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub lexis: RawLexicalEntry,
     pub transforms: Option<Vec<String>>
 }
 
 /// Defines the "base" JSON file for a word tree.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, JsonSchema)]
 pub struct WordGraph {
+    #[serde(serialize_with = "sorted_map")]
     pub words: HashMap<String, RawLexicalEntry>,
 }
 
@@ -78,34 +176,78 @@ fn default_archaic() ->bool{
 
 impl From<RawLexicalEntry> for Lexis{
     fn from(source: RawLexicalEntry) -> Self {
+        let (definition, glosses) = match source.definition {
+            Definition::Single(def) => (def, HashMap::new()),
+            Definition::Glosses(glosses) => (String::new(), glosses)
+        };
+        // a word without a definition can take its meaning from the semantic shift on its etymology
+        let definition = match source.etymology.iter().flat_map(|e| &e.etymons).find_map(|e| e.semantic_shift.as_ref()?.definition.clone()) {
+            Some(shifted) if definition.is_empty() && glosses.is_empty() => shifted,
+            _ => definition
+        };
+        let (word_create, create_constraints) = match source.generate {
+            Some(Generate::Type(lexis_type)) => (Some(lexis_type), None),
+            Some(Generate::Constrained { lexis_type, constraints }) => (Some(lexis_type), Some(constraints)),
+            None => (None, None)
+        };
         Lexis { 
             id: String::new(),
             word: source.word, 
             language: source.language.unwrap_or("".to_string()), 
             pos: source.part_of_speech, 
             lexis_type: source.word_type.unwrap_or("".to_string()), 
-            definition: source.definition, 
+            definition,
+            glosses,
             archaic: source.archaic,
-            tags: source.tags.unwrap_or(Vec::new()),
-            historical_metadata: source.historical_metadata.unwrap_or(HashMap::new()),
-            word_create: source.generate
+            reconstructed: source.reconstructed.unwrap_or_default(),
+            tags: source.tags.unwrap_or_default(),
+            historical_metadata: source.historical_metadata.unwrap_or_default(),
+            word_create,
+            create_constraints,
+            agglutination: source.agglutination,
+            noun_class: source.noun_class,
+            word_override: source.word_override,
+            examples: source.examples.unwrap_or_default(),
+            orthographies: HashMap::new(),
+            ipa: None,
+            semantic_field: source.semantic_field,
+            notes: source.notes,
+            status: source.status
         }
     }
 }
 
 impl From<Lexis> for RawLexicalEntry{
     fn from(value: Lexis) -> Self {
+        let definition = if value.glosses.is_empty() {
+            Definition::Single(value.definition)
+        } else {
+            Definition::Glosses(value.glosses)
+        };
         RawLexicalEntry { word: value.word, 
             word_type: if !value.lexis_type.is_empty() {Some(value.lexis_type)} else {None}, 
             language: if !value.language.is_empty() {Some(value.language)} else {None}, 
-            definition: value.definition, 
+            definition,
             part_of_speech: value.pos, 
             etymology: None, 
             archaic: value.archaic, 
+            reconstructed: Some(true).filter(|_| value.reconstructed),
             tags: if !value.tags.is_empty() {Some(value.tags)} else {None},
             historical_metadata: if !value.historical_metadata.is_empty() {Some(value.historical_metadata)} else {None},
             derivatives: None,
-            generate: value.word_create
+            generate: value.word_create.map(|lexis_type| match value.create_constraints {
+                Some(constraints) => Generate::Constrained { lexis_type, constraints },
+                None => Generate::Type(lexis_type)
+            }),
+            agglutination: value.agglutination,
+            noun_class: value.noun_class,
+            word_override: value.word_override,
+            examples: if !value.examples.is_empty() {Some(value.examples)} else {None},
+            semantic_field: value.semantic_field,
+            notes: value.notes,
+            status: value.status,
+            descendants: None,
+            ipa: value.ipa
         }
     }
 }
@@ -113,19 +255,38 @@ impl From<Lexis> for RawLexicalEntry{
 /// take the output of a call to to_vec_etymons() and structure it like a graph json file structure
 /// If render_metadata is false, any historical_metadata fields will not be copied.
 /// This is useful in situations where we're writing out derivative values, and don't want metadata that will be 
-/// re-derived during ingest to get copied over
-pub fn create_json_graph<F>(lex: Vec<(Lexis, Etymology)>,mut key_gen: F, render_metadata: bool) -> Result<WordGraph>
+/// re-derived during ingest to get copied over.
+/// If render_descendants is true, each entry will contain a list of the IDs of the words derived from it.
+/// If render_transforms is false, the resolved definitions of each etymology transform are removed, as when writing tree files.
+pub fn create_json_graph<F>(lex: Vec<(Lexis, Etymology)>,mut key_gen: F, render_metadata: bool, render_descendants: bool, render_transforms: bool) -> Result<WordGraph>
     where F: FnMut(Lexis) -> String
     {
     let mut graph: HashMap<String, RawLexicalEntry> = HashMap::new();
+    let mut descendant_map: HashMap<String, Vec<String>> = HashMap::new();
+    if render_descendants {
+        for (word, ety) in &lex {
+            for edge in &ety.etymons {
+                descendant_map.entry(edge.etymon.clone()).or_default().push(word.id.clone());
+            }
+        }
+    }
 
-    for (word, ety) in lex{
+    for (word, mut ety) in lex{
+        if !render_transforms {
+            for edge in ety.etymons.iter_mut() {
+                edge.resolved_transforms = None;
+            }
+        }
         let base: RawLexicalEntry = word.clone().into();
         let found_ety = if !ety.etymons.is_empty() {Some(ety)} else {None};
         let mut complete = RawLexicalEntry{etymology: found_ety, ..base};
         if !render_metadata{
             complete.historical_metadata = None
         }
+        if let Some(mut found) = descendant_map.remove(&word.id) {
+            found.sort();
+            complete.descendants = Some(found);
+        }
         let key = key_gen(word);
         let found = graph.insert(key.clone(), complete.clone());
         if let Some(existing) = found{
@@ -0,0 +1,210 @@
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::LanguageTree, word::Derivation, lemma::Lemma};
+use crate::cli::ExplainFormat;
+
+/// Print a trace of how the word with the given ID was derived from its etymons.
+/// If show_applied is set, the trace includes the layer of each transform, and the transforms that changed each word.
+/// If show_functions is set, the trace includes the word after each function of every transform.
+pub fn render_explain(tree: &LanguageTree, id: &str, format: ExplainFormat, show_applied: bool, show_functions: bool) -> Result<String> {
+    let mut trace = tree.explain(id)?.ok_or(anyhow!("no word with ID '{}' found", id))?;
+    if !show_applied {
+        remove_applied(&mut trace);
+    }
+    if !show_functions {
+        remove_functions(&mut trace);
+    }
+    match format {
+        ExplainFormat::Json => Ok(serde_json::to_string_pretty(&trace)?),
+        ExplainFormat::Text => {
+            let mut lines: Vec<String> = Vec::new();
+            render_derivation(&trace, 0, show_applied, &mut lines);
+            if let Some(word) = tree.get_by_id(id) {
+                if let Some(status) = word.status {
+                    lines.push(format!("status: {}", status));
+                }
+                if let Some(notes) = word.notes {
+                    lines.push(format!("notes: {}", notes));
+                }
+            }
+            Ok(lines.join("\n"))
+        }
+    }
+}
+
+fn remove_applied(trace: &mut Derivation) {
+    trace.applied.clear();
+    for edge in trace.etymons.iter_mut() {
+        remove_applied(&mut edge.etymon);
+    }
+}
+
+fn remove_functions(trace: &mut Derivation) {
+    for edge in trace.etymons.iter_mut() {
+        edge.steps.iter_mut().for_each(|s| s.functions.clear());
+        remove_functions(&mut edge.etymon);
+    }
+}
+
+fn render_derivation(trace: &Derivation, indent: usize, show_applied: bool, lines: &mut Vec<String>) {
+    let pad = "  ".repeat(indent);
+    let mut head = format!("{}{}", pad, trace.id);
+    if !trace.language.is_empty() {
+        head = format!("{} ({})", head, trace.language);
+    }
+    head = format!("{}: {}{}", head, if trace.reconstructed {"*"} else {""}, form(&trace.word));
+    if trace.word_override.is_some() {
+        head = format!("{} [irregular]", head);
+    }
+    lines.push(head);
+    for rule in &trace.applied {
+        match &rule.etymon {
+            Some(etymon) => lines.push(format!("{}  applied {} ({}, from {})", pad, rule.name, rule.layer, etymon)),
+            None => lines.push(format!("{}  applied {} ({})", pad, rule.name, rule.layer))
+        }
+    }
+    for edge in &trace.etymons {
+        lines.push(format!("{}  from {}: {} -> {}", pad, edge.etymon.id, form(&edge.input), form(&edge.output)));
+        if let Some(shift) = &edge.semantic_shift {
+            lines.push(format!("{}    meaning ({}): {} -> {}", pad, shift, meaning(&edge.etymon.definition), meaning(&trace.definition)));
+        }
+        for step in &edge.steps {
+            let name = if show_applied {format!("{} [{}]", step.transform, step.layer)} else {step.transform.clone()};
+            if step.applied {
+                lines.push(format!("{}    {}: {} -> {}", pad, name, form(&step.input), form(&step.output)));
+            } else {
+                lines.push(format!("{}    {}: skipped", pad, name));
+            }
+            for func in &step.functions {
+                lines.push(format!("{}      {}: {}", pad, func.function, form(&func.output)));
+            }
+        }
+        render_derivation(&edge.etymon, indent + 1, show_applied, lines);
+    }
+}
+
+fn meaning(definition: &Option<String>) -> String {
+    match definition {
+        Some(def) => format!("'{}'", def),
+        None => String::from("[none]")
+    }
+}
+
+fn form(word: &Option<Lemma>) -> String {
+    match word {
+        Some(w) => w.string_without_sep(),
+        None => String::from("[none]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::{word::{Derivation, ShiftKind, TransformLayer}, kirum::LanguageTree};
+    use crate::{files::read_and_compute, cli::ExplainFormat};
+    use super::render_explain;
+
+    #[test]
+    fn test_explain_json() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/nested_der")))?;
+        let id = leaf_id(&computed);
+        let rendered = render_explain(&computed, &id, ExplainFormat::Json, false, false)?;
+        let trace: Derivation = serde_json::from_str(&rendered)?;
+        assert_eq!(trace.id, id);
+        assert_eq!(trace.word, Some("kirumaiuo".into()));
+        assert_eq!(trace.etymons.len(), 1);
+        let edge = &trace.etymons[0];
+        assert_eq!(edge.output, trace.word);
+        assert!(!edge.steps.is_empty());
+        assert!(render_explain(&computed, "not_a_word", ExplainFormat::Json, false, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_text() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/nested_der")))?;
+        let rendered = render_explain(&computed, &leaf_id(&computed), ExplainFormat::Text, false, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[0].ends_with(": kirumaiuo"));
+        assert_eq!(lines[1], "  from root_word-autoderive-0-autoderive-0: kirumaiu -> kirumaiuo");
+        assert_eq!(lines[2], "    plural: kirumaiu -> kirumaiuo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_show_applied() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/language_pairs")))?;
+        let rendered = render_explain(&computed, "derived", ExplainFormat::Text, true, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "derived (New Exemplum): kirū");
+        assert_eq!(lines[1], "  applied vowel-shift (language pair, from root)");
+        assert_eq!(lines[3], "    loanword [edge]: kirau -> kirau");
+        assert_eq!(lines[4], "    vowel-shift [language pair]: kirau -> kirū");
+
+        let rendered = render_explain(&computed, "borrowed", ExplainFormat::Json, true, false)?;
+        let trace: Derivation = serde_json::from_str(&rendered)?;
+        assert_eq!(trace.applied[0].layer, TransformLayer::Global);
+        let rendered = render_explain(&computed, "borrowed", ExplainFormat::Json, false, false)?;
+        let trace: Derivation = serde_json::from_str(&rendered)?;
+        assert!(trace.applied.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_functions() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/test_der")))?;
+        let rendered = render_explain(&computed, "latin_example", ExplainFormat::Text, false, true)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[2], "    latin-from-verb: emere -> exemplum");
+        assert!(lines[3].ends_with(": emplum"), "{}", rendered);
+        assert!(lines[4].ends_with(": exemplum"), "{}", rendered);
+
+        let rendered = render_explain(&computed, "latin_example", ExplainFormat::Json, false, false)?;
+        let trace: Derivation = serde_json::from_str(&rendered)?;
+        assert!(trace.etymons[0].steps[0].functions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_status() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/drafts")))?;
+        let rendered = render_explain(&computed, "keeper", ExplainFormat::Text, false, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines, vec!["keeper (Old Exemplum): amo", "status: draft", "notes: maybe too close to Latin?"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_semantic_shift() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/semantic_shift")))?;
+        assert_eq!(computed.get_by_id("deer").unwrap().definition, "deer");
+        assert_eq!(computed.get_by_id("beast").unwrap().definition, "brute, cruel person");
+
+        let rendered = render_explain(&computed, "deer", ExplainFormat::Text, false, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[1], "  from animal: dior -> diora");
+        assert_eq!(lines[2], "    meaning (narrowing: once any wild animal, now only deer): 'animal' -> 'deer'");
+        let rendered = render_explain(&computed, "beast", ExplainFormat::Text, false, false)?;
+        assert!(rendered.contains("    meaning (pejoration): 'animal' -> 'brute, cruel person'"), "{}", rendered);
+
+        let rendered = render_explain(&computed, "deer", ExplainFormat::Json, false, false)?;
+        let trace: Derivation = serde_json::from_str(&rendered)?;
+        assert_eq!(trace.etymons[0].semantic_shift.as_ref().unwrap().kind, Some(ShiftKind::Narrowing));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_reconstructed() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/reconstructed")))?;
+        let rendered = render_explain(&computed, "water", ExplainFormat::Text, false, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "water (Old Exemplum): weda");
+        assert_eq!(lines[1], "  from proto_water: wed -> weda");
+        assert_eq!(lines[3], "  proto_water (Proto-Exemplum): *wed");
+        Ok(())
+    }
+
+    // the derived word at the end of the nested_der chain
+    fn leaf_id(tree: &LanguageTree) -> String {
+        tree.to_vec().into_iter().find(|l| l.word == Some("kirumaiuo".into())).unwrap().id
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{Result, anyhow};
+use libkirum::kirum::{LanguageTree, Lexis};
+
+/// Print an ASCII tree of the word with the given ID and all of its descendants
+pub fn render_family(tree: &LanguageTree, id: &str) -> Result<String> {
+    let root = tree.get_by_id(id).ok_or(anyhow!("no word with ID '{}' found", id))?;
+    let mut lines = vec![format_word(&root)];
+    let mut seen = vec![root.id.clone()];
+    render_descendants(tree, &root, "", &mut lines, &mut seen);
+    Ok(lines.join("\n"))
+}
+
+fn render_descendants(tree: &LanguageTree, lex: &Lexis, prefix: &str, lines: &mut Vec<String>, seen: &mut Vec<String>) {
+    let mut descendants = tree.descendants(&lex.id);
+    descendants.sort_by(|a, b| a.id.cmp(&b.id));
+    let count = descendants.len();
+    for (idx, desc) in descendants.into_iter().enumerate() {
+        let last = idx == count - 1;
+        let (branch, next_prefix) = if last {("└── ", "    ")} else {("├── ", "│   ")};
+        lines.push(format!("{}{}{}", prefix, branch, format_word(&desc)));
+        // a word with multiple etymons can be reached more than once, only expand it the first time
+        if seen.contains(&desc.id) {
+            continue;
+        }
+        seen.push(desc.id.clone());
+        render_descendants(tree, &desc, &format!("{}{}", prefix, next_prefix), lines, seen);
+    }
+}
+
+fn format_word(lex: &Lexis) -> String {
+    let word = match &lex.word {
+        Some(w) => w.string_without_sep(),
+        None => format!("[{}]", lex.id)
+    };
+    let mut rendered = word;
+    if !lex.language.is_empty() {
+        rendered = format!("{} ({})", rendered, lex.language);
+    }
+    let gloss = lex.gloss(None);
+    if !gloss.is_empty() {
+        rendered = format!("{}: {}", rendered, gloss);
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use crate::files::read_and_compute;
+    use super::render_family;
+
+    #[test]
+    fn test_family_tree() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/nested_der")))?;
+        let rendered = render_family(&computed, "root_word")?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("└── "));
+        assert!(lines[3].starts_with("        └── kirumaiuo"));
+        assert!(render_family(&computed, "not_a_word").is_err());
+        Ok(())
+    }
+}
@@ -1,17 +1,19 @@
-use std::{path::{PathBuf, Path},  collections::HashMap, fs::File, io::Write};
+use std::{path::{PathBuf, Path},  collections::HashMap};
 use anyhow::{Result, Context, anyhow};
-use libkirum::{kirum::{LanguageTree, Lexis}, transforms::{Transform, TransformFunc, GlobalTransform}, word::{Etymology, Edge}, lexcreate::LexPhonology};
+use libkirum::{errors::TransformError, kirum::{LanguageTree, Lexis, LanguageInheritance}, lexicon::ComputedLexicon, transforms::{Transform, TransformFunc, GlobalTransform, LanguagePairTransform}, word::{Etymology, Edge, PartOfSpeech}, lexcreate::LexPhonology};
 use serde::Serialize;
 use walkdir::{WalkDir, DirEntry};
-use crate::{entries::{RawTransform, RawLexicalEntry, TransformGraph, WordGraph}, global::Global};
+use crate::{entries::{RawTransform, RawLexicalEntry, TransformGraph, WordGraph}, format::{FileFormat, find_project_file, PROJECT_FORMATS, TRANSFORM_FORMATS}, global::Global, progress, interpolate::{from_str_with_env, map_from_str_with_env}, taxonomy::Taxonomy, transaction::WriteTransaction};
 use handlebars::Handlebars;
 
 /// contains path data for everything needed for a project
+#[derive(Clone)]
 pub struct Project {
     pub graphs: Vec<PathBuf>,
     pub transforms: Vec<PathBuf>,
     pub phonetic_rules: Option<Vec<PathBuf>>,
-    pub globals: Option<PathBuf>
+    pub globals: Option<PathBuf>,
+    pub taxonomy: Option<PathBuf>
 }
 
 /// renders any templating code that was written into word definitions
@@ -32,94 +34,288 @@ pub fn apply_def_vars(var_file: Option<String>, dict: &mut Vec<Lexis>) -> Result
     Ok(())
 }
 
+/// replace the definition of each word with the gloss in the selected language, falling back
+/// to the default definition if no gloss exists for that language
+pub fn apply_gloss_lang(gloss_lang: Option<String>, dict: &mut Vec<Lexis>) {
+    for word in dict {
+        word.definition = word.gloss(gloss_lang.as_deref());
+    }
+}
+
+/// replace each word with its form in the selected orthography.
+/// Words in languages that don't define that orthography are left unchanged.
+pub fn apply_orthography(orthography: Option<String>, dict: &mut Vec<Lexis>) {
+    let name = match orthography {
+        Some(n) => n,
+        None => return
+    };
+    for word in dict {
+        if let Some(written) = word.orthographies.get(&name) {
+            word.word = Some(written.clone().into());
+        }
+    }
+}
+
+/// replace the part of speech of each word with the abbreviation declared in globals.json, if any
+pub fn apply_pos_abbreviations(globals: &Global, dict: &mut Vec<Lexis>) {
+    for word in dict {
+        let abbreviation = word.pos.as_ref().and_then(|p| globals.part_of_speech(p)).and_then(|s| s.abbreviation.clone());
+        if let Some(abbr) = abbreviation {
+            word.pos = Some(PartOfSpeech::Other(abbr));
+        }
+    }
+}
+
+/// prefix the word of each reconstructed lexis with `*`, following the usual convention for reconstructed forms
+pub fn apply_reconstructed(dict: &mut [Lexis]) {
+    for word in dict.iter_mut().filter(|l| l.reconstructed) {
+        if let Some(lemma) = word.word.as_mut() {
+            lemma.add_prefix(&"*".into());
+        }
+    }
+}
+
+/// the word of a lexis without the `*` added by [apply_reconstructed], i.e. to find the letter it's listed under
+pub fn bare_word(lex: &Lexis) -> String {
+    let word = lex.word.as_ref().map(|w| w.string_without_sep()).unwrap_or_default();
+    match word.strip_prefix('*') {
+        Some(bare) if lex.reconstructed => bare.to_string(),
+        _ => word
+    }
+}
+
+/// unless drafts are requested, remove words with the draft status, along with the editorial notes of every remaining word
+pub fn apply_drafts(include_drafts: bool, dict: &mut Vec<Lexis>) {
+    if include_drafts {
+        return
+    }
+    dict.retain(|l| !l.is_draft());
+    for word in dict {
+        word.notes = None;
+    }
+}
+
 /// read a list of tree and transform files, return the raw Language Tree Object
 pub fn read_from_files(proj: Project) -> Result<LanguageTree>{
     //first merge all the files into one giant hashmap for the transforms and graph
     // because we later need to get random words from the map to construct the etymology from the rawLex "etymology" fields,
     // the giant hashmaps of everything need to be made first
-    let transform_map = read_transform_files(&proj.transforms)?;
+    let globals = match &proj.globals {
+        Some(path) => read_globals(path)?,
+        None => Global::default()
+    };
+    let strict = globals.strict.unwrap_or_default();
 
-    let language_map = read_tree_files(&proj.graphs)?;
+    // read both sets of files before returning, so all the errors in a project are reported at once
+    let (transform_map, mut language_map) = match (read_transform_files(&proj.transforms, strict), read_tree_files(&proj.graphs, strict)) {
+        (Ok(transforms), Ok(words)) => (transforms, words),
+        (Err(trans_err), Err(tree_err)) => return Err(anyhow!("{:#}\n{:#}", trans_err, tree_err)),
+        (Err(e), _) | (_, Err(e)) => return Err(e)
+    };
     
     if language_map.is_empty(){
         return Err(anyhow!("specified language tree does not contain any data. Tree files used: {:?}", proj.graphs));
     }
 
+    let inherit_language = globals.language_inheritance.unwrap_or_default() != LanguageInheritance::None;
+    if let Some(default_lang) = &globals.default_language {
+        for node in language_map.values_mut() {
+            // if languages are inherited, words with an etymology will get their language from their etymons
+            if inherit_language && node.etymology.is_some() {
+                continue;
+            }
+            if node.language.is_none() {
+                node.language = Some(default_lang.clone());
+            }
+        }
+    }
+
     let mut tree = LanguageTree::new();
     if let Some(phonetic_files) = proj.phonetic_rules{
-        tree.word_creator_phonology = create_phonetics(phonetic_files)?;
+        tree.word_creator_phonology = create_phonetics(phonetic_files, strict)?;
     }
 
-    for (lex_name, node) in &language_map{
+    // sort the keys so words are added to the tree in the same order between runs
+    let mut lex_names: Vec<&String> = language_map.keys().collect();
+    lex_names.sort();
+    for lex_name in lex_names {
         debug!("creating node entry {}", lex_name);
+        let node = &language_map[lex_name];
         let node_lex: Lexis = Lexis { id: lex_name.to_string(), ..node.clone().into() };
         add_single_word(&mut tree, &transform_map, &language_map, &node_lex, &node.etymology)?; 
     }
 
-    if let Some(globals) = proj.globals  {
-        let raw = std::fs::read_to_string(globals)?;
-        let global_trans: Global = serde_json::from_str(&raw)?;
-        if let Some(raw_trans) = global_trans.transforms {
-            let mut final_trans: Vec<GlobalTransform> = Vec::new();
-            for trans in raw_trans {
-                final_trans.push(trans.into())
-            }
-            tree.global_transforms = Some(final_trans);
-        }
+    apply_globals(&mut tree, globals, &transform_map)?;
+
+    Ok(tree)
+}
 
+/// read and parse the globals file. If the globals enable strict mode, the globals file itself is also checked for unknown fields.
+pub fn read_globals(path: &Path) -> Result<Global> {
+    let raw = std::fs::read_to_string(path).context(format!("error reading globals file {}", path.display()))?;
+    let format = FileFormat::from_path(path);
+    let globals: Global = from_str_with_env(&raw, format, false).context(format!("error parsing globals file {}", path.display()))?;
+    if globals.strict.unwrap_or_default() {
+        from_str_with_env::<Global>(&raw, format, true).context(format!("error parsing globals file {}", path.display()))?;
     }
+    Ok(globals)
+}
 
-    Ok(tree)
+/// apply the transforms and settings from the globals file to the language tree
+fn apply_globals(tree: &mut LanguageTree, globals: Global, transform_map: &HashMap<String, RawTransform>) -> Result<()> {
+    let daughters = globals.language_daughters();
+    if let Some(raw_trans) = globals.transforms {
+        let mut final_trans: Vec<GlobalTransform> = Vec::new();
+        for (idx, trans) in raw_trans.into_iter().enumerate() {
+            if trans.transforms.is_empty() {
+                return Err(anyhow!("global transform {} does not define any transforms", idx));
+            }
+            trans.conditional.lexis.validate().context(format!("invalid lexis conditional in global transform {}", idx))?;
+            if let Some(ety) = &trans.conditional.etymon {
+                ety.validate().context(format!("invalid etymon conditional in global transform {}", idx))?;
+            }
+            final_trans.push(trans.into())
+        }
+        tree.global_transforms = Some(final_trans);
+    }
+    if let Some(pairs) = globals.language_transforms {
+        for pair in pairs {
+            let transforms = find_transforms(&pair.transforms, transform_map)
+            .context(format!("invalid transforms for language pair {} -> {}", pair.from, pair.to))?;
+            tree.language_pair_transforms.push(LanguagePairTransform { from: pair.from, to: pair.to, transforms });
+        }
+    }
+    if let Some(precedence) = globals.transform_precedence {
+        tree.transform_precedence = precedence;
+    }
+    if let Some(languages) = globals.languages {
+        for (lang_name, settings) in languages {
+            if let Some(rules) = settings.sandhi {
+                tree.sandhi_rules.insert(lang_name.clone(), rules);
+            }
+            if let Some(alphabet) = settings.collation {
+                tree.language_collation.insert(lang_name.clone(), alphabet);
+            }
+            if let Some(graphemes) = settings.graphemes {
+                tree.graphemes.insert(lang_name.clone(), graphemes);
+            }
+            if let Some(orthographies) = settings.orthographies {
+                tree.orthographies.insert(lang_name.clone(), orthographies);
+            }
+            if let Some(ipa) = settings.ipa {
+                tree.ipa.insert(lang_name, ipa);
+            }
+        }
+    }
+    if let Some(inheritance) = globals.metadata_inheritance {
+        tree.metadata_inheritance = inheritance;
+    }
+    if let Some(agglutination) = globals.agglutination {
+        tree.agglutination = agglutination;
+    }
+    if let Some(inheritance) = globals.language_inheritance {
+        tree.language_inheritance = inheritance;
+        tree.language_daughters = daughters;
+    }
+    if let Some(blacklist) = globals.blacklist {
+        for entry in blacklist {
+            tree.blacklist.push(entry.to_regex().context(format!("invalid blacklist entry {:?}", entry))?);
+        }
+    }
+    tree.seed = globals.seed;
+    tree.collation = globals.collation;
+    Ok(())
 }
 
-pub fn read_tree_files(files: &Vec<PathBuf>) -> Result<HashMap<String, RawLexicalEntry>> {
+/// read and merge the words of every tree file. Errors in every file are collected and reported together.
+/// If strict is set, unknown fields are reported as errors.
+pub fn read_tree_files(files: &Vec<PathBuf>, strict: bool) -> Result<HashMap<String, RawLexicalEntry>> {
     let mut language_map: HashMap<String, RawLexicalEntry> = HashMap::new();
+    let mut errors: Vec<String> = Vec::new();
     for lang_file in files{
         let graph_raw = std::fs::read_to_string(lang_file.clone()).context(format!("error reading tree file {}", lang_file.display()))?;
-        let raw_graph: WordGraph = serde_json::from_str(&graph_raw).context(format!("error reading tree file {}", lang_file.display()))?;
+        let words: HashMap<String, RawLexicalEntry> = match map_from_str_with_env(&graph_raw, FileFormat::from_path(lang_file), "words", strict) {
+            Ok(words) => words,
+            Err(e) => {
+                errors.push(format!("error reading tree file {}:\n{}", lang_file.display(), indent(&format!("{:#}", e))));
+                continue;
+            }
+        };
         debug!("read in language file: {}", lang_file.display());
         // read in derivative words, convert them to "normal" words in the graph
-        for (lex_name, node) in &raw_graph.words{
-            if let Some(derivatives) = &node.derivatives {
-                debug!("Node {} has derivatives, adding", lex_name);
-                for (count, der) in derivatives.iter().enumerate() {
-                    let der_id = format!("{}-autoderive-{}", lex_name, count);
-                    let der_lex_raw = RawLexicalEntry{
-                        etymology: Some(Etymology { 
-                            etymons: vec![Edge{etymon: lex_name.to_string(), 
-                            transforms: der.transforms.clone(),
-                            agglutination_order: None}] }),
-                        historical_metadata: node.historical_metadata.clone(),
-                        ..der.lexis.clone()
-                    };
-                    language_map.insert(der_id, der_lex_raw);
-                }
-            }
-
+        for (lex_name, node) in &words{
+            add_derivatives(lex_name, node, &mut language_map)
+            .context(format!("error reading derivatives in tree file {}", lang_file.display()))?;
         }
-        for (key, lex) in raw_graph.words {
+        for (key, lex) in words {
             let found = language_map.insert(key.clone(), lex);
             if found.is_some() {
                 return Err(anyhow!("Error: Key '{}' found multiple times", key));
             }
         }
     }
+    if !errors.is_empty() {
+        return Err(anyhow!("{}", errors.join("\n")));
+    }
 
     Ok(language_map)
 }
 
-pub fn read_transform_files(files: &Vec<PathBuf>) -> Result<HashMap<String, RawTransform>> {
+/// Recursively flatten any derivatives of the given node into the language map.
+/// Derivatives without an explicit ID are keyed by appending to the ID of their parent, so IDs remain stable as long as the list order does.
+fn add_derivatives(lex_name: &str, node: &RawLexicalEntry, language_map: &mut HashMap<String, RawLexicalEntry>) -> Result<()> {
+    if let Some(derivatives) = &node.derivatives {
+        debug!("Node {} has derivatives, adding", lex_name);
+        for (count, der) in derivatives.iter().enumerate() {
+            let der_id = match &der.id {
+                Some(id) => id.clone(),
+                None => format!("{}-autoderive-{}", lex_name, count)
+            };
+            let mut der_lex_raw = RawLexicalEntry{
+                etymology: Some(Etymology { 
+                    etymons: vec![Edge{etymon: lex_name.to_string(), 
+                    transforms: der.transforms.clone(),
+                    agglutination_order: None, resolved_transforms: None, borrowed: None, semantic_shift: None, metadata: None}] }),
+                historical_metadata: node.historical_metadata.clone(),
+                ..der.lexis.clone()
+            };
+            add_derivatives(&der_id, &der_lex_raw, language_map)?;
+            der_lex_raw.derivatives = None;
+            if language_map.insert(der_id.clone(), der_lex_raw).is_some() {
+                return Err(anyhow!("Error: derivative key '{}' found multiple times", der_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// read and merge the transforms of every etymology file. Errors in every file are collected and reported together.
+/// If strict is set, unknown fields are reported as errors.
+pub fn read_transform_files(files: &Vec<PathBuf>, strict: bool) -> Result<HashMap<String, RawTransform>> {
     let mut transform_map: HashMap<String, RawTransform> = HashMap::new();
+    let mut errors: Vec<String> = Vec::new();
     for trans_file in files {
         let trans_raw = std::fs::read_to_string(trans_file.clone()).context(format!("error reading etymology file {}", trans_file.display()))?;
-        let transforms: TransformGraph = serde_json::from_str(&trans_raw).context(format!("error parsing etymology file {}", trans_file.display()))?;
-        debug!("read in transform file: {}", trans_file.display());
-        transform_map.extend(transforms.transforms);
+        match map_from_str_with_env(&trans_raw, FileFormat::from_path(trans_file), "transforms", strict) {
+            Ok(transforms) => {
+                debug!("read in transform file: {}", trans_file.display());
+                transform_map.extend(transforms);
+            },
+            Err(e) => errors.push(format!("error parsing etymology file {}:\n{}", trans_file.display(), indent(&format!("{:#}", e))))
+        }
     };
+    if !errors.is_empty() {
+        return Err(anyhow!("{}", errors.join("\n")));
+    }
 
     Ok(transform_map)
 }
 
+/// indent each line of an error message, to nest it under the file it came from
+fn indent(msg: &str) -> String {
+    msg.lines().map(|l| format!("    {}", l)).collect::<Vec<String>>().join("\n")
+}
+
 /// Add a single word entry to the tree, including any derivative words
 fn add_single_word(tree: &mut LanguageTree, trans_map: &HashMap<String, RawTransform>, 
     lex_map: &HashMap<String, RawLexicalEntry>, node_lex: &Lexis, lex_ety: &Option<Etymology>) -> Result<()> {
@@ -134,6 +330,11 @@ fn add_single_word(tree: &mut LanguageTree, trans_map: &HashMap<String, RawTrans
                 let ety_lex: RawLexicalEntry = lex_map.get(&e.etymon).context(format!("etymon {} does not exist ", &e.etymon))?.clone();
                 debug!("adding lex {} with etymon {}", node_lex.id, e.etymon);
                 tree.connect_etymology(node_lex.clone(), Lexis { id: e.etymon.clone(), ..ety_lex.into()}, word_transforms, e.agglutination_order);
+                if let Some(edge) = tree.edge_mut(&e.etymon, &node_lex.id) {
+                    edge.borrowed = e.borrowed.unwrap_or_default();
+                    edge.semantic_shift = e.semantic_shift.clone();
+                    edge.metadata = e.metadata.clone().unwrap_or_default();
+                }
             }
         } else {
             debug!("Adding lex {} without etymology", node_lex.id);
@@ -147,13 +348,14 @@ fn add_single_word(tree: &mut LanguageTree, trans_map: &HashMap<String, RawTrans
 }
 
 /// Generate phonology structures from files
-pub fn create_phonetics(paths: Vec<PathBuf>) -> Result<LexPhonology>{
+pub fn create_phonetics(paths: Vec<PathBuf>, strict: bool) -> Result<LexPhonology>{
     let mut phonetic_set = LexPhonology::default();
     for path in paths{
-        let raw = std::fs::read_to_string(&path)?;
-        let parsed: LexPhonology = serde_json::from_str(&raw)?;
+        let raw = std::fs::read_to_string(&path).context(format!("error reading phonetic file {}", path.display()))?;
+        let parsed: LexPhonology = from_str_with_env(&raw, FileFormat::from_path(&path), strict).context(format!("error parsing phonetic file {}", path.display()))?;
         phonetic_set.groups.extend(parsed.groups);
         phonetic_set.lexis_types.extend(parsed.lexis_types);
+        phonetic_set.fallbacks.extend(parsed.fallbacks);
     }
 
     Ok(phonetic_set)
@@ -177,38 +379,39 @@ pub fn handle_directory(path: &str) -> Result<Project> {
     let lang_graph_dir = lang_dir.join("tree");
     let lang_transform_dir = lang_dir.join("etymology");
     let phonetics_path = lang_dir.join("phonetics");
-    let globals_file = lang_dir.join("globals.json");
 
     debug!("using tree path: {}", lang_graph_dir.display());
-    let  graphs: Vec<PathBuf> = read_subdir_create_list(lang_graph_dir)?;
+    let  graphs: Vec<PathBuf> = read_subdir_create_list(lang_graph_dir, PROJECT_FORMATS)?;
 
     debug!("using etymology path: {}", lang_transform_dir.display());
-    let  transforms: Vec<PathBuf> = read_subdir_create_list(lang_transform_dir)?;
+    // a project without any transforms doesn't need an etymology directory
+    let transforms: Vec<PathBuf> = if lang_transform_dir.exists() {
+        read_subdir_create_list(lang_transform_dir, TRANSFORM_FORMATS)?
+    } else {
+        Vec::new()
+    };
 
     debug!("using phonetics path: {}", phonetics_path.display());
     
     let phonetic_rules: Option<Vec<PathBuf>> = if phonetics_path.exists(){
-        Some(read_subdir_create_list(phonetics_path)?)
+        Some(read_subdir_create_list(phonetics_path, PROJECT_FORMATS)?)
     } else {
         None
     };
 
-    let global_trans: Option<PathBuf> = if globals_file.exists() {
-        Some(globals_file)
-    } else {
-        None
-    };
-    
+    let global_trans: Option<PathBuf> = find_project_file(lang_dir, "globals");
+    let taxonomy: Option<PathBuf> = find_project_file(lang_dir, "taxonomy");
 
     Ok(Project { graphs, 
         transforms, 
         phonetic_rules,
-        globals: global_trans})
+        globals: global_trans,
+        taxonomy})
 }
 
-fn read_subdir_create_list(path: PathBuf) -> Result<Vec<PathBuf>>{
+pub fn read_subdir_create_list(path: PathBuf, formats: &[FileFormat]) -> Result<Vec<PathBuf>>{
     let mut paths: Vec<PathBuf> = Vec::new();
-    for entry in WalkDir::new(path).into_iter().filter_entry(check_path){
+    for entry in WalkDir::new(path).into_iter().filter_entry(|e| check_path(e, formats)){
         let found_path = entry?.path().to_path_buf();
         if !found_path.is_dir(){
             paths.push(found_path);
@@ -218,13 +421,13 @@ fn read_subdir_create_list(path: PathBuf) -> Result<Vec<PathBuf>>{
     Ok(paths)
 }
 
-/// check if the path is a valid file we want to read
-fn check_path(dir: &DirEntry) -> bool {
+/// check if the path is a valid file we want to read, in one of the given formats
+fn check_path(dir: &DirEntry, formats: &[FileFormat]) -> bool {
     debug!("checking path: {:?}", dir);
     if dir.file_type().is_dir(){
         true
     } else  {
-        dir.path().extension().unwrap_or_default() == "json"
+        FileFormat::from_extension(dir.path()).is_some_and(|f| formats.contains(&f))
     }
     
 }
@@ -232,44 +435,117 @@ fn check_path(dir: &DirEntry) -> bool {
 /// read in the existing files and generate a graph
 /// deals with the logic of listed files versus a specified directory
 pub fn read_and_compute(directory: Option<String>) -> Result<LanguageTree>{
-    let new_project: Project = if directory.is_some(){
-        handle_directory(&directory.unwrap())?
+    let new_project: Project = if let Some(dir) = directory {
+        handle_directory(&dir)?
     } else {
         return Err(anyhow!("must specify either a graph and transform file, or a directory"));
     }; 
     info!("Reading in existing language files...");
-    let mut lang_tree = read_from_files(new_project)?;
+    let mut lang_tree = read_from_files(new_project.clone())?;
     info!("rendering tree...");
-    lang_tree.compute_lexicon()?;
+    lang_tree.on_progress(progress::bar("computing words"));
+    lang_tree.compute_lexicon().map_err(|e| transform_error_context(e, &new_project))?;
     Ok(lang_tree)
 }
 
+/// Like read_and_compute(), but without showing progress, as a snapshot for commands that recompute the project while they run.
+pub fn read_lexicon(directory: &str) -> Result<ComputedLexicon> {
+    let project = handle_directory(directory)?;
+    let tree = read_from_files(project.clone())?;
+    ComputedLexicon::new(tree).map_err(|e| transform_error_context(e, &project))
+}
+
+/// Add the project files that define the word and transform named in a TransformError,
+/// so a failing transform in a large project can be found without searching the tree by hand.
+pub fn transform_error_context(err: TransformError, project: &Project) -> anyhow::Error {
+    let mut found = Vec::new();
+    if let TransformError::Transform { lexis, transform, .. } = &err {
+        if let Some(path) = project.graphs.iter().find(|path| file_defines(path, "words", lexis)) {
+            found.push(format!("'{}' is in {}", lexis, path.display()));
+        }
+        if transform.starts_with("global transform") {
+            if let Some(path) = &project.globals {
+                found.push(format!("global transforms are in {}", path.display()));
+            }
+        } else if let Some(path) = project.transforms.iter().find(|path| file_defines(path, "transforms", transform)) {
+            found.push(format!("'{}' is in {}", transform, path.display()));
+        }
+    }
+    if found.is_empty() {
+        anyhow::Error::new(err).context("error computing words")
+    } else {
+        anyhow::Error::new(err).context(format!("error computing words ({})", found.join(", ")))
+    }
+}
+
+/// check if the object under key in a project file has an entry with the given ID.
+/// Words defined as derivatives inside another word are found by their ID, or by the word they were automatically derived from.
+fn file_defines(path: &Path, key: &str, id: &str) -> bool {
+    let value = match std::fs::read_to_string(path).ok().and_then(|raw| FileFormat::from_path(path).parse_value(&raw).ok()) {
+        Some(value) => value,
+        None => return false
+    };
+    let entries = match value.get(key).and_then(serde_json::Value::as_object) {
+        Some(entries) => entries,
+        None => return false
+    };
+    let parent = id.split("-autoderive-").next().unwrap_or(id);
+    entries.contains_key(id) || entries.contains_key(parent) || entries.values().any(|entry| defines_derivative(entry, id))
+}
+
+fn defines_derivative(entry: &serde_json::Value, id: &str) -> bool {
+    entry.get("derivatives").and_then(serde_json::Value::as_array).into_iter().flatten()
+    .any(|der| der.get("id").and_then(serde_json::Value::as_str) == Some(id) || der.get("lexis").is_some_and(|lex| defines_derivative(lex, id)))
+}
+
+/// read the globals file from the project directory, returning the default settings if none exists
+pub fn read_project_globals(directory: Option<String>) -> Result<Global> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    match handle_directory(&dir)?.globals {
+        Some(path) => read_globals(&path),
+        None => Ok(Global::default())
+    }
+}
+
+/// read the taxonomy file from the project directory, if one exists
+pub fn read_taxonomy(directory: Option<String>) -> Result<Option<Taxonomy>> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    match handle_directory(&dir)?.taxonomy {
+        Some(path) => {
+            let raw = std::fs::read_to_string(&path).context(format!("error reading taxonomy file {}", path.display()))?;
+            let taxonomy: Taxonomy = from_str_with_env(&raw, FileFormat::from_path(&path), false).context(format!("error parsing taxonomy file {}", path.display()))?;
+            Ok(Some(taxonomy))
+        },
+        None => Ok(None)
+    }
+}
+
 /// add a tree file to the existing directory
-pub fn add_tree_file<P: AsRef<Path>>(path: P, name: &str, data: WordGraph) -> Result<()> {
+pub fn add_tree_file<P: AsRef<Path>>(tx: &mut WriteTransaction, path: P, name: &str, data: WordGraph) -> Result<()> {
     let write_to = Path::new(path.as_ref()).join("tree").join(name);
-    add_file(&write_to, data).context(format!("error adding file {} to project", write_to.display()))?;
+    add_file(tx, &write_to, data).context(format!("error adding file {} to project", write_to.display()))?;
     Ok(())
 }
 
-pub fn add_ety_file<P: AsRef<Path>>(path: P, name: &str, data: TransformGraph) -> Result<()> {
-    let write_to = Path::new(path.as_ref()).join("etymology").join(name);
-    add_file(&write_to, data).context(format!("error adding file {} to project", write_to.display()))?;
+pub fn add_ety_file<P: AsRef<Path>>(tx: &mut WriteTransaction, path: P, name: &str, data: TransformGraph) -> Result<()> {
+    let ety_dir = Path::new(path.as_ref()).join("etymology");
+    tx.create_dir_all(&ety_dir)?;
+    let write_to = ety_dir.join(name);
+    add_file(tx, &write_to, data).context(format!("error adding file {} to project", write_to.display()))?;
     Ok(())
 }
 
-/// add a file to the project at the specified path
-pub fn add_file<P, S>(path: P, data: S) -> Result<()>
+/// add a file to the project at the specified path, as part of a write transaction
+pub fn add_file<P, S>(tx: &mut WriteTransaction, path: P, data: S) -> Result<()>
 where
     P: AsRef<Path> + std::fmt::Debug,
     S: Sized + Serialize
     {
-        let mut file = File::create(&path)
-        .context(format!("error creating file {:?}", path))?;
-    
         let graph_data = serde_json::to_string_pretty(&data)
                 .context("error creating JSON from graph")?;
     
-        write!(file, "{}", graph_data)?;
+        tx.write(&path, graph_data)
+        .context(format!("error creating file {:?}", path))?;
         Ok(())
 }
 
@@ -278,11 +554,14 @@ mod tests {
     use std::collections::HashMap;
 
     use anyhow::Result;
-    use libkirum::{kirum::Lexis, lexcreate::LexPhonology};
+    use libkirum::{kirum::Lexis, lexcreate::LexPhonology, word::{TransformLayer, EditorialStatus}};
 
     use crate::files::read_and_compute;
+    use crate::test_util::{copy_project, copy_project_with_rules};
+    use std::path::Path;
 
-    use super::apply_def_vars;
+    use super::{apply_def_vars, apply_gloss_lang, apply_orthography, apply_drafts};
+    use crate::entries::{create_json_graph, WordGraph};
 
     #[test]
     fn test_phonetic_ingest() -> Result<()>{
@@ -297,7 +576,8 @@ mod tests {
             ]),
             lexis_types: HashMap::from([
                 ("word".into(), vec!["S".try_into()?, "SuS".try_into()?, "iSSS".try_into()?, "SSSS".try_into()?])
-            ])
+            ]),
+            fallbacks: HashMap::new()
         };
 
         assert_eq!(example, parsed);
@@ -316,6 +596,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_constrained_create() -> Result<()> {
+        let directory = copy_project_with_rules("constrained_generate", "kirum_constrained_generate")?;
+        for _ in 0..10 {
+            let computed = read_and_compute(Some(directory.clone()))?;
+            let word = computed.get_by_id("constrained").unwrap().word.unwrap();
+            let raw = word.string_without_sep();
+            assert!(raw.contains('x'));
+            assert!(!raw.contains("ee") && !raw.contains("aa"));
+            assert!(word.len() >= 8);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_ingest_with_derivatives() -> Result<()> {
         let directory = Some(String::from("src/test_files/test_der"));
@@ -326,6 +620,254 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_nested_derivatives() -> Result<()> {
+        let directory = Some(String::from("src/test_files/nested_der"));
+        let computed = read_and_compute(directory)?;
+
+        let second = computed.get_by_id("root_word-autoderive-0-autoderive-0").unwrap();
+        assert_eq!(second.word.unwrap().string_without_sep(), "kirumaiu");
+        let third = computed.get_by_id("root_word-autoderive-0-autoderive-0-autoderive-0").unwrap();
+        assert_eq!(third.word.unwrap().string_without_sep(), "kirumaiuo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_derivatives() -> Result<()> {
+        let directory = Some(String::from("src/test_files/named_der"));
+        let computed = read_and_compute(directory)?;
+
+        let named = computed.get_by_id("gardener").unwrap();
+        assert_eq!(named.word.unwrap().string_without_sep(), "kirumu");
+        // other words can use a named derivative as an etymon
+        let downstream = computed.get_by_id("gardeners").unwrap();
+        assert_eq!(downstream.word.unwrap().string_without_sep(), "kirumuo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sandhi_from_globals() -> Result<()> {
+        let directory = Some(String::from("src/test_files/sandhi"));
+        let computed = read_and_compute(directory)?;
+
+        let joined = computed.get_by_id("compound").unwrap();
+        assert_eq!(joined.word.unwrap().string_without_sep(), "kiramo");
+
+        let sorted: Vec<String> = computed.to_vec().into_iter().map(|l| l.id).collect();
+        assert_eq!(sorted, vec!["first".to_string(), "compound".to_string(), "second".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_settings() -> Result<()> {
+        let directory = Some(String::from("src/test_files/global_settings"));
+        let computed = read_and_compute(directory)?;
+
+        let joined = computed.get_by_id("compound").unwrap();
+        assert_eq!(joined.word.unwrap().string_without_sep(), "kira-amo");
+        assert_eq!(joined.language, "Old Exemplum");
+        assert!(joined.historical_metadata.is_empty());
+
+        let sorted: Vec<String> = computed.to_vec().into_iter().map(|l| l.id).collect();
+        assert_eq!(sorted, vec!["second".to_string(), "first".to_string(), "compound".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_language_inheritance() -> Result<()> {
+        let directory = Some(String::from("src/test_files/language_inheritance"));
+        let computed = read_and_compute(directory)?;
+
+        assert_eq!(computed.get_by_id("root").unwrap().language, "Old Exemplum");
+        assert_eq!(computed.get_by_id("derived").unwrap().language, "New Exemplum");
+        assert_eq!(computed.get_by_id("explicit").unwrap().language, "Latin");
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_transforms() -> Result<()> {
+        let directory = Some(String::from("src/test_files/global_transforms"));
+        let computed = read_and_compute(directory)?;
+
+        let derived = computed.get_by_id("derived").unwrap();
+        assert_eq!(derived.word.unwrap().string_without_sep(), "kirū");
+        let root = computed.get_by_id("root").unwrap();
+        assert_eq!(root.word.unwrap().string_without_sep(), "kirau");
+        Ok(())
+    }
+
+    #[test]
+    fn test_language_pair_transforms() -> Result<()> {
+        let directory = Some(String::from("src/test_files/language_pairs"));
+        let computed = read_and_compute(directory)?;
+
+        // the language pair transform takes precedence over the global transform
+        let derived = computed.get_by_id("derived").unwrap();
+        assert_eq!(derived.word.unwrap().string_without_sep(), "kirū");
+        let applied: Vec<(TransformLayer, String)> = computed.applied_rules("derived").into_iter().map(|r| (r.layer, r.name)).collect();
+        assert_eq!(applied, vec![(TransformLayer::LanguagePair, "vowel-shift".to_string())]);
+        // the edge transform takes precedence over both
+        let loaned = computed.get_by_id("loaned").unwrap();
+        assert_eq!(loaned.word.unwrap().string_without_sep(), "cirau");
+        assert_eq!(computed.applied_rules("loaned")[0].layer, TransformLayer::Edge);
+        // no language pair for Latin, so the global transform applies
+        let borrowed = computed.get_by_id("borrowed").unwrap();
+        assert_eq!(borrowed.word.unwrap().string_without_sep(), "hortusa");
+        assert_eq!(computed.applied_rules("borrowed")[0].layer, TransformLayer::Global);
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_project() -> Result<()> {
+        let directory = Some(String::from("src/test_files/yaml"));
+        let computed = read_and_compute(directory)?;
+
+        let derived = computed.get_by_id("derived").unwrap();
+        assert_eq!(derived.word.unwrap().string_without_sep(), "kirauki");
+        assert_eq!(derived.language, "Old Exemplum");
+        let generated = computed.get_by_id("generated").unwrap();
+        assert_eq!(generated.word.unwrap().string_without_sep().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_transforms() -> Result<()> {
+        let directory = Some(String::from("src/test_files/toml_transforms"));
+        let computed = read_and_compute(directory)?;
+
+        let derived = computed.get_by_id("derived").unwrap();
+        assert_eq!(derived.word.unwrap().string_without_sep(), "kirūin");
+        // the conditional only matches nouns and verbs
+        let greenish = computed.get_by_id("greenish").unwrap();
+        assert_eq!(greenish.word.unwrap().string_without_sep(), "verdi");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_global_transforms() {
+        let directory = Some(String::from("src/test_files/bad_globals"));
+        let res = read_and_compute(directory);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_drafts() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/drafts")))?;
+        // draft words are still used to derive other words
+        assert_eq!(computed.get_by_id("gardener").unwrap().word, Some("amo".into()));
+
+        let mut rendered = computed.to_vec();
+        apply_drafts(false, &mut rendered);
+        assert_eq!(rendered.len(), 2);
+        assert!(rendered.iter().all(|l| l.id != "keeper" && l.notes.is_none()));
+
+        let mut drafts = computed.to_vec();
+        apply_drafts(true, &mut drafts);
+        assert_eq!(drafts.len(), 3);
+        let keeper = drafts.iter().find(|l| l.id == "keeper").unwrap();
+        assert_eq!(keeper.status, Some(EditorialStatus::Draft));
+        assert_eq!(keeper.notes, Some("maybe too close to Latin?".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gloss_lang() -> Result<()> {
+        let directory = Some(String::from("src/test_files/glosses"));
+        let computed = read_and_compute(directory)?;
+
+        let mut german = computed.to_vec();
+        apply_gloss_lang(Some("de".to_string()), &mut german);
+        let defs: Vec<String> = german.into_iter().map(|l| format!("{}: {}", l.id, l.definition)).collect();
+        assert!(defs.contains(&"garden: Garten".to_string()));
+        assert!(defs.contains(&"keeper: keeper".to_string()));
+
+        let mut french = computed.to_vec();
+        apply_gloss_lang(Some("fr".to_string()), &mut french);
+        let defs: Vec<String> = french.into_iter().map(|l| format!("{}: {}", l.id, l.definition)).collect();
+        assert!(defs.contains(&"garden: Garten".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_orthography() -> Result<()> {
+        let directory = Some(String::from("src/test_files/orthography"));
+        let computed = read_and_compute(directory)?;
+
+        let compound = computed.get_by_id("compound").unwrap();
+        assert_eq!(compound.orthographies.get("cyrillic"), Some(&"кирачмо".to_string()));
+        assert_eq!(compound.orthographies.get("runes"), Some(&"ᚲᛁᚱᚨᚲᚺᛗᛟ".to_string()));
+
+        let mut cyrillic = computed.to_vec();
+        apply_orthography(Some("cyrillic".to_string()), &mut cyrillic);
+        let words: Vec<String> = cyrillic.into_iter().map(|l| format!("{}: {}", l.id, l.word.unwrap().string_without_sep())).collect();
+        assert!(words.contains(&"compound: кирачмо".to_string()));
+        // no orthographies defined for this language
+        assert!(words.contains(&"loan: tapu".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ipa() -> Result<()> {
+        let directory = Some(String::from("src/test_files/orthography"));
+        let computed = read_and_compute(directory)?;
+
+        assert_eq!(computed.get_by_id("compound").unwrap().ipa, Some("kiɾat͡ʃmo".to_string()));
+        assert_eq!(computed.get_by_id("loan").unwrap().ipa, None);
+
+        let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, false)?;
+        assert_eq!(graph.words["first"].ipa, Some("kiɾa".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_examples() -> Result<()> {
+        let directory = Some(String::from("src/test_files/examples"));
+        let computed = read_and_compute(directory)?;
+
+        let garden = computed.get_by_id("garden").unwrap();
+        assert_eq!(garden.examples.len(), 2);
+        assert_eq!(garden.examples[0].translation, Some("the gardener".to_string()));
+
+        let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, false)?;
+        assert_eq!(graph.words["garden"].examples, Some(garden.examples));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_descendants() -> Result<()> {
+        let directory = Some(String::from("src/test_files/nested_der"));
+        let computed = read_and_compute(directory)?;
+
+        let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, true, false)?;
+        assert_eq!(graph.words["root_word"].descendants, Some(vec!["root_word-autoderive-0".to_string()]));
+        assert_eq!(graph.words["root_word-autoderive-0-autoderive-0-autoderive-0"].descendants, None);
+
+        let without = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, false)?;
+        assert_eq!(without.words["root_word"].descendants, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_transforms() -> Result<()> {
+        let directory = Some(String::from("src/test_files/test_der"));
+        let computed = read_and_compute(directory)?;
+
+        let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, true)?;
+        let edge = &graph.words["latin_example"].etymology.as_ref().unwrap().etymons[0];
+        let resolved = edge.resolved_transforms.as_ref().unwrap();
+        assert_eq!(resolved[0].name, "latin-from-verb");
+        assert_eq!(resolved[0].transforms.len(), 2);
+        // the resolved transforms can be read back in, along with the rest of the JSON output
+        let rendered = serde_json::to_string(&graph)?;
+        let parsed: WordGraph = serde_json::from_str(&rendered)?;
+        assert_eq!(&parsed.words["latin_example"].etymology.as_ref().unwrap().etymons[0], edge);
+
+        let without = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, false)?;
+        assert!(without.words["latin_example"].etymology.as_ref().unwrap().etymons[0].resolved_transforms.is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_def_templates() -> Result<()> {
         let vars = Some(String::from("src/test_files/test_tmpl_vars.toml"));
@@ -344,6 +886,60 @@ mod tests {
         let directory = Some(String::from("src/test_files/repeated_keys"));
         let res = read_and_compute(directory);
 
-        assert_eq!(true, res.is_err());
+        assert!(res.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_json_graph_is_sorted() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/test_der")))?;
+        let render = || -> Result<String> {
+            let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, true, true, false)?;
+            Ok(serde_json::to_string_pretty(&graph)?)
+        };
+        let first = render()?;
+        assert_eq!(first, render()?);
+
+        let ids: Vec<usize> = ["daughter-gen-hsshmplh", "latin_example", "latin_example-autoderive-0", "latin_verb"].iter()
+            .map(|id| first.find(&format!("\"{}\": {{", id)).unwrap()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_edge_metadata() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/edge_metadata")))?;
+        assert_eq!(computed.edge("water", "river").unwrap().metadata["date"], "c. 900");
+
+        // the metadata is written back out in the same form, so it round-trips through rendered tree files
+        let graph = create_json_graph(computed.to_vec_etymons(|_| true), |l| l.id, false, false, false)?;
+        let rendered = serde_json::to_string_pretty(&graph)?;
+        let value: serde_json::Value = serde_json::from_str(&rendered)?;
+        let metadata = &value["words"]["river"]["etymology"]["etymons"][0]["metadata"];
+        assert_eq!(metadata["certainty"], "likely");
+        assert_eq!(metadata["source"], "Exemplum Glosses, f. 12");
+        assert!(rendered.find("\"certainty\"").unwrap() < rendered.find("\"date\"").unwrap());
+        let reread: WordGraph = serde_json::from_str(&rendered)?;
+        assert_eq!(reread.words["river"].etymology.as_ref().unwrap().etymons[0].metadata.as_ref().unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform_error_context() -> Result<()> {
+        let dir_name = copy_project("test_der", "kirum_test_transform_error")?;
+        let dir = Path::new(&dir_name);
+        let ety_path = dir.join("etymology/ety.json");
+        let mut ety: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&ety_path)?)?;
+        ety["transforms"]["latin-from-verb"]["transforms"].as_array_mut().unwrap()
+            .push(serde_json::json!({"rhai_script": {"file": "missing.rhai"}}));
+        std::fs::write(&ety_path, ety.to_string())?;
+
+        let err = read_and_compute(Some(dir_name.clone())).err().unwrap();
+        let msg = format!("{:#}", err);
+        assert!(msg.starts_with(&format!("error computing words ('latin_example' is in {}, 'latin-from-verb' is in {})", 
+            dir.join("tree/test_der.json").display(), ety_path.display())), "{}", msg);
+        assert!(msg.contains("error applying transforms[2] of 'latin-from-verb' to 'latin_example' from 'latin_verb'"), "{}", msg);
+        assert!(msg.contains("error evaluating Rhai script missing.rhai"), "{}", msg);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context, anyhow};
+use crate::{files::handle_directory, format::FileFormat, transaction::WriteTransaction};
+
+/// Rewrite the tree, etymology and phonetics files of a project in a canonical format:
+/// keys in sorted order, including word IDs and transform names, the default indentation of each file format, and a trailing newline.
+/// Returns the files that were changed. If check is set, nothing is written, and an error listing every file that isn't formatted is returned instead.
+pub fn format_project(directory: &str, check: bool) -> Result<Vec<PathBuf>> {
+    let project = handle_directory(directory)?;
+    let mut files = project.graphs;
+    files.extend(project.transforms);
+    files.extend(project.phonetic_rules.unwrap_or_default());
+
+    let mut tx = WriteTransaction::new();
+    let mut changed = Vec::new();
+    for path in files {
+        if let Some(formatted) = format_file(&path)? {
+            if !check {
+                tx.write(&path, formatted).context(format!("error writing {}", path.display()))?;
+                debug!("formatted {}", path.display());
+            }
+            changed.push(path);
+        }
+    }
+    tx.commit();
+    if check && !changed.is_empty() {
+        let names: Vec<String> = changed.iter().map(|p| format!("  {}", p.display())).collect();
+        return Err(anyhow!("{} files are not formatted:\n{}", changed.len(), names.join("\n")));
+    }
+    Ok(changed)
+}
+
+/// format a single file, returning the new contents if they differ from the file
+fn format_file(path: &Path) -> Result<Option<String>> {
+    let format = FileFormat::from_path(path);
+    let raw = std::fs::read_to_string(path).context(format!("error reading {}", path.display()))?;
+    let mut parsed = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+    parsed.sort_all_objects();
+    let mut formatted = format.write_value(&parsed).context(format!("error creating {:?} data", format))?;
+    if !formatted.ends_with('\n') {
+        formatted.push('\n');
+    }
+    Ok(if formatted == raw {None} else {Some(formatted)})
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use super::format_project;
+
+
+    #[test]
+    fn test_format_project() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_fmt")?);
+        let words = dir.join("tree/words.json");
+        let before = std::fs::read_to_string(&words)?;
+
+        assert!(format_project(dir.to_str().unwrap(), true).is_err());
+        assert_eq!(std::fs::read_to_string(&words)?, before);
+
+        let changed = format_project(dir.to_str().unwrap(), false)?;
+        assert_eq!(changed.len(), 3);
+        let formatted = std::fs::read_to_string(&words)?;
+        assert!(formatted.find("\"borrowed\"").unwrap() < formatted.find("\"generated\"").unwrap());
+        assert!(formatted.find("\"generated\"").unwrap() < formatted.find("\"root\"").unwrap());
+        assert!(formatted.ends_with("}\n"));
+
+        assert!(format_project(dir.to_str().unwrap(), false)?.is_empty());
+        assert!(format_project(dir.to_str().unwrap(), true)?.is_empty());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+use anyhow::Result;
+use serde_json::Value;
+
+/// The formats that project files can be written in, detected by file extension
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Yaml,
+    Toml
+}
+
+/// The formats accepted for tree, phonetics, globals and taxonomy files
+pub const PROJECT_FORMATS: &[FileFormat] = &[FileFormat::Json, FileFormat::Yaml];
+
+/// The formats accepted for etymology files. Transforms are flat enough to be written as TOML tables.
+pub const TRANSFORM_FORMATS: &[FileFormat] = &[FileFormat::Json, FileFormat::Yaml, FileFormat::Toml];
+
+impl FileFormat {
+    /// Detect the format of a project file from its extension, or None if the extension isn't a supported format
+    pub fn from_extension(path: &Path) -> Option<FileFormat> {
+        match path.extension()?.to_str()? {
+            "json" => Some(FileFormat::Json),
+            "yaml" | "yml" => Some(FileFormat::Yaml),
+            "toml" => Some(FileFormat::Toml),
+            _ => None
+        }
+    }
+
+    /// Detect the format of a project file from its extension, falling back to JSON
+    pub fn from_path(path: &Path) -> FileFormat {
+        FileFormat::from_extension(path).unwrap_or(FileFormat::Json)
+    }
+
+    /// parse a file in this format into a generic JSON value
+    pub fn parse_value(&self, raw: &str) -> Result<Value> {
+        let parsed = match self {
+            FileFormat::Json => serde_json::from_str(raw)?,
+            FileFormat::Yaml => serde_yaml::from_str(raw)?,
+            FileFormat::Toml => toml::from_str(raw)?
+        };
+        Ok(parsed)
+    }
+
+    /// write a value back out in this format
+    pub fn write_value(&self, value: &Value) -> Result<String> {
+        let written = match self {
+            FileFormat::Json => serde_json::to_string_pretty(value)?,
+            FileFormat::Yaml => serde_yaml::to_string(value)?,
+            FileFormat::Toml => toml::to_string_pretty(value)?
+        };
+        Ok(written)
+    }
+}
+
+/// find a project file with the given name, such as `globals`, in any of the supported formats
+pub fn find_project_file(dir: &Path, name: &str) -> Option<std::path::PathBuf> {
+    ["json", "yaml", "yml"].iter().map(|ext| dir.join(name).with_extension(ext)).find(|p| p.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use serde_json::json;
+    use super::FileFormat;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(FileFormat::from_extension(Path::new("tree/words.json")), Some(FileFormat::Json));
+        assert_eq!(FileFormat::from_extension(Path::new("tree/words.yml")), Some(FileFormat::Yaml));
+        assert_eq!(FileFormat::from_extension(Path::new("tree/words.yaml")), Some(FileFormat::Yaml));
+        assert_eq!(FileFormat::from_extension(Path::new("etymology/ety.toml")), Some(FileFormat::Toml));
+        assert_eq!(FileFormat::from_extension(Path::new("tree/notes.txt")), None);
+        assert_eq!(FileFormat::from_path(Path::new("tree/words")), FileFormat::Json);
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let raw = "words:\n  test:\n    word: kira\n    tags:\n    - root\n    archaic: true\n";
+        let parsed = FileFormat::Yaml.parse_value(raw).unwrap();
+        assert_eq!(parsed, json!({"words": {"test": {"word": "kira", "tags": ["root"], "archaic": true}}}));
+        let written = FileFormat::Yaml.write_value(&parsed).unwrap();
+        assert_eq!(FileFormat::Yaml.parse_value(&written).unwrap(), parsed);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let raw = "[transforms.of-from-latin]\ntransforms = [{match_replace = {old = \"um\", new = \"e\"}}]\n";
+        let parsed = FileFormat::Toml.parse_value(raw).unwrap();
+        assert_eq!(parsed, json!({"transforms": {"of-from-latin": {"transforms": [{"match_replace": {"old": "um", "new": "e"}}]}}}));
+        let written = FileFormat::Toml.write_value(&parsed).unwrap();
+        assert_eq!(FileFormat::Toml.parse_value(&written).unwrap(), parsed);
+    }
+}
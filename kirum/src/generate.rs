@@ -1,18 +1,24 @@
-use std::{fs::File, io::Write, collections::HashMap, path::PathBuf};
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
 use anyhow::{Result, Context, anyhow};
 use libkirum::{transforms::Transform, kirum::Lexis, word::Etymology};
-use crate::{files::read_and_compute, entries, cli::SeparateValues};
+use crate::{files::{read_and_compute, handle_directory, read_transform_files, add_ety_file}, concepts::slug, search, entries, progress, cli::SeparateValues, transaction::WriteTransaction};
 
-/// Create a daughter language from the specified language files
+/// Create a daughter language from the specified language files.
+/// Daughter words are created from every word in the ancestor language that matches the search expression, if either is set.
+/// If output is None, the daughter words are added to the tree directory of the project.
 pub fn daughter(daughter_ety: String, 
-    ancestor: String, 
+    ancestor: Option<String>, 
+    selection: Option<String>, 
     lang_name: String, 
     directory: Option<String>, 
-    output: String, 
+    output: Option<String>, 
     by_field: Option<SeparateValues>) -> Result<String> {
         // setup, read files, etc
-        let mut computed = read_and_compute(directory)
+        let condition = selection.as_deref().map(search::parse).transpose()
+        .context("error parsing search expression for ancestor words")?;
+        let mut computed = read_and_compute(directory.clone())
         .context("error reading existing graph and transforms")?;
+        let existing: HashSet<String> = computed.iter().map(|l| l.id.clone()).collect();
 
         let trans_raw = std::fs::read_to_string(daughter_ety.clone())
         .context(format!("error reading daughter transformation file {}", daughter_ety))?;
@@ -20,34 +26,51 @@ pub fn daughter(daughter_ety: String,
         let daughter_transform_map: entries::TransformGraph = serde_json::from_str(&trans_raw)
         .context("error parsing daughter transformations")?;
 
-        let processed_transforms: Vec<Transform> = daughter_transform_map.transforms.into_iter()
+        // apply transforms in order of name, so the generated words are the same on every run
+        let mut processed_transforms: Vec<Transform> = daughter_transform_map.transforms.into_iter()
         .map(|(n, t)| Transform{name: n, ..t.into()}).collect();
+        processed_transforms.sort_by(|a, b| a.name.cmp(&b.name));
 
         // actually start creating language
-        debug!("Creating daughter language '{}' from '{}'", lang_name, ancestor);
+        debug!("Creating daughter language '{}' from '{}'", lang_name, ancestor.as_deref().unwrap_or("any language"));
+        computed.on_progress(progress::bar("generating daughter words"));
         computed.generate_daughter_language(lang_name.clone(), 
-        processed_transforms, |l| l.language == ancestor, 
-        |l| Lexis { id: format!("daughter-from-{}", l.id), tags: [l.tags.clone(), ["autogenerated".to_string()].to_vec()].concat(), ..l.clone()})?;
+        processed_transforms, |l| ancestor.as_ref().is_none_or(|a| l.language == *a) && condition.as_ref().is_none_or(|c| c.matches(l)), 
+        // editorial notes, status and reconstruction belong to the ancestor word, not the generated one
+        |l| Lexis { id: format!("daughter-from-{}", l.id), tags: [l.tags.clone(), ["autogenerated".to_string()].to_vec()].concat(),
+            notes: None, status: None, reconstructed: false, ..l.clone()})?;
 
         let rendered_dict = computed.to_vec_etymons(|word|word.language == lang_name);
 
         debug!("grouping output files by: {:?}", by_field);
+        // if any file can't be written, remove the files that were already written
+        let mut tx = WriteTransaction::new();
+        let output = match output {
+            Some(output) => {
+                if by_field.is_none() {
+                    // in cases where there's no grouping, make sure we have the expected file
+                    let out_path: PathBuf = output.clone().into();
+                    if !out_path.is_file() {
+                        return Err(anyhow!("File {} does not have an extension. Did you mean to set group_by?", out_path.display()))
+                    }
+                }
+                output
+            },
+            None => {
+                let dir = directory.as_ref().ok_or(anyhow!("must specify a project directory to generate a daughter language in place"))?;
+                add_to_project(&mut tx, dir, &lang_name, &daughter_ety, &existing, &rendered_dict, by_field.is_some())?
+            }
+        };
         // write files
         let file_map = group_by(by_field, rendered_dict, output.clone());
 
         if by_field.is_some() {
             debug!("creating root directory at {}", &output);
-            std::fs::create_dir_all(&output)?;
-        } else {
-            // in cases where there's no grouping, make sure we have the expected file
-            let out_path: PathBuf = output.clone().into();
-            if !out_path.is_file() {
-                return Err(anyhow!("File {} does not have an extension. Did you mean to set group_by?", out_path.display()))
-            }
+            tx.create_dir_all(&output)?;
         }
 
         for (fname, data) in file_map {
-            let graph = entries::create_json_graph(data, |l| l.id, false)
+            let graph = entries::create_json_graph(data, |l| l.id, false, false, false)
             .context("error creating map from new language data")?;
 
             let graph_data = serde_json::to_string_pretty(&graph)
@@ -62,16 +85,50 @@ pub fn daughter(daughter_ety: String,
             }
 
             debug!("Creating daughter language file {}", &out_path.display());
-            let mut file = File::create(&out_path)
+            tx.write(&out_path, graph_data)
             .context(format!("error creating file {}", out_path.display()))?;
 
-            write!(file, "{}", graph_data)?;
-
         }
+        tx.commit();
 
         Ok(String::new())
     }
 
+/// Prepare to write a daughter language into the tree directory of the project, returning the path to write the words to:
+/// a file named after the language, or a directory if the words are grouped.
+/// If the daughter transforms aren't part of the project, they're copied into the etymology directory,
+/// so the etymology of the new words can be computed along with the rest of the project.
+fn add_to_project(tx: &mut WriteTransaction, directory: &str, lang_name: &str, daughter_ety: &str, 
+    existing: &HashSet<String>, words: &[(Lexis, Etymology)], grouped: bool) -> Result<String> {
+    let project = handle_directory(directory)?;
+    let file_name = slug(lang_name);
+    let out_path = Path::new(directory).join("tree").join(if grouped {file_name.clone()} else {format!("{}.json", file_name)});
+    if out_path.exists() {
+        return Err(anyhow!("{} already exists", out_path.display()));
+    }
+    if let Some((word, _)) = words.iter().find(|(w, _)| existing.contains(&w.id)) {
+        return Err(anyhow!("a word with ID '{}' already exists in the project", word.id));
+    }
+
+    let ety_path = Path::new(daughter_ety).canonicalize().context(format!("error reading daughter transformation file {}", daughter_ety))?;
+    let in_project = project.transforms.iter().any(|p| p.canonicalize().is_ok_and(|p| p == ety_path));
+    if !in_project {
+        let transforms: entries::TransformGraph = serde_json::from_str(&std::fs::read_to_string(&ety_path)?)
+        .context("error parsing daughter transformations")?;
+        let project_transforms = read_transform_files(&project.transforms, false)?;
+        if let Some(name) = transforms.transforms.keys().find(|n| project_transforms.contains_key(*n)) {
+            return Err(anyhow!("transform '{}' from {} already exists in the project", name, daughter_ety));
+        }
+        let ety_file = Path::new(directory).join("etymology").join(format!("{}.json", file_name));
+        if ety_file.exists() {
+            return Err(anyhow!("{} already exists", ety_file.display()));
+        }
+        add_ety_file(tx, directory, &format!("{}.json", file_name), transforms)?;
+    }
+    tx.create_dir_all(Path::new(directory).join("tree"))?;
+    Ok(out_path.to_string_lossy().to_string())
+}
+
 /// groups a set of language values by the type specified in `field`
 fn group_by(field: Option<SeparateValues>, dict: Vec<(Lexis, Etymology)>, default: String) -> HashMap<String, Vec<(Lexis, Etymology)>> {
     let mut files: HashMap<String, Vec<(Lexis, Etymology)>> = HashMap::new();
@@ -114,3 +171,50 @@ fn group_by(field: Option<SeparateValues>, dict: Vec<(Lexis, Etymology)>, defaul
 
     files
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use crate::files::read_and_compute;
+    use super::daughter;
+
+
+    #[test]
+    fn test_daughter_in_place() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_daughter_in_place")?;
+        let generate = || daughter("src/test_files/daughter_transforms.json".to_string(), Some("Latin".to_string()), None,
+            "Vulgar Latin".to_string(), Some(dir.clone()), None, None);
+        generate()?;
+        assert!(Path::new(&dir).join("tree/vulgar-latin.json").exists());
+        assert!(Path::new(&dir).join("etymology/vulgar-latin.json").exists());
+
+        let computed = read_and_compute(Some(dir.clone()))?;
+        let derived = computed.get_by_id("daughter-from-latin_example").unwrap();
+        assert_eq!(derived.language, "Vulgar Latin");
+        assert_eq!(derived.word.unwrap().string_without_sep(), "exemplom");
+        assert_eq!(computed.etymons("daughter-from-latin_example")[0].id, "latin_example");
+
+        // the words already exist, so nothing is written a second time
+        assert!(generate().is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_daughter_selection() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_daughter_selection")?;
+        daughter("src/test_files/daughter_transforms.json".to_string(), None, Some("pos=noun and language!=Test".to_string()),
+            "Vulgar Latin".to_string(), Some(dir.clone()), None, None)?;
+        let computed = read_and_compute(Some(dir.clone()))?;
+        let mut ids: Vec<String> = computed.iter().filter(|l| l.language == "Vulgar Latin").map(|l| l.id.clone()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["daughter-from-latin_example", "daughter-from-latin_example-autoderive-0"]);
+
+        assert!(daughter("src/test_files/daughter_transforms.json".to_string(), None, Some("pos=".to_string()),
+            "Vulgar Latin".to_string(), Some(dir.clone()), None, None).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
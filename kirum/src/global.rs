@@ -1,26 +1,164 @@
-use libkirum::{transforms::{TransformFunc, GlobalTransform}, matching::LexisMatch};
+use std::collections::{BTreeMap, HashMap};
+
+use libkirum::{word::PartOfSpeech, transforms::{TransformFunc, GlobalTransform}, matching::LexisMatch, sandhi::SandhiRule, kirum::{Agglutination, MetadataInheritance, LanguageInheritance, TransformPrecedence}, sorted::sorted_option_map};
+use regex::Regex;
 use serde::{Serialize, Deserialize};
 use serde_with::skip_serializing_none;
+use schemars::JsonSchema;
 
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
 /// Defines the contents of the global.json file
 pub struct Global {
     /// Specifies global transforms
-    pub transforms: Option<Vec<RawGlobalTransform>>
+    pub transforms: Option<Vec<RawGlobalTransform>>,
+    /// Per-language settings, keyed by the language name used in the "language" field of tree files
+    #[serde(serialize_with = "sorted_option_map")]
+    pub languages: Option<HashMap<String, LanguageSettings>>,
+    /// Language applied to any word that does not specify a language
+    pub default_language: Option<String>,
+    /// Determines how historical_metadata is inherited by derived words
+    pub metadata_inheritance: Option<MetadataInheritance>,
+    /// Default rules for joining words created from multiple etymons
+    pub agglutination: Option<Agglutination>,
+    /// Seed used when generating words from phonetic rules, so words are generated identically between runs
+    pub seed: Option<u64>,
+    /// An ordered list of letters used to sort rendered output
+    pub collation: Option<Vec<String>>,
+    /// Determines how words without a language inherit one from their etymons. One of "none", "etymon", or "daughter"
+    pub language_inheritance: Option<LanguageInheritance>,
+    /// Strings or patterns that words must not contain, such as real-world slurs or brand names.
+    /// Generated words that match are regenerated, and `kirum lint` reports any other matching words.
+    pub blacklist: Option<Vec<BlacklistEntry>>,
+    /// Transforms applied to every word derived from a word of one language into another
+    pub language_transforms: Option<Vec<LanguagePair>>,
+    /// Determines how edge, language pair and global transforms combine. One of "stack" or "override"
+    pub transform_precedence: Option<TransformPrecedence>,
+    /// Reject any unknown fields in project files, such as a misspelled `definiton`, instead of ignoring them
+    pub strict: Option<bool>,
+    /// The parts of speech used by the project, keyed by name, i.e. {"noun": {"abbreviation": "n."}, "classifier": {"abbreviation": "clf."}}.
+    /// If set, `kirum lint` reports any word with a part of speech that isn't listed, and rendered dictionaries use the abbreviations.
+    #[serde(serialize_with = "sorted_option_map")]
+    pub parts_of_speech: Option<HashMap<String, PartOfSpeechSettings>>
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+/// Settings for a part of speech declared by the project
+pub struct PartOfSpeechSettings {
+    /// The short form used in rendered dictionaries, such as "n." or "clf."
+    pub abbreviation: Option<String>,
+    /// Optional description of the part of speech
+    pub description: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+/// A list of named transforms from the etymology files, applied to every word of the `to` language derived from an etymon of the `from` language
+pub struct LanguagePair {
+    pub from: String,
+    pub to: String,
+    pub transforms: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(untagged)]
+/// A single blacklist entry; either a string that is matched case-insensitively anywhere in the word, or a regular expression, i.e. `{"regex": "^foo"}`
+pub enum BlacklistEntry {
+    Literal(String),
+    Regex{regex: String}
+}
+
+impl BlacklistEntry {
+    pub fn to_regex(&self) -> Result<Regex, regex::Error> {
+        match self {
+            BlacklistEntry::Literal(lit) => Regex::new(&format!("(?i){}", regex::escape(lit))),
+            BlacklistEntry::Regex { regex } => Regex::new(regex)
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+/// Settings that only apply to words of a single language
+pub struct LanguageSettings {
+    /// Boundary sandhi rules applied at morpheme seams when a word of this language is created via agglutination
+    pub sandhi: Option<Vec<SandhiRule>>,
+    /// The alphabetical order of the language, used to sort rendered output. Letters can be multiple characters, such as "ch"
+    pub collation: Option<Vec<String>>,
+    /// The language this language directly descends from, i.e. "Old French" for "Middle French"
+    pub parent: Option<String>,
+    /// Letters made of multiple characters, such as "ch" or "ng". Words of this language written as plain strings
+    /// will be split into these letters, instead of single characters.
+    pub graphemes: Option<Vec<String>>,
+    /// Additional scripts the language can be written in, keyed by the name of the orthography.
+    /// Each orthography maps letters of the word to their representation in that script, i.e. {"cyrillic": {"k": "к", "ch": "ч"}}
+    #[serde(serialize_with = "sorted_orthographies")]
+    pub orthographies: Option<HashMap<String, HashMap<String, String>>>,
+    /// A map of letters to their IPA transcription, used to generate the IPA pronunciation of each word, i.e. {"ch": "t͡ʃ", "y": "j"}
+    #[serde(serialize_with = "sorted_option_map")]
+    pub ipa: Option<HashMap<String, String>>
+}
+
+/// serialize the orthographies of a language with both the orthography names and the letters sorted
+fn sorted_orthographies<S: serde::Serializer>(orthographies: &Option<HashMap<String, HashMap<String, String>>>, serializer: S) -> Result<S::Ok, S::Error> {
+    orthographies.as_ref().map(|o| o.iter().map(|(name, letters)| (name, letters.iter().collect::<BTreeMap<_, _>>())).collect::<BTreeMap<_, _>>())
+    .serialize(serializer)
+}
+
+impl Global {
+    /// Return the ancestors of a language as declared in the `parent` field of the language settings, nearest first.
+    pub fn language_ancestors(&self, language: &str) -> Vec<String> {
+        let mut ancestors: Vec<String> = Vec::new();
+        let languages = match &self.languages {
+            Some(l) => l,
+            None => return ancestors
+        };
+        let mut current = language.to_string();
+        while let Some(parent) = languages.get(&current).and_then(|s| s.parent.clone()) {
+            // guard against cycles in the declared parents
+            if parent == language || ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent.clone());
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// Return the settings of a part of speech declared in `parts_of_speech`, or None if the project doesn't declare it.
+    /// Names are compared as parts of speech, so `adj` declares adjectives.
+    pub fn part_of_speech(&self, pos: &PartOfSpeech) -> Option<&PartOfSpeechSettings> {
+        self.parts_of_speech.as_ref()?.iter()
+        .find(|(name, _)| name.parse::<PartOfSpeech>().is_ok_and(|p| p == *pos))
+        .map(|(_, settings)| settings)
+    }
+
+    /// Return a map of each language to its daughter, as declared in the `parent` field of the language settings.
+    /// Languages with more than one daughter are skipped, as there is no way to choose between them.
+    pub fn language_daughters(&self) -> HashMap<String, String> {
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        if let Some(languages) = &self.languages {
+            for (name, settings) in languages {
+                if let Some(parent) = &settings.parent {
+                    children.entry(parent.clone()).or_default().push(name.clone());
+                }
+            }
+        }
+        children.into_iter().filter(|(_, c)| c.len() == 1).map(|(p, mut c)| (p, c.remove(0))).collect()
+    }
 }
 
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
 pub struct RawGlobalTransform {
     pub transforms: Vec<TransformFunc>,
     pub conditional: GlobalConditionals
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
 pub struct GlobalConditionals {
     pub etymon: Option<LexisMatch>,
     pub lexis: LexisMatch
@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{Result, Context};
+use libkirum::{kirum::{Lexis, LanguageTree}, word::Etymology};
+use quick_xml::escape::escape;
+use serde_json::Value;
+
+const GRAPHML_NAMESPACE: &str = "http://graphml.graphdrawing.org/xmlns";
+/// the key used for the display label of each node, read by Gephi and yEd
+const LABEL_KEY: &str = "label";
+/// the key used for the names of the transforms on each edge
+const TRANSFORMS_KEY: &str = "transforms";
+const AGGLUTINATION_KEY: &str = "agglutination_order";
+
+/// A single attribute value of a node or edge
+struct Data {
+    key: String,
+    value: String
+}
+
+impl Data {
+    fn write(&self, out: &mut Vec<String>) {
+        out.push(format!("      <data key=\"{}\">{}</data>", escape(&self.key), escape(&self.value)));
+    }
+}
+
+/// Render the words as a GraphML document, for exploring a language family in graph tools like Gephi or yEd.
+/// Every field of a word is written as a node attribute, and each edge points from an etymon to the word derived from it.
+/// Edges are only written if both words are rendered.
+pub fn generate_graphml(tree: &LanguageTree, rendered_lang: Vec<Lexis>) -> Result<String> {
+    let etymologies: HashMap<String, Etymology> = tree.to_vec_etymons(|_| true).into_iter()
+    .map(|(l, e)| (l.id, e)).collect();
+    let rendered_ids: HashSet<&String> = rendered_lang.iter().map(|l| &l.id).collect();
+
+    // attribute names, along with their GraphML type
+    let mut node_keys: BTreeMap<String, String> = BTreeMap::from([(LABEL_KEY.to_string(), "string".to_string())]);
+    let mut nodes: Vec<(String, Vec<Data>)> = Vec::new();
+    let mut edges: Vec<(String, String, Vec<Data>)> = Vec::new();
+    for lex in &rendered_lang {
+        let mut data = vec![Data{key: LABEL_KEY.to_string(), value: lex.word.clone().unwrap_or_default().string_without_sep()}];
+        let fields = match serde_json::to_value(lex).context(format!("could not serialize word {}", lex.id))? {
+            Value::Object(fields) => fields,
+            _ => continue
+        };
+        for (name, value) in fields {
+            let (attr_type, value) = match value {
+                Value::Null => continue,
+                Value::Array(a) if a.is_empty() => continue,
+                Value::Object(o) if o.is_empty() => continue,
+                Value::Bool(b) => ("boolean", b.to_string()),
+                Value::Number(n) => (if n.is_f64() {"double"} else {"long"}, n.to_string()),
+                Value::String(s) => ("string", s),
+                other => ("string", other.to_string())
+            };
+            node_keys.entry(name.clone()).or_insert(attr_type.to_string());
+            data.push(Data { key: name, value });
+        }
+        nodes.push((lex.id.clone(), data));
+
+        for edge in etymologies.get(&lex.id).map(|e| e.etymons.clone()).unwrap_or_default() {
+            if !rendered_ids.contains(&edge.etymon) {
+                continue;
+            }
+            let mut data = vec![Data { key: TRANSFORMS_KEY.to_string(), value: edge.transforms.unwrap_or_default().join(", ") }];
+            if let Some(order) = edge.agglutination_order {
+                data.push(Data { key: AGGLUTINATION_KEY.to_string(), value: order.to_string() });
+            }
+            edges.push((edge.etymon, lex.id.clone(), data));
+        }
+    }
+
+    let mut out: Vec<String> = vec!["<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(), format!("<graphml xmlns=\"{}\">", GRAPHML_NAMESPACE)];
+    let edge_keys = [(TRANSFORMS_KEY.to_string(), "string".to_string()), (AGGLUTINATION_KEY.to_string(), "int".to_string())];
+    for (key_for, (name, attr_type)) in node_keys.into_iter().map(|k| ("node", k)).chain(edge_keys.into_iter().map(|k| ("edge", k))) {
+        out.push(format!("  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>", escape(&name), key_for, attr_type));
+    }
+    out.push("  <graph id=\"lexicon\" edgedefault=\"directed\">".to_string());
+    for (id, data) in nodes {
+        out.push(format!("    <node id=\"{}\">", escape(&id)));
+        data.iter().for_each(|d| d.write(&mut out));
+        out.push("    </node>".to_string());
+    }
+    for (source, target, data) in edges {
+        out.push(format!("    <edge source=\"{}\" target=\"{}\">", escape(&source), escape(&target)));
+        data.iter().for_each(|d| d.write(&mut out));
+        out.push("    </edge>".to_string());
+    }
+    out.push("  </graph>".to_string());
+    out.push("</graphml>".to_string());
+    Ok(format!("{}\n", out.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::{kirum::{Lexis, LanguageTree}, transforms::{Transform, TransformFunc}};
+    use super::generate_graphml;
+
+    #[test]
+    fn test_graphml() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        let root = Lexis{id: "root".to_string(), word: Some("kirum".into()), language: "Old".to_string(),
+            definition: "garden".to_string(), archaic: true, ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), language: "New".to_string(), definition: "garden & orchard".to_string(), ..Default::default()};
+        let change = Transform{name: "m-to-n".to_string(), lex_match: None,
+            transforms: vec![TransformFunc::MatchReplace { old: "m".into(), new: "n".into() }]};
+        tree.connect_etymology(derived, root, vec![change], None);
+        tree.compute_lexicon().unwrap();
+
+        let rendered = generate_graphml(&tree, tree.to_vec())?;
+        assert!(rendered.contains(r#"<key id="archaic" for="node" attr.name="archaic" attr.type="boolean"/>"#));
+        assert!(rendered.contains(r#"<edge source="root" target="derived">"#));
+        assert!(rendered.contains(r#"<data key="transforms">m-to-n</data>"#));
+        assert!(rendered.contains(r#"<data key="label">kirun</data>"#));
+        assert!(rendered.contains("garden &amp; orchard"));
+
+        // edges to words that aren't rendered are left out
+        let partial = generate_graphml(&tree, tree.to_vec().into_iter().filter(|l| l.id == "derived").collect())?;
+        assert!(!partial.contains("<edge"));
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Result, Context};
+use serde::Serialize;
+
+use crate::cli::Format;
+
+/// A summary of the project state a dictionary was rendered from, so published output can be traced back to it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    /// The name of the project directory
+    pub project: String,
+    /// The git revision of the project, if it's in a git repository. Marked as modified if there are uncommitted changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// The number of words rendered
+    pub words: usize,
+    /// The version of kirum used to render the project
+    pub version: String,
+    /// The seed used to generate words from phonetic rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>
+}
+
+impl Header {
+    pub fn new(directory: &str, words: usize, seed: Option<u64>) -> Self {
+        let path = Path::new(directory);
+        let project = path.canonicalize().ok().as_deref().unwrap_or(path)
+        .file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| directory.to_string());
+        Header { project, revision: git_revision(path), words, version: env!("CARGO_PKG_VERSION").to_string(), seed }
+    }
+
+    /// the header as a list of human-readable lines
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("project: {}", self.project)];
+        if let Some(rev) = &self.revision {
+            lines.push(format!("revision: {}", rev));
+        }
+        lines.push(format!("words: {}", self.words));
+        lines.push(format!("kirum version: {}", self.version));
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed: {}", seed));
+        }
+        lines
+    }
+
+    /// Embed the header into rendered output, as a comment in the syntax of the output format.
+    /// JSON has no comments, so the header is added as a `header` field of the root object.
+    /// CSV and TSV have no comments either, and any extra line would be read as a row, so they're left unchanged.
+    pub fn embed(&self, format: &Format, rendered: String) -> Result<String> {
+        let embedded = match format {
+            Format::Json { .. } => {
+                let mut parsed: serde_json::Value = serde_json::from_str(&rendered).context("could not read rendered JSON")?;
+                if let Some(root) = parsed.as_object_mut() {
+                    root.shift_insert(0, "header".to_string(), serde_json::to_value(self)?);
+                }
+                serde_json::to_string_pretty(&parsed)?
+            },
+            Format::Html { .. } | Format::Lift | Format::Xdxf { .. } | Format::Graphml => {
+                // keep the doctype or XML declaration as the first line
+                let comment = format!("<!--\n{}\n-->", self.lines().join("\n"));
+                match rendered.split_once('\n') {
+                    Some((first, rest)) if first.starts_with("<!") || first.starts_with("<?") => format!("{}\n{}\n{}", first, comment, rest),
+                    _ => format!("{}\n{}", comment, rendered)
+                }
+            },
+            Format::Markdown { .. } => format!("<!--\n{}\n-->\n\n{}", self.lines().join("\n"), rendered),
+            Format::Mdf { .. } => {
+                // header fields go after the Toolbox database marker, before the first record
+                let fields: Vec<String> = self.lines().iter().map(|l| format!("\\_kirum {}", l)).collect();
+                match rendered.split_once('\n') {
+                    Some((first, rest)) => format!("{}\n{}\n{}", first, fields.join("\n"), rest),
+                    None => format!("{}\n{}", rendered, fields.join("\n"))
+                }
+            },
+            // templates receive the header as a variable instead, as the output can be in any syntax
+            Format::Template { .. } | Format::Csv | Format::Tsv => rendered,
+            Format::Line { .. } | Format::ReverseIndex => {
+                let comments: Vec<String> = self.lines().iter().map(|l| format!("# {}", l)).collect();
+                format!("{}\n{}", comments.join("\n"), rendered)
+            }
+        };
+        Ok(embedded)
+    }
+}
+
+/// get the short git revision of the directory, if it's in a git repository
+fn git_revision(path: &Path) -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).current_dir(path).output().ok()?;
+    if !output.status.success() {
+        return None
+    }
+    let rev = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let status = Command::new("git").args(["status", "--porcelain", "--", "."]).current_dir(path).output().ok()?;
+    if !status.stdout.is_empty() {
+        return Some(format!("{} (modified)", rev))
+    }
+    Some(rev)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use crate::cli::{Format, ColorChoice};
+    use super::Header;
+
+    fn test_header() -> Header {
+        Header { project: "exemplum".to_string(), revision: Some("abc1234".to_string()), words: 3, version: "0.1.0".to_string(), seed: Some(10) }
+    }
+
+    #[test]
+    fn test_new_header() {
+        let header = Header::new("src/test_files/global_settings", 3, Some(10));
+        assert_eq!(header.project, "global_settings");
+        assert_eq!(header.version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_embed_comments() -> Result<()> {
+        let header = test_header();
+        let line = header.embed(&Format::Line { wide: false, fields: Vec::new(), color: ColorChoice::Never, format: None }, "kira".to_string())?;
+        assert_eq!(line, "# project: exemplum\n# revision: abc1234\n# words: 3\n# kirum version: 0.1.0\n# seed: 10\nkira");
+
+        let html = header.embed(&Format::Html { title: "Lexicon".to_string() }, "<!DOCTYPE html>\n<html></html>".to_string())?;
+        assert!(html.starts_with("<!DOCTYPE html>\n<!--\nproject: exemplum\n"));
+        assert!(html.ends_with("-->\n<html></html>"));
+
+        let mdf = header.embed(&Format::Mdf { language: None }, "\\_sh v3.0  400  MDF 4.0\n\n\\lx kira".to_string())?;
+        assert!(mdf.starts_with("\\_sh v3.0  400  MDF 4.0\n\\_kirum project: exemplum\n"));
+        assert!(mdf.ends_with("\\_kirum seed: 10\n\n\\lx kira"));
+
+        let csv = header.embed(&Format::Csv, "word,definition\nkira,garden".to_string())?;
+        assert_eq!(csv, "word,definition\nkira,garden");
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_json() -> Result<()> {
+        let header = test_header();
+        let json = header.embed(&Format::Json { descendants: false, transforms: false }, "{\"words\": {}}".to_string())?;
+        let parsed: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(parsed["header"]["project"], "exemplum");
+        assert_eq!(parsed["header"]["seed"], 10);
+        assert!(parsed["words"].is_object());
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use handlebars::{Handlebars, Helper, RenderContext, Output, HelperResult, RenderError};
+use libkirum::kirum::{Lexis, LanguageTree};
+use anyhow::Result;
+use serde::Serialize;
+use crate::tmpl::TemplateWord;
+
+const HTML_TEMPLATE: &str = include_str!("templates/dictionary.html.hbs");
+
+/// The data passed to the built-in HTML template
+#[derive(Serialize, Debug, Default)]
+struct HtmlContext {
+    title: String,
+    languages: Vec<HtmlLanguage>
+}
+
+/// The words of a single language, split into sections by their first letter
+#[derive(Serialize, Debug, Default)]
+struct HtmlLanguage {
+    name: String,
+    anchor: String,
+    letters: Vec<HtmlLetter>
+}
+
+#[derive(Serialize, Debug, Default)]
+struct HtmlLetter {
+    letter: String,
+    anchor: String,
+    words: Vec<TemplateWord>
+}
+
+/// Render a standalone HTML dictionary, with a section for each language, split by the first letter of each word.
+/// Letters are listed in alphabetical order, and the words under each letter keep the order they're given in,
+/// so the words can be sorted by anything.
+pub fn generate_html(tree: &LanguageTree, rendered_lang: Vec<Lexis>, title: String) -> Result<String> {
+    let mut reg = Handlebars::new();
+    reg.register_helper("html_id", Box::new(html_id_helper));
+    reg.register_template_string("html", HTML_TEMPLATE)?;
+
+    let vowels = tree.vowels();
+    let index = tree.index();
+    let mut languages: Vec<HtmlLanguage> = Vec::new();
+    for lex in rendered_lang {
+        let name = if lex.language.is_empty() {String::from("Unknown Language")} else {lex.language.clone()};
+        // reconstructed words are listed under the letter after the `*`
+        let letter = lex.word.as_ref().and_then(|w| w.iter().find(|l| !(lex.reconstructed && *l == "*")))
+            .unwrap_or_default().to_uppercase();
+        let word = TemplateWord::new(lex, &index, &vowels);
+
+        let lang_idx = match languages.iter().position(|l| l.name == name) {
+            Some(idx) => idx,
+            None => {
+                languages.push(HtmlLanguage { anchor: anchor("lang", &name), name, letters: Vec::new() });
+                languages.len() - 1
+            }
+        };
+        let lang = &mut languages[lang_idx];
+        match lang.letters.iter_mut().find(|l| l.letter == letter) {
+            Some(current) => current.words.push(word),
+            None => lang.letters.push(HtmlLetter { letter, anchor: String::new(), words: vec![word] })
+        }
+    }
+    languages.sort_by(|a, b| a.name.cmp(&b.name));
+    for lang in &mut languages {
+        // words without a word to take a letter from go last
+        lang.letters.sort_by(|a, b| a.letter.is_empty().cmp(&b.letter.is_empty()).then(a.letter.cmp(&b.letter)));
+        for (idx, letter) in lang.letters.iter_mut().enumerate() {
+            letter.anchor = format!("{}-{}", lang.anchor, idx);
+        }
+    }
+
+    Ok(reg.render("html", &HtmlContext { title, languages })?)
+}
+
+/// create a string that can be used as an HTML id, prefixed so ids of different kinds can't collide
+fn anchor(prefix: &str, name: &str) -> String {
+    let slug: String = name.to_lowercase().chars().map(|c| if c.is_alphanumeric() {c} else {'-'}).collect();
+    format!("{}-{}", prefix, slug)
+}
+
+/// a template helper that turns a word ID into an HTML id
+fn html_id_helper<'reg, 'rc>(
+    helper: &Helper<'reg, 'rc>,
+    _: &'reg Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let param = helper.param(0).ok_or(RenderError::new("param in html_id not found"))?;
+    out.write(&anchor("word", &param.render()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::kirum::{Lexis, LanguageTree};
+    use super::generate_html;
+
+    #[test]
+    fn test_generate_html() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        let one = Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(), definition: "garden".to_string(), ..Default::default()};
+        let two = Lexis{id: "two".to_string(), word: Some("amo".into()), language: "New Exemplum".to_string(), definition: "<keeper>".to_string(), ..Default::default()};
+        let three = Lexis{id: "three".to_string(), word: Some("kirum".into()), language: "Old Exemplum".to_string(), ..Default::default()};
+        tree.connect_etymology(three, one, Vec::new(), None);
+        tree.add_lexis(two);
+
+        let rendered = generate_html(&tree, tree.to_vec(), "Exemplum".to_string())?;
+        assert!(rendered.starts_with("<!DOCTYPE html>"));
+        assert!(rendered.contains("<title>Exemplum</title>"));
+        assert!(rendered.contains(r##"<a href="#lang-new-exemplum">New Exemplum</a><a href="#lang-old-exemplum">Old Exemplum</a>"##));
+        // both Old Exemplum words are under a single letter
+        assert!(rendered.contains(r##"<a href="#lang-old-exemplum-0">K</a></nav>"##));
+        assert!(rendered.contains(r##"From <a href="#word-one"><i>kira</i></a> (Old Exemplum) &lsquo;garden&rsquo;."##));
+        // definitions are escaped
+        assert!(rendered.contains("&lt;keeper&gt;"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_reconstructed_letter() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        tree.add_lexis(Lexis{id: "wed".to_string(), word: Some("wed".into()), language: "Proto-Exemplum".to_string(), reconstructed: true, ..Default::default()});
+        let mut words = tree.to_vec();
+        crate::files::apply_reconstructed(&mut words);
+        let rendered = generate_html(&tree, words, "Exemplum".to_string())?;
+        assert!(rendered.contains(r##"<a href="#lang-proto-exemplum-0">W</a></nav>"##));
+        assert!(rendered.contains("*wed"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_unsorted_letters() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        for (id, word) in [("a", "kira"), ("b", "amo"), ("c", "kirum"), ("d", "amas")] {
+            tree.add_lexis(Lexis{id: id.to_string(), word: Some(word.into()), language: "Exemplum".to_string(), ..Default::default()});
+        }
+        // sorted by ID, as with --sort id, so words with the same letter aren't next to each other
+        let mut words = tree.to_vec();
+        words.sort_by(|a, b| a.id.cmp(&b.id));
+        let rendered = generate_html(&tree, words, "Exemplum".to_string())?;
+        assert!(rendered.contains(r##"<a href="#lang-exemplum-0">A</a><a href="#lang-exemplum-1">K</a></nav>"##));
+        assert_eq!(rendered.matches("<h3 ").count(), 2);
+        // the words under a letter keep their order
+        assert!(rendered.find("kira").unwrap() < rendered.find("kirum").unwrap());
+        Ok(())
+    }
+}
@@ -73,8 +73,9 @@ fn ingest_value(overrides: &RawLexicalEntry,
             };
         },
         Value::Object(obj) => {
-            let mut created_root = false;
             for (word_key, word_val) in obj {
+                // tracked per key, so a !transform on one child doesn't hide the siblings that come after it
+                let mut created_root = false;
                 match word_val.clone() {
                     Value::String(found_string) => {
                         // skip !-directives that were read in by the parent call
@@ -171,7 +172,7 @@ fn insert_into_map(overrides: &RawLexicalEntry, in_type: KeyType, parent: Option
     let id = format!("ingest-{}", input_word);
     let found = match in_type{
         KeyType::Definitions => {
-            graph.words.insert(id, RawLexicalEntry{definition: input_word.clone(), etymology: parent_ety, ..overrides.clone()})
+            graph.words.insert(id, RawLexicalEntry{definition: input_word.clone().into(), etymology: parent_ety, ..overrides.clone()})
         },
         KeyType::Words => {
             let new_lemma: Lemma = input_word.clone().into();
@@ -196,14 +197,14 @@ mod tests {
 
     #[test]
     fn test_with_override() {
-        let gen_statement = Some("example_generate".to_string());
+        let gen_statement = Some("example_generate".into());
         let test_over = RawLexicalEntry{generate: gen_statement.clone(), ..Default::default()};
         let mut new = WordGraph::default();
         let raw = r#"
         {
             "attack": ["attacking", "attacked"]
         }"#;
-        let parsed: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
         ingest_value(&test_over, KeyType::Definitions, None,  &mut new, parsed);
 
         println!("got: {:#?}", new);
@@ -213,6 +214,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_transform_sibling() {
+        // a `!transform` value on one key doesn't stop the keys after it from being linked to the parent
+        let mut new = WordGraph::default();
+        let parsed: serde_json::Value = serde_json::from_str(r#"{"twist": {"twistable": "!capability", "twisted": "untwisted"}}"#).unwrap();
+        ingest_value(&RawLexicalEntry::default(), KeyType::Definitions, None, &mut new, parsed);
+
+        let twisted = new.words.get("ingest-twisted").expect("twisted was not ingested");
+        assert_eq!(twisted.etymology.as_ref().unwrap().etymons[0].etymon, "ingest-twist");
+        assert_eq!(new.words["ingest-untwisted"].etymology.as_ref().unwrap().etymons[0].etymon, "ingest-twisted");
+    }
+
     #[test]
     fn basic_ingest_test() {
 
@@ -223,7 +236,7 @@ mod tests {
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "failure".to_string(),
+                    definition: "failure".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -232,47 +245,81 @@ mod tests {
                                     etymon: "ingest-fail".to_string(),
                                     transforms: Some(vec!["state_of".to_string()]),
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                     }),
                 ("ingest-grab".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "grab".to_string(),
+                    definition: "grab".into(),
                     part_of_speech: None,
                     etymology: None,
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-fail".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "fail".to_string(),
+                    definition: "fail".into(),
                     part_of_speech: None,
                     etymology: None,
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-twistable".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "twistable".to_string(),
+                    definition: "twistable".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -285,21 +332,35 @@ mod tests {
                                         ],
                                     ),
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-failing".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "failing".to_string(),
+                    definition: "failing".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -308,21 +369,35 @@ mod tests {
                                     etymon: "ingest-fail".to_string(),
                                     transforms: Some(vec!["to_do".to_string()]),
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-unretwistable".to_string(), RawLexicalEntry {  
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "unretwistable".to_string(),
+                    definition: "unretwistable".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -331,21 +406,35 @@ mod tests {
                                     etymon: "ingest-retwistable".to_string(),
                                     transforms: None,
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-untwistable".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "untwistable".to_string(),
+                    definition: "untwistable".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -354,34 +443,58 @@ mod tests {
                                     etymon: "ingest-twistable".to_string(),
                                     transforms: Some(vec!["negate".to_string()]),
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-twist".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "twist".to_string(),
+                    definition: "twist".into(),
                     part_of_speech: None,
                     etymology: None,
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-retwistable".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "retwistable".to_string(),
+                    definition: "retwistable".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -390,34 +503,58 @@ mod tests {
                                     etymon: "ingest-twistable".to_string(),
                                     transforms: None,
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-attack".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "attack".to_string(),
+                    definition: "attack".into(),
                     part_of_speech: None,
                     etymology: None,
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-attacked".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "attacked".to_string(),
+                    definition: "attacked".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -426,21 +563,35 @@ mod tests {
                                     etymon: "ingest-attack".to_string(),
                                     transforms: None,
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
                 ("ingest-attacking".to_string(), RawLexicalEntry {
                     historical_metadata: None,
                     word: None,
                     word_type: None,
                     language: None,
-                    definition: "attacking".to_string(),
+                    definition: "attacking".into(),
                     part_of_speech: None,
                     etymology: Some(
                         Etymology {
@@ -449,14 +600,28 @@ mod tests {
                                     etymon: "ingest-attack".to_string(),
                                     transforms: None,
                                     agglutination_order: None,
+                                    resolved_transforms: None,
+                                    borrowed: None,
+                                    semantic_shift: None,
+                                    metadata: None,
                                 },
                             ],
                         },
                     ),
                     archaic: false,
+                    reconstructed: None,
                     tags: None,
                     generate: None,
                     derivatives: None,
+                    agglutination: None,
+                    noun_class: None,
+                    word_override: None,
+                    examples: None,
+                    semantic_field: None,
+                    notes: None,
+                    status: None,
+                    descendants: None,
+                    ipa: None,
                 }),
             ]),
         };
@@ -483,7 +648,7 @@ mod tests {
         let path = "src/test_files/test_ingest/basic.json";
         let res = ingest(path, RawLexicalEntry::default()).unwrap();
         println!("got basic data: {:#?}", res.1);
-        assert!(res.1.transforms.get("capability").is_some());
+        assert!(res.1.transforms.contains_key("capability"));
     }
 
 }
\ No newline at end of file
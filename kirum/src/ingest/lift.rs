@@ -0,0 +1,152 @@
+use std::{path::Path, collections::HashMap};
+
+use anyhow::Result;
+use libkirum::word::{Etymology, Edge, Example};
+use crate::{entries::{WordGraph, RawLexicalEntry, TransformGraph, RawTransform, Definition},
+lift::{parse_lift, trait_values, forms_by_lang, parse_pos, Entry, DEFAULT_GLOSS_LANG, LANGUAGE_TRAIT, TYPE_TRAIT, TAG_TRAIT,
+    ARCHAIC_TRAIT, RECONSTRUCTED_TRAIT, NOUN_CLASS_TRAIT, SEMANTIC_DOMAIN_TRAIT, TRANSFORM_TRAIT, AGGLUTINATION_TRAIT}};
+
+/// Ingest a LIFT XML file, creating a lexis for each entry.
+/// Etymologies with a source that matches the ID of another entry are linked to that entry;
+/// any other etymology with a form, such as one from FieldWorks where the source is a language name, becomes a new lexis.
+pub fn ingest<P: AsRef<Path>>(path: P, overrides: RawLexicalEntry) -> Result<(WordGraph, TransformGraph)> {
+    let raw = std::fs::read_to_string(path)?;
+    let parsed = parse_lift(&raw)?;
+    let ids: Vec<String> = parsed.entries.iter().map(|e| e.id.clone()).collect();
+
+    let mut working = WordGraph::default();
+    let mut transforms = TransformGraph::default();
+    for entry in parsed.entries {
+        let mut edges: Vec<Edge> = Vec::new();
+        for (idx, ety) in entry.etymologies.iter().enumerate() {
+            let etymon_transforms: Vec<String> = trait_values(&ety.traits, TRANSFORM_TRAIT).cloned().collect();
+            for trans in &etymon_transforms {
+                transforms.transforms.insert(trans.clone(), RawTransform{conditional: None, transforms: vec![]});
+            }
+            let etymon = if ids.contains(&ety.source) {
+                ety.source.clone()
+            } else if let Some(form) = ety.forms.first() {
+                let id = format!("{}-etymon-{}", entry.id, idx);
+                let source_lang = if ety.source.is_empty() {None} else {Some(ety.source.clone())};
+                working.words.insert(id.clone(), RawLexicalEntry{
+                    word: Some(form.text.clone().into()),
+                    language: source_lang.or(overrides.language.clone()),
+                    definition: ety.glosses.first().map(|g| g.text.clone()).unwrap_or_default().into(),
+                    ..overrides.clone()
+                });
+                id
+            } else {
+                warn!("etymology of {} has no form, and source '{}' is not an entry in the file; skipping", entry.id, ety.source);
+                continue;
+            };
+            edges.push(Edge{
+                etymon,
+                transforms: if etymon_transforms.is_empty() {None} else {Some(etymon_transforms)},
+                agglutination_order: trait_values(&ety.traits, AGGLUTINATION_TRAIT).next().and_then(|o| o.parse().ok()),
+                resolved_transforms: None,
+                borrowed: Some(true).filter(|_| ety.etymology_type == "borrowed"),
+                semantic_shift: None,
+                metadata: None
+            });
+        }
+        let etymology = if edges.is_empty() {None} else {Some(Etymology{etymons: edges})};
+        let id = entry.id.clone();
+        if working.words.insert(id.clone(), entry_to_lexis(entry, etymology, &overrides)).is_some() {
+            warn!("inserted word {} multiple times, check the LIFT file for repeated IDs", id);
+        }
+    }
+    Ok((working, transforms))
+}
+
+/// map the fields of a LIFT entry to a lexis. Only the first sense of an entry is used.
+fn entry_to_lexis(entry: Entry, etymology: Option<Etymology>, overrides: &RawLexicalEntry) -> RawLexicalEntry {
+    let first_trait = |name: &str| trait_values(&entry.traits, name).next().cloned();
+    let tags: Vec<String> = trait_values(&entry.traits, TAG_TRAIT).cloned().collect();
+    let metadata: HashMap<String, String> = entry.fields.iter()
+    .filter_map(|f| f.forms.first().map(|form| (f.field_type.clone(), form.text.clone()))).collect();
+
+    let mut lexis = RawLexicalEntry{
+        word: entry.lexical_unit.as_ref().and_then(|u| u.first()).map(|w| w.into()).or(overrides.word.clone()),
+        language: first_trait(LANGUAGE_TRAIT)
+            .or(overrides.language.clone())
+            // the form language is the best guess for files that weren't written by Kirum
+            .or(entry.lexical_unit.as_ref().and_then(|u| u.forms.first().map(|f| f.lang.clone()))),
+        word_type: first_trait(TYPE_TRAIT).or(overrides.word_type.clone()),
+        noun_class: first_trait(NOUN_CLASS_TRAIT).or(overrides.noun_class.clone()),
+        archaic: first_trait(ARCHAIC_TRAIT).map(|a| a == "true").unwrap_or(overrides.archaic),
+        reconstructed: first_trait(RECONSTRUCTED_TRAIT).map(|r| r == "true").filter(|r| *r).or(overrides.reconstructed),
+        tags: if tags.is_empty() {overrides.tags.clone()} else {Some(tags)},
+        historical_metadata: if metadata.is_empty() {overrides.historical_metadata.clone()} else {Some(metadata)},
+        etymology,
+        ..overrides.clone()
+    };
+
+    if let Some(sense) = entry.senses.into_iter().next() {
+        lexis.part_of_speech = sense.grammatical_info.as_ref().and_then(parse_pos).or(lexis.part_of_speech);
+        lexis.semantic_field = trait_values(&sense.traits, SEMANTIC_DOMAIN_TRAIT).next().cloned().or(lexis.semantic_field);
+        // the definition is preferred, with the gloss as a fallback
+        let forms = match &sense.definition {
+            Some(def) if !def.forms.is_empty() => &def.forms,
+            _ => &sense.glosses
+        };
+        lexis.definition = match forms.len() {
+            0 => lexis.definition,
+            1 if forms[0].lang == DEFAULT_GLOSS_LANG => Definition::Single(forms[0].text.clone()),
+            _ => Definition::Glosses(forms_by_lang(forms))
+        };
+        let examples: Vec<Example> = sense.examples.iter().filter_map(|ex| ex.forms.first().map(|form| Example{
+            text: form.text.clone(),
+            gloss: None,
+            translation: ex.translations.first().and_then(|t| t.first())
+        })).collect();
+        if !examples.is_empty() {
+            lexis.examples = Some(examples);
+        }
+    }
+    if let Some(ipa) = entry.pronunciations.first().and_then(|p| p.first()) {
+        debug!("ignoring pronunciation '{}' of {}; IPA is generated from the language settings", ipa, entry.id);
+    }
+    lexis
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::word::PartOfSpeech;
+    use crate::entries::{RawLexicalEntry, Definition};
+    use super::ingest;
+
+    #[test]
+    fn test_lift_ingest() {
+        let path = "src/test_files/test_ingest/basic.lift";
+        let overrides = RawLexicalEntry{word_type: Some("ingested".to_string()), ..Default::default()};
+        let (words, transforms) = ingest(path, overrides).unwrap();
+        assert_eq!(words.words.len(), 4);
+        assert!(transforms.transforms.contains_key("m-to-n"));
+
+        let root = &words.words["root"];
+        assert_eq!(root.word, Some("kirum".into()));
+        assert_eq!(root.language, Some("Old Exemplum".to_string()));
+        assert_eq!(root.word_type, Some("root".to_string()));
+        assert_eq!(root.part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(root.tags, Some(vec!["nature".to_string()]));
+        assert_eq!(root.historical_metadata.as_ref().unwrap()["source"], "test");
+        assert_eq!(root.definition, Definition::Single("garden".to_string()));
+
+        let derived = &words.words["derived"];
+        assert_eq!(derived.word_type, Some("ingested".to_string()));
+        let ety = derived.etymology.as_ref().unwrap();
+        assert_eq!(ety.etymons[0].etymon, "root");
+        assert_eq!(ety.etymons[0].transforms, Some(vec!["m-to-n".to_string()]));
+        assert_eq!(derived.examples.as_ref().unwrap()[0].translation, Some("the orchard keeper".to_string()));
+
+        // an entry written by another tool, with a language name as the etymology source
+        let borrowed = &words.words["hortus-a1b2"];
+        assert_eq!(borrowed.language, Some("qaa-x-newex".to_string()));
+        assert_eq!(borrowed.semantic_field, Some("nature".to_string()));
+        assert!(matches!(&borrowed.definition, Definition::Glosses(g) if g["de"] == "Garten" && g["en"] == "garden"));
+        let etymon = &words.words["hortus-a1b2-etymon-0"];
+        assert_eq!(etymon.word, Some("hortus".into()));
+        assert_eq!(etymon.language, Some("Latin".to_string()));
+        assert_eq!(borrowed.etymology.as_ref().unwrap().etymons[0].etymon, "hortus-a1b2-etymon-0");
+    }
+}
@@ -8,7 +8,7 @@ pub fn ingest<P: AsRef<Path>>(file: P, overrides: RawLexicalEntry) -> Result<Wor
     let mut working = WordGraph::default();
     for line in raw.split('\n') {
         let label = format!("ingest-{}", line);
-        let entry = RawLexicalEntry{definition: line.to_string(), ..overrides.clone() };
+        let entry = RawLexicalEntry{definition: line.into(), ..overrides.clone() };
         working.words.insert(label, entry);
     };
 
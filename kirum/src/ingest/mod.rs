@@ -1,3 +1,6 @@
 pub mod json;
 pub mod lines;
-pub mod overrides;
\ No newline at end of file
+pub mod lift;
+pub mod polyglot;
+pub mod overrides;
+mod zip;
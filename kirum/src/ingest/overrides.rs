@@ -20,7 +20,8 @@ pub fn parse(list: Vec<String>) -> Result<RawLexicalEntry> {
             "pos" => working.part_of_speech = Some(PartOfSpeech::from_str(&stripped_val)?),
             "archaic" => working.archaic = bool::from_str(&stripped_val)?,
             "tag" => working.tags = Some(vec![stripped_val]),
-            "generate" => working.generate = Some(stripped_val),
+            "generate" => working.generate = Some(stripped_val.into()),
+            "noun_class" | "gender" => working.noun_class = Some(stripped_val),
             _ => {
                 return Err(anyhow!("unknown value {} specified for override", raw_values[0]));
             }
@@ -37,7 +38,7 @@ mod tests {
     fn test_override() {
         let list = vec!["generate=test_gen".to_string(), "pos=noun".to_string()];
         let parsed = parse(list).unwrap();
-        assert_eq!(parsed.generate, Some(String::from("test_gen")));
+        assert_eq!(parsed.generate, Some("test_gen".into()));
         assert_eq!(parsed.part_of_speech, Some(libkirum::word::PartOfSpeech::Noun));
     }
 }
\ No newline at end of file
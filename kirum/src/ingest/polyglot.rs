@@ -0,0 +1,221 @@
+use std::{path::Path, collections::HashMap};
+
+use anyhow::{Result, Context};
+use regex::Regex;
+use serde::Deserialize;
+use crate::entries::{WordGraph, RawLexicalEntry};
+use super::zip;
+
+/// The name of the lexicon file inside a PolyGlot .pgd archive
+const PGD_DICTIONARY: &str = "PGDictionary.xml";
+/// Class names that are read as the noun class of a word
+const NOUN_CLASS_NAMES: [&str; 3] = ["gender", "noun class", "class"];
+
+// The element names used by PolyGlot's PGDictionary.xml. Only the parts that map to Kirum values are modeled.
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename = "dictionary")]
+struct PgDictionary {
+    #[serde(rename = "languageProperties", default)]
+    properties: PgProperties,
+    #[serde(rename = "wordGrammarClassCollection", default)]
+    classes: PgClassCollection,
+    #[serde(rename = "partsOfSpeech", default)]
+    parts_of_speech: PgPosCollection,
+    #[serde(default)]
+    lexicon: PgLexicon
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgProperties {
+    #[serde(rename = "langName", default)]
+    name: String
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgPosCollection {
+    #[serde(rename = "class", default)]
+    parts: Vec<PgPos>
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgPos {
+    #[serde(rename = "classId")]
+    id: String,
+    #[serde(rename = "className", default)]
+    name: String
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgClassCollection {
+    #[serde(rename = "wordGrammarClassNode", default)]
+    classes: Vec<PgClass>
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgClass {
+    #[serde(rename = "wordGrammarClassID")]
+    id: String,
+    #[serde(rename = "wordGrammarClassName", default)]
+    name: String,
+    #[serde(rename = "wordGrammarClassValuesCollection", default)]
+    values: PgClassValues
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgClassValues {
+    #[serde(rename = "wordGrammarClassValueNode", default)]
+    values: Vec<PgClassValue>
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgClassValue {
+    #[serde(rename = "wordGrammarClassValueId")]
+    id: String,
+    #[serde(rename = "wordGrammarClassValueName", default)]
+    name: String
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgLexicon {
+    #[serde(rename = "word", default)]
+    words: Vec<PgWord>
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgWord {
+    #[serde(rename = "wordId")]
+    id: String,
+    #[serde(rename = "conWord", default)]
+    con_word: String,
+    #[serde(rename = "localWord", default)]
+    local_word: String,
+    #[serde(rename = "wordTypeId", default)]
+    pos_id: String,
+    #[serde(default)]
+    definition: String,
+    #[serde(default)]
+    pronunciation: String,
+    #[serde(rename = "wordClassCollection", default)]
+    classes: PgWordClasses
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PgWordClasses {
+    /// a class and value ID pair, written as `class,value`
+    #[serde(rename = "wordClassification", default)]
+    values: Vec<String>
+}
+
+/// Ingest a PolyGlot lexicon, either a .pgd archive or the PGDictionary.xml file extracted from one.
+/// The conlang word becomes the word, and the local word becomes the definition, with the PolyGlot definition as a fallback.
+/// Grammatical classes are stored as historical metadata, except for gender or noun class, which becomes the noun class.
+pub fn ingest<P: AsRef<Path>>(path: P, overrides: RawLexicalEntry) -> Result<WordGraph> {
+    let raw = read_dictionary(path.as_ref())?;
+    let parsed: PgDictionary = quick_xml::de::from_str(&raw).context("could not parse PolyGlot dictionary")?;
+
+    let parts_of_speech: HashMap<&str, &str> = parsed.parts_of_speech.parts.iter().map(|p| (p.id.as_str(), p.name.as_str())).collect();
+    let mut classes: HashMap<(String, String), (&str, &str)> = HashMap::new();
+    for class in &parsed.classes.classes {
+        for value in &class.values.values {
+            classes.insert((class.id.clone(), value.id.clone()), (&class.name, &value.name));
+        }
+    }
+    let html = Regex::new(r"<[^>]*>")?;
+
+    let mut working = WordGraph::default();
+    for word in &parsed.lexicon.words {
+        let mut lexis = RawLexicalEntry{
+            word: if word.con_word.is_empty() {overrides.word.clone()} else {Some(word.con_word.clone().into())},
+            language: overrides.language.clone().or(if parsed.properties.name.is_empty() {None} else {Some(parsed.properties.name.clone())}),
+            ..overrides.clone()
+        };
+
+        let definition = if word.local_word.trim().is_empty() {
+            html.replace_all(&word.definition, " ").split_whitespace().collect::<Vec<&str>>().join(" ")
+        } else {
+            word.local_word.trim().to_string()
+        };
+        if !definition.is_empty() {
+            lexis.definition = definition.into();
+        }
+
+        if let Some(pos_name) = parts_of_speech.get(word.pos_id.as_str()) {
+            // parts of speech that Kirum doesn't have built in are kept as user-defined values
+            lexis.part_of_speech = pos_name.parse().ok();
+        }
+
+        let mut metadata = lexis.historical_metadata.clone().unwrap_or_default();
+        for pair in &word.classes.values {
+            let found = pair.split_once(',').and_then(|(class, value)| classes.get(&(class.trim().to_string(), value.trim().to_string())));
+            match found {
+                Some((class, value)) if NOUN_CLASS_NAMES.contains(&class.to_lowercase().as_str()) => lexis.noun_class = Some(value.to_string()),
+                Some((class, value)) => {metadata.insert(class.to_string(), value.to_string());},
+                None => warn!("word {} has an unknown grammatical class '{}'; skipping", word.id, pair)
+            }
+        }
+        if !metadata.is_empty() {
+            lexis.historical_metadata = Some(metadata);
+        }
+        if !word.pronunciation.is_empty() {
+            debug!("ignoring pronunciation '{}' of {}; IPA is generated from the language settings", word.pronunciation, word.id);
+        }
+
+        let id = format!("{}-{}", word.con_word, word.id);
+        if working.words.insert(id.clone(), lexis).is_some() {
+            warn!("inserted word {} multiple times, check the PolyGlot file for repeated IDs", id);
+        }
+    }
+    Ok(working)
+}
+
+/// read the lexicon XML, either directly or from inside a .pgd file, which is a zip archive
+fn read_dictionary(path: &Path) -> Result<String> {
+    let raw = std::fs::read(path).context(format!("could not read {}", path.display()))?;
+    if !raw.starts_with(b"PK") {
+        return Ok(String::from_utf8(raw)?)
+    }
+    let xml = zip::read_file(&raw, PGD_DICTIONARY).context(format!("could not read {} from {}", PGD_DICTIONARY, path.display()))?;
+    Ok(String::from_utf8(xml)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::word::PartOfSpeech;
+    use crate::entries::{RawLexicalEntry, Definition};
+    use super::ingest;
+
+    #[test]
+    fn test_polyglot_ingest() {
+        let path = "src/test_files/test_ingest/basic_polyglot.xml";
+        let overrides = RawLexicalEntry{word_type: Some("ingested".to_string()), ..Default::default()};
+        let words = ingest(path, overrides).unwrap();
+        assert_eq!(words.words.len(), 3);
+
+        let garden = &words.words["kirum-1"];
+        assert_eq!(garden.word, Some("kirum".into()));
+        assert_eq!(garden.language, Some("Exemplum".to_string()));
+        assert_eq!(garden.word_type, Some("ingested".to_string()));
+        assert_eq!(garden.part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(garden.definition, Definition::Single("garden".to_string()));
+        assert_eq!(garden.noun_class, Some("Feminine".to_string()));
+        assert_eq!(garden.historical_metadata.as_ref().unwrap()["Register"], "Formal");
+
+        // no local word, so the definition is used, without the HTML
+        let quickly = &words.words["sa-2"];
+        assert_eq!(quickly.definition, Definition::Single("quickly".to_string()));
+        assert_eq!(quickly.part_of_speech, Some(PartOfSpeech::Adverb));
+
+        let plant = &words.words["amo-3"];
+        assert_eq!(plant.definition, Definition::Single("to plant".to_string()));
+        assert!(plant.historical_metadata.is_none());
+    }
+
+    #[test]
+    fn test_polyglot_archive() {
+        let archive = ingest("src/test_files/test_ingest/basic_polyglot.pgd", RawLexicalEntry::default()).unwrap();
+        let xml = ingest("src/test_files/test_ingest/basic_polyglot.xml", RawLexicalEntry::default()).unwrap();
+        assert_eq!(archive.words.len(), 3);
+        assert_eq!(archive.words["kirum-1"], xml.words["kirum-1"]);
+    }
+}
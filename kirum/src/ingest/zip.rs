@@ -0,0 +1,252 @@
+use anyhow::{Result, anyhow};
+
+const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+const CENTRAL_DIR_ENTRY: u32 = 0x02014b50;
+const LOCAL_HEADER: u32 = 0x04034b50;
+/// the end of central directory record is at least this long, followed by a comment of up to 64KiB
+const END_RECORD_LEN: usize = 22;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// Read the contents of a file from a zip archive, such as a PolyGlot .pgd file.
+/// Only stored and deflated files are supported; zip64, encryption and multi-disk archives aren't.
+pub fn read_file(archive: &[u8], name: &str) -> Result<Vec<u8>> {
+    let end = (0..=archive.len().saturating_sub(END_RECORD_LEN)).rev().take(u16::MAX as usize + 1)
+        .find(|&pos| read_u32(archive, pos) == Some(END_OF_CENTRAL_DIR))
+        .ok_or(anyhow!("not a zip archive"))?;
+    let entries = read_u16(archive, end + 10).unwrap_or_default();
+    let mut pos = read_u32(archive, end + 16).unwrap_or_default() as usize;
+
+    for _ in 0..entries {
+        if read_u32(archive, pos) != Some(CENTRAL_DIR_ENTRY) {
+            return Err(anyhow!("invalid central directory entry at offset {}", pos))
+        }
+        let field = |offset: usize| read_u16(archive, pos + offset).unwrap_or_default() as usize;
+        let (name_len, extra_len, comment_len) = (field(28), field(30), field(32));
+        let entry_name = archive.get(pos + 46..pos + 46 + name_len).ok_or(anyhow!("truncated zip archive"))?;
+        if entry_name == name.as_bytes() {
+            let flags = read_u16(archive, pos + 8).unwrap_or_default();
+            if flags & 1 != 0 {
+                return Err(anyhow!("{} is encrypted", name))
+            }
+            let method = read_u16(archive, pos + 10).unwrap_or_default();
+            let crc = read_u32(archive, pos + 16).unwrap_or_default();
+            let compressed = read_u32(archive, pos + 20).unwrap_or_default();
+            let size = read_u32(archive, pos + 24).unwrap_or_default();
+            if compressed == u32::MAX || size == u32::MAX {
+                return Err(anyhow!("{} is in zip64 format, which isn't supported", name))
+            }
+            let header = read_u32(archive, pos + 42).unwrap_or_default() as usize;
+            let data = entry_data(archive, header, compressed as usize)?;
+            let contents = match method {
+                METHOD_STORED => data.to_vec(),
+                METHOD_DEFLATE => inflate(data, size as usize)?,
+                other => return Err(anyhow!("{} uses compression method {}, which isn't supported", name, other))
+            };
+            if contents.len() != size as usize || crc32(&contents) != crc {
+                return Err(anyhow!("{} is corrupt", name))
+            }
+            return Ok(contents)
+        }
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Err(anyhow!("{} not found in archive", name))
+}
+
+/// the data of an entry, following its local header
+fn entry_data(archive: &[u8], header: usize, len: usize) -> Result<&[u8]> {
+    if read_u32(archive, header) != Some(LOCAL_HEADER) {
+        return Err(anyhow!("invalid local header at offset {}", header))
+    }
+    // the name and extra field can differ from the central directory, so the lengths are read again
+    let name_len = read_u16(archive, header + 26).unwrap_or_default() as usize;
+    let extra_len = read_u16(archive, header + 28).unwrap_or_default() as usize;
+    let start = header + 30 + name_len + extra_len;
+    archive.get(start..start + len).ok_or(anyhow!("truncated zip archive"))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        *entry = (0..8).fold(n as u32, |c, _| if c & 1 != 0 {0xedb88320 ^ (c >> 1)} else {c >> 1});
+    }
+    !data.iter().fold(!0u32, |crc, &b| table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+// The base values and extra bits of the length and distance codes, from RFC 1951
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073,
+    4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// the order the code length code lengths of a dynamic block are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+const MAX_BITS: usize = 15;
+
+/// reads the bits of a deflate stream, starting from the least significant bit of each byte
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32> {
+        let mut value = 0;
+        for n in 0..count {
+            let byte = self.data.get(self.pos / 8).ok_or(anyhow!("unexpected end of deflate data"))?;
+            value |= (((byte >> (self.pos % 8)) & 1) as u32) << n;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+/// a canonical Huffman code, stored as the number of codes of each length and the symbols ordered by their code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; MAX_BITS + 1];
+        lengths.iter().for_each(|&l| counts[l as usize] += 1);
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&s| lengths[s as usize] != 0).collect();
+        symbols.sort_by_key(|&s| lengths[s as usize]);
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= bits.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).copied().ok_or(anyhow!("invalid Huffman code"))
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(anyhow!("invalid Huffman code"))
+    }
+}
+
+/// decompress raw deflate data, as described in RFC 1951
+fn inflate(data: &[u8], size_hint: usize) -> Result<Vec<u8>> {
+    let mut bits = Bits { data, pos: 0 };
+    let mut out: Vec<u8> = Vec::with_capacity(size_hint);
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let start = bits.pos / 8;
+                let len = read_u16(data, start).ok_or(anyhow!("unexpected end of deflate data"))? as usize;
+                let stored = data.get(start + 4..start + 4 + len).ok_or(anyhow!("unexpected end of deflate data"))?;
+                out.extend_from_slice(stored);
+                bits.pos = (start + 4 + len) * 8;
+            },
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                inflate_block(&mut bits, &mut out, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            },
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            },
+            _ => return Err(anyhow!("invalid deflate block type"))
+        }
+        if last {
+            return Ok(out)
+        }
+    }
+}
+
+/// read the Huffman codes at the start of a block compressed with dynamic codes
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman)> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let dist_count = bits.bits(5)? as usize + 1;
+    let code_count = bits.bits(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &idx in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[idx] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(literal_count + dist_count);
+    while lengths.len() < literal_count + dist_count {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            sym @ 0..=15 => (sym as u8, 1),
+            16 => (*lengths.last().ok_or(anyhow!("invalid code lengths"))?, 3 + bits.bits(2)?),
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?)
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + dist_count {
+        return Err(anyhow!("invalid code lengths"))
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+/// decode the symbols of a compressed block until the end of the block
+fn inflate_block(bits: &mut Bits, out: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Result<()> {
+    loop {
+        let sym = literals.decode(bits)? as usize;
+        match sym {
+            0..=255 => out.push(sym as u8),
+            256 => return Ok(()),
+            _ => {
+                let idx = sym - 257;
+                let len = *LENGTH_BASE.get(idx).ok_or(anyhow!("invalid length code"))? as usize + bits.bits(LENGTH_EXTRA[idx])? as usize;
+                let dist_idx = distances.decode(bits)? as usize;
+                let dist = *DIST_BASE.get(dist_idx).ok_or(anyhow!("invalid distance code"))? as usize
+                    + bits.bits(DIST_EXTRA[dist_idx])? as usize;
+                if dist > out.len() {
+                    return Err(anyhow!("invalid distance in deflate data"))
+                }
+                // the copy can overlap the bytes it's writing, so it's done a byte at a time
+                let start = out.len() - dist;
+                for n in 0..len {
+                    out.push(out[start + n]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_file;
+
+    #[test]
+    fn test_read_file() {
+        let archive = std::fs::read("src/test_files/test_ingest/basic_polyglot.pgd").unwrap();
+        let expected = std::fs::read("src/test_files/test_ingest/basic_polyglot.xml").unwrap();
+        // deflated
+        assert_eq!(read_file(&archive, "PGDictionary.xml").unwrap(), expected);
+        // stored
+        assert_eq!(read_file(&archive, "xmlDictionary.xml").unwrap(), b"PolyGlot\n");
+        assert!(read_file(&archive, "missing.xml").is_err());
+        assert!(read_file(&expected, "PGDictionary.xml").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{value::RawValue, Value};
+use crate::format::FileFormat;
+
+/// An error from deserializing a single value in a project file, with the location of the value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The path of keys leading to the value, i.e. `words.latin_verb.etymology.etymons[0]`
+    pub path: String,
+    /// The line of the file the value was found on, if it could be found
+    pub line: Option<usize>,
+    /// The column of the line the value was found at
+    pub column: Option<usize>,
+    pub msg: String
+}
+
+impl ParseError {
+    fn new(raw: &str, format: FileFormat, path: String, msg: String) -> ParseError {
+        let (line, column) = locate(raw, format, &path).unzip();
+        ParseError { path, line, column, msg }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {}, column {}, at {}: {}", line, column, self.path, self.msg),
+            _ => write!(f, "at {}: {}", self.path, self.msg)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a project file in the given format, expanding any `${VAR}` or `${VAR:-default}` references
+/// in string values with the matching environment variable. `$${` can be used to write a literal `${`.
+/// If strict is set, any field that isn't part of the file format is an error, instead of being ignored.
+pub fn from_str_with_env<T: DeserializeOwned>(raw: &str, format: FileFormat, strict: bool) -> Result<T> {
+    let parsed = parse_with_env(raw, format)?;
+    let final_val: T = deserialize_at(raw, format, parsed, "", strict).map_err(|errors| {
+        let report: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        anyhow!("{}", report.join("\n"))
+    })?;
+    Ok(final_val)
+}
+
+/// Parse a project file that holds a map of named entries under a single key, such as the `words` of a tree file.
+/// Each entry is deserialized separately, so every invalid entry in the file is reported, instead of just the first.
+pub fn map_from_str_with_env<T: DeserializeOwned>(raw: &str, format: FileFormat, field: &str, strict: bool) -> Result<HashMap<String, T>> {
+    let mut parsed = parse_with_env(raw, format)?;
+    let entries = match parsed.get_mut(field).map(Value::take) {
+        Some(Value::Object(entries)) => entries,
+        Some(_) => return Err(anyhow!("'{}' must be a map of entries", field)),
+        None => return Err(anyhow!("missing field '{}'", field))
+    };
+
+    let mut found: HashMap<String, T> = HashMap::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    for (key, entry) in entries {
+        match deserialize_at(raw, format, entry, &format!("{}.{}", field, key), strict) {
+            Ok(val) => {
+                found.insert(key, val);
+            },
+            Err(e) => errors.extend(e)
+        }
+    }
+    if !errors.is_empty() {
+        errors.sort_by(|a, b| a.line.cmp(&b.line).then(a.column.cmp(&b.column)).then(a.path.cmp(&b.path)));
+        let report: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        return Err(anyhow!("{}", report.join("\n")));
+    }
+    Ok(found)
+}
+
+/// parse a file into a generic value, and interpolate environment variables
+fn parse_with_env(raw: &str, format: FileFormat) -> Result<Value> {
+    let mut parsed: Value = format.parse_value(raw)?;
+    interpolate_value(&mut parsed, &|key| std::env::var(key).ok())?;
+    Ok(parsed)
+}
+
+/// deserialize a value found at the given path of a file, reporting the path and location of the value that failed.
+/// In strict mode, every unknown field is also reported.
+fn deserialize_at<T: DeserializeOwned>(raw: &str, format: FileFormat, value: Value, prefix: &str, strict: bool) -> Result<T, Vec<ParseError>> {
+    let full_path = |inner: String| match (prefix.is_empty(), inner.as_str()) {
+        (true, _) => inner,
+        (false, ".") => prefix.to_string(),
+        (false, _) => format!("{}.{}", prefix, inner)
+    };
+    let mut unknown: Vec<String> = Vec::new();
+    let mut track = |path: serde_ignored::Path| unknown.push(path.to_string());
+    let ignored = serde_ignored::Deserializer::new(value, &mut track);
+    let res: Result<T, _> = serde_path_to_error::deserialize(ignored);
+    let mut errors: Vec<ParseError> = Vec::new();
+    if let Err(e) = &res {
+        let path = full_path(e.path().to_string());
+        errors.push(ParseError::new(raw, format, path, e.inner().to_string()));
+    }
+    if strict {
+        for field in unknown {
+            let path = full_path(normalize_ignored_path(&field));
+            let name = path.rsplit('.').next().unwrap_or_default().to_string();
+            errors.push(ParseError::new(raw, format, path, format!("unknown field `{}`", name)));
+        }
+    }
+    match res {
+        Ok(val) if errors.is_empty() => Ok(val),
+        _ => Err(errors)
+    }
+}
+
+/// serde_ignored writes optional values as a `?` segment and array indices as plain segments,
+/// so rewrite its paths to match the `etymons[0]` style used for other errors
+fn normalize_ignored_path(path: &str) -> String {
+    let mut out = String::new();
+    for segment in path.split('.').filter(|s| *s != "?") {
+        if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+            out.push_str(&format!("[{}]", segment));
+        } else {
+            if !out.is_empty() {
+                out.push('.');
+            }
+            out.push_str(segment);
+        }
+    }
+    out
+}
+
+/// Find the line and column of the value at a path of keys, i.e. `words.first.etymology.etymons[0]`.
+/// JSON values are found with raw values, which borrow from the file. The other parsers only report locations in errors,
+/// so the file is read again up to the value, and the location is taken from the error raised there.
+fn locate(raw: &str, format: FileFormat, path: &str) -> Option<(usize, usize)> {
+    let segments = path_segments(path);
+    match format {
+        FileFormat::Json => {
+            let mut value: &RawValue = serde_json::from_str(raw).ok()?;
+            for segment in &segments {
+                value = match segment {
+                    PathSegment::Key(key) => serde_json::from_str::<HashMap<String, &RawValue>>(value.get()).ok()?.remove(key)?,
+                    PathSegment::Index(idx) => *serde_json::from_str::<Vec<&RawValue>>(value.get()).ok()?.get(*idx)?
+                };
+            }
+            Some(line_column(raw, value.get().as_ptr() as usize - raw.as_ptr() as usize))
+        },
+        FileFormat::Yaml => {
+            let err = Locate { path: &segments }.deserialize(serde_yaml::Deserializer::from_str(raw)).err()?;
+            err.location().map(|l| (l.line(), l.column()))
+        },
+        FileFormat::Toml => {
+            let err = Locate { path: &segments }.deserialize(toml::Deserializer::new(raw)).err()?;
+            Some(line_column(raw, err.span()?.start))
+        }
+    }
+}
+
+/// the line and column of a byte offset in a file, both starting from 1
+fn line_column(raw: &str, offset: usize) -> (usize, usize) {
+    let line_start = raw[..offset].rfind('\n').map_or(0, |i| i + 1);
+    (raw[..offset].matches('\n').count() + 1, raw[line_start..offset].chars().count() + 1)
+}
+
+/// a key or array index in a path of keys
+#[derive(Debug, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize)
+}
+
+/// split a path such as `words.first.etymons[0]` into its keys and indices. `?` segments for unknown values are skipped.
+fn path_segments(path: &str) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|p| !p.is_empty() && *p != "?") {
+        let mut pieces = part.split('[');
+        if let Some(key) = pieces.next().filter(|k| !k.is_empty()) {
+            segments.push(PathSegment::Key(key.to_string()));
+        }
+        segments.extend(pieces.filter_map(|i| i.trim_end_matches(']').parse().ok()).map(PathSegment::Index));
+    }
+    segments
+}
+
+/// Walks a YAML or TOML file to the value at a path, and fails there, so the error has the location of the value.
+/// Every other value is skipped.
+struct Locate<'a> {
+    path: &'a [PathSegment]
+}
+
+impl<'de> DeserializeSeed<'de> for Locate<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+// plain values aren't handled, so the visitor fails with an invalid type error when it reaches one
+impl<'de> Visitor<'de> for Locate<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "the value to locate")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let (key, rest) = match self.path.split_first() {
+            Some((PathSegment::Key(key), rest)) => (key, rest),
+            _ => return Err(de::Error::custom("found value"))
+        };
+        while let Some(found) = map.next_key::<String>()? {
+            if &found == key {
+                return map.next_value_seed(Locate { path: rest });
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let (idx, rest) = match self.path.split_first() {
+            Some((PathSegment::Index(idx), rest)) => (*idx, rest),
+            _ => return Err(de::Error::custom("found value"))
+        };
+        for _ in 0..idx {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(Locate { path: rest }).map(|_| ())
+    }
+}
+
+/// Find the byte offset of the end of the last key in a path of keys, by searching for each key of the path in turn.
+/// This works with any of the supported formats, as it only relies on keys being written in order.
+pub fn find_offset(raw: &str, path: &str) -> Option<usize> {
+    let mut pos = 0;
+    let mut found = false;
+    for segment in path.split('.') {
+        // array indices don't appear in the file
+        let key = segment.split('[').next().unwrap_or_default();
+        if key.is_empty() || key == "?" {
+            continue;
+        }
+        let quoted = format!("\"{}\"", key);
+        let offset = raw[pos..].find(&quoted).or_else(|| raw[pos..].find(key))?;
+        pos += offset + key.len();
+        found = true;
+    }
+    if !found {
+        return None;
+    }
+    Some(pos)
+}
+
+/// walk a JSON value, interpolating every string found in it.
+fn interpolate_value<F>(value: &mut Value, lookup: &F) -> Result<()>
+    where F: Fn(&str) -> Option<String>
+{
+    match value {
+        Value::String(s) if s.contains('$') => {
+            *s = interpolate_str(s, lookup)?;
+        },
+        Value::Array(arr) => {
+            for item in arr {
+                interpolate_value(item, lookup)?;
+            }
+        },
+        Value::Object(obj) => {
+            for (_, item) in obj.iter_mut() {
+                interpolate_value(item, lookup)?;
+            }
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+/// expand any variable references in a single string
+fn interpolate_str<F>(raw: &str, lookup: &F) -> Result<String>
+    where F: Fn(&str) -> Option<String>
+{
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(escaped) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+        } else if let Some(body_start) = tail.strip_prefix("${") {
+            let end = body_start.find('}').ok_or_else(|| anyhow!("unterminated variable reference in '{}'", raw))?;
+            let body = &body_start[..end];
+            let (name, default) = match body.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (body, None)
+            };
+            if name.is_empty() {
+                return Err(anyhow!("empty variable name in '{}'", raw));
+            }
+            match (lookup(name), default) {
+                (Some(found), _) => out.push_str(&found),
+                (None, Some(default)) => out.push_str(default),
+                (None, None) => return Err(anyhow!("environment variable '{}' referenced in '{}' is not set, and no default is given", name, raw))
+            }
+            rest = &body_start[end+1..];
+        } else {
+            out.push('$');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::collections::HashMap;
+    use crate::{entries::{RawLexicalEntry, WordGraph}, format::FileFormat};
+    use super::{interpolate_str, interpolate_value, from_str_with_env, map_from_str_with_env};
+
+    fn lookup(key: &str) -> Option<String> {
+        match key {
+            "SCRIPT_DIR" => Some("/home/user/scripts".to_string()),
+            "LANG_NAME" => Some("Old Exemplum".to_string()),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn test_basic_interpolation() {
+        let res = interpolate_str("${SCRIPT_DIR}/vowels.rhai", &lookup).unwrap();
+        assert_eq!(res, "/home/user/scripts/vowels.rhai");
+    }
+
+    #[test]
+    fn test_default() {
+        let res = interpolate_str("${MISSING:-./scripts}/vowels.rhai", &lookup).unwrap();
+        assert_eq!(res, "./scripts/vowels.rhai");
+        let res = interpolate_str("${LANG_NAME:-Unknown}", &lookup).unwrap();
+        assert_eq!(res, "Old Exemplum");
+    }
+
+    #[test]
+    fn test_escape_and_plain_dollar() {
+        let res = interpolate_str("costs $5, $${LANG_NAME}", &lookup).unwrap();
+        assert_eq!(res, "costs $5, ${LANG_NAME}");
+    }
+
+    #[test]
+    fn test_missing_var() {
+        assert!(interpolate_str("${MISSING}", &lookup).is_err());
+        assert!(interpolate_str("${LANG_NAME", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_nested_values() {
+        let mut val = json!({"words": {"test": {"language": "${LANG_NAME}", "tags": ["${MISSING:-tag}"], "archaic": true}}});
+        interpolate_value(&mut val, &lookup).unwrap();
+        assert_eq!(val, json!({"words": {"test": {"language": "Old Exemplum", "tags": ["tag"], "archaic": true}}}));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let raw = "{\n  \"words\": {\n    \"first\": {\n      \"definition\": \"garden\",\n      \"archaic\": \"yes\"\n    }\n  }\n}";
+        let err = from_str_with_env::<WordGraph>(raw, FileFormat::Json, false).unwrap_err().to_string();
+        assert!(err.starts_with("line 5, column 18, at words.first.archaic:"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_error_location_repeated_key() {
+        // the key is also written as a value before the field itself
+        let raw = "{\n  \"words\": {\n    \"archaic\": {\n      \"definition\": \"archaic\",\n      \"archaic\": \"yes\"\n    }\n  }\n}";
+        let err = map_from_str_with_env::<RawLexicalEntry>(raw, FileFormat::Json, "words", false).unwrap_err().to_string();
+        assert!(err.starts_with("line 5, column 18, at words.archaic.archaic:"), "got: {}", err);
+
+        let raw = "[transforms.shift]\nconditional = \"shift\"\n\n[[transforms.shift.transforms]]\npostfix = { value = 5 }\n";
+        let err = from_str_with_env::<crate::entries::TransformGraph>(raw, FileFormat::Toml, false).unwrap_err().to_string();
+        assert!(err.starts_with("line 2, column 15, at transforms.shift.conditional:"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let raw = "words:\n  first:\n    definition: garden\n    archaic: yes\n  second:\n    definition: tree\n  third:\n    pos: [tree]\n";
+        let err = map_from_str_with_env::<RawLexicalEntry>(raw, FileFormat::Yaml, "words", false).unwrap_err().to_string();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("line 4, column 14, at words.first.archaic:"), "got: {}", lines[0]);
+        assert!(lines[1].starts_with("line 8, column 10, at words.third.pos:"), "got: {}", lines[1]);
+
+        let found: HashMap<String, RawLexicalEntry> = map_from_str_with_env(
+            "words:\n  second:\n    definition: tree\n", FileFormat::Yaml, "words", false).unwrap();
+        assert_eq!(found["second"].definition.to_string(), "tree");
+    }
+
+    #[test]
+    fn test_strict() {
+        let raw = "words:\n  first:\n    definiton: garden\n    etymology:\n      etymons:\n        - etymon: root\n          trasforms: [shift]\n";
+        let lenient: HashMap<String, RawLexicalEntry> = map_from_str_with_env(raw, FileFormat::Yaml, "words", false).unwrap();
+        assert_eq!(lenient["first"].definition.to_string(), "");
+
+        let err = map_from_str_with_env::<RawLexicalEntry>(raw, FileFormat::Yaml, "words", true).unwrap_err().to_string();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines, vec!["line 3, column 16, at words.first.definiton: unknown field `definiton`",
+            "line 7, column 22, at words.first.etymology.etymons[0].trasforms: unknown field `trasforms`"]);
+    }
+}
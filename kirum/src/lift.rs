@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, Context};
+use libkirum::{kirum::{Lexis, LanguageTree}, word::{Example, PartOfSpeech, Etymology}};
+use serde::{Serialize, Deserialize};
+
+/// The version of the LIFT format that is written
+const LIFT_VERSION: &str = "0.13";
+/// The language code used for definitions and translations that aren't in a specific language
+pub const DEFAULT_GLOSS_LANG: &str = "en";
+/// The writing system used for IPA transcriptions
+const IPA_LANG: &str = "und-fonipa";
+
+// trait and field names used to store Kirum values that LIFT has no element for
+pub const LANGUAGE_TRAIT: &str = "language";
+pub const TYPE_TRAIT: &str = "type";
+pub const TAG_TRAIT: &str = "tag";
+pub const ARCHAIC_TRAIT: &str = "archaic";
+pub const RECONSTRUCTED_TRAIT: &str = "reconstructed";
+pub const NOUN_CLASS_TRAIT: &str = "noun-class";
+pub const SEMANTIC_DOMAIN_TRAIT: &str = "semantic-domain";
+pub const TRANSFORM_TRAIT: &str = "transform";
+pub const AGGLUTINATION_TRAIT: &str = "agglutination-order";
+
+/// The root of a LIFT (Lexicon Interchange FormaT) document, as used by FieldWorks and other lexicography tools.
+/// Only the parts of the format that map to Kirum values are modeled; anything else is ignored when reading.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename = "lift")]
+pub struct Lift {
+    #[serde(rename = "@version", default)]
+    pub version: String,
+    #[serde(rename = "entry", default)]
+    pub entries: Vec<Entry>
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Entry {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "lexical-unit", skip_serializing_if = "Option::is_none")]
+    pub lexical_unit: Option<Multitext>,
+    #[serde(rename = "pronunciation", default, skip_serializing_if = "Vec::is_empty")]
+    pub pronunciations: Vec<Multitext>,
+    #[serde(rename = "trait", default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<Trait>,
+    #[serde(rename = "field", default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    #[serde(rename = "sense", default, skip_serializing_if = "Vec::is_empty")]
+    pub senses: Vec<Sense>,
+    #[serde(rename = "etymology", default, skip_serializing_if = "Vec::is_empty")]
+    pub etymologies: Vec<LiftEtymology>
+}
+
+/// A piece of text in one or more writing systems
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Multitext {
+    #[serde(rename = "form", default)]
+    pub forms: Vec<Form>
+}
+
+impl Multitext {
+    /// the text of the first form, if there is one
+    pub fn first(&self) -> Option<String> {
+        self.forms.first().map(|f| f.text.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Form {
+    #[serde(rename = "@lang")]
+    pub lang: String,
+    #[serde(default)]
+    pub text: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Trait {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@value")]
+    pub value: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Field {
+    #[serde(rename = "@type")]
+    pub field_type: String,
+    #[serde(rename = "form", default)]
+    pub forms: Vec<Form>
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Sense {
+    #[serde(rename = "grammatical-info", skip_serializing_if = "Option::is_none")]
+    pub grammatical_info: Option<GrammaticalInfo>,
+    #[serde(rename = "gloss", default, skip_serializing_if = "Vec::is_empty")]
+    pub glosses: Vec<Form>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition: Option<Multitext>,
+    #[serde(rename = "example", default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<LiftExample>,
+    #[serde(rename = "trait", default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<Trait>
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct GrammaticalInfo {
+    #[serde(rename = "@value")]
+    pub value: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LiftExample {
+    #[serde(rename = "form", default)]
+    pub forms: Vec<Form>,
+    #[serde(rename = "translation", default, skip_serializing_if = "Vec::is_empty")]
+    pub translations: Vec<Multitext>
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LiftEtymology {
+    #[serde(rename = "@type", default)]
+    pub etymology_type: String,
+    /// The source of the etymology. Kirum writes the ID of the etymon, other tools usually write a language name
+    #[serde(rename = "@source", default)]
+    pub source: String,
+    #[serde(rename = "form", default, skip_serializing_if = "Vec::is_empty")]
+    pub forms: Vec<Form>,
+    #[serde(rename = "gloss", default, skip_serializing_if = "Vec::is_empty")]
+    pub glosses: Vec<Form>,
+    #[serde(rename = "trait", default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<Trait>
+}
+
+/// find the values of all the traits with the given name
+pub fn trait_values<'a>(traits: &'a [Trait], name: &'a str) -> impl Iterator<Item = &'a String> {
+    traits.iter().filter(move |t| t.name == name).map(|t| &t.value)
+}
+
+/// The writing system code used for forms of a language. Kirum language names aren't language codes,
+/// so a private use code is created from the name; the full name is kept in the `language` trait.
+fn writing_system(language: &str) -> String {
+    let private: String = language.chars().filter(|c| c.is_ascii_alphanumeric()).take(8).collect::<String>().to_lowercase();
+    if private.is_empty() {
+        String::from("qaa")
+    } else {
+        format!("qaa-x-{}", private)
+    }
+}
+
+fn new_trait(name: &str, value: &str) -> Trait {
+    Trait { name: name.to_string(), value: value.to_string() }
+}
+
+fn new_form(lang: &str, text: String) -> Form {
+    Form { lang: lang.to_string(), text }
+}
+
+/// create a LIFT entry from a single word, and its etymology
+fn lift_entry(word: &Lexis, ety: Option<&Etymology>, all_words: &HashMap<String, (Lexis, Etymology)>) -> Entry {
+    let ws = writing_system(&word.language);
+    let mut traits: Vec<Trait> = Vec::new();
+    if !word.language.is_empty() {
+        traits.push(new_trait(LANGUAGE_TRAIT, &word.language));
+    }
+    if !word.lexis_type.is_empty() {
+        traits.push(new_trait(TYPE_TRAIT, &word.lexis_type));
+    }
+    if word.archaic {
+        traits.push(new_trait(ARCHAIC_TRAIT, "true"));
+    }
+    if word.reconstructed {
+        traits.push(new_trait(RECONSTRUCTED_TRAIT, "true"));
+    }
+    if let Some(class) = &word.noun_class {
+        traits.push(new_trait(NOUN_CLASS_TRAIT, class));
+    }
+    traits.extend(word.tags.iter().map(|t| new_trait(TAG_TRAIT, t)));
+
+    let mut metadata: Vec<(&String, &String)> = word.historical_metadata.iter().collect();
+    metadata.sort();
+    let fields = metadata.into_iter()
+    .map(|(key, val)| Field { field_type: key.clone(), forms: vec![new_form(DEFAULT_GLOSS_LANG, val.clone())] }).collect();
+
+    let mut glosses: Vec<(&String, &String)> = word.glosses.iter().collect();
+    glosses.sort();
+    let definitions: Vec<Form> = if glosses.is_empty() {
+        vec![new_form(DEFAULT_GLOSS_LANG, word.definition.clone())]
+    } else {
+        glosses.into_iter().map(|(lang, def)| new_form(lang, def.clone())).collect()
+    };
+    let sense = Sense {
+        grammatical_info: word.pos.as_ref().filter(|p| **p != PartOfSpeech::None).map(|p| GrammaticalInfo { value: p.to_string() }),
+        glosses: Vec::new(),
+        definition: Some(Multitext { forms: definitions }),
+        examples: word.examples.iter().map(|ex| lift_example(ex, &ws)).collect(),
+        traits: word.semantic_field.iter().map(|f| new_trait(SEMANTIC_DOMAIN_TRAIT, f)).collect()
+    };
+
+    let edges = ety.map(|e| e.etymons.clone()).unwrap_or_default();
+    let etymologies = edges.into_iter().map(|edge| {
+        let mut traits: Vec<Trait> = edge.transforms.unwrap_or_default().iter().map(|t| new_trait(TRANSFORM_TRAIT, t)).collect();
+        if let Some(order) = edge.agglutination_order {
+            traits.push(new_trait(AGGLUTINATION_TRAIT, &order.to_string()));
+        }
+        let etymon = all_words.get(&edge.etymon).map(|(l, _)| l);
+        LiftEtymology {
+            etymology_type: String::from(if edge.borrowed.unwrap_or_default() {"borrowed"} else {"proto"}),
+            forms: etymon.and_then(|e| e.word.as_ref().map(|w| new_form(&writing_system(&e.language), w.string_without_sep()))).into_iter().collect(),
+            glosses: etymon.filter(|e| !e.definition.is_empty()).map(|e| new_form(DEFAULT_GLOSS_LANG, e.definition.clone())).into_iter().collect(),
+            source: edge.etymon,
+            traits
+        }
+    }).collect();
+
+    Entry {
+        id: word.id.clone(),
+        lexical_unit: word.word.as_ref().map(|w| Multitext { forms: vec![new_form(&ws, w.string_without_sep())] }),
+        pronunciations: word.ipa.iter().map(|ipa| Multitext { forms: vec![new_form(IPA_LANG, ipa.clone())] }).collect(),
+        traits,
+        fields,
+        senses: vec![sense],
+        etymologies
+    }
+}
+
+fn lift_example(example: &Example, ws: &str) -> LiftExample {
+    LiftExample {
+        forms: vec![new_form(ws, example.text.clone())],
+        translations: example.translation.iter().map(|t| Multitext { forms: vec![new_form(DEFAULT_GLOSS_LANG, t.clone())] }).collect()
+    }
+}
+
+/// Render the words as a LIFT XML document, with an entry for each word
+pub fn generate_lift(tree: &LanguageTree, rendered_lang: Vec<Lexis>) -> Result<String> {
+    let all_words: HashMap<String, (Lexis, Etymology)> = tree.to_vec_etymons(|_| true).into_iter()
+    .map(|(l, e)| (l.id.clone(), (l, e))).collect();
+    let lift = Lift {
+        version: LIFT_VERSION.to_string(),
+        entries: rendered_lang.iter().map(|l| lift_entry(l, all_words.get(&l.id).map(|(_, e)| e), &all_words)).collect()
+    };
+    let mut body = String::new();
+    let mut ser = quick_xml::se::Serializer::new(&mut body);
+    ser.indent(' ', 2);
+    lift.serialize(ser).context("could not create LIFT document")?;
+    Ok(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n", body))
+}
+
+/// Parse a LIFT XML document
+pub fn parse_lift(raw: &str) -> Result<Lift> {
+    let parsed: Lift = quick_xml::de::from_str(raw).context("could not parse LIFT document")?;
+    Ok(parsed)
+}
+
+/// Map a LIFT part of speech to a Kirum part of speech. Any that Kirum doesn't have built in are kept as user-defined values
+pub fn parse_pos(info: &GrammaticalInfo) -> Option<PartOfSpeech> {
+    info.value.parse().ok()
+}
+
+/// Collect the forms of a multitext into a map of language codes to text
+pub fn forms_by_lang(forms: &[Form]) -> HashMap<String, String> {
+    forms.iter().map(|f| (f.lang.clone(), f.text.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::{kirum::{Lexis, LanguageTree}, word::{PartOfSpeech, Example}, transforms::{Transform, TransformFunc}};
+    use super::{generate_lift, parse_lift, trait_values, writing_system};
+
+    #[test]
+    fn test_writing_system() {
+        assert_eq!(writing_system("Old Exemplum"), "qaa-x-oldexemp");
+        assert_eq!(writing_system(""), "qaa");
+    }
+
+    #[test]
+    fn test_generate_lift() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        let root = Lexis{id: "root".to_string(), word: Some("kirum".into()), language: "Old Exemplum".to_string(), lexis_type: "root".to_string(),
+            definition: "garden".to_string(), pos: Some(PartOfSpeech::Noun), tags: vec!["nature".to_string()],
+            historical_metadata: [("source".to_string(), "test & check".to_string())].into(), reconstructed: true, ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), language: "New Exemplum".to_string(), definition: "orchard".to_string(),
+            examples: vec![Example{text: "kirun amo".to_string(), gloss: None, translation: Some("the orchard keeper".to_string())}], ..Default::default()};
+        let change = Transform{name: "m-to-n".to_string(), lex_match: None,
+            transforms: vec![TransformFunc::MatchReplace { old: "m".into(), new: "n".into() }]};
+        tree.connect_etymology(derived, root, vec![change], None);
+        tree.compute_lexicon()?;
+
+        let mut words = tree.to_vec();
+        words.sort_by(|a, b| b.id.cmp(&a.id));
+        let rendered = generate_lift(&tree, words)?;
+        assert!(rendered.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<lift version=\"0.13\">"));
+        assert!(rendered.contains("test &amp; check"));
+
+        let parsed = parse_lift(&rendered)?;
+        assert_eq!(parsed.entries.len(), 2);
+        let root = &parsed.entries[0];
+        assert_eq!(root.id, "root");
+        assert_eq!(root.lexical_unit.as_ref().unwrap().first(), Some("kirum".to_string()));
+        assert_eq!(trait_values(&root.traits, "language").collect::<Vec<_>>(), vec!["Old Exemplum"]);
+        assert_eq!(trait_values(&root.traits, "tag").collect::<Vec<_>>(), vec!["nature"]);
+        assert_eq!(trait_values(&root.traits, "reconstructed").collect::<Vec<_>>(), vec!["true"]);
+        assert_eq!(root.senses[0].grammatical_info.as_ref().unwrap().value, "noun");
+        assert_eq!(root.fields[0].forms[0].text, "test & check");
+
+        let derived = &parsed.entries[1];
+        assert_eq!(derived.lexical_unit.as_ref().unwrap().first(), Some("kirun".to_string()));
+        assert_eq!(derived.etymologies[0].source, "root");
+        assert_eq!(derived.etymologies[0].forms[0].text, "kirum");
+        assert_eq!(trait_values(&derived.etymologies[0].traits, "transform").collect::<Vec<_>>(), vec!["m-to-n"]);
+        assert_eq!(derived.senses[0].examples[0].translations[0].first(), Some("the orchard keeper".to_string()));
+        Ok(())
+    }
+}
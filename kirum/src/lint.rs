@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use libkirum::{kirum::LanguageTree, word::PartOfSpeech};
+use crate::{taxonomy::Taxonomy, global::Global, stat::word_origins};
+
+/// A single problem found in a language project
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    /// ID of the lexis that caused the warning
+    pub id: String,
+    pub message: String
+}
+
+impl std::fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.id, self.message)
+    }
+}
+
+/// check a computed language tree for likely mistakes.
+/// If `unused_roots` is set, words with no etymology that nothing derives from are also reported.
+pub fn lint(tree: &LanguageTree, taxonomy: Option<&Taxonomy>, globals: &Global, unused_roots: bool) -> Vec<LintWarning> {
+    let mut warnings: Vec<LintWarning> = Vec::new();
+    for lex in tree.clone() {
+        if let (Some(field), Some(tax)) = (&lex.semantic_field, taxonomy) {
+            if !tax.contains(field) {
+                warnings.push(LintWarning { id: lex.id.clone(), message: format!("semantic field '{}' is not in the taxonomy", field) });
+            }
+        }
+        if let (Some(pos), Some(_)) = (&lex.pos, &globals.parts_of_speech) {
+            if *pos != PartOfSpeech::None && globals.part_of_speech(pos).is_none() {
+                warnings.push(LintWarning { id: lex.id.clone(), message: format!("part of speech '{}' is not declared in globals.json", pos) });
+            }
+        }
+        if let Some(word) = &lex.word {
+            if tree.is_blacklisted(word) {
+                warnings.push(LintWarning { id: lex.id.clone(), message: format!("word '{}' matches the blacklist", word.string_without_sep()) });
+            }
+        }
+    }
+    warnings.extend(check_language_chain(tree, globals));
+    warnings.extend(check_orphans(tree));
+    if unused_roots {
+        warnings.extend(tree.unused_roots().into_iter()
+        .map(|id| LintWarning { id, message: "root word has no derivatives".to_string() }));
+    }
+    warnings.sort_by(|a, b| a.id.cmp(&b.id));
+    warnings
+}
+
+/// Report every language where more than `max_percent` percent of the words were generated from the phonetic rules,
+/// rather than written or derived. The ID of each warning is the name of the language.
+pub fn check_generated(tree: &LanguageTree, max_percent: f64) -> Vec<LintWarning> {
+    word_origins(tree, |_| true).into_iter().filter_map(|(language, origins)| {
+        let total = origins.explicit + origins.derived + origins.generated;
+        let percent = origins.generated as f64 / total as f64 * 100.0;
+        (total > 0 && percent > max_percent).then(|| LintWarning { id: language,
+            message: format!("{} of {} words ({:.0}%) are generated from phonetic rules, more than {}%", origins.generated, total, percent, max_percent) })
+    }).collect()
+}
+
+/// Check the language of each etymon against the language parents declared in globals.json.
+/// A word should descend from an etymon in its own language, or its parent language; 
+/// an etymon from a more distant ancestor skips an intermediate language, and an etymon from a descendant language is anachronistic.
+/// Etymons from unrelated languages are treated as borrowings, and are not flagged.
+fn check_language_chain(tree: &LanguageTree, globals: &Global) -> Vec<LintWarning> {
+    let mut warnings: Vec<LintWarning> = Vec::new();
+    let words = tree.to_vec_etymons(|_| true);
+    let languages: HashMap<String, String> = words.iter().map(|(l, _)| (l.id.clone(), l.language.clone())).collect();
+    for (lex, ety) in &words {
+        let ancestors = globals.language_ancestors(&lex.language);
+        // loanwords can come from any language
+        for edge in ety.etymons.iter().filter(|e| !e.borrowed.unwrap_or_default()) {
+            let etymon_lang = match languages.get(&edge.etymon) {
+                Some(lang) => lang,
+                None => continue
+            };
+            if *etymon_lang == lex.language {
+                continue;
+            }
+            if let Some(pos) = ancestors.iter().position(|a| a == etymon_lang) {
+                if pos > 0 {
+                    warnings.push(LintWarning { id: lex.id.clone(), 
+                        message: format!("etymon '{}' is in {}, skipping intermediate language(s) {}", edge.etymon, etymon_lang, ancestors[..pos].join(", ")) });
+                }
+            } else if globals.language_ancestors(etymon_lang).contains(&lex.language) {
+                warnings.push(LintWarning { id: lex.id.clone(), 
+                    message: format!("etymon '{}' is in {}, which is a descendant of {}", edge.etymon, etymon_lang, lex.language) });
+            }
+        }
+    }
+    warnings
+}
+
+/// Find words that will never have a word: they have no word or generate key, 
+/// and either have no etymology or derive only from other words that have no word.
+fn check_orphans(tree: &LanguageTree) -> Vec<LintWarning> {
+    tree.orphans().into_iter().map(|id| {
+        let message = if tree.etymons(&id).is_empty() {
+            "no word, generate key, or etymology to derive a word from".to_string()
+        } else {
+            "no word can be derived, as none of its etymons have a word".to_string()
+        };
+        LintWarning { id, message }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use crate::{files::{read_and_compute, read_taxonomy, read_project_globals}, test_util::copy_project_with_rules};
+    use super::{lint, check_generated};
+
+    #[test]
+    fn test_lint_semantic_fields() -> Result<()> {
+        let directory = Some(String::from("src/test_files/taxonomy"));
+        let computed = read_and_compute(directory.clone())?;
+        let taxonomy = read_taxonomy(directory.clone())?;
+        assert!(taxonomy.is_some());
+
+        let warnings = lint(&computed, taxonomy.as_ref(), &read_project_globals(directory)?, false);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].id, "foot");
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_parts_of_speech() -> Result<()> {
+        let directory = Some(String::from("src/test_files/parts_of_speech"));
+        let computed = read_and_compute(directory.clone())?;
+        let warnings = lint(&computed, None, &read_project_globals(directory)?, false);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "quickly: part of speech 'adverb' is not declared in globals.json");
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_blacklist() -> Result<()> {
+        let directory = Some(copy_project_with_rules("blacklist", "kirum_lint_blacklist")?);
+        let computed = read_and_compute(directory.clone())?;
+        let generated = computed.get_by_id("generated").unwrap().word.unwrap().string_without_sep();
+        assert!(!generated.contains('x'));
+
+        let warnings = lint(&computed, None, &read_project_globals(directory)?, false);
+        let ids: Vec<&str> = warnings.iter().map(|w| w.id.as_str()).collect();
+        assert_eq!(ids, vec!["brand", "derived"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_language_chain() -> Result<()> {
+        let directory = Some(String::from("src/test_files/language_chain"));
+        let computed = read_and_compute(directory.clone())?;
+        let globals = read_project_globals(directory)?;
+        assert_eq!(globals.language_ancestors("Modern Exemplum"), vec!["Old Exemplum".to_string(), "Proto-Exemplum".to_string()]);
+
+        let warnings = lint(&computed, None, &globals, false);
+        let ids: Vec<&str> = warnings.iter().map(|w| w.id.as_str()).collect();
+        assert_eq!(ids, vec!["backwards", "skipped"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_orphans() -> Result<()> {
+        let directory = Some(String::from("src/test_files/orphans"));
+        let computed = read_and_compute(directory.clone())?;
+        let globals = read_project_globals(directory)?;
+
+        let warnings = lint(&computed, None, &globals, false);
+        let ids: Vec<&str> = warnings.iter().map(|w| w.id.as_str()).collect();
+        assert_eq!(ids, vec!["orphan", "orphan_child"]);
+
+        let warnings = lint(&computed, None, &globals, true);
+        let ids: Vec<&str> = warnings.iter().map(|w| w.id.as_str()).collect();
+        assert_eq!(ids, vec!["lonely", "orphan", "orphan_child"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_generated() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/with_phonetic_rules")))?;
+        // half of the Latin words are generated
+        let warnings = check_generated(&computed, 40.0);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "Latin: 1 of 2 words (50%) are generated from phonetic rules, more than 40%");
+        assert!(check_generated(&computed, 50.0).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use std::io::IsTerminal;
+
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::Lexis, word::PartOfSpeech};
+use crate::cli::{LineField, ColorChoice};
+
+/// The width used for the listing when the terminal width can't be found
+const DEFAULT_WIDTH: usize = 100;
+/// The space between columns
+const COLUMN_GAP: &str = "  ";
+
+/// The fields of a word that can be used in a format string, as they're named in tree files
+const FORMAT_FIELDS: [&str; 22] = ["id", "word", "language", "pos", "lexis_type", "definition", "glosses", "examples", "orthographies",
+    "ipa", "semantic_field", "archaic", "reconstructed", "tags", "historical_metadata", "word_create", "create_constraints", "agglutination",
+    "noun_class", "word_override", "notes", "status"];
+
+/// The fields printed when none are selected
+pub const DEFAULT_FIELDS: [LineField; 4] = [LineField::Word, LineField::Language, LineField::Pos, LineField::Gloss];
+
+/// Render the words as a column-aligned listing, with one word per line.
+/// If max_width is set, the last column is truncated so lines fit in the given number of characters.
+pub fn render_lines(words: &[Lexis], fields: &[LineField], max_width: Option<usize>, color: bool) -> String {
+    let header: Vec<String> = fields.iter().map(|f| f.title().to_string()).collect();
+    let rows: Vec<Vec<String>> = words.iter().map(|w| fields.iter().map(|f| field_value(w, *f)).collect()).collect();
+
+    // the last column isn't padded, so its width isn't needed
+    let mut widths: Vec<usize> = vec![0; fields.len().saturating_sub(1)];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (idx, width) in widths.iter_mut().enumerate() {
+            *width = (*width).max(row[idx].chars().count());
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for (row_idx, row) in std::iter::once(&header).chain(rows.iter()).enumerate() {
+        let mut line = String::new();
+        let mut used = 0;
+        for (idx, value) in row.iter().enumerate() {
+            let field = fields[idx];
+            let (text, pad) = match widths.get(idx) {
+                Some(width) => (value.clone(), width - value.chars().count()),
+                None => (truncate(value, max_width.map(|m| m.saturating_sub(used))), 0)
+            };
+            let styled = if !color || text.is_empty() {
+                text.clone()
+            } else if row_idx == 0 {
+                format!("\x1b[1;4m{}\x1b[0m", text)
+            } else {
+                match field.color() {
+                    Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+                    None => text.clone()
+                }
+            };
+            line.push_str(&styled);
+            if idx + 1 < row.len() {
+                line.push_str(&" ".repeat(pad));
+                line.push_str(COLUMN_GAP);
+                used += text.chars().count() + pad + COLUMN_GAP.len();
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+/// A single part of a parsed format string
+#[derive(Debug, PartialEq)]
+enum FormatPart {
+    Text(String),
+    Field(String)
+}
+
+/// Render the words with a format string such as `{word}\t{pos}\t{definition}`, with one word per line.
+/// `\t`, `\n` and `\\` are escapes, and `{{` and `}}` print a literal brace.
+pub fn render_format(words: &[Lexis], format: &str) -> Result<String> {
+    let parts = parse_format(format)?;
+    let mut lines: Vec<String> = Vec::new();
+    for word in words {
+        let value = serde_json::to_value(word)?;
+        let mut line = String::new();
+        for part in &parts {
+            match part {
+                FormatPart::Text(text) => line.push_str(text),
+                FormatPart::Field(name) => line.push_str(&format_value(word, &value, name))
+            }
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+fn parse_format(format: &str) -> Result<Vec<FormatPart>> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => text.push('\t'),
+                Some('n') => text.push('\n'),
+                Some('\\') => text.push('\\'),
+                Some(other) => {
+                    text.push('\\');
+                    text.push(other);
+                },
+                None => text.push('\\')
+            },
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(anyhow!("unclosed '{{' in format string '{}'", format))
+                    }
+                }
+                let name = name.trim();
+                if !is_format_field(name) {
+                    return Err(anyhow!("unknown field '{}' in format string, expected one of {}, or meta.KEY, glosses.LANG or orthographies.NAME",
+                        name, FORMAT_FIELDS.join(", ")));
+                }
+                if !text.is_empty() {
+                    parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(FormatPart::Field(name.to_string()));
+            },
+            '}' => return Err(anyhow!("unmatched '}}' in format string '{}', use '}}}}' for a literal brace", format)),
+            c => text.push(c)
+        }
+    }
+    if !text.is_empty() {
+        parts.push(FormatPart::Text(text));
+    }
+    Ok(parts)
+}
+
+/// map the short names used elsewhere, such as in search queries, to the name of the field
+fn format_field_name(name: &str) -> &str {
+    match name {
+        "type" => "lexis_type",
+        "gloss" | "def" => "definition",
+        "part_of_speech" => "pos",
+        "lang" => "language",
+        "metadata" | "meta" => "historical_metadata",
+        other => other
+    }
+}
+
+fn is_format_field(name: &str) -> bool {
+    match name.split_once('.') {
+        Some((map, key)) => !key.is_empty() && ["meta", "metadata", "glosses", "orthographies"].contains(&map),
+        None => FORMAT_FIELDS.contains(&format_field_name(name))
+    }
+}
+
+/// the value of a field in a format string, from the serialized word.
+/// Lists are joined with commas, and whitespace is collapsed so a value can't break the line.
+fn format_value(word: &Lexis, value: &serde_json::Value, name: &str) -> String {
+    let field = match name.split_once('.') {
+        Some(("meta" | "metadata", key)) => word.historical_metadata.get(key).cloned().map(serde_json::Value::String),
+        Some((map, key)) => value.get(map).and_then(|m| m.get(key)).cloned(),
+        None => value.get(format_field_name(name)).cloned()
+    };
+    let text = match field {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => s,
+        Some(serde_json::Value::Array(items)) if items.iter().all(|i| i.is_string()) => {
+            items.iter().filter_map(|i| i.as_str()).collect::<Vec<&str>>().join(", ")
+        },
+        Some(other) => other.to_string()
+    };
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Determine if the listing should be colored
+pub fn use_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+    }
+}
+
+/// The width of the terminal, from the COLUMNS environment variable. None if stdout isn't a terminal,
+/// so output that's piped or redirected to a file is never shortened
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None
+    }
+    Some(std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(DEFAULT_WIDTH))
+}
+
+fn field_value(word: &Lexis, field: LineField) -> String {
+    let value = match field {
+        LineField::Id => word.id.clone(),
+        LineField::Word => word.word.clone().map(|w| w.string_without_sep()).unwrap_or_default(),
+        LineField::Language => word.language.clone(),
+        LineField::Pos => word.pos.as_ref().filter(|p| **p != PartOfSpeech::None).map(|p| p.to_string()).unwrap_or_default(),
+        LineField::Gloss => word.definition.clone(),
+        LineField::Type => word.lexis_type.clone(),
+        LineField::Ipa => word.ipa.clone().unwrap_or_default(),
+        LineField::Tags => word.tags.join(", "),
+        LineField::Status => word.status.map(|s| s.to_string()).unwrap_or_default(),
+        LineField::Notes => word.notes.clone().unwrap_or_default(),
+    };
+    // a value that spans lines would break the columns
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// shorten a value to the given number of characters, ending it with an ellipsis
+fn truncate(value: &str, max: Option<usize>) -> String {
+    match max {
+        Some(max) if value.chars().count() > max => {
+            let short: String = value.chars().take(max.saturating_sub(1)).collect();
+            format!("{}…", short.trim_end())
+        },
+        _ => value.to_string()
+    }
+}
+
+impl LineField {
+    fn title(&self) -> &str {
+        match self {
+            LineField::Id => "ID",
+            LineField::Word => "WORD",
+            LineField::Language => "LANGUAGE",
+            LineField::Pos => "POS",
+            LineField::Gloss => "GLOSS",
+            LineField::Type => "TYPE",
+            LineField::Ipa => "IPA",
+            LineField::Tags => "TAGS",
+            LineField::Status => "STATUS",
+            LineField::Notes => "NOTES",
+        }
+    }
+
+    /// the ANSI color code of the field
+    fn color(&self) -> Option<&str> {
+        match self {
+            LineField::Word => Some("1"),
+            LineField::Language => Some("36"),
+            LineField::Pos => Some("33"),
+            LineField::Ipa => Some("35"),
+            LineField::Status => Some("32"),
+            LineField::Id | LineField::Tags | LineField::Notes => Some("2"),
+            LineField::Gloss | LineField::Type => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::{kirum::Lexis, word::PartOfSpeech};
+    use crate::cli::LineField;
+    use super::{render_format, render_lines, DEFAULT_FIELDS};
+
+    fn test_words() -> Vec<Lexis> {
+        vec![
+            Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(),
+                definition: "garden".to_string(), pos: Some(PartOfSpeech::Noun), ..Default::default()},
+            Lexis{id: "two".to_string(), word: Some("kirumaiu".into()), language: "New Exemplum".to_string(),
+                definition: "a walled garden,\n or orchard".to_string(), ..Default::default()},
+        ]
+    }
+
+    #[test]
+    fn test_render_lines() {
+        let rendered = render_lines(&test_words(), &DEFAULT_FIELDS, None, false);
+        let expected = "WORD      LANGUAGE      POS   GLOSS\n\
+        kira      Old Exemplum  noun  garden\n\
+        kirumaiu  New Exemplum        a walled garden, or orchard";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_render_lines_truncated() {
+        let rendered = render_lines(&test_words(), &[LineField::Id, LineField::Gloss], Some(15), false);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[1], "one  garden");
+        assert_eq!(lines[2], "two  a walled…");
+    }
+
+    #[test]
+    fn test_render_format() {
+        let mut words = test_words();
+        words[0].tags = vec!["old".to_string(), "plant".to_string()];
+        words[0].historical_metadata.insert("era".to_string(), "early".to_string());
+        let rendered = render_format(&words, "{word}\\t{pos}\\t{definition} [{tags}] {{{meta.era}}} {archaic}").unwrap();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "kira\tnoun\tgarden [old, plant] {early} false");
+        assert_eq!(lines[1], "kirumaiu\t\ta walled garden, or orchard [] {} false");
+        assert_eq!(render_format(&words, "{id}: {type}{gloss}").unwrap(), "one: garden\ntwo: a walled garden, or orchard");
+
+        assert!(render_format(&words, "{spelling}").is_err());
+        assert!(render_format(&words, "{word").is_err());
+        assert!(render_format(&words, "word}").is_err());
+    }
+
+    #[test]
+    fn test_render_lines_color() {
+        let rendered = render_lines(&test_words(), &[LineField::Word, LineField::Language], None, true);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[1], "\x1b[1mkira\x1b[0m      \x1b[36mOld Exemplum\x1b[0m");
+    }
+}
@@ -0,0 +1,363 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use anyhow::{Result, Context, anyhow};
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _, PublishDiagnostics},
+    request::{Completion, GotoDefinition, Request as _},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse, Diagnostic, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse,
+    Location, OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde_json::Value;
+use crate::{files::handle_directory, format::FileFormat, interpolate::find_offset};
+
+/// The kind of project file being edited, detected from the directory it's in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FileKind {
+    Tree,
+    Etymology
+}
+
+impl FileKind {
+    fn from_path(path: &Path) -> Option<FileKind> {
+        match path.parent()?.file_name()?.to_str()? {
+            "tree" => Some(FileKind::Tree),
+            "etymology" => Some(FileKind::Etymology),
+            _ => None
+        }
+    }
+}
+
+/// The word IDs and transform names defined in a project, along with where each one is defined
+#[derive(Default, Debug)]
+pub struct ProjectIndex {
+    pub words: HashMap<String, (PathBuf, Range)>,
+    pub transforms: HashMap<String, (PathBuf, Range)>
+}
+
+impl ProjectIndex {
+    /// Index every tree and etymology file in the project. Files in `open` are read from the editor instead of the disk,
+    /// so unsaved changes are included.
+    pub fn build(root: &Path, open: &HashMap<PathBuf, String>) -> Result<ProjectIndex> {
+        let project = handle_directory(&root.to_string_lossy())?;
+        let mut index = ProjectIndex::default();
+        for (paths, section, target) in [(&project.graphs, "words", &mut index.words), (&project.transforms, "transforms", &mut index.transforms)] {
+            for path in paths {
+                let raw = match open.get(path) {
+                    Some(raw) => raw.clone(),
+                    None => std::fs::read_to_string(path).context(format!("error reading {}", path.display()))?
+                };
+                let parsed = match FileFormat::from_path(path).parse_value(&raw) {
+                    Ok(parsed) => parsed,
+                    // files that don't parse are reported when they're opened
+                    Err(_) => continue
+                };
+                for key in parsed.get(section).and_then(|s| s.as_object()).map(|s| s.keys()).into_iter().flatten() {
+                    let range = key_range(&raw, section, key).unwrap_or_default();
+                    target.insert(key.clone(), (path.clone(), range));
+                }
+            }
+        }
+        Ok(index)
+    }
+}
+
+/// Check a project file for errors: files that can't be parsed, and etymons or transforms that don't exist in the project
+pub fn diagnostics(raw: &str, format: FileFormat, kind: FileKind, index: &ProjectIndex) -> Vec<Diagnostic> {
+    let parsed = match format.parse_value(raw) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let line = e.downcast_ref::<serde_json::Error>().map(|j| j.line().saturating_sub(1) as u32).unwrap_or(0);
+            return vec![error(Range::new(Position::new(line, 0), Position::new(line, 0)), format!("{:#}", e))];
+        }
+    };
+    if kind != FileKind::Tree {
+        return Vec::new();
+    }
+    let mut found: Vec<Diagnostic> = Vec::new();
+    let words = parsed.get("words").and_then(|w| w.as_object()).into_iter().flatten();
+    for (id, word) in words {
+        let edges = word.pointer("/etymology/etymons").and_then(|e| e.as_array()).into_iter().flatten();
+        for edge in edges {
+            if let Some(etymon) = edge.get("etymon").and_then(|e| e.as_str()) {
+                if !index.words.contains_key(etymon) {
+                    found.push(error(value_range(raw, id, etymon), format!("etymon '{}' does not exist", etymon)));
+                }
+            }
+            for name in edge.get("transforms").and_then(|t| t.as_array()).into_iter().flatten().filter_map(Value::as_str) {
+                if !index.transforms.contains_key(name) {
+                    found.push(error(value_range(raw, id, name), format!("transform '{}' does not exist", name)));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Find the definition of the word ID or transform name under the cursor
+pub fn definition(raw: &str, pos: Position, index: &ProjectIndex) -> Option<(PathBuf, Range)> {
+    let value = string_at(raw, pos)?;
+    index.words.get(&value).or_else(|| index.transforms.get(&value)).cloned()
+}
+
+/// Complete word IDs after an `etymon` key, and transform names inside a `transforms` list
+pub fn completions(raw: &str, pos: Position, index: &ProjectIndex) -> Vec<CompletionItem> {
+    let offset = match to_offset(raw, pos) {
+        Some(offset) => offset,
+        None => return Vec::new()
+    };
+    let before = &raw[..offset];
+    let line = before.rsplit('\n').next().unwrap_or_default();
+    // only complete inside a string
+    if line.matches('"').count().is_multiple_of(2) {
+        return Vec::new();
+    }
+    let (names, kind) = if line.trim_start().starts_with("\"etymon\"") {
+        (&index.words, CompletionItemKind::REFERENCE)
+    } else if before.rfind("\"transforms\"").is_some_and(|start| before[start..].matches('[').count() > before[start..].matches(']').count()) {
+        (&index.transforms, CompletionItemKind::FUNCTION)
+    } else {
+        return Vec::new();
+    };
+    let mut items: Vec<CompletionItem> = names.keys()
+    .map(|name| CompletionItem { label: name.clone(), kind: Some(kind), ..Default::default() }).collect();
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items
+}
+
+fn error(range: Range, message: String) -> Diagnostic {
+    Diagnostic { range, severity: Some(DiagnosticSeverity::ERROR), source: Some("kirum".to_string()), message, ..Default::default() }
+}
+
+/// the range of the key of an entry in a section, such as `words.<id>`
+fn key_range(raw: &str, section: &str, key: &str) -> Option<Range> {
+    let end = find_offset(raw, &format!("{}.{}", section, key))?;
+    let start = raw[..(end + 1).min(raw.len())].rfind(key)?;
+    Some(Range::new(to_position(raw, start), to_position(raw, start + key.len())))
+}
+
+/// the range of the first quoted value after the key of a word
+fn value_range(raw: &str, id: &str, value: &str) -> Range {
+    let start = find_offset(raw, &format!("words.{}", id)).unwrap_or(0);
+    match raw[start..].find(&format!("\"{}\"", value)) {
+        Some(found) => Range::new(to_position(raw, start + found + 1), to_position(raw, start + found + 1 + value.len())),
+        None => Range::new(to_position(raw, start), to_position(raw, start))
+    }
+}
+
+/// the contents of the string under the cursor
+fn string_at(raw: &str, pos: Position) -> Option<String> {
+    let offset = to_offset(raw, pos)?;
+    let line_start = raw[..offset].rfind('\n').map(|l| l + 1).unwrap_or(0);
+    let line_end = raw[offset..].find('\n').map(|l| offset + l).unwrap_or(raw.len());
+    let line = &raw[line_start..line_end];
+    let cursor = offset - line_start;
+    let quotes: Vec<usize> = line.match_indices('"').map(|(i, _)| i).collect();
+    quotes.chunks(2).find(|pair| pair.len() == 2 && pair[0] < cursor && cursor <= pair[1])
+    .map(|pair| line[pair[0] + 1..pair[1]].to_string())
+}
+
+/// convert a byte offset to an LSP position, which counts characters in UTF-16 code units
+fn to_position(raw: &str, offset: usize) -> Position {
+    let before = &raw[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|l| l + 1).unwrap_or(0);
+    Position::new(line as u32, before[line_start..].encode_utf16().count() as u32)
+}
+
+fn to_offset(raw: &str, pos: Position) -> Option<usize> {
+    let line_start = if pos.line == 0 {0} else {raw.match_indices('\n').nth(pos.line as usize - 1)?.0 + 1};
+    let line = raw[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= pos.character as usize {
+            return Some(line_start + idx);
+        }
+        units += c.len_utf16();
+    }
+    Some(line_start + line.len())
+}
+
+/// The open documents, and the project each one belongs to
+#[derive(Default)]
+struct Server {
+    open: HashMap<PathBuf, String>
+}
+
+impl Server {
+    /// the project root of a tree or etymology file, along with the index of that project
+    fn index_for(&self, path: &Path) -> Option<(FileKind, ProjectIndex)> {
+        let kind = FileKind::from_path(path)?;
+        let root = path.parent()?.parent()?;
+        match ProjectIndex::build(root, &self.open) {
+            Ok(index) => Some((kind, index)),
+            Err(e) => {
+                error!("could not index project {}: {:#}", root.display(), e);
+                None
+            }
+        }
+    }
+
+    fn publish(&self, connection: &Connection, uri: Url) -> Result<()> {
+        let path = uri.to_file_path().map_err(|_| anyhow!("{} is not a file", uri))?;
+        let diags = match (self.open.get(&path), self.index_for(&path)) {
+            (Some(raw), Some((kind, index))) => diagnostics(raw, FileFormat::from_path(&path), kind, &index),
+            _ => Vec::new()
+        };
+        let params = PublishDiagnosticsParams { uri, diagnostics: diags, version: None };
+        connection.sender.send(Message::Notification(Notification::new(PublishDiagnostics::METHOD.to_string(), params)))?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, connection: &Connection, note: Notification) -> Result<()> {
+        match note.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(note.params)?;
+                if let Ok(path) = params.text_document.uri.to_file_path() {
+                    self.open.insert(path, params.text_document.text);
+                }
+                self.publish(connection, params.text_document.uri)?;
+            },
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(note.params)?;
+                if let (Ok(path), Some(change)) = (params.text_document.uri.to_file_path(), params.content_changes.into_iter().last()) {
+                    self.open.insert(path, change.text);
+                }
+                self.publish(connection, params.text_document.uri)?;
+            },
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(note.params)?;
+                if let Ok(path) = params.text_document.uri.to_file_path() {
+                    self.open.remove(&path);
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, req: Request) -> Result<Response> {
+        let result = match req.method.as_str() {
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = serde_json::from_value(req.params)?;
+                let doc = params.text_document_position_params;
+                let found = self.document(&doc.text_document.uri).and_then(|(raw, index)| definition(&raw, doc.position, &index));
+                let location = found.and_then(|(path, range)| Url::from_file_path(path).ok().map(|uri| Location { uri, range }));
+                serde_json::to_value(location.map(GotoDefinitionResponse::Scalar))?
+            },
+            Completion::METHOD => {
+                let params: CompletionParams = serde_json::from_value(req.params)?;
+                let doc = params.text_document_position;
+                let items = self.document(&doc.text_document.uri).map(|(raw, index)| completions(&raw, doc.position, &index)).unwrap_or_default();
+                serde_json::to_value(CompletionResponse::Array(items))?
+            },
+            _ => return Ok(Response::new_err(req.id, lsp_server::ErrorCode::MethodNotFound as i32, format!("unsupported method {}", req.method)))
+        };
+        Ok(Response::new_ok(req.id, result))
+    }
+
+    /// the text of an open document, and the index of its project
+    fn document(&self, uri: &Url) -> Option<(String, ProjectIndex)> {
+        let path = uri.to_file_path().ok()?;
+        let raw = self.open.get(&path)?.clone();
+        let (_, index) = self.index_for(&path)?;
+        Some((raw, index))
+    }
+}
+
+/// Run a language server over stdin and stdout, for editing project files
+pub fn run_lsp() -> Result<String> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions { trigger_characters: Some(vec!["\"".to_string()]), ..Default::default() }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?).map_err(|e| anyhow!("could not start language server: {}", e))?;
+    let mut server = Server::default();
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req).map_err(|e| anyhow!("error shutting down: {}", e))? {
+                    break;
+                }
+                let id = req.id.clone();
+                let resp = server.handle_request(req)
+                .unwrap_or_else(|e| Response::new_err(id, lsp_server::ErrorCode::InternalError as i32, format!("{:#}", e)));
+                connection.sender.send(Message::Response(resp))?;
+            },
+            Message::Notification(note) => {
+                if let Err(e) = server.handle_notification(&connection, note) {
+                    error!("error handling notification: {:#}", e);
+                }
+            },
+            Message::Response(_) => {}
+        }
+    }
+    // the writer thread only stops once the connection is dropped
+    drop(connection);
+    io_threads.join()?;
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::{Path, PathBuf}};
+    use anyhow::Result;
+    use lsp_types::Position;
+    use crate::format::FileFormat;
+    use super::{ProjectIndex, FileKind, diagnostics, definition, completions};
+
+    const PROJECT: &str = "src/test_files/test_der";
+    const TREE: &str = "{\n  \"words\": {\n    \"new_word\": {\n      \"etymology\": {\n        \"etymons\": [\n          {\n            \"etymon\": \"latin_verb\",\n            \"transforms\": [\"latin-from-verb\", \"missing-transform\"]\n          },\n          {\n            \"etymon\": \"missing_word\"\n          }\n        ]\n      }\n    }\n  }\n}";
+
+    fn index() -> Result<ProjectIndex> {
+        let open: HashMap<PathBuf, String> = HashMap::from([(Path::new(PROJECT).join("tree/new.json"), TREE.to_string())]);
+        ProjectIndex::build(Path::new(PROJECT), &open)
+    }
+
+    #[test]
+    fn test_lsp_diagnostics() -> Result<()> {
+        let index = index()?;
+        assert!(index.words.contains_key("latin_verb"));
+        assert!(index.transforms.contains_key("latin-from-verb"));
+
+        let found = diagnostics(TREE, FileFormat::Json, FileKind::Tree, &index);
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["transform 'missing-transform' does not exist", "etymon 'missing_word' does not exist"]);
+        assert_eq!(found[0].range.start, Position::new(7, 47));
+        assert_eq!(found[1].range.start, Position::new(10, 23));
+
+        let broken = diagnostics("{\n  \"words\": {", FileFormat::Json, FileKind::Tree, &index);
+        assert_eq!(broken.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lsp_definition() -> Result<()> {
+        let index = index()?;
+        let (path, range) = definition(TREE, Position::new(6, 26), &index).unwrap();
+        assert!(path.starts_with(Path::new(PROJECT).join("tree")));
+        let raw = std::fs::read_to_string(&path)?;
+        let line = raw.lines().nth(range.start.line as usize).unwrap();
+        assert_eq!(&line[range.start.character as usize..range.end.character as usize], "latin_verb");
+
+        assert!(definition(TREE, Position::new(7, 35), &index).unwrap().0.to_string_lossy().contains("etymology"));
+        assert!(definition(TREE, Position::new(1, 2), &index).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lsp_completions() -> Result<()> {
+        let index = index()?;
+        let words = completions(TREE, Position::new(6, 24), &index);
+        assert!(words.iter().any(|c| c.label == "latin_verb"));
+        let transforms = completions(TREE, Position::new(7, 29), &index);
+        assert!(transforms.iter().any(|c| c.label == "latin-from-verb"));
+        assert!(!transforms.iter().any(|c| c.label == "latin_verb"));
+        assert!(completions(TREE, Position::new(2, 2), &index).is_empty());
+        Ok(())
+    }
+}
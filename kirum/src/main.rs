@@ -8,15 +8,53 @@ mod generate;
 mod ingest;
 mod import;
 mod global;
+mod interpolate;
+mod taxonomy;
+mod lint;
+mod reverse;
+mod family;
+mod shell;
+mod explain;
+mod html;
+mod markdown;
+mod tabular;
+mod edit;
+mod mdf;
+mod transaction;
+mod lift;
+mod header;
+mod xdxf;
+mod listing;
+mod concepts;
+mod format;
+mod schema;
+mod graphml;
+mod serve;
+mod browse;
+mod render;
+mod watch;
+mod lsp;
+mod add;
+mod rm;
+mod search;
+mod merge;
+mod fmt;
+mod bake;
+mod cache;
+mod progress;
+mod dryrun;
+mod testing;
+mod borrow;
+#[cfg(test)]
+mod test_util;
 
 use clap::Parser;
-use entries::create_json_graph;
-use files::{read_and_compute, apply_def_vars};
+use files::{read_and_compute, read_taxonomy, read_project_globals};
+use taxonomy::semantic_filter;
 use new::create_new_project;
-use anyhow::{Result, Context};
+use anyhow::{Result, Context, anyhow};
 use stat::gen_stats;
-use std::{fs::File, io::Write};
-//use csv::WriterBuilder;
+use std::{collections::HashMap, fs::File, io::Write};
 use env_logger::Builder;
 use log::LevelFilter;
 
@@ -24,9 +62,8 @@ use log::LevelFilter;
 #[macro_use]
 extern crate log;
 
-fn main() -> Result<()> {
-    let cli = cli::Args::parse();
-
+/// set up logging from the verbosity, log file and per-module filters specified on the command line
+fn init_logger(cli: &cli::Args) -> Result<()> {
     let log_level: log::LevelFilter = if cli.verbose == 0 {
         LevelFilter::Info
     } else if cli.verbose ==1 {
@@ -34,9 +71,38 @@ fn main() -> Result<()> {
     } else {
         LevelFilter::Trace
     };
+    let mut builder = Builder::new();
+    builder.filter_level(log_level);
+    for module_filter in &cli.log_filter {
+        if !module_filter.contains('=') {
+            return Err(anyhow!("could not parse log filter '{}', expecting module=level", module_filter));
+        }
+        builder.parse_filters(module_filter);
+    }
+    if let Some(path) = &cli.log_file {
+        let log_file = File::create(path).context(format!("could not create log file {}", path))?;
+        builder.target(env_logger::Target::Pipe(Box::new(log_file)));
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": buf.timestamp_millis().to_string(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string()
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    builder.init();
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = cli::Args::parse();
+
     if !cli.quiet {
-        Builder::new().filter_level(log_level).init();    
+        init_logger(&cli)?;
     }
+    progress::enable(!cli.quiet);
     
 
     let out_data: String = match cli.command.clone(){
@@ -44,59 +110,147 @@ fn main() -> Result<()> {
             create_new_project(&name)?;
             format!("created new project {}", name)
         },
-        cli::Commands::Graphviz{directory} =>{
+        cli::Commands::Completions { shell } => {
+            shell::completions(shell)?
+        },
+        cli::Commands::Manpages { directory } => {
+            shell::manpages(&directory)?
+        },
+        cli::Commands::Schema { file } => {
+            schema::generate_schema(file)?
+        },
+        cli::Commands::Serve { directory, address, port } => {
+            serve::serve(directory, address, port)?
+        },
+        cli::Commands::Lsp => {
+            lsp::run_lsp()?
+        },
+        cli::Commands::Add { directory, file, id, word, definition, language, pos, word_type, etymon, transform, generate, tag } => {
+            let entry = add::NewEntry { id, word, definition, language, part_of_speech: pos, word_type,
+                etymons: etymon, transforms: transform, generate, tags: tag };
+            add::add_entry(directory, file, entry)?
+        },
+        cli::Commands::Rm { directory, id, drop, reparent } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            let derivatives = if drop {
+                rm::Derivatives::Drop
+            } else if reparent {
+                rm::Derivatives::Reparent
+            } else {
+                rm::Derivatives::Orphan
+            };
+            rm::remove_entry(&dir, &id, derivatives)?
+        },
+        cli::Commands::Mv { directory, old, new } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            let changed = edit::rename_id(&dir, &old, &new)?;
+            format!("renamed '{}' to '{}' in {} files", old, new, changed)
+        },
+        cli::Commands::Bake { directory, ids } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            bake::bake(&dir, &ids)?
+        },
+        cli::Commands::Test { directory, filter, snapshot, update } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            if snapshot {
+                testing::check_snapshot(&dir, update)?
+            } else {
+                testing::run_tests(&dir, filter.as_deref())?
+            }
+        },
+        cli::Commands::Search { directory, query, format } => {
             let computed = read_and_compute(directory)?;
-            computed.graphviz()
+            search::search(&computed, &query, format)?
+        },
+        cli::Commands::Merge { directory, other, on_conflict, prefix } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            merge::merge(&dir, &other, on_conflict, prefix)?
         },
-        cli::Commands::Stat { directory } => {
+        cli::Commands::Fmt { directory, check } => {
+            let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+            let changed = fmt::format_project(&dir, check)?;
+            if check {
+                String::from("all files are formatted")
+            } else {
+                format!("formatted {} files", changed.len())
+            }
+        },
+        cli::Commands::Graphviz{directory, highlight, cache} =>{
+            let computed = cache::read_and_compute_cached(directory, cache)?;
+            if highlight {
+                let mut colors: HashMap<String, String> = computed.unused_roots().into_iter().map(|id| (id, "gray".to_string())).collect();
+                colors.extend(computed.orphans().into_iter().map(|id| (id, "red".to_string())));
+                computed.graphviz_highlighted(&colors)
+            } else {
+                computed.graphviz()
+            }
+        },
+        cli::Commands::Stat { directory, semantic_field, distance, segments, ngrams, lengths, cache } => {
+            let computed = cache::read_and_compute_cached(directory.clone(), cache)?;
+            let taxonomy = read_taxonomy(directory)?;
+            let languages = distance.map(|mut langs| (langs.remove(0), langs.remove(0)));
+            gen_stats(computed, semantic_filter(semantic_field, taxonomy.as_ref()), languages, segments, ngrams, lengths)
+        },
+        cli::Commands::Family { directory, id } => {
+            let computed = read_and_compute(directory)?;
+            family::render_family(&computed, &id)?
+        },
+        cli::Commands::Browse { directory } => {
+            browse::browse(directory)?
+        },
+        cli::Commands::Explain { directory, id, format, show_applied, show_functions } => {
             let computed = read_and_compute(directory)?;
-            gen_stats(computed)
+            explain::render_explain(&computed, &id, format, show_applied, show_functions)?
+        },
+        cli::Commands::Lint { directory, unused_roots, max_generated } => {
+            let computed = read_and_compute(directory.clone())?;
+            let taxonomy = read_taxonomy(directory.clone())?;
+            let globals = read_project_globals(directory)?;
+            let mut warnings = lint::lint(&computed, taxonomy.as_ref(), &globals, unused_roots);
+            if let Some(max) = max_generated {
+                warnings.extend(lint::check_generated(&computed, max));
+            }
+            if !warnings.is_empty() {
+                let report: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+                return Err(anyhow!("{}\nfound {} problems", report.join("\n"), warnings.len()));
+            }
+            String::from("no problems found")
         },
         cli::Commands::Ingest {command, directory, out, overrides} => {
             import::ingest_from_cli(overrides, directory, out, command)?;
             String::from("")
         },
-        cli::Commands::Render{command, directory, variables} =>{
-            let computed = read_and_compute(directory)?;
-            debug!("computed {} raw entries", computed.len());
-            let mut rendered_dict = computed.to_vec();
-            apply_def_vars(variables, &mut rendered_dict)?;
-            debug!("rendered lexicon of {} lemmas", rendered_dict.len());
-            match command{
-                cli::Format::Line =>{
-                    let mut acc = String::new();
-                    for word in rendered_dict {
-                        acc = format!("{}\n{:?}", acc, word)
-                    }
-                    acc
-                },
-                // CSV is disabled because of serializing issues
-                // cli::Format::Csv =>{
-                //     let mut wrt = WriterBuilder::new().has_headers(true).from_writer(vec![]);
-                //     for word in rendered_dict {
-                //         wrt.serialize(word)?;
-                        
-                //     }
-                //    String::from_utf8(wrt.into_inner()?)?
-                // },
-                cli::Format::Template { template_file, rhai_files } =>{
-                    tmpl::generate_from_tmpl(rendered_dict, template_file, rhai_files)?
-                },
-                cli::Format::Json => {
-                    let words = computed.to_vec_etymons(|_|true);
-                    let word_data = create_json_graph(words, |l| l.id, false)
-                    .context("could not create map from language data")?;
-                    serde_json::to_string_pretty(&word_data)?
+        cli::Commands::Render(args) => {
+            render::render(args)?
+        },
+        cli::Commands::Watch(args) => {
+            watch::watch(args, cli.output.clone())?
+        },
+        cli::Commands::Edit { command } => {
+            match command {
+                cli::Edit::RenameLanguage { directory, old, new } => rename(directory, edit::RenameField::Language, old, new)?,
+                cli::Edit::RenameType { directory, old, new } => rename(directory, edit::RenameField::LexisType, old, new)?,
+                cli::Edit::RenameTag { directory, old, new } => rename(directory, edit::RenameField::Tag, old, new)?,
+                cli::Edit::Entry { directory, id, set, add_tag, remove_tag } => {
+                    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+                    let changes = edit::EntryChanges { set, add_tags: add_tag, remove_tags: remove_tag };
+                    let path = edit::edit_entry(&dir, &id, &changes)?;
+                    format!("edited {} in {}", id, path)
                 }
-                
             }
         },
         cli::Commands::Generate{command} =>{
             match command{
-                cli::Generate::Daughter { daughter_etymology, ancestor, 
-                    name:lang_name, directory, output, group_by: separate_by_field } =>{
+                cli::Generate::Daughter { daughter_etymology, ancestor, r#where, 
+                    name:lang_name, directory, output, group_by: separate_by_field, in_place: _ } =>{
                     generate::daughter(daughter_etymology, 
-                        ancestor, lang_name, directory, output, separate_by_field)?
+                        ancestor, r#where, lang_name, directory, output, separate_by_field)?
+                },
+                cli::Generate::Concepts { directory, list, language, generate } => {
+                    concepts::scaffold(directory, list, language, generate)?
+                },
+                cli::Generate::Borrow { directory, from, to, count, adapt, seed } => {
+                    borrow::borrow(directory, &from, &to, count, adapt, seed)?
                 }
                 
             }
@@ -113,5 +267,9 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-
-
+/// rename a field across the project, returning a summary of the change
+fn rename(directory: Option<String>, field: edit::RenameField, old: String, new: String) -> Result<String> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    let changed = edit::rename(&dir, field, &old, &new)?;
+    Ok(format!("renamed '{}' to '{}' in {} files", old, new, changed))
+}
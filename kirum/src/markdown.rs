@@ -0,0 +1,61 @@
+use handlebars::Handlebars;
+use libkirum::kirum::{Lexis, LanguageTree};
+use anyhow::Result;
+use serde::Serialize;
+use crate::tmpl::{TemplateWord, TemplateContext};
+
+const MARKDOWN_TEMPLATE: &str = include_str!("templates/dictionary.md.hbs");
+
+#[derive(Serialize)]
+struct MarkdownContext {
+    title: String,
+    #[serde(flatten)]
+    context: TemplateContext
+}
+
+/// Render a markdown dictionary, with a section for each language, and each word as a definition list entry
+pub fn generate_markdown(tree: &LanguageTree, rendered_lang: Vec<Lexis>, title: String) -> Result<String> {
+    let mut reg = Handlebars::new();
+    reg.register_escape_fn(escape_markdown);
+    reg.register_template_string("markdown", MARKDOWN_TEMPLATE)?;
+
+    let vowels = tree.vowels();
+    let index = tree.index();
+    let words: Vec<TemplateWord> = rendered_lang.into_iter().map(|l| TemplateWord::new(l, &index, &vowels)).collect();
+    Ok(reg.render("markdown", &MarkdownContext { title, context: words.into() })?)
+}
+
+/// escape characters that would otherwise be read as markdown formatting
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::{kirum::{Lexis, LanguageTree}, word::PartOfSpeech};
+    use super::generate_markdown;
+
+    #[test]
+    fn test_generate_markdown() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        let one = Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(), 
+            definition: "garden".to_string(), pos: Some(PartOfSpeech::Noun), ..Default::default()};
+        let two = Lexis{id: "two".to_string(), word: Some("amo".into()), language: "New Exemplum".to_string(), definition: "*keeper*".to_string(), ..Default::default()};
+        let three = Lexis{id: "three".to_string(), word: Some("kirum".into()), language: "Old Exemplum".to_string(), definition: "gardens".to_string(), ..Default::default()};
+        tree.connect_etymology(three, one, Vec::new(), None);
+        tree.add_lexis(two);
+
+        let rendered = generate_markdown(&tree, tree.to_vec(), "Exemplum".to_string())?;
+        let expected = "# Exemplum\n\n## New Exemplum\n\n**amo**\n: \\*keeper\\*\n\n## Old Exemplum\n\n**kira**\n: *noun* garden\n\n**kirum**\n: gardens ← *kira* (Old Exemplum)\n";
+        assert_eq!(rendered, expected);
+        Ok(())
+    }
+}
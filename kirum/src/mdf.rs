@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use libkirum::{kirum::{Lexis, LanguageTree}, word::PartOfSpeech};
+
+/// The header that marks a file as an MDF database for SIL Toolbox
+const MDF_HEADER: &str = "\\_sh v3.0  400  MDF 4.0";
+
+/// Render the words as MDF (Multi-Dictionary Formatter) records, which can be opened by SIL Toolbox, or imported into FLEx.
+/// If language is set, only words of that language are included, as Toolbox databases hold a single language.
+pub fn generate_mdf(tree: &LanguageTree, rendered_lang: Vec<Lexis>, language: Option<String>) -> String {
+    let words: Vec<Lexis> = rendered_lang.into_iter()
+    .filter(|l| l.word.is_some() && language.as_ref().map(|lang| *lang == l.language).unwrap_or(true))
+    .collect();
+
+    // words with the same form in the same language are homonyms, and get a \hm number
+    let mut forms: HashMap<(String, String), usize> = HashMap::new();
+    for word in &words {
+        *forms.entry(form_key(word)).or_default() += 1;
+    }
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+
+    let mut records: Vec<String> = vec![MDF_HEADER.to_string()];
+    for word in &words {
+        let key = form_key(word);
+        let homonym = if forms[&key] > 1 {
+            let count = seen.entry(key).or_default();
+            *count += 1;
+            Some(*count)
+        } else {
+            None
+        };
+        records.push(mdf_record(word, homonym, &tree.etymons(&word.id)));
+    }
+    format!("{}\n", records.join("\n\n"))
+}
+
+/// the language and written form of a word, used to find homonyms
+fn form_key(word: &Lexis) -> (String, String) {
+    (word.language.clone(), word.word.clone().unwrap_or_default().string_without_sep())
+}
+
+/// create a single record, with one backslash-coded field per line
+fn mdf_record(word: &Lexis, homonym: Option<usize>, etymons: &[Lexis]) -> String {
+    let mut fields: Vec<(&str, String)> = vec![("lx", word.word.clone().unwrap_or_default().string_without_sep())];
+    if let Some(num) = homonym {
+        fields.push(("hm", num.to_string()));
+    }
+    if let Some(pos) = &word.pos {
+        if *pos != PartOfSpeech::None {
+            fields.push(("ps", pos.to_string()));
+        }
+    }
+    if let Some(ipa) = &word.ipa {
+        fields.push(("ph", ipa.clone()));
+    }
+    if !word.definition.is_empty() {
+        fields.push(("ge", word.definition.clone()));
+    }
+    if let Some(domain) = &word.semantic_field {
+        fields.push(("sd", domain.clone()));
+    }
+    for example in &word.examples {
+        fields.push(("xv", example.text.clone()));
+        if let Some(translation) = &example.translation {
+            fields.push(("xe", translation.clone()));
+        }
+    }
+    for etymon in etymons.iter().filter(|e| e.word.is_some()) {
+        fields.push(("et", etymon.word.clone().unwrap_or_default().string_without_sep()));
+        if !etymon.definition.is_empty() {
+            fields.push(("eg", etymon.definition.clone()));
+        }
+        if !etymon.language.is_empty() {
+            fields.push(("es", etymon.language.clone()));
+        }
+    }
+    // MDF fields end at the next marker, so values can't span lines
+    let lines: Vec<String> = fields.into_iter()
+    .map(|(marker, value)| format!("\\{} {}", marker, value.split_whitespace().collect::<Vec<&str>>().join(" ")))
+    .collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::{kirum::{Lexis, LanguageTree}, word::{PartOfSpeech, Example}};
+    use super::generate_mdf;
+
+    fn test_tree() -> LanguageTree {
+        let mut tree = LanguageTree::new();
+        let one = Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(),
+            definition: "garden".to_string(), pos: Some(PartOfSpeech::Noun), ..Default::default()};
+        let two = Lexis{id: "two".to_string(), word: Some("kirum".into()), language: "New Exemplum".to_string(),
+            definition: "garden,\norchard".to_string(), ipa: Some("kiɾum".to_string()), semantic_field: Some("nature".to_string()),
+            examples: vec![Example{text: "kirum amo".to_string(), gloss: None, translation: Some("the garden keeper".to_string())}], ..Default::default()};
+        let three = Lexis{id: "three".to_string(), word: Some("kirum".into()), language: "New Exemplum".to_string(), definition: "to plant".to_string(),
+            pos: Some(PartOfSpeech::Verb), ..Default::default()};
+        tree.connect_etymology(two, one, Vec::new(), None);
+        tree.add_lexis(three);
+        tree
+    }
+
+    #[test]
+    fn test_generate_mdf() {
+        let tree = test_tree();
+        let mut words = tree.to_vec();
+        words.sort_by(|a, b| a.id.cmp(&b.id));
+        let rendered = generate_mdf(&tree, words, Some("New Exemplum".to_string()));
+        let expected = "\\_sh v3.0  400  MDF 4.0\n\n\
+        \\lx kirum\n\\hm 1\n\\ps verb\n\\ge to plant\n\n\
+        \\lx kirum\n\\hm 2\n\\ph kiɾum\n\\ge garden, orchard\n\\sd nature\n\\xv kirum amo\n\\xe the garden keeper\n\\et kira\n\\eg garden\n\\es Old Exemplum\n";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_generate_mdf_all_languages() {
+        let tree = test_tree();
+        let rendered = generate_mdf(&tree, tree.to_vec(), None);
+        assert_eq!(rendered.matches("\\lx ").count(), 3);
+        assert!(rendered.contains("\\lx kira\n\\ps noun\n\\ge garden"));
+    }
+}
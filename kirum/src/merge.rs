@@ -0,0 +1,315 @@
+use std::{collections::HashSet, path::{Path, PathBuf}};
+
+use anyhow::{Result, Context, anyhow};
+use serde_json::{Map, Value};
+use crate::{cli::MergeStrategy, edit::{rename_id_value, rename_key}, files::{handle_directory, read_and_compute, read_transform_files, read_tree_files},
+    format::FileFormat, transaction::WriteTransaction};
+
+/// a parsed file from one of the two projects
+struct ProjectFile {
+    path: PathBuf,
+    format: FileFormat,
+    value: Value
+}
+
+impl ProjectFile {
+    fn read(path: &Path) -> Result<ProjectFile> {
+        let format = FileFormat::from_path(path);
+        let raw = std::fs::read_to_string(path).context(format!("error reading {}", path.display()))?;
+        let value = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+        Ok(ProjectFile { path: path.to_path_buf(), format, value })
+    }
+
+    /// the top-level entries of the file under the given key, either the words of a tree file or the transforms of an etymology file
+    fn entries(&mut self, key: &str) -> Option<&mut Map<String, Value>> {
+        self.value.get_mut(key).and_then(Value::as_object_mut)
+    }
+}
+
+/// Merge the tree and etymology files of another project into a project.
+/// Each file of the other project is copied into the same directory of the project; if a file with the same name exists,
+/// the name of the other project is added to the start of the file name.
+/// Word IDs and transform names that exist in both projects are handled according to the strategy:
+/// skipped, renamed with the prefix, or overwritten, in which case the existing definition is removed.
+/// Globals and phonetic rules of the other project aren't merged.
+pub fn merge(directory: &str, other: &str, strategy: MergeStrategy, prefix: Option<String>) -> Result<String> {
+    let project = handle_directory(directory)?;
+    let incoming = handle_directory(other)?;
+    let other_name = Path::new(other).canonicalize().ok().and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or(String::from("merged"));
+    let prefix = prefix.unwrap_or(format!("{}-", other_name));
+
+    let existing_words: HashSet<String> = read_tree_files(&project.graphs, false).context("error reading existing tree files")?.into_keys().collect();
+    let existing_transforms: HashSet<String> = read_transform_files(&project.transforms, false).context("error reading existing etymology files")?.into_keys().collect();
+    let mut tree_files = incoming.graphs.iter().map(|p| ProjectFile::read(p)).collect::<Result<Vec<_>>>()?;
+    let mut ety_files = incoming.transforms.iter().map(|p| ProjectFile::read(p)).collect::<Result<Vec<_>>>()?;
+
+    let word_collisions: Vec<String> = colliding_words(&incoming.graphs, &existing_words)?;
+    let transform_collisions: Vec<String> = colliding(&mut ety_files, "transforms", &existing_transforms);
+    debug!("found {} colliding words and {} colliding transforms", word_collisions.len(), transform_collisions.len());
+
+    let mut changed: Vec<ProjectFile> = Vec::new();
+    match strategy {
+        MergeStrategy::Skip => {
+            // derivatives can only be skipped along with the word they're defined in
+            let top_level = top_level_ids(&mut tree_files);
+            if let Some(id) = word_collisions.iter().find(|id| !top_level.contains(*id)) {
+                return Err(anyhow!("cannot skip '{}', as it's a derivative defined inside another word", id));
+            }
+            for file in tree_files.iter_mut() {
+                file.entries("words").into_iter().for_each(|words| words.retain(|id, _| !word_collisions.contains(id)));
+            }
+            // a remaining reference to a skipped word would silently point to the unrelated word of the same ID in the project
+            for id in &word_collisions {
+                if tree_files.iter().chain(ety_files.iter()).any(|f| rename_id_value(&mut f.value.clone(), id, id)) {
+                    return Err(anyhow!("cannot skip '{}', as other words from {} refer to it; use --on-conflict rename instead", id, other));
+                }
+            }
+            for file in ety_files.iter_mut() {
+                file.entries("transforms").into_iter().for_each(|trans| trans.retain(|name, _| !transform_collisions.contains(name)));
+            }
+        },
+        MergeStrategy::Rename => {
+            let mut declared = top_level_ids(&mut tree_files);
+            tree_files.iter().for_each(|f| derivative_ids(&f.value, &mut declared));
+            for id in &word_collisions {
+                if !declared.contains(id) {
+                    return Err(anyhow!("cannot rename '{}', as its ID is generated from the word it's derived from", id));
+                }
+                let new = format!("{}{}", prefix, id);
+                if existing_words.contains(&new) {
+                    return Err(anyhow!("cannot rename '{}' to '{}', as the ID already exists", id, new));
+                }
+                tree_files.iter_mut().for_each(|f| {rename_id_value(&mut f.value, id, &new);});
+                ety_files.iter_mut().for_each(|f| {rename_id_value(&mut f.value, id, &new);});
+            }
+            for name in &transform_collisions {
+                let new = format!("{}{}", prefix, name);
+                if existing_transforms.contains(&new) {
+                    return Err(anyhow!("cannot rename transform '{}' to '{}', as the name already exists", name, new));
+                }
+                for transforms in ety_files.iter_mut().filter_map(|f| f.entries("transforms")) {
+                    rename_key(transforms, name, &new);
+                }
+                tree_files.iter_mut().for_each(|f| rename_transform_refs(&mut f.value, name, &new));
+            }
+        },
+        MergeStrategy::Overwrite => {
+            let mut removed: HashSet<String> = HashSet::new();
+            for path in &project.graphs {
+                let mut file = ProjectFile::read(path)?;
+                if remove_entries(&mut file, "words", &word_collisions, &mut removed) {
+                    changed.push(file);
+                }
+            }
+            // derivatives defined inside another word can't be removed on their own
+            if let Some(id) = word_collisions.iter().find(|id| !removed.contains(*id)) {
+                return Err(anyhow!("cannot overwrite '{}', as it's a derivative defined inside another word", id));
+            }
+            for path in &project.transforms {
+                let mut file = ProjectFile::read(path)?;
+                if remove_entries(&mut file, "transforms", &transform_collisions, &mut removed) {
+                    changed.push(file);
+                }
+            }
+        }
+    }
+
+    let mut tx = WriteTransaction::new();
+    for file in &changed {
+        let data = file.format.write_value(&file.value).context(format!("error creating {:?} data", file.format))?;
+        tx.write(&file.path, data).context(format!("error writing {}", file.path.display()))?;
+    }
+    let mut added = 0;
+    for (files, sub) in [(&tree_files, "tree"), (&ety_files, "etymology")] {
+        let dir = Path::new(directory).join(sub);
+        tx.create_dir_all(&dir)?;
+        for file in files {
+            let name = file.path.file_name().ok_or(anyhow!("invalid file name {}", file.path.display()))?.to_string_lossy().to_string();
+            let mut target = dir.join(&name);
+            if target.exists() {
+                target = dir.join(format!("{}-{}", other_name, name));
+            }
+            if target.exists() {
+                return Err(anyhow!("cannot copy {}, as {} already exists", file.path.display(), target.display()));
+            }
+            let data = file.format.write_value(&file.value).context(format!("error creating {:?} data", file.format))?;
+            tx.write(&target, data).context(format!("error writing {}", target.display()))?;
+            added += 1;
+        }
+    }
+    // make sure the merged project can still be read, so a broken merge is rolled back
+    read_and_compute(Some(directory.to_string())).context("merged project is invalid")?;
+    tx.commit();
+
+    let action = match strategy {
+        MergeStrategy::Skip => "skipped",
+        MergeStrategy::Rename => "renamed",
+        MergeStrategy::Overwrite => "overwrote"
+    };
+    Ok(format!("merged {} files from {}; {} {} colliding words and {} colliding transforms",
+        added, other, action, word_collisions.len(), transform_collisions.len()))
+}
+
+/// find the word IDs of the other project that already exist in the project, including the IDs of derivatives.
+/// Generated derivative IDs are left out when the word they're generated from also collides, as they're handled along with it.
+fn colliding_words(files: &Vec<PathBuf>, existing: &HashSet<String>) -> Result<Vec<String>> {
+    let incoming = read_tree_files(files, false).context("error reading tree files to merge")?;
+    let found: Vec<String> = incoming.into_keys().filter(|id| existing.contains(id)).collect();
+    let mut collisions: Vec<String> = found.iter()
+        .filter(|id| !found.iter().any(|parent| id.starts_with(&format!("{}-autoderive-", parent))))
+        .cloned().collect();
+    collisions.sort();
+    Ok(collisions)
+}
+
+/// the IDs of the words defined at the top level of the tree files
+fn top_level_ids(files: &mut [ProjectFile]) -> HashSet<String> {
+    files.iter_mut().filter_map(|f| f.entries("words")).flat_map(|words| words.keys().cloned().collect::<Vec<_>>()).collect()
+}
+
+/// collect the explicit IDs of derivatives, at any depth
+fn derivative_ids(value: &Value, ids: &mut HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Array(derivatives)) = map.get("derivatives") {
+                ids.extend(derivatives.iter().filter_map(|d| d.get("id")).filter_map(Value::as_str).map(String::from));
+            }
+            map.values().for_each(|child| derivative_ids(child, ids));
+        },
+        Value::Array(items) => items.iter().for_each(|i| derivative_ids(i, ids)),
+        _ => {}
+    }
+}
+
+/// find the top-level entries of the files that already exist in the project
+fn colliding(files: &mut [ProjectFile], key: &str, existing: &HashSet<String>) -> Vec<String> {
+    let mut found: Vec<String> = files.iter_mut().filter_map(|f| f.entries(key))
+        .flat_map(|entries| entries.keys().filter(|k| existing.contains(*k)).cloned().collect::<Vec<_>>()).collect();
+    found.sort();
+    found
+}
+
+/// remove the given entries from a file of the project, adding them to the removed set. Returns true if any were removed.
+fn remove_entries(file: &mut ProjectFile, key: &str, remove: &[String], removed: &mut HashSet<String>) -> bool {
+    let entries = match file.entries(key) {
+        Some(entries) => entries,
+        None => return false
+    };
+    let mut found = false;
+    for name in remove {
+        if entries.shift_remove(name).is_some() {
+            removed.insert(name.clone());
+            found = true;
+        }
+    }
+    found
+}
+
+/// rename a transform in the `transforms` lists of etymons and derivatives
+fn rename_transform_refs(value: &mut Value, old: &str, new: &str) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                match (key.as_str(), child) {
+                    ("transforms", Value::Array(names)) => {
+                        for name in names.iter_mut() {
+                            if name.as_str() == Some(old) {
+                                *name = Value::String(new.to_string());
+                            }
+                        }
+                    },
+                    (_, child) => rename_transform_refs(child, old, new)
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(|i| rename_transform_refs(i, old, new)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anyhow::Result;
+    use crate::test_util::copy_project;
+    use crate::{cli::MergeStrategy, files::read_and_compute};
+    use super::merge;
+
+
+    const OTHER: &str = "src/test_files/merge_other";
+
+    #[test]
+    fn test_merge_skip() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_merge_skip")?);
+        let other = copy_project("merge_other", "kirum_test_merge_skip_other")?;
+        std::fs::write(PathBuf::from(&other).join("tree/words.json"), r#"{"words": {
+            "generated": {"word": "pira", "type": "root", "language": "Old Lang", "definition": "field"},
+            "meadow": {"word": "pirat", "type": "word", "language": "New Lang", "definition": "meadow"}
+        }}"#)?;
+        let msg = merge(dir.to_str().unwrap(), &other, MergeStrategy::Skip, None)?;
+        assert_eq!(msg, format!("merged 2 files from {}; skipped 1 colliding words and 1 colliding transforms", other));
+        assert!(dir.join("tree/kirum_test_merge_skip_other-words.json").exists());
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.get_by_id("generated").unwrap().definition, "orchard");
+        assert_eq!(computed.get_by_id("meadow").unwrap().word, Some("pirat".into()));
+        std::fs::remove_dir_all(dir)?;
+        std::fs::remove_dir_all(other)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_skip_referenced() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_merge_skip_referenced")?);
+        let res = merge(dir.to_str().unwrap(), OTHER, MergeStrategy::Skip, None);
+        assert!(res.unwrap_err().to_string().starts_with("cannot skip 'generated', as other words"));
+        assert!(!dir.join("tree/merge_other-words.json").exists());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_rename() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_merge_rename")?);
+        merge(dir.to_str().unwrap(), OTHER, MergeStrategy::Rename, Some(String::from("other_")))?;
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.get_by_id("generated").unwrap().definition, "orchard");
+        assert_eq!(computed.get_by_id("other_generated").unwrap().definition, "field");
+        assert_eq!(computed.etymons("meadow")[0].id, "other_generated");
+        assert_eq!(computed.get_by_id("meadow").unwrap().word, Some("pirat".into()));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_rename_derivative() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_merge_rename_der")?);
+        let other = copy_project("merge_other", "kirum_test_merge_rename_der_other")?;
+        std::fs::write(PathBuf::from(&other).join("tree/words.json"), r#"{"words": {
+            "root": {"word": "pira", "type": "root", "language": "Old Lang", "definition": "field",
+                "derivatives": [{"lexis": {"language": "New Lang", "definition": "yard"}, "transforms": ["to-new"]},
+                    {"id": "borrowed", "lexis": {"language": "New Lang", "definition": "meadow"}, "transforms": ["to-new"]}]}
+        }}"#)?;
+        let msg = merge(dir.to_str().unwrap(), &other, MergeStrategy::Rename, Some(String::from("other_")))?;
+        // root-autoderive-0 is renamed along with root
+        assert!(msg.ends_with("renamed 2 colliding words and 1 colliding transforms"));
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.get_by_id("borrowed").unwrap().definition, "yard");
+        assert_eq!(computed.get_by_id("other_borrowed").unwrap().definition, "meadow");
+        assert_eq!(computed.get_by_id("other_root-autoderive-0").unwrap().word, Some("pirat".into()));
+        std::fs::remove_dir_all(dir)?;
+        std::fs::remove_dir_all(other)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_overwrite() -> Result<()> {
+        let dir = PathBuf::from(copy_project("rename", "kirum_test_merge_overwrite")?);
+        merge(dir.to_str().unwrap(), OTHER, MergeStrategy::Overwrite, None)?;
+        let computed = read_and_compute(dir.to_str().map(String::from))?;
+        assert_eq!(computed.get_by_id("generated").unwrap().definition, "field");
+        assert_eq!(computed.get_by_id("meadow").unwrap().word, Some("pirat".into()));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -1,16 +1,16 @@
-use std::{path::PathBuf, io::Write, collections::HashMap, fs::{self, File}};
+use std::{path::PathBuf, collections::HashMap};
 use libkirum::{transforms::TransformFunc, word::{Etymology, Edge}, lexcreate::LexPhonology};
-use crate::{entries::{RawTransform, TransformGraph, RawLexicalEntry, Derivative, WordGraph}, global::Global};
+use crate::{entries::{RawTransform, TransformGraph, RawLexicalEntry, Derivative, WordGraph}, global::Global, transaction::WriteTransaction};
 use anyhow::{Result, Context, anyhow};
 
-pub fn create_project_directory(name: &str) -> Result<()>{
+pub fn create_project_directory(tx: &mut WriteTransaction, name: &str) -> Result<()>{
     let base = PathBuf::from(name);
     let ety_path = base.join("etymology");
     let tree_path = base.join("tree");
     let phonetic_path = base.join("phonetics");
-    fs::create_dir_all(ety_path)?;
-    fs::create_dir_all(tree_path)?;
-    fs::create_dir_all(phonetic_path)?;
+    tx.create_dir_all(ety_path)?;
+    tx.create_dir_all(tree_path)?;
+    tx.create_dir_all(phonetic_path)?;
     Ok(())
 }
 
@@ -20,7 +20,8 @@ pub fn create_new_project(name: &str) -> Result<()> {
     let mut ety_path = base.join("etymology");
     let mut tree_path = base.join("tree");
     let mut phonetic_path = base.join("phonetics");
-    create_project_directory(name).context("error creating project directory")?;
+    let mut tx = WriteTransaction::new();
+    create_project_directory(&mut tx, name).context("error creating project directory")?;
 
     let mut transform_map: HashMap<String, RawTransform> = HashMap::new();
     transform_map.insert("of-from-latin".into(), RawTransform { 
@@ -48,10 +49,20 @@ pub fn create_new_project(name: &str) -> Result<()> {
         part_of_speech: Some(libkirum::word::PartOfSpeech::Verb), 
         etymology: None, 
         archaic: true, 
+        reconstructed: None,
         tags: None, 
         historical_metadata: None,
         derivatives: None, 
         generate: None,
+        agglutination: None,
+        noun_class: None,
+        word_override: None,
+        examples: None,
+        semantic_field: None,
+        notes: None,
+        status: None,
+        descendants: None,
+        ipa: None,
     });
     word_map.insert("latin_example".into(), RawLexicalEntry { 
         word: None, 
@@ -60,11 +71,21 @@ pub fn create_new_project(name: &str) -> Result<()> {
         language: Some("Latin".into()), 
         definition: "an instance, model, example".into(), 
         part_of_speech: Some(libkirum::word::PartOfSpeech::Noun), 
-        etymology: Some(Etymology { etymons: vec![Edge{etymon: "latin_verb".into(), transforms: Some(vec!["latin-from-verb".into()]), agglutination_order: None}] }), 
+        etymology: Some(Etymology { etymons: vec![Edge{etymon: "latin_verb".into(), transforms: Some(vec!["latin-from-verb".into()]), agglutination_order: None, resolved_transforms: None, borrowed: None, semantic_shift: None, metadata: None}] }), 
         archaic: true, 
+        reconstructed: None,
         tags: Some(vec!["example".into(), "default".into()]), 
         generate: None,
-        derivatives: Some(vec![Derivative{lexis: RawLexicalEntry { 
+        agglutination: None,
+        noun_class: None,
+        word_override: None,
+        examples: None,
+        semantic_field: None,
+        notes: None,
+        status: None,
+        descendants: None,
+        ipa: None,
+        derivatives: Some(vec![Derivative{id: None, lexis: RawLexicalEntry { 
                 word: None, 
                 word_type: None, 
                 language: Some("Old French".into()), 
@@ -72,10 +93,20 @@ pub fn create_new_project(name: &str) -> Result<()> {
                 part_of_speech: Some(libkirum::word::PartOfSpeech::Noun), 
                 etymology: None, 
                 archaic: true, 
+                reconstructed: None,
                 tags: None, 
                 historical_metadata: None,
                 derivatives: None,
                 generate: None,
+                agglutination: None,
+                noun_class: None,
+                word_override: None,
+                examples: None,
+                semantic_field: None,
+                notes: None,
+                status: None,
+                descendants: None,
+                ipa: None,
             },
             transforms: Some(vec!["of-from-latin".to_owned()]),
     }]) 
@@ -93,7 +124,8 @@ pub fn create_new_project(name: &str) -> Result<()> {
         ]),
         lexis_types: HashMap::from([
             ("word".into(), vec!["SSS".try_into()?])
-        ])
+        ]),
+        fallbacks: HashMap::new()
     };
 
     let phonetic_data = serde_json::to_string_pretty(&example_phonetics)?;
@@ -103,27 +135,24 @@ pub fn create_new_project(name: &str) -> Result<()> {
     let name_path: PathBuf = name.parse()?;
     let file_name = name_path.file_name()
     .ok_or_else(|| anyhow!("could not extract final path from {}", name_path.display()))?.to_string_lossy();
-    write_json(&file_name, &mut tree_path, graph_data).context(format!("error writing {}", file_name))?;
-    write_json("ety", &mut ety_path, trans_data).context("error writing ety file")?;
-    write_json("rules", &mut phonetic_path, phonetic_data).context("error writing rules file")?;
+    write_json(&mut tx, &file_name, &mut tree_path, graph_data).context(format!("error writing {}", file_name))?;
+    write_json(&mut tx, "ety", &mut ety_path, trans_data).context("error writing ety file")?;
+    write_json(&mut tx, "rules", &mut phonetic_path, phonetic_data).context("error writing rules file")?;
 
-    let base_globals = Global{transforms: None};
+    let base_globals = Global::default();
     let globals_data = serde_json::to_string_pretty(&base_globals)?;
-    let mut globals_file = File::create(base.join("globals.json")).context("could not create globals file")?;
-    write!(globals_file, "{}", globals_data).context("error writing globals file")?;
+    tx.write(base.join("globals.json"), globals_data).context("error writing globals file")?;
+    tx.commit();
  
     Ok(())
 }
 
-fn write_json(subpath: &str, base_path: &mut PathBuf, data: String) -> Result<()>{
+fn write_json(tx: &mut WriteTransaction, subpath: &str, base_path: &mut PathBuf, data: String) -> Result<()>{
     base_path.push(subpath);
     base_path.set_extension("json");
-    let mut phonetics_file = File::create(base_path.clone())
+    tx.write(base_path.clone(), data)
     .context(format!("could not create  json file {} {}", subpath, base_path.display()))?;
 
-    write!(phonetics_file, "{}", data)
-    .context("error writing phonetics file")?;
-
     Ok(())
 }
 
@@ -0,0 +1,72 @@
+use std::{io::{IsTerminal, Write}, sync::{atomic::{AtomicBool, Ordering}, Mutex}, time::{Duration, Instant}};
+
+// The bar is drawn by hand rather than with indicatif, which isn't available in the registry kirum is built against.
+// It only needs a label, a count and clearing the line when it's done, and callers only see the hook returned by bar(),
+// so moving to indicatif later only means replacing bar() and render().
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// operations that finish before this are never shown, so small projects don't flash a bar
+const SHOW_AFTER: Duration = Duration::from_millis(300);
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+/// Turn progress bars on or off. Bars are only drawn if stderr is a terminal.
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled && std::io::stderr().is_terminal(), Ordering::Relaxed);
+}
+
+struct BarState {
+    start: Instant,
+    last_draw: Option<Instant>
+}
+
+/// Return a progress hook for LanguageTree::on_progress() that draws a progress bar with the given label on stderr.
+/// The bar is only drawn if progress bars are enabled and the operation takes long enough to notice, and is cleared when it finishes.
+pub fn bar(label: &str) -> impl Fn(usize, usize) + Send + Sync + 'static {
+    let label = label.to_string();
+    let state = Mutex::new(BarState { start: Instant::now(), last_draw: None });
+    move |done, total| {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut state = match state.lock() {
+            Ok(state) => state,
+            Err(_) => return
+        };
+        let now = Instant::now();
+        let mut stderr = std::io::stderr();
+        if done >= total {
+            if state.last_draw.is_some() {
+                let _ = write!(stderr, "\r\x1b[2K");
+                let _ = stderr.flush();
+            }
+            // the hook can be used for more than one run, i.e. by watch
+            *state = BarState { start: now, last_draw: None };
+            return;
+        }
+        if now.duration_since(state.start) < SHOW_AFTER || state.last_draw.is_some_and(|l| now.duration_since(l) < REDRAW_EVERY) {
+            return;
+        }
+        let _ = write!(stderr, "\r{}", render(&label, done, total));
+        let _ = stderr.flush();
+        state.last_draw = Some(now);
+    }
+}
+
+/// render a single line of a progress bar, i.e. `computing words [#######-------] 1200/2400`
+fn render(label: &str, done: usize, total: usize) -> String {
+    let filled = (done.min(total) * BAR_WIDTH).checked_div(total).unwrap_or(BAR_WIDTH);
+    format!("{} [{}{}] {}/{}", label, "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), done, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render("computing words", 0, 10), format!("computing words [{}] 0/10", "-".repeat(30)));
+        assert_eq!(render("computing words", 5, 10), format!("computing words [{}{}] 5/10", "#".repeat(15), "-".repeat(15)));
+        assert_eq!(render("generating", 12, 10), format!("generating [{}] 12/10", "#".repeat(30)));
+    }
+}
@@ -0,0 +1,377 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, path::{Path, PathBuf}};
+use anyhow::{Result, Context, anyhow};
+use libkirum::{kirum::{LanguageTree, Lexis}, word::PartOfSpeech};
+use crate::{cli::{self, RenderArgs}, transaction::WriteTransaction, entries::create_json_graph, cache::read_and_compute_cached, files::{apply_def_vars, apply_gloss_lang, apply_orthography, apply_drafts, apply_pos_abbreviations, apply_reconstructed, bare_word, read_taxonomy, read_project_globals},
+    taxonomy::semantic_filter, dryrun::{self, DryRun}, explain, header, listing, tabular, tmpl, html, markdown, mdf, lift, graphml, xdxf, reverse};
+
+/// Compute the language in a project, and render the lexicon in the given format
+pub fn render(args: RenderArgs) -> Result<String> {
+    if let Some(rendered) = dry_run(&args) {
+        return rendered;
+    }
+    if args.command.is_none() {
+        return Err(anyhow!("must specify an output format, or a transform to dry-run"));
+    }
+    let computed = read_and_compute_cached(args.directory.clone(), args.cache)?;
+    render_computed(args, &computed)
+}
+
+/// Run the dry run given in the arguments, or return None if the arguments don't ask for one.
+pub fn dry_run(args: &RenderArgs) -> Option<Result<String>> {
+    let dir = args.directory.as_ref().ok_or(anyhow!("must specify a project directory"));
+    if let Some(edge) = &args.dry_run_edge {
+        Some(dir.and_then(|dir| dryrun::dry_run(dir, DryRun::Edge { etymon: edge[0].clone(), id: edge[1].clone() }, args.word.as_deref())))
+    } else if !args.dry_run_transform.is_empty() {
+        Some(dir.and_then(|dir| dryrun::dry_run(dir, DryRun::Transforms(args.dry_run_transform.clone()), args.word.as_deref())))
+    } else {
+        None
+    }
+}
+
+/// Render the lexicon of a project that has already been computed, in the format given in the arguments.
+/// The project directory is still read for the taxonomy, globals and header.
+pub fn render_computed(args: RenderArgs, computed: &LanguageTree) -> Result<String> {
+    let RenderArgs{command, directory, variables, gloss_lang, orthography, semantic_field, language, tag, pos, archaic, no_archaic, metadata,
+        header, include_drafts, explain, split_by, split_dir, sort, group_by, ..} = args;
+    let command = command.ok_or(anyhow!("must specify an output format, or a transform to dry-run"))?;
+    debug!("computed {} raw entries", computed.len());
+    for id in &explain {
+        // the trace goes to stderr, so the rendered output is unchanged
+        eprintln!("{}\n", explain::render_explain(computed, id, cli::ExplainFormat::Text, true, true)?);
+    }
+    let taxonomy = read_taxonomy(directory.clone())?;
+    let globals = read_project_globals(directory.clone())?;
+    let in_field = semantic_filter(semantic_field, taxonomy.as_ref());
+    let archaic = if archaic {Some(true)} else if no_archaic {Some(false)} else {None};
+    let selected = field_filter(language, tag, pos, archaic, metadata)?;
+    let filter = |l: &Lexis| in_field(l) && selected(l);
+    let mut rendered_dict = computed.to_vec();
+    rendered_dict.retain(&filter);
+    apply_drafts(include_drafts, &mut rendered_dict);
+    apply_gloss_lang(gloss_lang, &mut rendered_dict);
+    apply_orthography(orthography, &mut rendered_dict);
+    apply_def_vars(variables, &mut rendered_dict)?;
+    // sort by the full name, so the order doesn't depend on the abbreviations
+    order_words(&mut rendered_dict, sort, group_by);
+    apply_pos_abbreviations(&globals, &mut rendered_dict);
+    // templates and data formats get the reconstructed field instead, so the word itself is unchanged
+    if !matches!(command, cli::Format::Template { .. } | cli::Format::Lift | cli::Format::Graphml) {
+        apply_reconstructed(&mut rendered_dict);
+    }
+    debug!("rendered lexicon of {} lemmas", rendered_dict.len());
+    if header && matches!(command, cli::Format::Csv | cli::Format::Tsv) {
+        warn!("CSV and TSV output has no comment syntax, so the header is left out");
+    }
+    let header_info = if header {
+        let seed = globals.seed;
+        let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+        Some((dir, seed))
+    } else {
+        None
+    };
+    let make_header = |words: usize| header_info.as_ref().map(|(dir, seed)| header::Header::new(dir, words, *seed));
+    let json_filter = |l: &Lexis| filter(l) && (include_drafts || !l.is_draft());
+
+    let (split_by, split_dir) = match (split_by, split_dir) {
+        (Some(split_by), Some(split_dir)) => (split_by, split_dir),
+        _ => {
+            let render_header = make_header(rendered_dict.len());
+            return render_format(&command, computed, rendered_dict, &json_filter, include_drafts, render_header, true);
+        }
+    };
+    let mut groups: BTreeMap<String, Vec<Lexis>> = BTreeMap::new();
+    for lex in rendered_dict {
+        groups.entry(group_name(computed, &lex, split_by)).or_default().push(lex);
+    }
+    let mut tx = WriteTransaction::new();
+    tx.create_dir_all(&split_dir).context(format!("error creating directory {}", split_dir))?;
+    let mut written: HashMap<PathBuf, String> = HashMap::new();
+    for (name, words) in groups {
+        let path = Path::new(&split_dir).join(format!("{}.{}", file_stem(&name), extension(&command)));
+        if let Some(other) = written.insert(path.clone(), name.clone()) {
+            return Err(anyhow!("'{}' and '{}' would both be written to {}", other, name, path.display()));
+        }
+        let ids: HashSet<String> = words.iter().map(|l| l.id.clone()).collect();
+        let render_header = make_header(words.len());
+        let rendered = render_format(&command, computed, words, &|l| json_filter(l) && ids.contains(&l.id), include_drafts, render_header, false)
+            .context(format!("error rendering '{}'", name))?;
+        tx.write(&path, rendered).context(format!("error writing {}", path.display()))?;
+        debug!("wrote {} to {}", name, path.display());
+    }
+    tx.commit();
+    Ok(format!("wrote {} files to {}", written.len(), split_dir))
+}
+
+/// render a list of words in the given format. JSON output is created from the tree instead, using the filter.
+/// Lines are only shortened to fit the terminal when printed to stdout, not when written to files with --split-by.
+fn render_format(command: &cli::Format, computed: &LanguageTree, rendered_dict: Vec<Lexis>, filter: &dyn Fn(&Lexis) -> bool, 
+    include_drafts: bool, render_header: Option<header::Header>, to_stdout: bool) -> Result<String> {
+    let rendered = match command.clone() {
+        cli::Format::Line { format: Some(format), .. } => {
+            listing::render_format(&rendered_dict, &format)?
+        },
+        cli::Format::Line { wide, fields, color, format: None } =>{
+            let fields = if fields.is_empty() {listing::DEFAULT_FIELDS.to_vec()} else {fields};
+            let max_width = if wide || !to_stdout {None} else {listing::terminal_width()};
+            listing::render_lines(&rendered_dict, &fields, max_width, listing::use_color(color))
+        },
+        cli::Format::Csv => {
+            tabular::render_table(computed, &rendered_dict, b',')?
+        },
+        cli::Format::Tsv => {
+            tabular::render_table(computed, &rendered_dict, b'\t')?
+        },
+        cli::Format::Template { template_file, template_dir, rhai_files, grouped } =>{
+            tmpl::generate_from_tmpl(computed, rendered_dict, template_file, template_dir, rhai_files, grouped, render_header.clone())?
+        },
+        cli::Format::Html { title } => {
+            html::generate_html(computed, rendered_dict, title)?
+        },
+        cli::Format::Markdown { title } => {
+            markdown::generate_markdown(computed, rendered_dict, title)?
+        },
+        cli::Format::Mdf { language } => {
+            mdf::generate_mdf(computed, rendered_dict, language)
+        },
+        cli::Format::Lift => {
+            lift::generate_lift(computed, rendered_dict)?
+        },
+        cli::Format::Graphml => {
+            graphml::generate_graphml(computed, rendered_dict)?
+        },
+        cli::Format::Xdxf { title, language } => {
+            xdxf::generate_xdxf(computed, rendered_dict, title, language)?
+        },
+        cli::Format::ReverseIndex => {
+            reverse::render_reverse_index(&rendered_dict)
+        },
+        cli::Format::Json{descendants, transforms} => {
+            let mut words = computed.to_vec_etymons(filter);
+            if !include_drafts {
+                words.iter_mut().for_each(|(l, _)| l.notes = None);
+            }
+            let word_data = create_json_graph(words, |l| l.id, false, descendants, transforms)
+            .context("could not create map from language data")?;
+            serde_json::to_string_pretty(&word_data)?
+        }
+    };
+    let embedded = match render_header {
+        Some(found) => found.embed(command, rendered)?,
+        None => rendered
+    };
+    Ok(embedded)
+}
+
+/// Create a filter for the fields of a word. Words must match every field that's set, and any of the values given for a field.
+/// archaic selects either archaic or non-archaic words. Metadata is given as `key=value`, and words must match every value.
+fn field_filter(languages: Vec<String>, tags: Vec<String>, pos: Vec<PartOfSpeech>, archaic: Option<bool>, metadata: Vec<String>) -> Result<impl Fn(&Lexis) -> bool> {
+    let metadata: Vec<(String, String)> = metadata.iter()
+        .map(|m| m.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())).ok_or(anyhow!("metadata filter '{}' must be in the form key=value", m)))
+        .collect::<Result<_>>()?;
+    Ok(move |lex: &Lexis| {
+        (languages.is_empty() || languages.contains(&lex.language))
+        && (tags.is_empty() || lex.tags.iter().any(|t| tags.contains(t)))
+        && (pos.is_empty() || lex.pos.as_ref().is_some_and(|p| pos.contains(p)))
+        && archaic.is_none_or(|a| lex.archaic == a)
+        && metadata.iter().all(|(k, v)| lex.historical_metadata.get(k) == Some(v))
+    })
+}
+
+/// Sort the words before they're passed to a formatter, so every format has the same order.
+/// Words from the tree are already sorted alphabetically, and every sort is stable, so words that compare equal stay in alphabetical order.
+fn order_words(words: &mut [Lexis], sort: cli::SortOrder, group_by: Option<cli::GroupBy>) {
+    let pos_key = |l: &Lexis| (l.pos.is_none(), l.pos.as_ref().map(|p| p.to_string()).unwrap_or_default());
+    match sort {
+        cli::SortOrder::Alpha => {},
+        cli::SortOrder::Language => words.sort_by(|a, b| a.language.cmp(&b.language)),
+        cli::SortOrder::Pos => words.sort_by_cached_key(pos_key),
+        cli::SortOrder::Id => words.sort_by(|a, b| a.id.cmp(&b.id))
+    }
+    match group_by {
+        Some(cli::GroupBy::Language) => words.sort_by(|a, b| a.language.cmp(&b.language)),
+        Some(cli::GroupBy::Pos) => words.sort_by_cached_key(pos_key),
+        Some(cli::GroupBy::Type) => words.sort_by(|a, b| a.lexis_type.cmp(&b.lexis_type)),
+        None => {}
+    }
+}
+
+/// the group a word is written to with --split-by
+fn group_name(computed: &LanguageTree, lex: &Lexis, split_by: cli::SplitBy) -> String {
+    match split_by {
+        cli::SplitBy::Language => lex.language.clone(),
+        cli::SplitBy::Letter => {
+            let default_alphabet = computed.collation.clone().unwrap_or_default();
+            let alphabet = computed.language_collation.get(&lex.language).unwrap_or(&default_alphabet);
+            tmpl::first_letter(&bare_word(lex), alphabet)
+        }
+    }
+}
+
+/// create a file name from a group name, i.e. `old-french` for "Old French"
+fn file_stem(name: &str) -> String {
+    let lower = name.to_lowercase();
+    let stem = lower.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()).collect::<Vec<_>>().join("-");
+    if stem.is_empty() {String::from("none")} else {stem}
+}
+
+/// the file extension for files written in a format with --split-by
+fn extension(format: &cli::Format) -> String {
+    match format {
+        cli::Format::Template { template_file, .. } => {
+            // i.e. `lexicon.adoc.hbs` writes `.adoc` files
+            Path::new(template_file).file_stem().map(Path::new).and_then(|s| s.extension())
+            .map(|e| e.to_string_lossy().to_string()).unwrap_or(String::from("txt"))
+        },
+        cli::Format::Line { .. } | cli::Format::Mdf { .. } | cli::Format::ReverseIndex => String::from("txt"),
+        cli::Format::Csv => String::from("csv"),
+        cli::Format::Tsv => String::from("tsv"),
+        cli::Format::Html { .. } => String::from("html"),
+        cli::Format::Markdown { .. } => String::from("md"),
+        cli::Format::Lift => String::from("lift"),
+        cli::Format::Graphml => String::from("graphml"),
+        cli::Format::Xdxf { .. } => String::from("xdxf"),
+        cli::Format::Json { .. } => String::from("json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use clap::Parser;
+    use libkirum::{kirum::Lexis, word::PartOfSpeech};
+    use crate::cli::{Args, Commands, GroupBy, SortOrder};
+    use super::{render, file_stem, order_words, field_filter};
+
+    fn render_split(fixture: &str, split_by: &str, name: &str) -> Result<(String, std::path::PathBuf)> {
+        let dir = std::env::temp_dir().join(name);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        let args = Args::try_parse_from(["kirum", "render", "-d", &format!("src/test_files/{}", fixture), "--split-by", split_by, 
+            "--split-dir", &dir.to_string_lossy(), "csv"])?;
+        match args.command {
+            Commands::Render(args) => Ok((render(args)?, dir)),
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_pos_abbreviations() -> Result<()> {
+        let args = Args::try_parse_from(["kirum", "render", "-d", "src/test_files/parts_of_speech", "--sort", "id", "line", "--format", "{id} {pos}"])?;
+        let rendered = match args.command {
+            Commands::Render(args) => render(args)?,
+            _ => unreachable!()
+        };
+        assert_eq!(rendered.lines().collect::<Vec<_>>(), vec!["flat_thing clf.", "house n.", "quickly adverb", "to_go v."]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconstructed_prefix() -> Result<()> {
+        let render_with = |format: &[&str]| -> Result<String> {
+            let args = Args::try_parse_from([&["kirum", "render", "-d", "src/test_files/reconstructed", "--sort", "id"], format].concat())?;
+            match args.command {
+                Commands::Render(args) => render(args),
+                _ => unreachable!()
+            }
+        };
+        let rendered = render_with(&["line", "--format", "{id} {word}"])?;
+        assert_eq!(rendered.lines().collect::<Vec<_>>(), vec!["proto_water *wed", "water weda"]);
+        // the JSON output keeps the word as it is, and marks it with the field instead
+        let rendered: serde_json::Value = serde_json::from_str(&render_with(&["json"])?)?;
+        assert_eq!(rendered["words"]["proto_water"]["word"], "wed");
+        assert_eq!(rendered["words"]["proto_water"]["reconstructed"], true);
+        assert!(rendered["words"]["water"].get("reconstructed").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_resolved_transforms() -> Result<()> {
+        let render_json = |flags: &[&str]| -> Result<serde_json::Value> {
+            let args = Args::try_parse_from([&["kirum", "render", "-d", "src/test_files/test_der", "json"], flags].concat())?;
+            match args.command {
+                Commands::Render(args) => Ok(serde_json::from_str(&render(args)?)?),
+                _ => unreachable!()
+            }
+        };
+        let plain = render_json(&[])?;
+        assert!(plain["words"]["latin_example"]["etymology"]["etymons"][0].get("resolved_transforms").is_none());
+        let with_transforms = render_json(&["--transforms"])?;
+        assert_eq!(with_transforms["words"]["latin_example"]["etymology"]["etymons"][0]["resolved_transforms"][0]["name"], "latin-from-verb");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_language() -> Result<()> {
+        let (msg, dir) = render_split("test_der", "language", "kirum_test_split_language")?;
+        assert!(msg.starts_with("wrote 3 files"), "{}", msg);
+        let latin = std::fs::read_to_string(dir.join("latin.csv"))?;
+        assert_eq!(latin.lines().count(), 3);
+        assert!(latin.contains("exemplum") && !latin.contains("essemple"));
+        assert!(std::fs::read_to_string(dir.join("old-french.csv"))?.contains("essemple"));
+        assert!(dir.join("test.csv").exists());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_letter() -> Result<()> {
+        let (msg, dir) = render_split("test_der", "letter", "kirum_test_split_letter")?;
+        assert!(msg.starts_with("wrote 2 files"), "{}", msg);
+        assert_eq!(std::fs::read_to_string(dir.join("e.csv"))?.lines().count(), 4);
+        assert!(std::fs::read_to_string(dir.join("h.csv"))?.contains("hsshmplh"));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_reconstructed_by_letter() -> Result<()> {
+        let (msg, dir) = render_split("reconstructed", "letter", "kirum_test_split_reconstructed")?;
+        assert!(msg.starts_with("wrote 1 files"), "{}", msg);
+        let words = std::fs::read_to_string(dir.join("w.csv"))?;
+        assert!(words.contains("*wed") && words.contains("weda"));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_words() {
+        let word = |id: &str, language: &str, pos: Option<PartOfSpeech>| Lexis{id: id.to_string(), language: language.to_string(), pos, ..Default::default()};
+        // in alphabetical order, as returned by the tree
+        let words = vec![word("c", "New", Some(PartOfSpeech::Verb)), word("a", "Old", None), word("d", "Old", Some(PartOfSpeech::Noun)), word("b", "New", Some(PartOfSpeech::Noun))];
+        let ids = |sort, group_by| {
+            let mut sorted = words.clone();
+            order_words(&mut sorted, sort, group_by);
+            sorted.into_iter().map(|l| l.id).collect::<Vec<String>>().join("")
+        };
+        assert_eq!(ids(SortOrder::Alpha, None), "cadb");
+        assert_eq!(ids(SortOrder::Id, None), "abcd");
+        assert_eq!(ids(SortOrder::Language, None), "cbad");
+        assert_eq!(ids(SortOrder::Pos, None), "dbca");
+        assert_eq!(ids(SortOrder::Id, Some(GroupBy::Language)), "bcad");
+        assert_eq!(ids(SortOrder::Alpha, Some(GroupBy::Pos)), "dbca");
+    }
+
+    #[test]
+    fn test_field_filter() -> Result<()> {
+        let word = Lexis{id: "one".to_string(), language: "Old".to_string(), tags: vec!["a".to_string(), "b".to_string()], pos: Some(PartOfSpeech::Noun), 
+            archaic: true, historical_metadata: [("era".to_string(), "early".to_string())].into(), ..Default::default()};
+        assert!(field_filter(Vec::new(), Vec::new(), Vec::new(), None, Vec::new())?(&word));
+        assert!(field_filter(vec!["New".to_string(), "Old".to_string()], vec!["b".to_string()], vec![PartOfSpeech::Noun], Some(true), vec!["era=early".to_string()])?(&word));
+        assert!(!field_filter(vec!["New".to_string()], Vec::new(), Vec::new(), None, Vec::new())?(&word));
+        assert!(!field_filter(Vec::new(), vec!["c".to_string()], Vec::new(), None, Vec::new())?(&word));
+        assert!(!field_filter(Vec::new(), Vec::new(), vec![PartOfSpeech::Verb], None, Vec::new())?(&word));
+        assert!(!field_filter(Vec::new(), Vec::new(), Vec::new(), Some(false), Vec::new())?(&word));
+        assert!(!field_filter(Vec::new(), Vec::new(), Vec::new(), None, vec!["era=late".to_string()])?(&word));
+        assert!(field_filter(Vec::new(), Vec::new(), Vec::new(), None, vec!["era".to_string()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("Old French"), "old-french");
+        assert_eq!(file_stem("Ch"), "ch");
+        assert_eq!(file_stem("Proto-Ñ (reconstructed)"), "proto-ñ-reconstructed");
+        assert_eq!(file_stem(""), "none");
+    }
+}
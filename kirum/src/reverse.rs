@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use libkirum::kirum::Lexis;
+
+/// Common words that are skipped when extracting keywords from a definition
+const STOPWORDS: &[&str] = &["a", "an", "the", "to", "of", "and", "or", "in", "on", "at", "for", "with", "by", "from",
+"as", "be", "is", "one", "someone", "something", "that", "which", "who", "it", "its", "etc"];
+
+/// extract the lowercased keywords from a definition, skipping common stopwords
+fn keywords(definition: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for word in definition.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-') {
+        let word = word.trim_matches(|c| c == '\'' || c == '-').to_lowercase();
+        if word.is_empty() || STOPWORDS.contains(&word.as_str()) || found.contains(&word) {
+            continue;
+        }
+        found.push(word);
+    }
+    found
+}
+
+/// Create a reverse index from the definitions of the words,
+/// where each definition keyword maps to a list of the words it appears in.
+pub fn reverse_index(words: &[Lexis]) -> BTreeMap<String, Vec<Lexis>> {
+    let mut index: BTreeMap<String, Vec<Lexis>> = BTreeMap::new();
+    for word in words {
+        if word.word.is_none() {
+            continue;
+        }
+        for key in keywords(&word.definition) {
+            index.entry(key).or_default().push(word.clone());
+        }
+    }
+    index
+}
+
+/// render the reverse index as a human-readable list, one keyword per line
+pub fn render_reverse_index(words: &[Lexis]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for (key, found) in reverse_index(words) {
+        let rendered: Vec<String> = found.iter().map(|l| {
+            let word = l.word.clone().unwrap_or_default().string_without_sep();
+            if l.language.is_empty() {
+                word
+            } else {
+                format!("{} ({})", word, l.language)
+            }
+        }).collect();
+        lines.push(format!("{}: {}", key, rendered.join(", ")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::kirum::Lexis;
+    use super::{keywords, reverse_index, render_reverse_index};
+
+    fn test_words() -> Vec<Lexis> {
+        vec![
+            Lexis{id: "one".to_string(), word: Some("kiru".into()), language: "Old Exemplum".to_string(), definition: "a garden, orchard".to_string(), ..Default::default()},
+            Lexis{id: "two".to_string(), word: Some("nukaribu".into()), language: "Old Exemplum".to_string(), definition: "gardener; one who keeps the garden".to_string(), ..Default::default()},
+            Lexis{id: "three".to_string(), word: None, definition: "garden".to_string(), ..Default::default()},
+        ]
+    }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(keywords("To buy, remove; the buyer's"), vec!["buy".to_string(), "remove".to_string(), "buyer's".to_string()]);
+    }
+
+    #[test]
+    fn test_reverse_index() {
+        let index = reverse_index(&test_words());
+        let garden: Vec<String> = index["garden"].iter().map(|l| l.id.clone()).collect();
+        assert_eq!(garden, vec!["one".to_string(), "two".to_string()]);
+        assert_eq!(index["orchard"].len(), 1);
+        assert!(!index.contains_key("the"));
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render_reverse_index(&test_words());
+        assert!(rendered.contains("garden: kiru (Old Exemplum), nukaribu (Old Exemplum)"));
+    }
+}
@@ -0,0 +1,315 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use anyhow::{Result, Context, anyhow};
+use serde_json::{Map, Value};
+use crate::{files::handle_directory, format::FileFormat, transaction::WriteTransaction};
+
+/// What happens to the words derived from a removed word
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Derivatives {
+    /// Remove the etymon from each derived word, leaving it in place
+    #[default]
+    Orphan,
+    /// Remove every word derived from the removed word, recursively
+    Drop,
+    /// Replace the etymon of each derived word with the etymons of the removed word.
+    /// The transforms of the removed word are applied before the transforms of the derived word, so the derived word is unchanged.
+    /// Words derived from a compound can only be re-parented if their edges to the compound have no transforms.
+    Reparent
+}
+
+/// a parsed tree file
+struct TreeFile {
+    path: PathBuf,
+    format: FileFormat,
+    value: Value,
+    changed: bool
+}
+
+impl TreeFile {
+    fn words(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.value.get("words").and_then(Value::as_object).into_iter().flatten()
+    }
+
+    fn words_mut(&mut self) -> Option<&mut Map<String, Value>> {
+        self.value.get_mut("words").and_then(Value::as_object_mut)
+    }
+}
+
+/// Remove a word from the project, updating the etymology of every word derived from it.
+/// Only the tree files that change are re-written, in their original format, keeping the order of their keys.
+/// Returns a summary of the changes.
+pub fn remove_entry(directory: &str, id: &str, derivatives: Derivatives) -> Result<String> {
+    let project = handle_directory(directory)?;
+    let mut files: Vec<TreeFile> = Vec::new();
+    for path in project.graphs {
+        let format = FileFormat::from_path(&path);
+        let raw = std::fs::read_to_string(&path).context(format!("error reading {}", path.display()))?;
+        let value = format.parse_value(&raw).context(format!("error parsing {}", path.display()))?;
+        files.push(TreeFile { path, format, value, changed: false });
+    }
+
+    let removed_entry = files.iter().flat_map(|f| f.words()).find(|(word_id, _)| *word_id == id)
+        .map(|(_, entry)| entry.clone()).ok_or(anyhow!("no word with ID '{}' found in tree files", id))?;
+    let parent_edges = edges(&removed_entry).cloned().collect::<Vec<_>>();
+    if derivatives == Derivatives::Reparent && parent_edges.is_empty() {
+        return Err(anyhow!("'{}' has no etymons to re-parent its derivatives to", id));
+    }
+
+    let mut removed: HashSet<String> = HashSet::from([id.to_string()]);
+    if derivatives == Derivatives::Drop {
+        removed.extend(inline_ids(id, &removed_entry));
+        // keep going until no more words are found, so derivatives of derivatives are dropped too
+        loop {
+            let found: Vec<(String, Value)> = files.iter().flat_map(|f| f.words())
+            .filter(|(word_id, entry)| !removed.contains(*word_id) && edges(entry).any(|e| is_edge_to(e, &removed)))
+            .map(|(word_id, entry)| (word_id.clone(), entry.clone())).collect();
+            if found.is_empty() {
+                break;
+            }
+            for (word_id, entry) in found {
+                removed.extend(inline_ids(&word_id, &entry));
+                removed.insert(word_id);
+            }
+        }
+    }
+
+    let mut updated = 0;
+    for file in files.iter_mut() {
+        let words = match file.words_mut() {
+            Some(words) => words,
+            None => continue
+        };
+        let before = words.len();
+        // derivatives defined inside the removed word become words of their own, so they can be orphaned or re-parented
+        if derivatives != Derivatives::Drop {
+            if let Some(entry) = words.get(id).cloned() {
+                for (der_id, der) in hoist_derivatives(id, &entry) {
+                    if words.contains_key(&der_id) {
+                        return Err(anyhow!("cannot move derivative '{}' of '{}', as the ID already exists", der_id, id));
+                    }
+                    words.insert(der_id, der);
+                }
+            }
+        }
+        words.retain(|word_id, _| !removed.contains(word_id));
+        let mut changed = words.len() != before;
+        for (word_id, entry) in words.iter_mut() {
+            if update_etymology(entry, id, &parent_edges, derivatives).context(format!("cannot update '{}'", word_id))? {
+                updated += 1;
+                changed = true;
+            }
+        }
+        file.changed = changed;
+    }
+
+    let mut tx = WriteTransaction::new();
+    let mut written = 0;
+    for file in files.iter().filter(|f| f.changed) {
+        let data = file.format.write_value(&file.value).context(format!("error creating {:?} data", file.format))?;
+        tx.write(&file.path, data).context(format!("error writing {}", file.path.display()))?;
+        debug!("updated {}", file.path.display());
+        written += 1;
+    }
+    tx.commit();
+    Ok(format!("removed {} words and updated {} words in {} files", removed.len(), updated, written))
+}
+
+/// the etymons of an entry, as JSON values
+fn edges(entry: &Value) -> impl Iterator<Item = &Value> {
+    entry.pointer("/etymology/etymons").and_then(Value::as_array).into_iter().flatten()
+}
+
+/// the IDs of the derivatives defined inside an entry, including derivatives of derivatives
+fn inline_ids(id: &str, entry: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    for (der_id, der) in derivatives_of(id, entry) {
+        ids.extend(inline_ids(&der_id, &der["lexis"]));
+        ids.push(der_id);
+    }
+    ids
+}
+
+/// the derivatives defined inside an entry, keyed by the same IDs they get when the tree is read
+fn derivatives_of<'a>(id: &str, entry: &'a Value) -> Vec<(String, &'a Value)> {
+    entry.get("derivatives").and_then(Value::as_array).into_iter().flatten().enumerate().map(|(count, der)| {
+        let der_id = der.get("id").and_then(Value::as_str).map(String::from)
+        .unwrap_or(format!("{}-autoderive-{}", id, count));
+        (der_id, der)
+    }).collect()
+}
+
+/// turn the derivatives defined inside an entry into standalone entries, with an etymon pointing back to the entry.
+/// Historical metadata is copied from the entry, as it is when the tree is read.
+fn hoist_derivatives(id: &str, entry: &Value) -> Vec<(String, Value)> {
+    derivatives_of(id, entry).into_iter().map(|(der_id, der)| {
+        let mut lexis = der.get("lexis").and_then(Value::as_object).cloned().unwrap_or_default();
+        let mut edge = Map::new();
+        edge.insert("etymon".to_string(), Value::String(id.to_string()));
+        if let Some(transforms) = der.get("transforms").filter(|t| !t.is_null()) {
+            edge.insert("transforms".to_string(), transforms.clone());
+        }
+        lexis.insert("etymology".to_string(), serde_json::json!({"etymons": [edge]}));
+        if let (false, Some(metadata)) = (lexis.contains_key("historical_metadata"), entry.get("historical_metadata")) {
+            lexis.insert("historical_metadata".to_string(), metadata.clone());
+        }
+        (der_id, Value::Object(lexis))
+    }).collect()
+}
+
+fn is_edge_to(edge: &Value, ids: &HashSet<String>) -> bool {
+    edge.get("etymon").and_then(Value::as_str).is_some_and(|e| ids.contains(e))
+}
+
+/// replace or remove every etymon of the entry that points to the removed word. Returns true if the entry changed.
+fn update_etymology(entry: &mut Value, id: &str, parent_edges: &[Value], derivatives: Derivatives) -> Result<bool> {
+    let etymons = match entry.pointer_mut("/etymology/etymons").and_then(Value::as_array_mut) {
+        Some(etymons) => etymons,
+        None => return Ok(false)
+    };
+    let removed = HashSet::from([id.to_string()]);
+    if derivatives == Derivatives::Reparent && parent_edges.len() > 1
+    && etymons.iter().any(|e| is_edge_to(e, &removed) && !edge_transforms(e).is_empty()) {
+        // edge transforms apply to each part of a compound before the parts are joined, so they can't stand in for
+        // transforms applied to the whole compound
+        return Err(anyhow!("'{}' is a compound, and its transforms to the derived word can't be applied to each part", id));
+    }
+    let (to_removed, mut kept): (Vec<Value>, Vec<Value>) = etymons.drain(..).partition(|e| is_edge_to(e, &removed));
+    if to_removed.is_empty() {
+        *etymons = kept;
+        return Ok(false);
+    }
+    if derivatives == Derivatives::Reparent {
+        for child_edge in &to_removed {
+            for parent_edge in parent_edges {
+                let etymon = &parent_edge["etymon"];
+                if !kept.iter().any(|e| &e["etymon"] == etymon) {
+                    kept.push(reparent_edge(parent_edge, child_edge));
+                }
+            }
+        }
+    }
+    *etymons = kept;
+    if etymons.is_empty() {
+        if let Some(map) = entry.as_object_mut() {
+            map.shift_remove("etymology");
+        }
+    }
+    Ok(true)
+}
+
+/// the names of the transforms of an edge
+fn edge_transforms(edge: &Value) -> Vec<Value> {
+    edge.get("transforms").and_then(Value::as_array).cloned().unwrap_or_default()
+}
+
+/// create an edge from the removed word's etymon to the derived word. The transforms of the parent edge run first,
+/// followed by the transforms of the child edge, and the parent edge keeps its place in the agglutination order.
+/// Everything else about the child edge, such as whether the word was borrowed, carries over.
+fn reparent_edge(parent_edge: &Value, child_edge: &Value) -> Value {
+    let mut edge = child_edge.as_object().cloned().unwrap_or_default();
+    edge.insert("etymon".to_string(), parent_edge["etymon"].clone());
+    let transforms: Vec<Value> = edge_transforms(parent_edge).into_iter().chain(edge_transforms(child_edge)).collect();
+    if transforms.is_empty() {
+        edge.shift_remove("transforms");
+    } else {
+        edge.insert("transforms".to_string(), Value::Array(transforms));
+    }
+    match parent_edge.get("agglutination_order") {
+        Some(order) => {edge.insert("agglutination_order".to_string(), order.clone());},
+        None => {edge.shift_remove("agglutination_order");}
+    }
+    edge.shift_remove("resolved_transforms");
+    Value::Object(edge)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use anyhow::Result;
+    use serde_json::{json, Value};
+    use crate::test_util::copy_project;
+    use crate::files::{handle_directory, read_and_compute, read_tree_files};
+    use super::{remove_entry, Derivatives};
+
+
+    #[test]
+    fn test_remove_orphan() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_rm_orphan")?;
+        remove_entry(&dir, "latin_verb", Derivatives::Orphan)?;
+        let computed = read_and_compute(Some(dir.clone()))?;
+        assert!(computed.get_by_id("latin_verb").is_none());
+        assert!(computed.get_by_id("latin_example").unwrap().word.is_none());
+        assert!(remove_entry(&dir, "latin_verb", Derivatives::Orphan).is_err());
+
+        remove_entry(&dir, "latin_example", Derivatives::Orphan)?;
+        let computed = read_and_compute(Some(dir.clone()))?;
+        assert!(computed.etymons("latin_example-autoderive-0").is_empty());
+        assert_eq!(computed.descendants("latin_example-autoderive-0")[0].id, "daughter-gen-hsshmplh");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_drop() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_rm_drop")?;
+        let msg = remove_entry(&dir, "latin_verb", Derivatives::Drop)?;
+        assert_eq!(msg, "removed 4 words and updated 0 words in 2 files");
+        let files = handle_directory(&dir)?.graphs;
+        assert!(read_tree_files(&files, true)?.is_empty());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_reparent() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_rm_reparent")?;
+        let before = read_and_compute(Some(dir.clone()))?;
+        assert!(remove_entry(&dir, "latin_verb", Derivatives::Reparent).is_err());
+
+        remove_entry(&dir, "latin_example", Derivatives::Reparent)?;
+        let computed = read_and_compute(Some(dir.clone()))?;
+        assert!(computed.get_by_id("latin_example").is_none());
+        assert_eq!(computed.etymons("latin_example-autoderive-0")[0].id, "latin_verb");
+        for id in ["latin_example-autoderive-0", "daughter-gen-hsshmplh"] {
+            assert_eq!(computed.get_by_id(id).unwrap().word, before.get_by_id(id).unwrap().word);
+        }
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_reparent_compound() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_rm_reparent_compound")?;
+        let compound = |derived_edge: Value| json!({"words": {
+            "sea": {"word": "mare", "language": "Latin", "definition": "sea"},
+            "compound": {"language": "Latin", "definition": "sea purchase", "etymology": {"etymons": [
+                {"etymon": "latin_verb", "transforms": ["latin-from-verb"], "agglutination_order": 2},
+                {"etymon": "sea", "agglutination_order": 1}
+            ]}},
+            "derived": {"language": "Latin", "definition": "a purchase at sea", "etymology": {"etymons": [derived_edge]}}
+        }});
+        let tree_file = Path::new(&dir).join("tree/compound.json");
+
+        // transforms from the compound to the derived word would apply to each part, changing the word
+        std::fs::write(&tree_file, compound(json!({"etymon": "compound", "transforms": ["of-from-latin"]})).to_string())?;
+        assert!(remove_entry(&dir, "compound", Derivatives::Reparent).is_err());
+
+        std::fs::write(&tree_file, compound(json!({"etymon": "compound", "borrowed": true,
+            "semantic_shift": {"type": "narrowing"}, "metadata": {"source": "glossary"}})).to_string())?;
+        let before = read_and_compute(Some(dir.clone()))?;
+        remove_entry(&dir, "compound", Derivatives::Reparent)?;
+        let computed = read_and_compute(Some(dir.clone()))?;
+        assert_eq!(computed.get_by_id("derived").unwrap().word, before.get_by_id("derived").unwrap().word);
+
+        let words: Value = serde_json::from_str(&std::fs::read_to_string(&tree_file)?)?;
+        assert_eq!(words["words"]["derived"]["etymology"]["etymons"], json!([
+            {"etymon": "latin_verb", "borrowed": true, "semantic_shift": {"type": "narrowing"}, "metadata": {"source": "glossary"},
+                "transforms": ["latin-from-verb"], "agglutination_order": 2},
+            {"etymon": "sea", "borrowed": true, "semantic_shift": {"type": "narrowing"}, "metadata": {"source": "glossary"},
+                "agglutination_order": 1}
+        ]));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
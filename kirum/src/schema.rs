@@ -0,0 +1,47 @@
+use anyhow::Result;
+use libkirum::lexcreate::LexPhonology;
+use schemars::schema_for;
+use crate::{cli::SchemaFile, entries::{TransformGraph, WordGraph}, global::Global, taxonomy::Taxonomy, testing::TestFile};
+
+/// Generate the JSON Schema for a type of project file.
+/// Schemas are generated from the same types used to read the files, so they always match what kirum accepts.
+pub fn generate_schema(file: SchemaFile) -> Result<String> {
+    let schema = match file {
+        SchemaFile::Tree => schema_for!(WordGraph),
+        SchemaFile::Etymology => schema_for!(TransformGraph),
+        SchemaFile::Phonetics => schema_for!(LexPhonology),
+        SchemaFile::Globals => schema_for!(Global),
+        SchemaFile::Taxonomy => schema_for!(Taxonomy),
+        SchemaFile::Tests => schema_for!(TestFile)
+    };
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::Value;
+    use crate::cli::SchemaFile;
+    use super::generate_schema;
+
+    #[test]
+    fn test_tree_schema() -> Result<()> {
+        let schema: Value = serde_json::from_str(&generate_schema(SchemaFile::Tree)?)?;
+        assert_eq!(schema["title"], "WordGraph");
+        let entry = &schema["$defs"]["RawLexicalEntry"]["properties"];
+        assert!(entry["definition"].is_object());
+        assert!(entry["etymology"].is_object());
+        // fields that are only written in rendered output aren't part of the schema
+        assert!(entry["descendants"].is_null());
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_schemas() -> Result<()> {
+        for file in [SchemaFile::Tree, SchemaFile::Etymology, SchemaFile::Phonetics, SchemaFile::Globals, SchemaFile::Taxonomy, SchemaFile::Tests] {
+            let schema: Value = serde_json::from_str(&generate_schema(file)?)?;
+            assert!(schema["$schema"].is_string());
+        }
+        Ok(())
+    }
+}
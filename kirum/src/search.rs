@@ -0,0 +1,240 @@
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::{LanguageTree, Lexis}, query::{Comparison, Condition, Query, QueryField}};
+use regex::Regex;
+use crate::{cli::SearchFormat, entries::create_json_graph, listing};
+
+/// Parse a search expression, such as `language=Latin and tags has archaic and word ends "um"`, into a query condition
+pub fn parse(query: &str) -> Result<Condition> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.next() {
+        None => Ok(expr),
+        Some(tok) => Err(anyhow!("unexpected '{}' after end of expression", tok))
+    }
+}
+
+fn parse_field(name: &str) -> Result<QueryField> {
+    if let Some(key) = name.strip_prefix("metadata.").or(name.strip_prefix("meta.")) {
+        return Ok(QueryField::Metadata(key.to_string()));
+    }
+    let field = match name {
+        "id" => QueryField::Id,
+        "word" => QueryField::Word,
+        "language" | "lang" => QueryField::Language,
+        "pos" | "part_of_speech" => QueryField::Pos,
+        "type" | "lexis_type" => QueryField::LexisType,
+        "definition" | "def" => QueryField::Definition,
+        "tags" | "tag" => QueryField::Tags,
+        "semantic_field" | "field" => QueryField::SemanticField,
+        "noun_class" | "gender" => QueryField::NounClass,
+        "archaic" => QueryField::Archaic,
+        "reconstructed" => QueryField::Reconstructed,
+        "status" => QueryField::Status,
+        "ipa" => QueryField::Ipa,
+        _ => return Err(anyhow!("unknown field '{}'", name))
+    };
+    Ok(field)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    /// one of the symbol operators, `=`, `!=` or `~`
+    Symbol(&'static str),
+    /// a quoted string, which is never read as a keyword
+    Quoted(String),
+    Bare(String)
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Symbol(s) => write!(f, "{}", s),
+            Token::Quoted(s) => write!(f, "\"{}\"", s),
+            Token::Bare(s) => write!(f, "{}", s)
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '=' => tokens.push(Token::Symbol("=")),
+            '~' => tokens.push(Token::Symbol("~")),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                tokens.push(Token::Symbol("!="));
+            },
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => return Err(anyhow!("unterminated string \"{}", value))
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            },
+            c => {
+                let mut value = String::from(c);
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "()=~!\"".contains(*next) {
+                        break;
+                    }
+                    value.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Bare(value));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    /// consume the next token if it's the given keyword
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Bare(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Condition::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Condition> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Condition::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Condition> {
+        if self.keyword("not") {
+            return Ok(Condition::Not(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(tok) => Err(anyhow!("expected ')', found '{}'", tok)),
+                    None => Err(anyhow!("missing closing ')'"))
+                }
+            },
+            Some(Token::Bare(name)) => self.comparison(parse_field(&name)?, &name),
+            Some(tok) => Err(anyhow!("expected a field name, found '{}'", tok)),
+            None => Err(anyhow!("expected a field name, found end of expression"))
+        }
+    }
+
+    fn comparison(&mut self, field: QueryField, name: &str) -> Result<Condition> {
+        let op = match self.next() {
+            Some(Token::Symbol(sym)) => sym.to_string(),
+            Some(Token::Bare(word)) if ["has", "starts", "ends", "matches"].contains(&word.to_lowercase().as_str()) => word.to_lowercase(),
+            Some(tok) => return Err(anyhow!("expected an operator after '{}', found '{}'", name, tok)),
+            None => return Err(anyhow!("expected an operator after '{}'", name))
+        };
+        let value = match self.next() {
+            Some(Token::Quoted(value)) | Some(Token::Bare(value)) => value,
+            Some(tok) => return Err(anyhow!("expected a value after '{} {}', found '{}'", name, op, tok)),
+            None => return Err(anyhow!("expected a value after '{} {}'", name, op))
+        };
+        let cmp = match op.as_str() {
+            "=" | "has" => Comparison::Equals(value),
+            "!=" => Comparison::NotEquals(value),
+            "~" => Comparison::Contains(value),
+            "starts" => Comparison::StartsWith(value),
+            "ends" => Comparison::EndsWith(value),
+            _ => Comparison::Matches(Regex::new(&value).map_err(|e| anyhow!("invalid regex '{}': {}", value, e))?)
+        };
+        Ok(Condition::Compare(field, cmp))
+    }
+}
+
+/// Find the words in the tree that match the search expression, and render them in the given format
+pub fn search(tree: &LanguageTree, query: &str, format: SearchFormat) -> Result<String> {
+    let query = Query::new().condition(parse(query)?);
+    match format {
+        SearchFormat::Line => {
+            let mut found: Vec<Lexis> = query.run(tree).into_iter().map(|m| m.lexis).collect();
+            found.sort_by(|a, b| a.language.cmp(&b.language).then(a.id.cmp(&b.id)));
+            let color = listing::use_color(crate::cli::ColorChoice::Auto);
+            Ok(listing::render_lines(&found, &listing::DEFAULT_FIELDS, listing::terminal_width(), color))
+        },
+        SearchFormat::Json => {
+            let found = query.with_etymology().run(tree).into_iter().filter_map(|m| m.etymology.map(|ety| (m.lexis, ety))).collect();
+            let graph = create_json_graph(found, |l| l.id, true, false, false)?;
+            Ok(serde_json::to_string_pretty(&graph)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use crate::files::read_and_compute;
+    use libkirum::query::Query;
+    use super::parse;
+
+    fn matching(query: &str) -> Result<Vec<String>> {
+        let tree = read_and_compute(Some(String::from("src/test_files/test_der")))?;
+        let query = Query::new().condition(parse(query)?);
+        let mut ids: Vec<String> = query.run(&tree).into_iter().map(|m| m.lexis.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        assert_eq!(matching(r#"language=Latin and tags has example and word ends "um""#)?, vec!["latin_example"]);
+        assert!(matching("tags has exam")?.is_empty());
+        assert_eq!(matching("tags~exam and language=Latin")?, vec!["latin_example"]);
+        assert_eq!(matching("language = Latin and not pos=noun")?, vec!["latin_verb"]);
+        assert_eq!(matching("(language=Test or language=\"Old French\") and archaic=true")?,
+            vec!["daughter-gen-hsshmplh", "latin_example-autoderive-0"]);
+        assert_eq!(matching("word matches ^ex.*um$ or definition~buy")?, vec!["latin_example", "latin_verb"]);
+        assert_eq!(matching("tags!=default and language starts Lat")?, vec!["latin_verb"]);
+        assert!(matching("meta.era=early")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_errors() {
+        for bad in ["colour=red", "word", "word ends", "(language=Latin", "word matches \"(\"", "language=Latin or", "word=\"um"] {
+            assert!(parse(bad).is_err(), "expected error from {}", bad);
+        }
+    }
+}
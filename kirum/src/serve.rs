@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Result, anyhow};
+use libkirum::{kirum::{LanguageTree, Lexis}, lexicon::SharedLexicon};
+use log::{info, error};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use serde_json::json;
+use crate::{entries::{create_json_graph, RawLexicalEntry}, files::read_lexicon, stat::{word_origins, Origins}, watch::on_change};
+
+/// The web viewer served from the root of the server, which browses the lexicon using the JSON API
+const VIEWER: &str = include_str!("templates/viewer.html");
+
+/// A response to a request, as a status code, content type and body
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String
+}
+
+impl Response {
+    fn ok<T: Serialize>(value: &T) -> Response {
+        match serde_json::to_string_pretty(value) {
+            Ok(body) => Response { status: 200, content_type: "application/json", body },
+            Err(e) => Response::error(500, format!("could not serialize response: {}", e))
+        }
+    }
+
+    fn error(status: u16, msg: String) -> Response {
+        Response { status, content_type: "application/json", body: json!({"error": msg}).to_string() }
+    }
+}
+
+/// The summary returned by the stats endpoint
+#[derive(Serialize)]
+struct ProjectStats {
+    total: usize,
+    languages: BTreeMap<String, Origins>
+}
+
+/// Serve a read-only JSON API for a project over HTTP, until the process is stopped.
+/// The project is computed once, and recomputed in the background whenever a project file changes,
+/// so changes are reflected without restarting. Requests are answered from the last version that computed without errors.
+pub fn serve(directory: Option<String>, address: String, port: u16) -> Result<String> {
+    let dir = directory.ok_or(anyhow!("must specify a project directory"))?;
+    let lexicon = SharedLexicon::new(read_lexicon(&dir)?);
+    let server = tiny_http::Server::http((address.as_str(), port))
+    .map_err(|e| anyhow!("could not listen on {}:{}: {}", address, port, e))?;
+
+    let shared = lexicon.clone();
+    let watch_dir = dir.clone();
+    std::thread::spawn(move || {
+        // each change starts a recompute without waiting for the last one, and a failed recompute is logged by the lexicon
+        let res = on_change(&watch_dir, None, || {
+            let dir = watch_dir.clone();
+            shared.recompute(move || read_lexicon(&dir));
+        });
+        if let Err(e) = res {
+            error!("{:#}, changes to the project will not be served", e);
+        }
+    });
+
+    info!("serving {} on http://{}:{}", dir, address, port);
+    for request in server.incoming_requests() {
+        let response = if *request.method() == tiny_http::Method::Get {
+            handle(&lexicon.snapshot(), request.url())
+        } else {
+            Response::error(405, format!("method {} is not allowed", request.method()))
+        };
+        info!("{} {} {}", request.method(), request.url(), response.status);
+        let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], response.content_type.as_bytes())
+        .map_err(|_| anyhow!("could not create content type header"))?;
+        let reply = tiny_http::Response::from_string(response.body).with_status_code(response.status).with_header(header);
+        if let Err(e) = request.respond(reply) {
+            error!("could not send response: {}", e);
+        }
+    }
+    Ok(String::new())
+}
+
+/// Route a GET request for the given URL, answering from the computed tree.
+///
+/// - `/`, the web viewer
+/// - `/words`, optionally filtered with `?language=`
+/// - `/words/<id>`
+/// - `/words/<id>/explain`
+/// - `/search?q=`
+/// - `/stats`
+pub fn handle(tree: &LanguageTree, url: &str) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    if path == "/" || path == "/index.html" {
+        return Response { status: 200, content_type: "text/html; charset=utf-8", body: VIEWER.to_string() };
+    }
+    let params: HashMap<String, String> = form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+    let segments: Vec<String> = path.split('/').filter(|s| !s.is_empty())
+    .map(|s| percent_decode_str(s).decode_utf8_lossy().to_string()).collect();
+
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+    match segments.as_slice() {
+        ["words"] => words(tree, |l| params.get("language").is_none_or(|lang| &l.language == lang)),
+        ["words", id] => match words_graph(tree, |l| l.id == *id) {
+            Ok(mut graph) => match graph.remove(*id) {
+                Some(word) => Response::ok(&word),
+                None => Response::error(404, format!("no word with ID '{}' found", id))
+            },
+            Err(e) => Response::error(500, format!("{:#}", e))
+        },
+        ["words", id, "explain"] => match tree.explain(id) {
+            Ok(Some(trace)) => Response::ok(&trace),
+            Ok(None) => Response::error(404, format!("no word with ID '{}' found", id)),
+            Err(e) => Response::error(500, format!("could not explain word: {}", e))
+        },
+        ["search"] => match params.get("q") {
+            Some(term) => {
+                let term = term.to_lowercase();
+                words(tree, |l| l.id.to_lowercase().contains(&term) || l.definition.to_lowercase().contains(&term)
+                    || l.word.as_ref().is_some_and(|w| w.string_without_sep().to_lowercase().contains(&term)))
+            },
+            None => Response::error(400, "search requires a `q` parameter".to_string())
+        },
+        ["stats"] => {
+            let languages = word_origins(tree, |_| true);
+            let total = languages.values().map(|o| o.explicit + o.derived + o.generated).sum();
+            Response::ok(&ProjectStats { total, languages })
+        },
+        _ => Response::error(404, format!("no endpoint found for {}", path))
+    }
+}
+
+/// the words that match the filter, in the same structure as a tree file
+fn words_graph<F>(tree: &LanguageTree, filter: F) -> Result<HashMap<String, RawLexicalEntry>>
+where F: Fn(&Lexis) -> bool
+{
+    let graph = create_json_graph(tree.to_vec_etymons(filter), |l| l.id, true, false, false)?;
+    Ok(graph.words)
+}
+
+/// a response listing the words that match the filter, sorted by ID
+fn words<F>(tree: &LanguageTree, filter: F) -> Response
+where F: Fn(&Lexis) -> bool
+{
+    match words_graph(tree, filter) {
+        Ok(graph) => Response::ok(&graph.into_iter().collect::<BTreeMap<String, RawLexicalEntry>>()),
+        Err(e) => Response::error(500, format!("{:#}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::lexicon::{ComputedLexicon, SharedLexicon};
+    use serde_json::Value;
+    use crate::test_util::copy_project;
+    use crate::files::read_lexicon;
+    use super::handle;
+
+    fn project() -> ComputedLexicon {
+        read_lexicon("src/test_files/test_der").unwrap()
+    }
+
+    #[test]
+    fn test_serve_words() {
+        let lexicon = project();
+        let all = handle(&lexicon, "/words");
+        assert_eq!(all.status, 200);
+        let parsed: Value = serde_json::from_str(&all.body).unwrap();
+        assert!(parsed["latin_verb"].is_object());
+
+        let single = handle(&lexicon, "/words/latin_verb");
+        assert_eq!(single.status, 200);
+        let parsed: Value = serde_json::from_str(&single.body).unwrap();
+        assert!(parsed["word"].is_string());
+
+        assert_eq!(handle(&lexicon, "/words/missing").status, 404);
+        assert_eq!(handle(&lexicon, "/").content_type, "text/html; charset=utf-8");
+        assert_eq!(handle(&lexicon, "/nothing").status, 404);
+    }
+
+    #[test]
+    fn test_serve_explain_and_stats() {
+        let lexicon = project();
+        let trace = handle(&lexicon, "/words/latin_example/explain");
+        assert_eq!(trace.status, 200);
+        let parsed: Value = serde_json::from_str(&trace.body).unwrap();
+        assert_eq!(parsed["id"], "latin_example");
+
+        let stats = handle(&lexicon, "/stats");
+        assert_eq!(stats.status, 200);
+        let parsed: Value = serde_json::from_str(&stats.body).unwrap();
+        assert!(parsed["total"].as_u64().unwrap() > 0);
+
+        assert_eq!(handle(&lexicon, "/search").status, 400);
+        let found = handle(&lexicon, "/search?q=instance");
+        let parsed: Value = serde_json::from_str(&found.body).unwrap();
+        assert_eq!(parsed.as_object().unwrap().keys().collect::<Vec<&String>>(), vec!["latin_example"]);
+    }
+
+    #[test]
+    fn test_serve_reload() -> Result<()> {
+        let dir = copy_project("test_der", "kirum_test_serve_reload")?;
+        let shared = SharedLexicon::new(read_lexicon(&dir)?);
+        let before = shared.snapshot();
+
+        let tree_file = std::path::Path::new(&dir).join("tree/test_der.json");
+        let raw = std::fs::read_to_string(&tree_file)?;
+        std::fs::write(&tree_file, raw.replace("an instance", "a specimen"))?;
+        let reload_dir = dir.clone();
+        shared.recompute(move || read_lexicon(&reload_dir)).join().unwrap()?;
+
+        let found: Value = serde_json::from_str(&handle(&shared.snapshot(), "/search?q=specimen").body)?;
+        assert_eq!(found.as_object().unwrap().keys().collect::<Vec<&String>>(), vec!["latin_example"]);
+        // a request that started before the reload keeps answering from the old snapshot
+        let old: Value = serde_json::from_str(&handle(&before, "/search?q=specimen").body)?;
+        assert!(old.as_object().unwrap().is_empty());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
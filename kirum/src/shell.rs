@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use anyhow::{Result, Context};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use crate::cli::Args;
+
+/// generate a completion script for the given shell
+pub fn completions(shell: Shell) -> Result<String> {
+    let mut cmd = Args::command();
+    let name = cmd.get_name().to_string();
+    let mut buf: Vec<u8> = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut buf);
+    Ok(String::from_utf8(buf)?)
+}
+
+/// write a man page for kirum and each of its subcommands to the given directory
+pub fn manpages(directory: &str) -> Result<String> {
+    let out = Path::new(directory);
+    std::fs::create_dir_all(out).context(format!("could not create directory {}", directory))?;
+    clap_mangen::generate_to(Args::command(), out).context(format!("could not write man pages to {}", directory))?;
+    Ok(format!("wrote man pages to {}", directory))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use clap_complete::Shell;
+    use super::{completions, manpages};
+
+    #[test]
+    fn test_completions() -> Result<()> {
+        let script = completions(Shell::Bash)?;
+        assert!(script.contains("render"));
+        assert!(script.contains("lexis-type"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manpages() -> Result<()> {
+        let out = std::env::temp_dir().join("kirum_test_manpages");
+        manpages(out.to_str().unwrap())?;
+        assert!(out.join("kirum.1").exists());
+        assert!(out.join("kirum-render.1").exists());
+        std::fs::remove_dir_all(out)?;
+        Ok(())
+    }
+}
@@ -1,30 +1,342 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap, HashSet};
 
-use libkirum::kirum::LanguageTree;
+use libkirum::kirum::{LanguageTree, Lexis};
+use crate::tmpl::count_syllables;
+use serde::Serialize;
 use tabled::{Tabled, Table, settings::{object::FirstRow, Disable, panel::Header}};
 
 #[derive(Default, Tabled)]
 struct Stats {
-    nouns: i64,
-    verbs: i64,
-    adjectives: i64,
-    none: i64,
     total: usize
 }
 
-/// generate basic human-readable stats
-pub fn gen_stats(tree: LanguageTree) -> String {
+/// Counts of how the words of a language were created
+#[derive(Default, Debug, PartialEq, Serialize)]
+pub struct Origins {
+    /// words with a word field written in the tree file
+    pub explicit: usize,
+    /// words derived from one or more etymons
+    pub derived: usize,
+    /// words generated from phonetic rules
+    pub generated: usize
+}
+
+/// count the explicit, derived and generated words of each language.
+/// A word only counts as generated if its word was created from the phonetic rules, not if it has a `generate` key and a word of its own.
+pub fn word_origins<F>(tree: &LanguageTree, filter: F) -> BTreeMap<String, Origins>
+where F: Fn(&Lexis) -> bool
+{
+    let mut origins: BTreeMap<String, Origins> = BTreeMap::new();
+    let generated: HashSet<String> = tree.generated().into_iter().collect();
+    for (lex, ety) in tree.to_vec_etymons(filter) {
+        let lang_name = match lex.language.as_str() {
+            "" => "None Set",
+            st => st,
+        };
+        let counts = origins.entry(lang_name.to_string()).or_default();
+        if !ety.etymons.is_empty() {
+            counts.derived += 1;
+        } else if generated.contains(&lex.id) {
+            counts.generated += 1;
+        } else {
+            counts.explicit += 1;
+        }
+    }
+    origins
+}
+
+/// How often each segment appears in the words of a language
+#[derive(Default, Debug, PartialEq)]
+pub struct SegmentCounts {
+    /// the number of times each segment appears anywhere in a word
+    pub all: BTreeMap<String, usize>,
+    /// the number of words that start with each segment
+    pub initial: BTreeMap<String, usize>,
+    /// the number of words that end with each segment
+    pub last: BTreeMap<String, usize>,
+}
+
+impl SegmentCounts {
+    /// the total number of segments counted
+    pub fn total(&self) -> usize {
+        self.all.values().sum()
+    }
+}
+
+/// count the segments used by the words of each language, using the segments of each word's lemma
+pub fn segment_frequencies<F>(tree: &LanguageTree, filter: F) -> BTreeMap<String, SegmentCounts>
+where F: Fn(&Lexis) -> bool
+{
+    let mut frequencies: BTreeMap<String, SegmentCounts> = BTreeMap::new();
+    for lex in tree.iter().filter(|l| filter(l)) {
+        let Some(word) = &lex.word else {
+            continue;
+        };
+        let lang_name = match lex.language.as_str() {
+            "" => "None Set",
+            st => st,
+        };
+        let segments: Vec<&str> = word.iter().collect();
+        let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+            continue;
+        };
+        let counts = frequencies.entry(lang_name.to_string()).or_default();
+        *counts.initial.entry(first.to_string()).or_default() += 1;
+        *counts.last.entry(last.to_string()).or_default() += 1;
+        for seg in segments {
+            *counts.all.entry(seg.to_string()).or_default() += 1;
+        }
+    }
+    frequencies
+}
+
+/// print a table of segment frequencies for each language, from the most to least common segment
+fn segment_tables(frequencies: BTreeMap<String, SegmentCounts>) -> String {
+    let mut tables: Vec<String> = Vec::new();
+    for (lang, counts) in frequencies {
+        let total = counts.total();
+        let mut ordered: Vec<(&String, &usize)> = counts.all.iter().collect();
+        ordered.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["segment", "count", "frequency", "initial", "final"]);
+        for (seg, count) in ordered {
+            builder.push_record([seg.clone(), count.to_string(), format!("{:.1}%", *count as f64 / total as f64 * 100.0), 
+                counts.initial.get(seg).unwrap_or(&0).to_string(), counts.last.get(seg).unwrap_or(&0).to_string()]);
+        }
+        tables.push(builder.build().with(Header::new(format!("Segments: {}", lang))).to_string());
+    }
+    tables.join("\n")
+}
+
+/// The sequences of segments used by the words of a language
+#[derive(Default, Debug, PartialEq)]
+pub struct NgramCounts {
+    /// the number of times each pair of adjacent segments appears
+    pub bigrams: BTreeMap<String, usize>,
+    /// the number of times each run of three segments appears
+    pub trigrams: BTreeMap<String, usize>,
+    /// the number of times each cluster of two or more adjacent consonants appears
+    pub clusters: BTreeMap<String, usize>,
+}
+
+/// count the bigrams, trigrams and consonant clusters in the words of each language.
+/// N-grams don't cross word boundaries, and any segment that isn't one of the given vowels is treated as a consonant.
+pub fn ngram_frequencies<F>(tree: &LanguageTree, filter: F, vowels: &[String]) -> BTreeMap<String, NgramCounts>
+where F: Fn(&Lexis) -> bool
+{
+    let mut frequencies: BTreeMap<String, NgramCounts> = BTreeMap::new();
+    for lex in tree.iter().filter(|l| filter(l)) {
+        let Some(word) = &lex.word else {
+            continue;
+        };
+        let lang_name = match lex.language.as_str() {
+            "" => "None Set",
+            st => st,
+        };
+        let segments: Vec<&str> = word.iter().collect();
+        let counts = frequencies.entry(lang_name.to_string()).or_default();
+        for pair in segments.windows(2) {
+            *counts.bigrams.entry(pair.concat()).or_default() += 1;
+        }
+        for triple in segments.windows(3) {
+            *counts.trigrams.entry(triple.concat()).or_default() += 1;
+        }
+        let is_vowel = |seg: &&str| vowels.iter().any(|v| v == &seg.to_lowercase());
+        for cluster in segments.split(is_vowel).filter(|c| c.len() > 1) {
+            *counts.clusters.entry(cluster.concat()).or_default() += 1;
+        }
+    }
+    frequencies
+}
+
+/// print a table of counts, from the most to least common
+fn count_table(title: String, column: &str, counts: &BTreeMap<String, usize>) -> String {
+    let mut ordered: Vec<(&String, &usize)> = counts.iter().collect();
+    ordered.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record([column, "count"]);
+    for (value, count) in ordered {
+        builder.push_record([value.clone(), count.to_string()]);
+    }
+    builder.build().with(Header::new(title)).to_string()
+}
+
+/// print the bigram, trigram and cluster tables for each language
+fn ngram_tables(frequencies: BTreeMap<String, NgramCounts>) -> String {
+    let mut tables: Vec<String> = Vec::new();
+    for (lang, counts) in frequencies {
+        tables.push(count_table(format!("Bigrams: {}", lang), "bigram", &counts.bigrams));
+        tables.push(count_table(format!("Trigrams: {}", lang), "trigram", &counts.trigrams));
+        tables.push(count_table(format!("Consonant Clusters: {}", lang), "cluster", &counts.clusters));
+    }
+    tables.join("\n")
+}
+
+/// The number of words of each length in a language
+#[derive(Default, Debug, PartialEq)]
+pub struct LengthCounts {
+    /// the number of words with each number of segments
+    pub segments: BTreeMap<usize, usize>,
+    /// the number of words with each number of syllables
+    pub syllables: BTreeMap<usize, usize>,
+}
+
+/// count the length of each word in each language, in both segments and syllables
+pub fn word_lengths<F>(tree: &LanguageTree, filter: F, vowels: &[String]) -> BTreeMap<String, LengthCounts>
+where F: Fn(&Lexis) -> bool
+{
+    let mut lengths: BTreeMap<String, LengthCounts> = BTreeMap::new();
+    for lex in tree.iter().filter(|l| filter(l)) {
+        let Some(word) = &lex.word else {
+            continue;
+        };
+        let lang_name = match lex.language.as_str() {
+            "" => "None Set",
+            st => st,
+        };
+        let segments: Vec<&str> = word.iter().collect();
+        let counts = lengths.entry(lang_name.to_string()).or_default();
+        *counts.segments.entry(segments.len()).or_default() += 1;
+        *counts.syllables.entry(count_syllables(&segments, vowels)).or_default() += 1;
+    }
+    lengths
+}
+
+/// the width of the longest bar in a histogram
+const HISTOGRAM_WIDTH: usize = 20;
+
+/// print a histogram of word lengths for each language
+fn length_tables(lengths: BTreeMap<String, LengthCounts>) -> String {
+    let mut tables: Vec<String> = Vec::new();
+    for (lang, counts) in lengths {
+        let longest = counts.segments.keys().chain(counts.syllables.keys()).max().copied().unwrap_or(0);
+        let most = counts.segments.values().chain(counts.syllables.values()).max().copied().unwrap_or(0);
+        let bar = |count: usize| format!("{} {}", count, "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(most.max(1))));
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["length", "segments", "syllables"]);
+        for len in 0..=longest {
+            let (by_seg, by_syl) = (counts.segments.get(&len).unwrap_or(&0), counts.syllables.get(&len).unwrap_or(&0));
+            if *by_seg == 0 && *by_syl == 0 {
+                continue;
+            }
+            builder.push_record([len.to_string(), bar(*by_seg), bar(*by_syl)]);
+        }
+        tables.push(builder.build().with(Header::new(format!("Word Lengths: {}", lang))).to_string());
+    }
+    tables.join("\n")
+}
+
+/// The aggregate phonological distance between two languages
+#[derive(Debug, PartialEq)]
+pub struct Distance {
+    /// The number of cognate pairs compared
+    pub cognates: usize,
+    /// The mean edit distance between cognates, normalized to the length of the longer word, from 0 (identical) to 1
+    pub mean: f64
+}
+
+/// Compute the phonological distance between two languages, by comparing the letters of each pair of cognates.
+/// Two words are cognates if one is descended from the other, or they share an ancestor.
+/// Returns None if the languages have no cognates.
+pub fn language_distance(tree: &LanguageTree, first: &str, second: &str) -> Option<Distance> {
+    let lineage = |lang: &str| -> Vec<(&Lexis, HashSet<String>)> {
+        tree.iter().filter(|l| l.word.is_some() && l.language == lang)
+        .map(|l| (l, ancestors(tree, &l.id))).collect()
+    };
+    let first_words = lineage(first);
+    let second_words = lineage(second);
+    let mut total = 0.0;
+    let mut cognates = 0;
+    for (a, a_anc) in &first_words {
+        for (b, b_anc) in &second_words {
+            if a_anc.is_disjoint(b_anc) {
+                continue;
+            }
+            let a_letters = a.word.clone().unwrap_or_default().chars();
+            let b_letters = b.word.clone().unwrap_or_default().chars();
+            let longest = a_letters.len().max(b_letters.len());
+            if longest > 0 {
+                total += edit_distance(&a_letters, &b_letters) as f64 / longest as f64;
+                cognates += 1;
+            }
+        }
+    }
+    if cognates == 0 {
+        return None
+    }
+    Some(Distance { cognates, mean: total / cognates as f64 })
+}
+
+/// the IDs of the word and all of its ancestors
+fn ancestors(tree: &LanguageTree, id: &str) -> HashSet<String> {
+    let mut found: HashSet<String> = HashSet::new();
+    let mut pending = vec![id.to_string()];
+    while let Some(current) = pending.pop() {
+        if found.insert(current.clone()) {
+            pending.extend(tree.etymons(&current).into_iter().map(|l| l.id));
+        }
+    }
+    found
+}
+
+/// the Levenshtein distance between two lists of letters
+fn edit_distance(a: &[String], b: &[String]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_letter) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_letter) in b.iter().enumerate() {
+            let cost = if a_letter == b_letter {0} else {1};
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// generate basic human-readable stats for the words that match the filter.
+/// If distance is set, the phonological distance between the two languages is also reported.
+/// If segments is set, the frequency of each segment in each language is also reported,
+/// and if ngrams is set, the frequency of bigrams, trigrams and consonant clusters.
+/// If lengths is set, a histogram of word lengths in each language is also reported.
+pub fn gen_stats<F>(tree: LanguageTree, filter: F, distance: Option<(String, String)>, segments: bool, ngrams: bool, lengths: bool) -> String 
+where F: Fn(&Lexis) -> bool
+{
+    let origins = word_origins(&tree, &filter);
+    let segment_str = if segments {
+        format!("{}\n", segment_tables(segment_frequencies(&tree, &filter)))
+    } else {
+        String::new()
+    };
+    let ngram_str = if ngrams {
+        format!("{}\n", ngram_tables(ngram_frequencies(&tree, &filter, &tree.vowels())))
+    } else {
+        String::new()
+    };
+    let length_str = if lengths {
+        format!("{}\n", length_tables(word_lengths(&tree, &filter, &tree.vowels())))
+    } else {
+        String::new()
+    };
+    let distance_str = match distance {
+        Some((first, second)) => {
+            let mut builder = tabled::builder::Builder::default();
+            builder.push_record(["languages", "cognates", "mean distance"]);
+            match language_distance(&tree, &first, &second) {
+                Some(found) => builder.push_record([format!("{} - {}", first, second), found.cognates.to_string(), format!("{:.3}", found.mean)]),
+                None => builder.push_record([format!("{} - {}", first, second), String::from("0"), String::from("n/a")])
+            };
+            format!("{}\n", builder.build().with(Header::new("Phonological Distance")))
+        },
+        None => String::new()
+    };
     let mut languages: HashMap<String, i64> = HashMap::new();
     let mut types: HashMap<String, i64> = HashMap::new();
-    let mut stats = Stats{total: tree.len(), ..Stats::default()};
-    for lex in tree.into_iter() {
-        if let Some(pos) = lex.pos {
-            match pos {
-                libkirum::word::PartOfSpeech::Adjective => stats.adjectives+=1,
-                libkirum::word::PartOfSpeech::Verb => stats.verbs+=1,
-                libkirum::word::PartOfSpeech::Noun => stats.nouns+=1,
-                libkirum::word::PartOfSpeech::None => stats.none+=1
-            }
+    let mut parts_of_speech: BTreeMap<String, i64> = BTreeMap::new();
+    let mut stats = Stats::default();
+    for lex in tree.iter().filter(|l| filter(l)) {
+        stats.total += 1;
+        if let Some(pos) = &lex.pos {
+            *parts_of_speech.entry(pos.to_string()).or_insert(0) += 1;
         }
         let lang_name = match lex.language.as_str() {
             "" => "None Set",
@@ -34,7 +346,7 @@ pub fn gen_stats(tree: LanguageTree) -> String {
         languages.insert(lang_name.to_string(), new_lang_count);
         
         let new_type_count = types.get(&lex.lexis_type).unwrap_or(&0)+1;
-        types.insert(lex.lexis_type, new_type_count);
+        types.insert(lex.lexis_type.clone(), new_type_count);
     }
 
 
@@ -44,5 +356,120 @@ pub fn gen_stats(tree: LanguageTree) -> String {
     .with(Disable::row(FirstRow)).with(Header::new("Languages")).to_string();
     let type_str = Table::new(types)
     .with(Disable::row(FirstRow)).with(Header::new("Types")).to_string();
-    format!("\n{}\n{}\n{}\n", stat_str, lang_str, type_str)
-}
\ No newline at end of file
+    let pos_str = Table::new(parts_of_speech)
+    .with(Disable::row(FirstRow)).with(Header::new("Parts of Speech")).to_string();
+    let mut origin_builder = tabled::builder::Builder::default();
+    origin_builder.push_record(["language", "explicit", "derived", "generated"]);
+    for (lang, counts) in origins {
+        origin_builder.push_record([lang, counts.explicit.to_string(), counts.derived.to_string(), counts.generated.to_string()]);
+    }
+    let origin_str = origin_builder.build().with(Header::new("Word Origins")).to_string();
+    format!("\n{}\n{}\n{}\n{}\n{}\n{}{}{}{}", stat_str, pos_str, lang_str, type_str, origin_str, distance_str, segment_str, ngram_str, length_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use anyhow::Result;
+    use crate::files::read_and_compute;
+    use libkirum::{kirum::{Lexis, LanguageTree}, transforms::{Transform, TransformFunc}};
+    use super::{word_origins, Origins, language_distance, edit_distance, segment_frequencies, ngram_frequencies, word_lengths};
+
+    #[test]
+    fn test_word_origins() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/with_phonetic_rules")))?;
+        let origins = word_origins(&computed, |_| true);
+        assert_eq!(origins["Latin"], Origins{explicit: 0, derived: 1, generated: 1});
+        assert_eq!(origins["Old French"], Origins{explicit: 0, derived: 1, generated: 0});
+        Ok(())
+    }
+
+    #[test]
+    fn test_word_origins_written() -> Result<()> {
+        let mut computed = read_and_compute(Some(String::from("src/test_files/with_phonetic_rules")))?;
+        // a word with a generate key and a word of its own wasn't generated
+        computed.add_lexis(Lexis{id: "written".to_string(), word: Some("kira".into()), language: "Latin".to_string(),
+            word_create: Some("word".to_string()), ..Default::default()});
+        computed.compute_lexicon()?;
+        let origins = word_origins(&computed, |_| true);
+        assert_eq!(origins["Latin"], Origins{explicit: 1, derived: 1, generated: 1});
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let letters = |w: &str| -> Vec<String> {w.chars().map(|c| c.to_string()).collect()};
+        assert_eq!(edit_distance(&letters("kirum"), &letters("kirum")), 0);
+        assert_eq!(edit_distance(&letters("kirum"), &letters("kiran")), 2);
+        assert_eq!(edit_distance(&letters("kirum"), &letters("irum")), 1);
+        assert_eq!(edit_distance(&letters(""), &letters("amo")), 3);
+    }
+
+    #[test]
+    fn test_language_distance() {
+        let mut tree = LanguageTree::new();
+        let old = |id: &str, word: &'static str| Lexis{id: id.to_string(), word: Some(word.into()), language: "Old".to_string(), ..Default::default()};
+        let new = |id: &str| Lexis{id: id.to_string(), language: "New".to_string(), ..Default::default()};
+        let change = Transform{name: "m-to-n".to_string(), lex_match: None, 
+            transforms: vec![TransformFunc::MatchReplace { old: "m".into(), new: "n".into() }]};
+        tree.connect_etymology(new("kirun"), old("kirum", "kirum"), vec![change], None);
+        tree.connect_etymology(new("ato"), old("ato", "ato"), Vec::new(), None);
+        tree.add_lexis(old("unrelated", "pel"));
+        tree.compute_lexicon().unwrap();
+
+        let found = language_distance(&tree, "Old", "New").unwrap();
+        assert_eq!(found.cognates, 2);
+        assert!((found.mean - 0.1).abs() < 1e-9);
+        assert!(language_distance(&tree, "Old", "Other").is_none());
+    }
+
+    #[test]
+    fn test_segment_frequencies() {
+        let mut tree = LanguageTree::new();
+        let word = |id: &str, word: &'static str| Lexis{id: id.to_string(), word: Some(word.into()), language: "Old".to_string(), ..Default::default()};
+        tree.add_lexis(word("kirum", "kirum"));
+        tree.add_lexis(word("kira", "kira"));
+        tree.add_lexis(Lexis{id: "empty".to_string(), language: "Old".to_string(), ..Default::default()});
+
+        let found = segment_frequencies(&tree, |_| true);
+        let old = &found["Old"];
+        assert_eq!(old.total(), 9);
+        assert_eq!(old.all["k"], 2);
+        assert_eq!(old.all["a"], 1);
+        assert_eq!(old.initial["k"], 2);
+        assert_eq!(old.last.get("k"), None);
+        assert_eq!(old.last["m"], 1);
+    }
+
+    #[test]
+    fn test_ngram_frequencies() {
+        let mut tree = LanguageTree::new();
+        let word = |id: &str, word: &'static str| Lexis{id: id.to_string(), word: Some(word.into()), language: "Old".to_string(), ..Default::default()};
+        tree.add_lexis(word("strum", "strum"));
+        tree.add_lexis(word("kirst", "kirst"));
+        let vowels: Vec<String> = vec!["i".into(), "u".into()];
+
+        let found = ngram_frequencies(&tree, |_| true, &vowels);
+        let old = &found["Old"];
+        assert_eq!(old.bigrams["st"], 2);
+        assert_eq!(old.bigrams["ru"], 1);
+        assert_eq!(old.trigrams["str"], 1);
+        assert_eq!(old.trigrams.len(), 6);
+        assert_eq!(old.clusters.keys().collect::<Vec<&String>>(), vec!["rst", "str"]);
+    }
+
+    #[test]
+    fn test_word_lengths() {
+        let mut tree = LanguageTree::new();
+        let word = |id: &str, word: &'static str| Lexis{id: id.to_string(), word: Some(word.into()), language: "Old".to_string(), ..Default::default()};
+        tree.add_lexis(word("kirum", "kirum"));
+        tree.add_lexis(word("kiran", "kiran"));
+        tree.add_lexis(word("ato", "ato"));
+        let vowels: Vec<String> = vec!["a".into(), "i".into(), "o".into(), "u".into()];
+
+        let found = word_lengths(&tree, |_| true, &vowels);
+        let old = &found["Old"];
+        assert_eq!(old.segments, BTreeMap::from([(3, 1), (5, 2)]));
+        assert_eq!(old.syllables, BTreeMap::from([(2, 3)]));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Result, Context};
+use csv::WriterBuilder;
+use libkirum::{kirum::{LanguageTree, Lexis}, word::Etymology};
+
+/// The columns written for every word, before the historical metadata columns
+const COLUMNS: &[&str] = &["id", "word", "language", "pos", "lexis_type", "definition", "ipa",
+"semantic_field", "archaic", "noun_class", "tags", "etymology"];
+
+/// The prefix of the columns created from the historical_metadata fields of each word
+const METADATA_PREFIX: &str = "metadata.";
+
+/// render the etymology of a word as a single column, in the form `etymon (transform, transform); etymon`
+fn flatten_etymology(ety: &Etymology) -> String {
+    let edges: Vec<String> = ety.etymons.iter().map(|edge| {
+        match &edge.transforms {
+            Some(transforms) if !transforms.is_empty() => format!("{} ({})", edge.etymon, transforms.join(", ")),
+            _ => edge.etymon.clone()
+        }
+    }).collect();
+    edges.join("; ")
+}
+
+/// flatten a word into a single row, with a column for each of the given metadata keys
+fn flatten_lexis(word: &Lexis, ety: Option<&Etymology>, metadata_keys: &BTreeSet<String>) -> Vec<String> {
+    let mut row = vec![
+        word.id.clone(),
+        word.word.clone().unwrap_or_default().string_without_sep(),
+        word.language.clone(),
+        word.pos.as_ref().map(|p| p.to_string()).unwrap_or_default(),
+        word.lexis_type.clone(),
+        word.definition.clone(),
+        word.ipa.clone().unwrap_or_default(),
+        word.semantic_field.clone().unwrap_or_default(),
+        word.archaic.to_string(),
+        word.noun_class.clone().unwrap_or_default(),
+        word.tags.join("; "),
+        ety.map(flatten_etymology).unwrap_or_default(),
+    ];
+    for key in metadata_keys {
+        row.push(word.historical_metadata.get(key).cloned().unwrap_or_default());
+    }
+    row
+}
+
+/// render the words as a table of delimiter-separated values, with one row per word.
+/// Tags are joined into a single column, each historical_metadata key gets its own `metadata.` column,
+/// and the etymology is written as a single column of etymon IDs and transform names.
+pub fn render_table(tree: &LanguageTree, words: &[Lexis], delimiter: u8) -> Result<String> {
+    let etymologies: HashMap<String, Etymology> = tree.to_vec_etymons(|_| true).into_iter()
+    .map(|(lex, ety)| (lex.id, ety)).collect();
+    let metadata_keys: BTreeSet<String> = words.iter().flat_map(|w| w.historical_metadata.keys().cloned()).collect();
+
+    let mut wrt = WriterBuilder::new().delimiter(delimiter).from_writer(vec![]);
+    let mut header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+    header.extend(metadata_keys.iter().map(|k| format!("{}{}", METADATA_PREFIX, k)));
+    wrt.write_record(&header)?;
+    for word in words {
+        wrt.write_record(flatten_lexis(word, etymologies.get(&word.id), &metadata_keys))
+        .context(format!("could not write row for {}", word.id))?;
+    }
+    let raw = wrt.into_inner().context("could not flush table")?;
+    Ok(String::from_utf8(raw)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use crate::files::read_and_compute;
+    use super::render_table;
+
+    #[test]
+    fn test_csv() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/test_der")))?;
+        let mut words = computed.to_vec();
+        words.sort_by(|a, b| a.id.cmp(&b.id));
+        let rendered = render_table(&computed, &words, b',')?;
+        let mut reader = csv::Reader::from_reader(rendered.as_bytes());
+        let header: Vec<String> = reader.headers()?.iter().map(|h| h.to_string()).collect();
+        assert_eq!(header[0], "id");
+        assert_eq!(header[11], "etymology");
+
+        let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>()?;
+        assert_eq!(rows.len(), words.len());
+        for (row, word) in rows.iter().zip(&words) {
+            assert_eq!(&row[0], word.id);
+            assert_eq!(&row[1], word.word.clone().unwrap_or_default().string_without_sep());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tsv_metadata_and_etymology() -> Result<()> {
+        let computed = read_and_compute(Some(String::from("src/test_files/global_settings")))?;
+        let rendered = render_table(&computed, &computed.to_vec(), b'\t')?;
+        let mut reader = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(rendered.as_bytes());
+        let header: Vec<String> = reader.headers()?.iter().map(|h| h.to_string()).collect();
+        assert_eq!(header.last().unwrap(), "metadata.source");
+        let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>()?;
+
+        let first = rows.iter().find(|r| &r[0] == "first").unwrap();
+        assert_eq!(&first[12], "test");
+        assert_eq!(&first[11], "");
+        let compound = rows.iter().find(|r| &r[0] == "compound").unwrap();
+        assert_eq!(&compound[12], "");
+        let mut etymons: Vec<&str> = compound[11].split("; ").collect();
+        etymons.sort();
+        assert_eq!(etymons, vec!["first (loanword)", "second (loanword)"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use libkirum::{kirum::Lexis, sorted::sorted_map};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
+/// Defines the contents of the optional taxonomy.json file: a hierarchy of semantic domains,
+/// where each key is a domain name, and each value contains the subdomains, i.e `{"body": {"head": {"eye": {}}}}`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(transparent)]
+pub struct Taxonomy {
+    #[serde(serialize_with = "sorted_map")]
+    pub domains: HashMap<String, Taxonomy>
+}
+
+impl Taxonomy {
+    /// find the subdomains of the given domain, searching at any depth
+    pub fn find(&self, domain: &str) -> Option<&Taxonomy> {
+        if let Some(found) = self.domains.get(domain) {
+            return Some(found)
+        }
+        self.domains.values().find_map(|sub| sub.find(domain))
+    }
+
+    /// returns true if the domain exists at any depth in the taxonomy
+    pub fn contains(&self, domain: &str) -> bool {
+        self.find(domain).is_some()
+    }
+
+    /// Return a list of all domain names in the taxonomy
+    pub fn names(&self) -> Vec<String> {
+        let mut found: Vec<String> = Vec::new();
+        for (name, sub) in &self.domains {
+            found.push(name.clone());
+            found.extend(sub.names());
+        }
+        found
+    }
+
+    /// Return the given domain, along with all of its subdomains
+    pub fn expand(&self, domain: &str) -> Vec<String> {
+        let mut found = vec![domain.to_string()];
+        if let Some(sub) = self.find(domain) {
+            found.extend(sub.names());
+        }
+        found
+    }
+}
+
+/// Create a filter that matches any word in the given semantic field. If a taxonomy is supplied,
+/// words in any subdomain of the field will also match.
+pub fn semantic_filter(field: Option<String>, taxonomy: Option<&Taxonomy>) -> impl Fn(&Lexis) -> bool {
+    let allowed: Option<Vec<String>> = field.map(|f| match taxonomy {
+        Some(tax) => tax.expand(&f),
+        None => vec![f]
+    });
+    move |lex: &Lexis| {
+        match &allowed {
+            Some(domains) => lex.semantic_field.as_ref().map(|s| domains.contains(s)).unwrap_or(false),
+            None => true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libkirum::kirum::Lexis;
+    use super::{Taxonomy, semantic_filter};
+
+    fn test_taxonomy() -> Taxonomy {
+        serde_json::from_str(r#"{"body": {"head": {"eye": {}, "ear": {}}, "hand": {}}, "nature": {"plants": {}}}"#).unwrap()
+    }
+
+    #[test]
+    fn test_expand() {
+        let tax = test_taxonomy();
+        assert!(tax.contains("ear"));
+        assert!(!tax.contains("foot"));
+        let mut head = tax.expand("head");
+        head.sort();
+        assert_eq!(head, vec!["ear".to_string(), "eye".to_string(), "head".to_string()]);
+    }
+
+    #[test]
+    fn test_filter() {
+        let tax = test_taxonomy();
+        let filter = semantic_filter(Some("body".to_string()), Some(&tax));
+        assert!(filter(&Lexis{semantic_field: Some("eye".to_string()), ..Default::default()}));
+        assert!(!filter(&Lexis{semantic_field: Some("plants".to_string()), ..Default::default()}));
+        assert!(!filter(&Lexis::default()));
+
+        let no_tax = semantic_filter(Some("body".to_string()), None);
+        assert!(!no_tax(&Lexis{semantic_field: Some("eye".to_string()), ..Default::default()}));
+
+        let no_filter = semantic_filter(None, Some(&tax));
+        assert!(no_filter(&Lexis::default()));
+    }
+}
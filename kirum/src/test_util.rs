@@ -0,0 +1,33 @@
+use std::path::Path;
+use anyhow::Result;
+use walkdir::WalkDir;
+
+/// Copy a fixture project from `src/test_files` to a temporary directory with the given name, so tests can modify it.
+/// Any existing copy is removed first. Returns the path of the copy.
+pub fn copy_project(fixture: &str, name: &str) -> Result<String> {
+    let src = Path::new("src/test_files").join(fixture);
+    let dest = std::env::temp_dir().join(name);
+    if dest.exists() {
+        std::fs::remove_dir_all(&dest)?;
+    }
+    for entry in WalkDir::new(&src) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(&src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Copy a fixture project like [copy_project], and add the phonetic rules from the `with_phonetic_rules` fixture,
+/// so fixtures that only need some rules to generate words don't each keep their own copy.
+pub fn copy_project_with_rules(fixture: &str, name: &str) -> Result<String> {
+    let dest = copy_project(fixture, name)?;
+    let phonetics = Path::new(&dest).join("phonetics");
+    std::fs::create_dir_all(&phonetics)?;
+    std::fs::copy("src/test_files/with_phonetic_rules/phonetics/rules.json", phonetics.join("rules.json"))?;
+    Ok(dest)
+}
@@ -0,0 +1,172 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::Path};
+
+use anyhow::{Result, Context, anyhow};
+use libkirum::{kirum::Lexis, lemma::Lemma, word::PartOfSpeech};
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+use serde_with::skip_serializing_none;
+use crate::{entries::RawTransform, files::{create_phonetics, find_transforms, handle_directory, read_and_compute, read_project_globals, read_subdir_create_list, read_transform_files}, format::{FileFormat, TRANSFORM_FORMATS}, interpolate::map_from_str_with_env};
+
+/// The name of the snapshot file, written to the root of the project directory
+pub const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// A single test case in a file in the `tests` directory of a project:
+/// the transforms are applied to the word in order, and the result is compared with the expected word.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct TransformTest {
+    pub word: Lemma,
+    /// The names of the transforms to apply, in order
+    pub transforms: Vec<String>,
+    pub expected: Lemma,
+    /// The language of the input word, for transforms with a conditional
+    pub language: Option<String>,
+    /// The part of speech of the input word, for transforms with a conditional
+    pub part_of_speech: Option<PartOfSpeech>,
+    /// The type of the input word, for transforms with a conditional
+    #[serde(rename="type")]
+    pub word_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+pub struct TestFile {
+    pub tests: HashMap<String, TransformTest>
+}
+
+/// Run the transform tests in the `tests` directory of a project, returning a summary.
+/// If filter is set, only tests with a name containing it are run. Returns an error listing every failed test if any fail.
+pub fn run_tests(directory: &str, filter: Option<&str>) -> Result<String> {
+    let test_dir = Path::new(directory).join("tests");
+    if !test_dir.is_dir() {
+        return Err(anyhow!("no tests directory found in {}", directory));
+    }
+    let project = handle_directory(directory)?;
+    let transforms = read_transform_files(&project.transforms, false)?;
+    let vowels = project.phonetic_rules.map(|rules| create_phonetics(rules, false)).transpose()?.unwrap_or_default().vowels();
+
+    let mut cases: Vec<(String, String, TransformTest)> = Vec::new();
+    for path in read_subdir_create_list(test_dir, TRANSFORM_FORMATS)? {
+        let raw = std::fs::read_to_string(&path).context(format!("error reading test file {}", path.display()))?;
+        let tests: HashMap<String, TransformTest> = map_from_str_with_env(&raw, FileFormat::from_path(&path), "tests", false)
+        .context(format!("error parsing test file {}", path.display()))?;
+        cases.extend(tests.into_iter().map(|(name, test)| (path.display().to_string(), name, test)));
+    }
+    cases.retain(|(_, name, _)| filter.is_none_or(|f| name.contains(f)));
+    cases.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+
+    let mut failures: Vec<String> = Vec::new();
+    for (path, name, test) in &cases {
+        match run_test(test, &transforms, &vowels) {
+            Ok(None) => debug!("test {} passed", name),
+            Ok(Some(got)) => failures.push(format!("{} ({}): expected {}, got {}", name, path,
+                test.expected.string_without_sep(), got)),
+            Err(e) => failures.push(format!("{} ({}): {:#}", name, path, e))
+        }
+    }
+    let summary = format!("{} passed, {} failed", cases.len() - failures.len(), failures.len());
+    if failures.is_empty() {
+        Ok(summary)
+    } else {
+        Err(anyhow!("{}\n{}", failures.join("\n"), summary))
+    }
+}
+
+/// Compute the project and compare every word with the snapshot file, returning an error with a word-level diff if any changed.
+/// If update is set, the snapshot file is written from the computed words instead.
+/// The snapshot maps the ID of every word to its computed form, sorted by ID, so it can be committed and diffed alongside the project.
+pub fn check_snapshot(directory: &str, update: bool) -> Result<String> {
+    if read_project_globals(Some(directory.to_string()))?.seed.is_none() {
+        warn!("the project has no seed set in globals.json, so any generated words will differ from the snapshot");
+    }
+    let computed = read_and_compute(Some(directory.to_string()))?;
+    let current: BTreeMap<String, String> = computed.iter()
+        .filter_map(|lex| lex.word.as_ref().map(|w| (lex.id.clone(), w.string_without_sep()))).collect();
+    let path = Path::new(directory).join(SNAPSHOT_FILE);
+    if update {
+        let data = serde_json::to_string_pretty(&current).context("error creating snapshot data")?;
+        std::fs::write(&path, data).context(format!("error writing snapshot file {}", path.display()))?;
+        return Ok(format!("wrote {} words to {}", current.len(), path.display()));
+    }
+
+    let raw = std::fs::read_to_string(&path)
+        .context(format!("error reading snapshot file {}, create it with `kirum test --snapshot --update`", path.display()))?;
+    let saved: BTreeMap<String, String> = serde_json::from_str(&raw).context(format!("error parsing snapshot file {}", path.display()))?;
+    let ids: BTreeSet<&String> = saved.keys().chain(current.keys()).collect();
+    let mut diff: Vec<String> = Vec::new();
+    for id in ids {
+        match (saved.get(id), current.get(id)) {
+            (Some(old), Some(new)) if old != new => diff.push(format!("~ {}: {} -> {}", id, old, new)),
+            (Some(old), None) => diff.push(format!("- {}: {}", id, old)),
+            (None, Some(new)) => diff.push(format!("+ {}: {}", id, new)),
+            _ => {}
+        }
+    }
+    if diff.is_empty() {
+        Ok(format!("snapshot matches {} words", current.len()))
+    } else {
+        Err(anyhow!("{}\n{} words differ from the snapshot", diff.join("\n"), diff.len()))
+    }
+}
+
+/// apply the transforms of a test, returning the result if it doesn't match the expected word
+fn run_test(test: &TransformTest, transforms: &HashMap<String, RawTransform>, vowels: &[String]) -> Result<Option<String>> {
+    let mut lex = Lexis{word: Some(test.word.clone()), language: test.language.clone().unwrap_or_default(),
+        pos: test.part_of_speech.clone(), lexis_type: test.word_type.clone().unwrap_or_default(), ..Default::default()};
+    for trans in find_transforms(&test.transforms, transforms)? {
+        trans.transform_option(&mut lex, vowels)?;
+    }
+    let got = lex.word.map(|w| w.string_without_sep()).unwrap_or_default();
+    if got == test.expected.string_without_sep() {
+        Ok(None)
+    } else {
+        Ok(Some(got))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use std::path::Path;
+    use super::{check_snapshot, run_tests, SNAPSHOT_FILE};
+    use crate::test_util::copy_project;
+
+    #[test]
+    fn test_run_tests() -> Result<()> {
+        assert_eq!(run_tests("src/test_files/transform_tests", Some("plural"))?, "2 passed, 0 failed");
+        assert_eq!(run_tests("src/test_files/transform_tests", Some("vowel"))?, "1 passed, 0 failed");
+
+        let failed = run_tests("src/test_files/transform_tests", None).unwrap_err().to_string();
+        let lines: Vec<&str> = failed.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("broken-expected") && lines[0].ends_with("expected kirau, got kirū"), "{}", lines[0]);
+        assert!(lines[1].starts_with("broken-missing-transform") && lines[1].ends_with("transform not-a-transform does not exist"), "{}", lines[1]);
+        assert_eq!(lines[2], "3 passed, 2 failed");
+
+        assert!(run_tests("src/test_files/test_der", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot() -> Result<()> {
+        let dir_name = copy_project("test_der", "kirum_test_snapshot")?;
+        let dir = Path::new(&dir_name);
+        assert!(check_snapshot(&dir_name, false).is_err());
+        assert!(check_snapshot(&dir_name, true)?.starts_with("wrote 4 words"));
+        assert_eq!(check_snapshot(&dir_name, false)?, "snapshot matches 4 words");
+
+        let ety_path = dir.join("etymology/ety.json");
+        let raw = std::fs::read_to_string(&ety_path)?;
+        std::fs::write(&ety_path, raw.replace("\"new\": \"plum\"", "\"new\": \"plo\""))?;
+        let mut saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join(SNAPSHOT_FILE))?)?;
+        saved.as_object_mut().unwrap().insert("removed_word".to_string(), "gone".into());
+        std::fs::write(dir.join(SNAPSHOT_FILE), saved.to_string())?;
+
+        let diff = check_snapshot(&dir_name, false).unwrap_err().to_string();
+        let lines: Vec<&str> = diff.lines().collect();
+        assert!(lines.contains(&"~ latin_example: exemplum -> exemplo"), "{}", diff);
+        assert!(lines.contains(&"- removed_word: gone"), "{}", diff);
+        assert_eq!(lines.last(), Some(&"4 words differ from the snapshot"));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -1,14 +1,129 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::{Path, PathBuf}};
 
-use handlebars::{Handlebars, Helper, RenderContext, Output, HelperResult, Renderable, RenderError};
-use libkirum::kirum::Lexis;
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, Output, HelperResult, Renderable, RenderError, ScopedJson, handlebars_helper};
+use libkirum::{kirum::{Lexis, LanguageTree, WordIndex}, lemma::Lemma, word::SemanticShift};
 use anyhow::{Result, Context, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+use walkdir::WalkDir;
+use crate::header::Header;
 
-/// Render a dictionary from a list of words, and a template
-pub fn generate_from_tmpl(rendered_lang: Vec<Lexis>, template_file: String, rhai_files: Option<Vec<String>>) -> Result<String> {
+/// A single word as presented to templates, along with values computed from the language tree
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct TemplateWord {
+    #[serde(flatten)]
+    pub lexis: Lexis,
+    /// Number of letters in the word
+    pub length: usize,
+    /// Number of syllables in the word, counted as groups of adjacent vowels
+    pub syllables: usize,
+    /// Number of etymological steps between the word and its most distant ancestor
+    pub depth: usize,
+    /// IDs of the earliest ancestors of the word
+    pub roots: Vec<String>,
+    /// Words directly derived from this word
+    pub descendants: Vec<Lexis>,
+    /// The direct etymons of this word
+    pub etymons: Vec<Lexis>,
+    /// How the meaning of the word changed from each etymon that records a semantic shift
+    pub semantic_shifts: Vec<TemplateShift>,
+    /// The metadata of the etymology between the word and each etymon, keyed by the etymon ID
+    pub etymology_metadata: HashMap<String, HashMap<String, String>>
+}
+
+/// The semantic shift between a word and one of its etymons
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct TemplateShift {
+    /// The ID of the etymon
+    pub etymon: String,
+    #[serde(flatten)]
+    pub shift: SemanticShift
+}
+
+impl TemplateWord {
+    /// Create the template values of a word, looking up its etymology in the index of the tree it's from.
+    pub fn new(lexis: Lexis, tree: &WordIndex, vowels: &[String]) -> Self {
+        let letters = lexis.word.clone().map(|w| w.chars()).unwrap_or_default();
+        let etymons: Vec<Lexis> = tree.etymons(&lexis.id).into_iter().filter(|l| l.word.is_some()).collect();
+        let semantic_shifts = etymons.iter()
+            .filter_map(|e| tree.edge(&e.id, &lexis.id)?.semantic_shift.clone().map(|shift| TemplateShift { etymon: e.id.clone(), shift }))
+            .collect();
+        let etymology_metadata = etymons.iter()
+            .filter_map(|e| tree.edge(&e.id, &lexis.id).filter(|edge| !edge.metadata.is_empty()).map(|edge| (e.id.clone(), edge.metadata.clone())))
+            .collect();
+        TemplateWord { 
+            length: letters.len(), 
+            syllables: count_syllables(&letters, vowels), 
+            depth: tree.derivation_depth(&lexis.id), 
+            roots: tree.root_ancestors(&lexis.id).into_iter().map(|l| l.id).collect(),
+            descendants: tree.descendants(&lexis.id).into_iter().filter(|l| l.word.is_some()).collect(),
+            etymons,
+            semantic_shifts,
+            etymology_metadata,
+            lexis
+        }
+    }
+}
+
+/// The structured data model passed to templates when rendering with grouped output
+#[derive(Serialize, Debug, Default)]
+pub struct TemplateContext {
+    /// Words grouped by language, ordered by language name
+    pub languages: Vec<LanguageGroup>,
+    /// Every word in the lexicon
+    pub all_words: Vec<TemplateWord>,
+    /// A summary of the project state, if rendered with `--header`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Header>
+}
+
+/// All the words of a single language
+#[derive(Serialize, Debug, Default)]
+pub struct LanguageGroup {
+    pub name: String,
+    pub words: Vec<TemplateWord>
+}
+
+impl From<Vec<TemplateWord>> for TemplateContext {
+    fn from(value: Vec<TemplateWord>) -> Self {
+        let mut languages: Vec<LanguageGroup> = Vec::new();
+        for word in &value {
+            match languages.iter_mut().find(|l| l.name == word.lexis.language) {
+                Some(group) => group.words.push(word.clone()),
+                None => languages.push(LanguageGroup { name: word.lexis.language.clone(), words: vec![word.clone()] })
+            }
+        }
+        languages.sort_by(|a, b| a.name.cmp(&b.name));
+        TemplateContext { languages, all_words: value, header: None }
+    }
+}
+
+/// count the groups of adjacent vowels in a word
+pub fn count_syllables(letters: &[impl AsRef<str>], vowels: &[String]) -> usize {
+    let mut count = 0;
+    let mut in_vowel = false;
+    for letter in letters {
+        let is_vowel = vowels.iter().any(|v| v == &letter.as_ref().to_lowercase());
+        if is_vowel && !in_vowel {
+            count += 1;
+        }
+        in_vowel = is_vowel;
+    }
+    count
+}
+
+/// Render a dictionary from a list of words, and a template. The language tree is used to compute additional values for each word.
+/// If grouped is true, the template will receive a TemplateContext instead of a flat list of words, including the header, if one is set.
+/// Any templates in template_dir are registered as partials.
+pub fn generate_from_tmpl(tree: &LanguageTree, rendered_lang: Vec<Lexis>, template_file: String, template_dir: Option<String>, 
+    rhai_files: Option<Vec<String>>, grouped: bool, header: Option<Header>) -> Result<String> {
     let mut reg = Handlebars::new();
     reg.register_escape_fn(handlebars::no_escape);
     reg.register_helper("string_eq", Box::new(string_eq));
+    register_list_helpers(&mut reg, tree);
+    if let Some(dir) = template_dir {
+        register_partials(&mut reg, Path::new(&dir))?;
+    }
     reg.register_template_file("tmpl", &template_file).context(format!("could not add template file {}", template_file))?;
     if let Some(files) = rhai_files{
         for file in files{
@@ -20,11 +135,42 @@ pub fn generate_from_tmpl(rendered_lang: Vec<Lexis>, template_file: String, rhai
         }
     }
     
-   let rendered = reg.render("tmpl", &rendered_lang)?;
+   let vowels = tree.vowels();
+   let index = tree.index();
+   let words: Vec<TemplateWord> = rendered_lang.into_iter().map(|l| TemplateWord::new(l, &index, &vowels)).collect();
+   let rendered = if grouped {
+        let mut context: TemplateContext = words.into();
+        context.header = header;
+        reg.render("tmpl", &context)?
+   } else {
+        if header.is_some() {
+            warn!("the header is only passed to templates rendered with --grouped, as the `header` variable");
+        }
+        reg.render("tmpl", &words)?
+   };
 
    Ok(rendered)
 }
 
+/// register every .hbs file in a directory as a partial, named by its path relative to the directory, without the extension
+fn register_partials(reg: &mut Handlebars, dir: &Path) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("template directory {} does not exist", dir.display()));
+    }
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let path = entry?.into_path();
+        if path.is_dir() || path.extension().is_none_or(|e| e != "hbs") {
+            continue;
+        }
+        let relative = path.strip_prefix(dir)?.with_extension("");
+        let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        let raw = std::fs::read_to_string(&path).context(format!("could not read partial {}", path.display()))?;
+        reg.register_partial(&name, raw).context(format!("could not add partial {}", path.display()))?;
+        debug!("registered partial {} as {}", path.display(), name);
+    }
+    Ok(())
+}
+
 /// a template helper, defines a handlebars function that compares two strings
 fn string_eq<'reg, 'rc>(
     helper: &Helper<'reg, 'rc>,
@@ -42,4 +188,199 @@ fn string_eq<'reg, 'rc>(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Register the helpers for common dictionary layouts, which take a list of words and return a new list for use in a subexpression,
+/// i.e. `{{#each (sort_alpha (filter_tag this "default"))}}`. Sorting uses the collation of the tree.
+fn register_list_helpers(reg: &mut Handlebars, tree: &LanguageTree) {
+    let collation = Collation { default: tree.collation.clone().unwrap_or_default(), languages: tree.language_collation.clone() };
+    reg.register_helper("group_by_language", Box::new(group_by_language));
+    reg.register_helper("filter_tag", Box::new(filter_tag));
+    reg.register_helper("sort_alpha", Box::new(SortAlpha(collation.clone())));
+    reg.register_helper("first_letter", Box::new(FirstLetter(collation)));
+}
+
+handlebars_helper!(group_by_language: |words: array| {
+    let mut languages: Vec<(String, Vec<Value>)> = Vec::new();
+    for word in words {
+        let name = word_field(word, "language");
+        match languages.iter_mut().find(|(l, _)| l == &name) {
+            Some((_, group)) => group.push(word.clone()),
+            None => languages.push((name, vec![word.clone()]))
+        }
+    }
+    languages.sort_by(|a, b| a.0.cmp(&b.0));
+    languages.into_iter().map(|(name, words)| serde_json::json!({"name": name, "words": words})).collect::<Vec<Value>>()
+});
+
+handlebars_helper!(filter_tag: |words: array, tag: str| {
+    words.iter().filter(|w| w.get("tags").and_then(Value::as_array).is_some_and(|tags| tags.iter().any(|t| t == tag)))
+    .cloned().collect::<Vec<Value>>()
+});
+
+/// get a string field of a word passed to a template helper. A plain string is treated as the word itself.
+fn word_field(word: &Value, field: &str) -> String {
+    match word {
+        Value::String(s) if field == "word" => s.clone(),
+        _ => word.get(field).and_then(Value::as_str).unwrap_or_default().to_string()
+    }
+}
+
+/// The alphabets used to sort the words of each language
+#[derive(Clone)]
+struct Collation {
+    default: Vec<String>,
+    languages: HashMap<String, Vec<String>>
+}
+
+impl Collation {
+    fn alphabet(&self, word: &Value) -> &[String] {
+        self.languages.get(&word_field(word, "language")).unwrap_or(&self.default)
+    }
+}
+
+/// a template helper that sorts a list of words alphabetically, using the collation of each word's language
+struct SortAlpha(Collation);
+
+impl HelperDef for SortAlpha {
+    fn call_inner<'reg: 'rc, 'rc>(&self, helper: &Helper<'reg, 'rc>, _: &'reg Handlebars<'reg>, _: &'rc handlebars::Context,
+        _: &mut RenderContext<'reg, 'rc>) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let words = helper.param(0).and_then(|p| p.value().as_array())
+            .ok_or(RenderError::new("sort_alpha expects a list of words"))?;
+        let mut sorted = words.clone();
+        sorted.sort_by_cached_key(|w| Lemma::from(word_field(w, "word")).sort_key(self.0.alphabet(w)));
+        Ok(ScopedJson::Derived(Value::Array(sorted)))
+    }
+}
+
+/// a template helper that returns the first letter of a word, capitalized, for dictionary section headings.
+/// Multi-letter graphemes in the collation of the word's language, such as "ch", are treated as one letter.
+struct FirstLetter(Collation);
+
+impl HelperDef for FirstLetter {
+    fn call_inner<'reg: 'rc, 'rc>(&self, helper: &Helper<'reg, 'rc>, _: &'reg Handlebars<'reg>, _: &'rc handlebars::Context,
+        _: &mut RenderContext<'reg, 'rc>) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let word = helper.param(0).map(|p| p.value()).ok_or(RenderError::new("first_letter expects a word"))?;
+        Ok(ScopedJson::Derived(Value::String(first_letter(&word_field(word, "word"), self.0.alphabet(word)))))
+    }
+}
+
+/// Return the first letter of a word, capitalized. The longest matching letter in the alphabet is used, so digraphs like "ch" count as one letter.
+pub fn first_letter(word: &str, alphabet: &[String]) -> String {
+    let letter = match alphabet.iter().filter(|l| !l.is_empty() && word.starts_with(l.as_str())).max_by_key(|l| l.len()) {
+        Some(letter) => letter.clone(),
+        None => Lemma::from(word.to_string()).iter().next().unwrap_or_default().to_string()
+    };
+    let mut chars = letter.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::kirum::{Lexis, LanguageTree};
+    use super::{generate_from_tmpl, TemplateContext, TemplateWord, count_syllables};
+
+    fn test_tree() -> LanguageTree {
+        let mut tree = LanguageTree::new();
+        let one = Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(), ..Default::default()};
+        let two = Lexis{id: "two".to_string(), word: Some("amo".into()), language: "New Exemplum".to_string(), ..Default::default()};
+        let three = Lexis{id: "three".to_string(), word: Some("kirum".into()), language: "Old Exemplum".to_string(), ..Default::default()};
+        tree.connect_etymology(three, one, Vec::new(), None);
+        tree.add_lexis(two);
+        tree
+    }
+
+    fn test_words(tree: &LanguageTree) -> Vec<TemplateWord> {
+        tree.to_vec().into_iter().map(|l| TemplateWord::new(l, &tree.index(), &["a".to_string(), "i".to_string(), "o".to_string(), "u".to_string()])).collect()
+    }
+
+    #[test]
+    fn test_grouped_context() {
+        let tree = test_tree();
+        let ctx: TemplateContext = test_words(&tree).into();
+        assert_eq!(ctx.all_words.len(), 3);
+        let names: Vec<&str> = ctx.languages.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["New Exemplum", "Old Exemplum"]);
+        let old_ids: Vec<&str> = ctx.languages[1].words.iter().map(|l| l.lexis.id.as_str()).collect();
+        assert_eq!(old_ids, vec!["one", "three"]);
+    }
+
+    #[test]
+    fn test_computed_fields() {
+        let tree = test_tree();
+        let words = test_words(&tree);
+        let one = words.iter().find(|w| w.lexis.id == "one").unwrap();
+        assert_eq!(one.length, 4);
+        assert_eq!(one.syllables, 2);
+        assert_eq!(one.depth, 0);
+        assert_eq!(one.descendants[0].id, "three");
+
+        let three = words.iter().find(|w| w.lexis.id == "three").unwrap();
+        assert_eq!(three.depth, 1);
+        assert_eq!(three.roots, vec!["one".to_string()]);
+    }
+
+    #[test]
+    fn test_semantic_shifts() -> Result<()> {
+        let tree = crate::files::read_and_compute(Some("src/test_files/semantic_shift".to_string()))?;
+        let deer = TemplateWord::new(tree.get_by_id("deer").unwrap(), &tree.index(), &[]);
+        assert_eq!(deer.semantic_shifts[0].etymon, "animal");
+        let value = serde_json::to_value(&deer)?;
+        assert_eq!(value["semantic_shifts"][0]["type"], "narrowing");
+        assert_eq!(value["semantic_shifts"][0]["definition"], "deer");
+        assert!(TemplateWord::new(tree.get_by_id("animal").unwrap(), &tree.index(), &[]).semantic_shifts.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_etymology_metadata() -> Result<()> {
+        let tree = crate::files::read_and_compute(Some("src/test_files/edge_metadata".to_string()))?;
+        let river = TemplateWord::new(tree.get_by_id("river").unwrap(), &tree.index(), &[]);
+        assert_eq!(river.etymology_metadata["water"]["certainty"], "likely");
+        assert!(TemplateWord::new(tree.get_by_id("water").unwrap(), &tree.index(), &[]).etymology_metadata.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_syllables() {
+        let vowels = vec!["a".to_string(), "e".to_string(), "i".to_string()];
+        let letters: Vec<String> = ["k", "a", "i", "r", "e", "t"].iter().map(|l| l.to_string()).collect();
+        assert_eq!(count_syllables(&letters, &vowels), 2);
+    }
+
+    #[test]
+    fn test_grouped_template() -> Result<()> {
+        let tree = test_tree();
+        let rendered = generate_from_tmpl(&tree, tree.to_vec(), "src/test_files/test_grouped.hbs".to_string(), None, None, true, None)?;
+        assert_eq!(rendered, "New Exemplum: two\nOld Exemplum: one three\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_partials() -> Result<()> {
+        let tree = test_tree();
+        let rendered = generate_from_tmpl(&tree, tree.to_vec(), "src/test_files/test_partials.hbs".to_string(), 
+            Some("src/test_files/partials".to_string()), None, true, None)?;
+        assert_eq!(rendered, "New Exemplum\ntwo: amo\nOld Exemplum\none: kira\nthree: kirum (from one)\n");
+        assert!(generate_from_tmpl(&tree, tree.to_vec(), "src/test_files/test_partials.hbs".to_string(), 
+            Some("src/test_files/missing".to_string()), None, true, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_helpers() -> Result<()> {
+        let mut tree = LanguageTree::new();
+        for (id, word, language) in [("kira", "kira", "Old Exemplum"), ("chiru", "chiru", "Old Exemplum"), ("cara", "cara", "Old Exemplum"), ("amo", "amo", "New Exemplum")] {
+            let tags = if id == "kira" {vec!["old".to_string()]} else {Vec::new()};
+            tree.add_lexis(Lexis{id: id.to_string(), word: Some(word.to_string().into()), language: language.to_string(), tags, ..Default::default()});
+        }
+        tree.language_collation.insert("Old Exemplum".to_string(), ["a", "c", "ch", "i", "k", "r", "u"].iter().map(|l| l.to_string()).collect());
+        let rendered = generate_from_tmpl(&tree, tree.to_vec(), "src/test_files/test_helpers.hbs".to_string(), None, None, false, None)?;
+        assert_eq!(rendered, "New Exemplum: A-amo\nOld Exemplum: C-cara Ch-chiru K-kira\ntagged: kira\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context};
+
+/// A set of file writes that either all succeed, or are all rolled back.
+/// Commands that write multiple files to a project use a transaction, so a failure halfway through
+/// doesn't leave the project in a state that can't be loaded.
+/// If the transaction is dropped before commit() is called, every change is rolled back.
+#[derive(Default)]
+pub struct WriteTransaction {
+    /// the files that were written, along with their original contents, if they existed
+    written: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// directories created by the transaction, in the order they were created
+    created_dirs: Vec<PathBuf>,
+    committed: bool
+}
+
+impl WriteTransaction {
+    pub fn new() -> Self {
+        WriteTransaction::default()
+    }
+
+    /// create a directory and any missing parents, recording them so they can be removed on rollback
+    pub fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut missing: Vec<PathBuf> = path.ancestors().take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+        .map(|p| p.to_path_buf()).collect();
+        missing.reverse();
+        for dir in missing {
+            std::fs::create_dir(&dir).context(format!("could not create directory {}", dir.display()))?;
+            self.created_dirs.push(dir);
+        }
+        Ok(())
+    }
+
+    /// write a file, saving the original contents so the write can be rolled back
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> Result<()> {
+        let path = path.as_ref();
+        let original = if path.is_file() {
+            Some(std::fs::read(path).context(format!("could not read existing file {}", path.display()))?)
+        } else {
+            None
+        };
+        // record the file before writing, so a partial write is also rolled back
+        self.written.push((path.to_path_buf(), original));
+        std::fs::write(path, contents).context(format!("could not write file {}", path.display()))?;
+        Ok(())
+    }
+
+    /// keep all the changes made by the transaction
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// undo every change made by the transaction, newest first
+    fn rollback(&mut self) {
+        while let Some((path, original)) = self.written.pop() {
+            let res = match original {
+                Some(contents) => std::fs::write(&path, contents),
+                None if path.exists() => std::fs::remove_file(&path),
+                None => Ok(())
+            };
+            if let Err(e) = res {
+                error!("could not roll back changes to {}: {}", path.display(), e);
+            }
+        }
+        while let Some(dir) = self.created_dirs.pop() {
+            if let Err(e) = std::fs::remove_dir(&dir) {
+                error!("could not remove directory {}: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+impl Drop for WriteTransaction {
+    fn drop(&mut self) {
+        if self.committed || (self.written.is_empty() && self.created_dirs.is_empty()) {
+            return
+        }
+        warn!("rolling back changes to {} files", self.written.len());
+        self.rollback();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use super::WriteTransaction;
+
+    #[test]
+    fn test_commit() -> Result<()> {
+        let dir = std::env::temp_dir().join("kirum_test_transaction_commit");
+        let mut tx = WriteTransaction::new();
+        tx.create_dir_all(dir.join("tree"))?;
+        tx.write(dir.join("tree/words.json"), "{}")?;
+        tx.commit();
+        assert_eq!(std::fs::read_to_string(dir.join("tree/words.json"))?, "{}");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        let dir = std::env::temp_dir().join("kirum_test_transaction_rollback");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("existing.json"), "original")?;
+        {
+            let mut tx = WriteTransaction::new();
+            tx.write(dir.join("existing.json"), "changed")?;
+            tx.create_dir_all(dir.join("new/nested"))?;
+            tx.write(dir.join("new/nested/created.json"), "created")?;
+            // writing to a path under a file fails, and the transaction is dropped without a commit
+            assert!(tx.write(dir.join("existing.json/bad.json"), "bad").is_err());
+        }
+        assert_eq!(std::fs::read_to_string(dir.join("existing.json"))?, "original");
+        assert!(!dir.join("new").exists());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::{path::{Path, PathBuf}, sync::mpsc, time::Duration};
+
+use anyhow::{Result, Context, anyhow};
+use notify::{EventKind, RecursiveMode, Watcher};
+use libkirum::{kirum::LanguageTree, lexicon::{ComputedLexicon, SharedLexicon}};
+use crate::{cli::RenderArgs, cache::read_and_compute_cached, format::FileFormat, render::{dry_run, render_computed}};
+
+/// how long to wait for more changes after a file changes, so a burst of writes from an editor only triggers one render
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Render the project, then watch the project directory and re-render every time a project file changes.
+/// Output is written to the given file, or printed if no file is set. Errors are logged, and watching continues,
+/// so a typo in a file doesn't end the session.
+/// Each render is made from a snapshot that's only replaced once the project computes without errors.
+pub fn watch(args: RenderArgs, output: Option<String>) -> Result<String> {
+    let dir = args.directory.clone().ok_or(anyhow!("must specify a project directory"))?;
+    let out_path = output.as_ref().map(|o| absolute(Path::new(o)));
+
+    let lexicon = SharedLexicon::new(ComputedLexicon::from_computed(LanguageTree::new()));
+    update(&lexicon, &args, output.as_deref());
+    on_change(&dir, out_path.as_deref(), || update(&lexicon, &args, output.as_deref()))
+}
+/// Watch the project directory, and call changed every time a project file changes, until the watcher stops.
+/// Changes to the ignored file, such as the output of a render, don't count.
+pub fn on_change(dir: &str, ignored: Option<&Path>, mut changed: impl FnMut()) -> Result<String> {
+    let (send, recv) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(send).context("could not create file watcher")?;
+    watcher.watch(Path::new(dir), RecursiveMode::Recursive).context(format!("could not watch directory {}", dir))?;
+
+    info!("watching {} for changes", dir);
+    loop {
+        let event = recv.recv().context("file watcher stopped")?;
+        let mut project_changed = is_change(&event, ignored);
+        while let Ok(next) = recv.recv_timeout(DEBOUNCE) {
+            project_changed = project_changed || is_change(&next, ignored);
+        }
+        if project_changed {
+            changed();
+        }
+    }
+}
+
+/// recompute the project and render the new snapshot, or run the dry run in the arguments.
+/// A project that doesn't compute is logged by the lexicon, and the last output is left as it is.
+fn update(lexicon: &SharedLexicon, args: &RenderArgs, output: Option<&str>) {
+    if let Some(rendered) = dry_run(args) {
+        write_output(rendered, output);
+        return;
+    }
+    let compute_args = args.clone();
+    let computed = lexicon.recompute(move || read_and_compute_cached(compute_args.directory, compute_args.cache)
+        .map(ComputedLexicon::from_computed)).join();
+    if matches!(computed, Ok(Ok(()))) {
+        write_output(render_computed(args.clone(), &lexicon.snapshot()), output);
+    }
+}
+
+/// write the rendered output, logging any errors
+fn write_output(rendered: Result<String>, output: Option<&str>) {
+    let rendered = match rendered {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            error!("{:#}", e);
+            return;
+        }
+    };
+    match output {
+        Some(path) => match std::fs::write(path, rendered) {
+            Ok(_) => info!("rendered to {}", path),
+            Err(e) => error!("could not write {}: {}", path, e)
+        },
+        None => println!("{}", rendered)
+    }
+}
+
+/// check if an event from the file watcher changed a project file.
+/// Changes to the output file are ignored, so rendering into the project directory doesn't loop forever.
+fn is_change(event: &notify::Result<notify::Event>, output: Option<&Path>) -> bool {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            error!("error watching files: {}", e);
+            return false;
+        }
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        return false;
+    }
+    event.paths.iter().any(|p| is_project_file(p, output))
+}
+
+fn is_project_file(path: &Path, output: Option<&Path>) -> bool {
+    if output.is_some_and(|out| absolute(path) == out) {
+        return false;
+    }
+    FileFormat::from_extension(path).is_some()
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::is_project_file;
+
+    #[test]
+    fn test_project_files() {
+        let out = std::path::absolute("project/tree/out.json").unwrap();
+        assert!(is_project_file(Path::new("project/tree/words.json"), Some(&out)));
+        assert!(is_project_file(Path::new("project/etymology/ety.toml"), None));
+        assert!(!is_project_file(Path::new("project/tree/.words.json.swp"), None));
+        assert!(!is_project_file(Path::new("project/tree/out.json"), Some(&out)));
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{Result, Context};
+use libkirum::{kirum::{Lexis, LanguageTree}, word::PartOfSpeech};
+use serde::Serialize;
+
+/// The revision of the XDXF standard that is written
+const XDXF_REVISION: &str = "034";
+/// The ISO 639 code for constructed languages, as Kirum language names aren't language codes
+const XDXF_LANG_FROM: &str = "ART";
+/// The language of definitions
+const XDXF_LANG_TO: &str = "ENG";
+const XDXF_DOCTYPE: &str = "<!DOCTYPE xdxf SYSTEM \"https://raw.github.com/soshial/xdxf_makedict/master/format_standard/xdxf_strict.dtd\">";
+
+/// The root of an XDXF (XML Dictionary Exchange Format) dictionary, in the logical format
+#[derive(Serialize, Debug, Default)]
+#[serde(rename = "xdxf")]
+struct Xdxf {
+    #[serde(rename = "@lang_from")]
+    lang_from: String,
+    #[serde(rename = "@lang_to")]
+    lang_to: String,
+    #[serde(rename = "@format")]
+    format: String,
+    #[serde(rename = "@revision")]
+    revision: String,
+    meta_info: MetaInfo,
+    lexicon: Lexicon
+}
+
+#[derive(Serialize, Debug, Default)]
+struct MetaInfo {
+    title: String,
+    full_title: String,
+    description: String
+}
+
+#[derive(Serialize, Debug, Default)]
+struct Lexicon {
+    #[serde(rename = "ar")]
+    articles: Vec<Article>
+}
+
+/// A single dictionary article, with a headword and its definition
+#[derive(Serialize, Debug, Default)]
+struct Article {
+    k: String,
+    def: Definition
+}
+
+#[derive(Serialize, Debug, Default)]
+struct Definition {
+    /// grammatical information
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gr: Option<String>,
+    /// transcription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tr: Option<String>,
+    /// editorial comment, used for the language of the word
+    #[serde(skip_serializing_if = "Option::is_none")]
+    co: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deftext: Option<String>,
+    #[serde(rename = "ex", skip_serializing_if = "Vec::is_empty")]
+    examples: Vec<XdxfExample>,
+    #[serde(rename = "etm", skip_serializing_if = "Vec::is_empty")]
+    etymology: Vec<String>
+}
+
+#[derive(Serialize, Debug, Default)]
+struct XdxfExample {
+    ex_orig: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ex_tran: Option<String>
+}
+
+/// Render the words as an XDXF dictionary, which can be loaded by offline dictionary readers such as GoldenDict,
+/// or converted to StarDict for readers such as KOReader.
+/// If language is set, only words of that language are included, otherwise each article notes the language of the word.
+pub fn generate_xdxf(tree: &LanguageTree, rendered_lang: Vec<Lexis>, title: String, language: Option<String>) -> Result<String> {
+    let articles: Vec<Article> = rendered_lang.iter()
+    .filter(|l| l.word.is_some() && language.as_ref().map(|lang| *lang == l.language).unwrap_or(true))
+    .map(|l| xdxf_article(l, &tree.etymons(&l.id), language.is_none()))
+    .collect();
+
+    let description = match &language {
+        Some(lang) => format!("A dictionary of {}, with {} words", lang, articles.len()),
+        None => format!("A dictionary of {} words", articles.len())
+    };
+    let dict = Xdxf {
+        lang_from: XDXF_LANG_FROM.to_string(),
+        lang_to: XDXF_LANG_TO.to_string(),
+        format: String::from("logical"),
+        revision: XDXF_REVISION.to_string(),
+        meta_info: MetaInfo { title: title.clone(), full_title: title, description },
+        lexicon: Lexicon { articles }
+    };
+    let mut body = String::new();
+    let mut ser = quick_xml::se::Serializer::new(&mut body);
+    ser.indent(' ', 2);
+    dict.serialize(ser).context("could not create XDXF document")?;
+    Ok(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n{}\n", XDXF_DOCTYPE, body))
+}
+
+fn xdxf_article(word: &Lexis, etymons: &[Lexis], with_language: bool) -> Article {
+    let etymology = etymons.iter().filter_map(|e| e.word.as_ref().map(|w| {
+        let mut etm = format!("from {}", w.string_without_sep());
+        if !e.language.is_empty() {
+            etm = format!("{} ({})", etm, e.language);
+        }
+        if !e.definition.is_empty() {
+            etm = format!("{} ‘{}’", etm, e.definition);
+        }
+        etm
+    })).collect();
+
+    Article {
+        k: word.word.clone().unwrap_or_default().string_without_sep(),
+        def: Definition {
+            gr: word.pos.as_ref().filter(|p| **p != PartOfSpeech::None).map(|p| p.to_string()),
+            tr: word.ipa.clone(),
+            co: if with_language && !word.language.is_empty() {Some(word.language.clone())} else {None},
+            deftext: if word.definition.is_empty() {None} else {Some(word.definition.clone())},
+            examples: word.examples.iter().map(|ex| XdxfExample { ex_orig: ex.text.clone(), ex_tran: ex.translation.clone() }).collect(),
+            etymology
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use libkirum::{kirum::{Lexis, LanguageTree}, word::{PartOfSpeech, Example}};
+    use super::generate_xdxf;
+
+    fn test_tree() -> LanguageTree {
+        let mut tree = LanguageTree::new();
+        let one = Lexis{id: "one".to_string(), word: Some("kira".into()), language: "Old Exemplum".to_string(),
+            definition: "garden".to_string(), pos: Some(PartOfSpeech::Noun), ..Default::default()};
+        let two = Lexis{id: "two".to_string(), word: Some("kirum".into()), language: "New Exemplum".to_string(),
+            definition: "garden & orchard".to_string(), ipa: Some("kiɾum".to_string()),
+            examples: vec![Example{text: "kirum amo".to_string(), gloss: None, translation: Some("the garden keeper".to_string())}], ..Default::default()};
+        tree.connect_etymology(two, one, Vec::new(), None);
+        tree
+    }
+
+    #[test]
+    fn test_generate_xdxf() -> Result<()> {
+        let tree = test_tree();
+        let rendered = generate_xdxf(&tree, tree.to_vec(), "Lexicon".to_string(), Some("New Exemplum".to_string()))?;
+        assert!(rendered.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE xdxf"));
+        assert!(rendered.contains("<xdxf lang_from=\"ART\" lang_to=\"ENG\" format=\"logical\" revision=\"034\">"));
+        assert!(rendered.contains("<title>Lexicon</title>"));
+        assert_eq!(rendered.matches("<ar>").count(), 1);
+        assert!(rendered.contains("<k>kirum</k>"));
+        assert!(rendered.contains("<tr>kiɾum</tr>"));
+        assert!(rendered.contains("<deftext>garden &amp; orchard</deftext>"));
+        assert!(rendered.contains("<ex_tran>the garden keeper</ex_tran>"));
+        assert!(rendered.contains("<etm>from kira (Old Exemplum) ‘garden’</etm>"));
+        assert!(!rendered.contains("<co>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_xdxf_all_languages() -> Result<()> {
+        let tree = test_tree();
+        let rendered = generate_xdxf(&tree, tree.to_vec(), "Lexicon".to_string(), None)?;
+        assert_eq!(rendered.matches("<ar>").count(), 2);
+        assert!(rendered.contains("<gr>noun</gr>"));
+        assert!(rendered.contains("<co>Old Exemplum</co>"));
+        Ok(())
+    }
+}
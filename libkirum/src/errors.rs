@@ -34,8 +34,63 @@ pub struct LemmaFromError {
 
 #[derive(thiserror::Error, Debug)]
 pub enum TransformError {
-    #[error("error evaluating Rhai script")]
-    EvalError(#[from] Box<EvalAltResult>),
-    #[error("could not parse return value from script")]
-    ScriptReturnValueError(#[from] LemmaFromError)
+    #[error("error evaluating Rhai script {file}")]
+    EvalError {
+        file: String,
+        #[source]
+        source: Box<EvalAltResult>
+    },
+    #[error("could not parse return value from Rhai script {file}")]
+    ScriptReturnValueError {
+        file: String,
+        #[source]
+        source: LemmaFromError
+    },
+    /// A variant created from the alternative outcomes of a transform has the same ID as another word
+    #[error("the variant '{id}' created from the alternatives of '{transform}' has the same ID as an existing word")]
+    VariantCollision {
+        id: String,
+        transform: String
+    },
+    /// An error from a single step of a transform, with the word it was applied to.
+    /// The step is the index of the failing function in the `transforms` list of the transform.
+    #[error("error applying transforms[{step}] of '{transform}' to '{lexis}'{}", .etymon.as_ref().map(|e| format!(" from '{}'", e)).unwrap_or_default())]
+    Transform {
+        lexis: String,
+        /// The etymon the word was derived from, if the transform was applied to an etymological link
+        etymon: Option<String>,
+        transform: String,
+        step: usize,
+        #[source]
+        source: Box<TransformError>
+    }
+}
+
+impl TransformError {
+    /// add the transform and step the error happened in. Errors that already have a location are unchanged.
+    pub(crate) fn in_step(self, lexis: &str, transform: &str, step: usize) -> Self {
+        match self {
+            located @ TransformError::Transform { .. } => located,
+            source => TransformError::Transform { lexis: lexis.to_string(), etymon: None, transform: transform.to_string(), 
+                step, source: Box::new(source) }
+        }
+    }
+
+    /// set the word and etymon of the error. Transforms on an etymological link are applied to a copy of the etymon,
+    /// so the error only knows the ID of the etymon until this is called.
+    pub(crate) fn for_word(mut self, id: &str, etymon_id: &str) -> Self {
+        if let TransformError::Transform { lexis, etymon, .. } = &mut self {
+            *lexis = id.to_string();
+            *etymon = Some(etymon_id.to_string());
+        }
+        self
+    }
+
+    /// set the name of the transform the error happened in
+    pub(crate) fn in_transform(mut self, name: &str) -> Self {
+        if let TransformError::Transform { transform, .. } = &mut self {
+            *transform = name.to_string();
+        }
+        self
+    }
 }
\ No newline at end of file
@@ -1,16 +1,24 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 use crate::errors::TransformError;
 use crate::lemma::Lemma;
 use crate::lexcreate;
-use crate::transforms::{Transform, GlobalTransform};
-use crate::word::{PartOfSpeech, Etymology, Edge};
+use crate::sandhi::{self, SandhiRule};
+use crate::sorted::sorted_map;
+use crate::transforms::{Transform, GlobalTransform, TransformFunc, LanguagePairTransform};
+use crate::word::{PartOfSpeech, Etymology, Edge, Example, Derivation, DerivationEdge, DerivationStep, AppliedRule, TransformLayer, EditorialStatus, SemanticShift};
 use petgraph::Direction::{Incoming, Outgoing, self};
 use petgraph::dot::{Dot, Config};
 use petgraph::graph::EdgeReference;
-use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::stable_graph::{NodeIndex, EdgeIndex};
 use petgraph::Graph;
-use log::{trace, debug};
+use log::{trace, debug, warn};
+use rand::{SeedableRng, rngs::StdRng};
+use regex::Regex;
+use schemars::JsonSchema;
 
 #[derive(Clone, Default,  serde::Deserialize, serde::Serialize)]
 /// A Lexis represents a headword in Kirum's lexicon, be it a word, word stem, morpheme, etc.
@@ -28,18 +36,58 @@ pub struct Lexis {
     pub lexis_type: String,
     /// Dictionary definition
     pub definition: String,
+    /// Optional definitions in multiple metalanguages, keyed by a language code such as "en" or "de"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted_map")]
+    pub glosses: HashMap<String, String>,
+    /// Optional example sentences demonstrating usage of the lexis
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Example>,
+    /// Representations of the word in other scripts, keyed by the orthography name. 
+    /// This is generated during compute_lexicon() from the orthographies of the LanguageTree.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted_map")]
+    pub orthographies: HashMap<String, String>,
+    /// IPA transcription of the word. This is generated during compute_lexicon() from the IPA mapping of the LanguageTree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipa: Option<String>,
+    /// Optional semantic domain of the lexis, such as "body" or "kinship"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantic_field: Option<String>,
     /// Marks the lexis as archaic. Currently not used by any internal methods.
     pub archaic: bool,
+    /// Marks the word as a reconstructed form rather than an attested one.
+    /// Like the status, this isn't copied to derivative words.
+    #[serde(default)]
+    pub reconstructed: bool,
     /// Optional user-supplied tags
     //#[serde(skip)]
     pub tags: Vec<String>,
     /// Optional user-supplied metadata. Unlike tags, historical_metadata will trickle down to any derivative words.
     /// This shared metadata can be used to track common qualities of words, for filtering, templating, etc
+    #[serde(serialize_with = "sorted_map")]
     pub historical_metadata: HashMap<String, String>,
     /// Optional field that can be used to randomly generate a word value if none exists, separate from any etymology.
     /// If the given word has no etymology, this value takes prescience.
     /// The string value is used to generate a word based on the underlying phonology rules supplied to the TreeEtymology structure.
-    pub word_create: Option<String>
+    pub word_create: Option<String>,
+    /// Optional constraints on a word generated from the word_create field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_constraints: Option<lexcreate::CreateConstraints>,
+    /// Optional rules for joining the upstream etymons of this lexis. If unset, the LanguageTree's agglutination rules are used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agglutination: Option<Agglutination>,
+    /// Optional noun class or grammatical gender. If unset, the lexis will take any noun class set by the transforms of its etymons.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noun_class: Option<String>,
+    /// Optional irregular form. If set, this will replace the word derived from the etymology of the lexis during compute_lexicon(),
+    /// while keeping the etymological links intact.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word_override: Option<Lemma>,
+    /// Optional editorial notes, for contributors rather than readers of the dictionary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Optional editorial status of the lexis. Unlike most fields, the status isn't copied to derivative words.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<EditorialStatus>
 }
 
 // this custom implementation exists because we don't want history metadata to count towards equality
@@ -52,13 +100,39 @@ impl PartialEq for Lexis {
         self.pos == other.pos &&
         self.lexis_type == other.lexis_type && 
         self.definition == other.definition && 
+        self.glosses == other.glosses &&
+        self.examples == other.examples &&
+        self.semantic_field == other.semantic_field &&
         self.archaic == other.archaic &&
+        self.reconstructed == other.reconstructed &&
         self.tags == other.tags && 
-        self.word_create == other.word_create
+        self.word_create == other.word_create &&
+        self.noun_class == other.noun_class &&
+        self.notes == other.notes &&
+        self.status == other.status
+
+    }
+}
 
+impl Lexis {
+    /// Returns true if the lexis has the draft editorial status
+    pub fn is_draft(&self) -> bool {
+        self.status == Some(EditorialStatus::Draft)
     }
-    fn ne(&self, other: &Self) -> bool {
-        ! self.eq(other)
+
+    /// Return the definition in the requested gloss language. If the lexis has no gloss in that language,
+    /// or no language is requested, this falls back to the `definition` field, and then to the gloss 
+    /// of the alphabetically-first language.
+    pub fn gloss(&self, lang: Option<&str>) -> String {
+        if let Some(found) = lang.and_then(|l| self.glosses.get(l)) {
+            return found.clone()
+        }
+        if !self.definition.is_empty() || self.glosses.is_empty() {
+            return self.definition.clone()
+        }
+        let mut langs: Vec<&String> = self.glosses.keys().collect();
+        langs.sort();
+        langs.first().and_then(|l| self.glosses.get(*l)).cloned().unwrap_or_default()
     }
 }
 
@@ -83,36 +157,127 @@ impl std::fmt::Debug for Lexis {
 }
 
 
+/// Determines how the upstream etymons of a lexis are joined together when a word is created via agglutination.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct Agglutination {
+    /// Optional value inserted at every morpheme boundary, such as a hyphen or a linking vowel.
+    pub joiner: Option<Lemma>,
+    /// Determines if boundary sandhi rules should be applied at the seams between morphemes.
+    #[serde(default = "default_sandhi")]
+    pub sandhi: bool,
+}
+
+impl Default for Agglutination {
+    fn default() -> Self {
+        Agglutination { joiner: None, sandhi: true }
+    }
+}
+
+fn default_sandhi() -> bool {
+    true
+}
+
+/// Determines how historical_metadata is inherited from upstream etymons
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub enum MetadataInheritance {
+    /// Metadata from upstream etymons overwrites any existing value with the same key
+    #[default]
+    #[serde(rename="overwrite")]
+    Overwrite,
+    /// Metadata from upstream etymons is only added for keys the lexis doesn't already have
+    #[serde(rename="preserve")]
+    Preserve,
+    /// Metadata is not inherited
+    #[serde(rename="none")]
+    None,
+}
+
+/// Determines how a lexis without a language is assigned one during compute_lexicon()
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub enum LanguageInheritance {
+    /// The language is left unset
+    #[default]
+    #[serde(rename="none")]
+    None,
+    /// The lexis takes the language of its first etymon
+    #[serde(rename="etymon")]
+    Etymon,
+    /// The lexis takes the daughter language of its first etymon's language, as set in LanguageTree.language_daughters.
+    /// If the etymon's language has no daughter, the etymon's language is used.
+    #[serde(rename="daughter")]
+    Daughter,
+}
+
+/// Determines how transforms from the edge, language pair and global layers combine when a word is derived
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub enum TransformPrecedence {
+    /// Every matching transform is applied: edge transforms first, then language pair transforms, then global transforms
+    #[default]
+    #[serde(rename="stack")]
+    Stack,
+    /// Only the highest-precedence layer with a transform that changes the word is applied, in the order edge > language pair > global.
+    /// For example, an irregular sound change on an edge prevents the regular sound changes between two languages from applying.
+    #[serde(rename="override")]
+    Override,
+}
+
 /// TreeEtymology represents the graph edge of the language tree, and
 /// determines the relationship of one word to another.
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct TreeEtymology {
     /// A list of Transforms that define the etymology between one word and another.
     pub transforms: Vec<Transform>,
+    // the intermediate values are only used while computing the tree
+    #[serde(skip)]
     intermediate_word: Option<Lemma>,
+    #[serde(skip)]
+    intermediate_class: Option<String>,
+    /// the transforms that changed the intermediate word
+    applied: Vec<AppliedRule>,
     /// Determines what order this morpheme is agglutinated in to create derived lexii.
     /// For example, if a lexis has two upstream etymons, Word A with agglutination_order=1 
     /// and Word B with agglutination_order=2, the lexis will by generated by agglutinating A+B
     pub agglutination_order: Option<i32>,
+    /// Set if the lexis was borrowed from the etymon, rather than inherited from it
+    #[serde(default)]
+    pub borrowed: bool,
+    /// How the meaning of the lexis changed from the etymon
+    #[serde(default)]
+    pub semantic_shift: Option<SemanticShift>,
+    /// User-defined metadata about the relationship between the words
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted_map")]
+    pub metadata: HashMap<String, String>,
 }
 
 impl TreeEtymology{
-    /// a helper function to apply the given lexis to all transforms in the graph edge
-    fn apply_transforms(&self, etymon: &mut Lexis) -> Result<(), TransformError>{
-
-        //let mut transformed = etymon.clone();
-        for trans in self.transforms.clone(){
-            trans.transform(etymon)?;
-        };
-        Ok(())
-    }
-
     /// A helper function that returns a vector of all names transforms in the graph edges
     pub fn names(&self) -> Vec<String>{
        self.transforms.clone().into_iter().map(|t| t.name).collect()
     }
 }
 
+/// A snapshot of the words and etymologies of a computed LanguageTree.
+/// It can be serialized, and restored into a tree read from the same files with LanguageTree::restore_computed(),
+/// so compute_lexicon() doesn't have to be run again.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct ComputedGraph {
+    words: Vec<Lexis>,
+    /// the letters of each word, as a Lemma serializes to a plain string
+    letters: Vec<Option<Vec<String>>>,
+    etymologies: Vec<ComputedEdge>,
+    applied_rules: Vec<(usize, Vec<AppliedRule>)>,
+    /// the words created from the phonology rules
+    #[serde(default)]
+    generated: Vec<usize>
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct ComputedEdge {
+    etymon: usize,
+    lexis: usize,
+    etymology: TreeEtymology
+}
+
 /// Represents an entire language family tree as tracked by libkirum.
 #[derive(Clone)]
 pub struct LanguageTree {
@@ -126,15 +291,88 @@ pub struct LanguageTree {
     /// An optional set of global transforms.
     /// If specified, every word in the tree will be matched to the global transform list, 
     /// and the transform will be applied _after_ any other matching transform
-    pub global_transforms: Option<Vec<GlobalTransform>>
+    pub global_transforms: Option<Vec<GlobalTransform>>,
+
+    /// Transforms applied to every word derived from a word of one language into another.
+    /// These are applied after the transforms of the edge between the two words.
+    pub language_pair_transforms: Vec<LanguagePairTransform>,
+
+    /// Determines which transform layers are applied when more than one matches a word
+    pub transform_precedence: TransformPrecedence,
+
+    /// The transforms that changed each word during compute_lexicon()
+    applied_rules: HashMap<NodeIndex, Vec<AppliedRule>>,
+
+    /// The words created from the phonology rules during compute_lexicon()
+    generated: HashSet<NodeIndex>,
+
+    /// The default rules used to join multiple upstream etymons. Can be overridden by an individual Lexis.
+    pub agglutination: Agglutination,
+
+    /// Boundary sandhi rules, keyed by language name.
+    /// When a lexis is created by agglutinating multiple etymons, the rules for the language of the lexis
+    /// are applied at each seam where two morphemes meet.
+    pub sandhi_rules: HashMap<String, Vec<SandhiRule>>,
+
+    /// Determines how historical_metadata trickles down to derived words.
+    pub metadata_inheritance: MetadataInheritance,
+
+    /// Optional seed for the random number generator used to create words from phonology rules.
+    /// If set, compute_lexicon will generate the same words each time for the same tree.
+    pub seed: Option<u64>,
+
+    /// Optional alphabetical order used to sort words in to_vec(). 
+    /// Each value is a letter, and any letter not in the list is sorted after the listed letters.
+    pub collation: Option<Vec<String>>,
+
+    /// Optional per-language alphabetical order, keyed by language name. If a word's language has a collation, 
+    /// it is used instead of the tree-wide collation when sorting in to_vec().
+    pub language_collation: HashMap<String, Vec<String>>,
+
+    /// Determines how words without a language are assigned one from their etymons
+    pub language_inheritance: LanguageInheritance,
+
+    /// A map of language names to the name of their daughter language, used by LanguageInheritance::Daughter
+    pub language_daughters: HashMap<String, String>,
+
+    /// Optional per-language inventory of multi-character letters, such as "ch" or "ng", keyed by language name.
+    /// During compute_lexicon(), the words of a language with an inventory are split into letters using Lemma::segment()
+    pub graphemes: HashMap<String, Vec<String>>,
+
+    /// Optional per-language orthographies, keyed by language name, then orthography name. 
+    /// Each orthography is a transliteration table of letters to their representation in the script.
+    pub orthographies: HashMap<String, HashMap<String, HashMap<String, String>>>,
+
+    /// Optional per-language mappings of letters to their IPA transcription, keyed by language name.
+    pub ipa: HashMap<String, HashMap<String, String>>,
+
+    /// Patterns that words generated from phonology rules must not match, such as real-world slurs or brand names.
+    /// A generated word that matches any pattern is regenerated.
+    pub blacklist: Vec<Regex>,
+
+    /// Optional hook called with the number of words done and the total number of words
+    /// as compute_lexicon() and generate_daughter_language() run
+    progress: Option<Arc<ProgressHook>>
 }
 
+/// A function called with the number of words done and the total, see LanguageTree::on_progress()
+pub type ProgressHook = dyn Fn(usize, usize) + Send + Sync;
+
 impl Default for LanguageTree{
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl<'a> IntoIterator for &'a LanguageTree {
+    type Item = &'a Lexis;
+    type IntoIter = Box<dyn Iterator<Item = &'a Lexis> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
 impl IntoIterator for LanguageTree {
     type Item = Lexis;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -152,12 +390,42 @@ impl IntoIterator for LanguageTree {
 impl LanguageTree {
     pub fn new() -> Self {
         LanguageTree {graph: Graph::<Lexis, TreeEtymology, petgraph::Directed>::new(), 
-            word_creator_phonology: lexcreate::LexPhonology { groups: HashMap::new(), lexis_types: HashMap::new() },
+            word_creator_phonology: lexcreate::LexPhonology { groups: HashMap::new(), lexis_types: HashMap::new(), fallbacks: HashMap::new() },
             global_transforms: None,
+            language_pair_transforms: Vec::new(),
+            transform_precedence: TransformPrecedence::default(),
+            applied_rules: HashMap::new(),
+            generated: HashSet::new(),
+            agglutination: Agglutination::default(),
+            sandhi_rules: HashMap::new(),
+            metadata_inheritance: MetadataInheritance::default(),
+            seed: None,
+            collation: None,
+            language_collation: HashMap::new(),
+            language_inheritance: LanguageInheritance::default(),
+            language_daughters: HashMap::new(),
+            graphemes: HashMap::new(),
+            orthographies: HashMap::new(),
+            ipa: HashMap::new(),
+            blacklist: Vec::new(),
+            progress: None,
         }
 
     }
 
+    /// Set a hook that's called with the number of words done and the total number of words as
+    /// compute_lexicon() and generate_daughter_language() run, i.e. `tree.on_progress(|done, total| println!("{}/{}", done, total))`.
+    /// The hook is called once for every word, and a final time with done equal to total.
+    pub fn on_progress(&mut self, hook: impl Fn(usize, usize) + Send + Sync + 'static) {
+        self.progress = Some(Arc::new(hook));
+    }
+
+    fn report_progress(&self, done: usize, total: usize) {
+        if let Some(hook) = &self.progress {
+            hook(done, total);
+        }
+    }
+
     /// Adds a single lexis entry to the language tree. 
     pub fn add_lexis(&mut self, lex: Lexis){
         self.graph.add_node(lex);
@@ -173,6 +441,44 @@ impl LanguageTree {
         false
     }
 
+    /// Return a snapshot of the words and etymologies of the tree, which should be called after compute_lexicon()
+    pub fn computed_graph(&self) -> ComputedGraph {
+        let words: Vec<Lexis> = self.graph.node_weights().cloned().collect();
+        let letters = words.iter().map(|l| l.word.clone().map(|w| w.into_iter().collect())).collect();
+        let etymologies = self.graph.edge_references().map(|e| ComputedEdge { 
+            etymon: e.source().index(), lexis: e.target().index(), etymology: e.weight().clone() }).collect();
+        let mut applied_rules: Vec<(usize, Vec<AppliedRule>)> = self.applied_rules.iter().map(|(n, r)| (n.index(), r.clone())).collect();
+        applied_rules.sort_by_key(|(n, _)| *n);
+        let mut generated: Vec<usize> = self.generated.iter().map(|n| n.index()).collect();
+        generated.sort();
+        ComputedGraph { words, letters, etymologies, applied_rules, generated }
+    }
+
+    /// Replace the words and etymologies of the tree with a snapshot from computed_graph(), instead of running compute_lexicon().
+    /// The tree should be read from the same source as the snapshot; if the IDs of the words in the tree
+    /// don't match the start of the snapshot, the tree is unchanged and false is returned.
+    pub fn restore_computed(&mut self, computed: ComputedGraph) -> bool {
+        let ComputedGraph { words, letters, etymologies, applied_rules, generated } = computed;
+        let matching = self.graph.node_count() <= words.len() && words.len() == letters.len()
+            && self.graph.node_weights().zip(words.iter()).all(|(current, cached)| current.id == cached.id)
+            && etymologies.iter().all(|e| e.etymon < words.len() && e.lexis < words.len());
+        if !matching {
+            return false;
+        }
+        let mut graph = Graph::<Lexis, TreeEtymology, petgraph::Directed>::new();
+        for (mut lex, letters) in words.into_iter().zip(letters) {
+            lex.word = letters.map(|l| l.into());
+            graph.add_node(lex);
+        }
+        for edge in etymologies {
+            graph.add_edge(NodeIndex::new(edge.etymon), NodeIndex::new(edge.lexis), edge.etymology);
+        }
+        self.graph = graph;
+        self.applied_rules = applied_rules.into_iter().map(|(n, r)| (NodeIndex::new(n), r)).collect();
+        self.generated = generated.into_iter().map(NodeIndex::new).collect();
+        true
+    }
+
     /// returns the total number of words
     pub fn len(&self) -> usize {
         self.graph.node_count()
@@ -186,7 +492,7 @@ impl LanguageTree {
 
     /// A quick and ugly helper that returns a graphviz.dot render of the graph. Useful for debugging.
     pub fn graphviz(&self) -> String{
-       format!("{:?}", Dot::with_config(&self.graph, &[Config::EdgeNoLabel])) 
+        self.graphviz_highlighted(&HashMap::new())
     }
 
     /// creates an etymological link between two words: an upstream etymon, and a base word. If neither word exists, they will be added.
@@ -225,7 +531,7 @@ impl LanguageTree {
             lex_idx = Some(self.graph.add_node(lex));
         }
 
-        self.graph.add_edge(ety_idx.unwrap(), lex_idx.unwrap(), TreeEtymology { transforms: trans, intermediate_word: None, agglutination_order });
+        self.graph.add_edge(ety_idx.unwrap(), lex_idx.unwrap(), TreeEtymology { transforms: trans, agglutination_order, ..Default::default() });
 
     }
 
@@ -241,118 +547,280 @@ impl LanguageTree {
         }
     }
 
+    /// Return the etymology connecting the etymon to the lexis with the given ID.
+    /// Returns None if either word doesn't exist, or the lexis isn't derived from the etymon.
+    pub fn edge(&self, etymon_id: &str, id: &str) -> Option<&TreeEtymology> {
+        let find = |id: &str| self.graph.node_indices().find(|n| self.graph[*n].id == id);
+        let edge = self.graph.find_edge(find(etymon_id)?, find(id)?)?;
+        self.graph.edge_weight(edge)
+    }
+
+    /// Return the etymology connecting the etymon to the lexis with the given ID, so it can be changed after the words are connected.
+    /// Returns None if either word doesn't exist, or the lexis isn't derived from the etymon.
+    pub fn edge_mut(&mut self, etymon_id: &str, id: &str) -> Option<&mut TreeEtymology> {
+        let find = |id: &str| self.graph.node_indices().find(|n| self.graph[*n].id == id);
+        let edge = self.graph.find_edge(find(etymon_id)?, find(id)?)?;
+        self.graph.edge_weight_mut(edge)
+    }
+
 
     /// Fill out the graph, walking the structure until all possible lexii have been generated or updated.
     /// This method is idempotent, and can be run any time to calculate unpopulated or incorrect lexii in the language tree.
     pub fn compute_lexicon(&mut self) -> Result<(), TransformError> {
-        let mut incomplete = true;
-        let mut updated: HashMap<NodeIndex, bool> = HashMap::new();
-        while incomplete{
-            let mut changes = 0;
+        self.expand_alternatives()?;
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy()
+        };
+        // try word generation from supplied phonetic rules first, before transforms.
+        // Words are created in node order, so a seeded tree always creates the same words.
+        for node in self.graph.node_indices() {
+            if self.graph[node].word_create.is_some() && self.graph[node].word.is_none() {
+                self.create_word(node, &mut rng);
+            }
+        }
 
-            for node in self.graph.node_indices(){
+        // each lexis is computed once, after every etymon it's waiting on has passed its word down the connecting edge.
+        // Edges that already have a word from an earlier call aren't waited on.
+        let mut waiting: Vec<usize> = self.graph.node_indices()
+        .map(|n| self.graph.edges_directed(n, Incoming).filter(|e| e.weight().intermediate_word.is_none()).count()).collect();
+        let mut ready: VecDeque<NodeIndex> = self.graph.node_indices().filter(|n| waiting[n.index()] == 0).collect();
+        let total = self.graph.node_count();
+        let mut done = 0;
+        while let Some(node) = ready.pop_front() {
+            self.report_progress(done, total);
+            done += 1;
+            if self.graph.edges_directed(node, Incoming).next().is_some() {
+                self.derive_word(node)?;
+            } else if self.graph[node].word.is_some() {
+                // we have a lexis with no upstream edges, but contains a word. mark as updated.
+                trace!("updated node '{}' with no upstreams: {:?}", self.graph[node].id, self.graph[node].word);
+                self.segment_word(node);
+            } else {
+                // a root without a word, so nothing derived from it can be computed
+                continue;
+            }
 
-                let mut is_ready = true;
-                let mut upstreams: Vec<(i32, Lemma)> = Vec::new();
-                
-                if !updated.contains_key(&node){
-
-                    // try word generation from supplied phonetic rules first, before transforms
-                    if self.graph[node].word_create.is_some() && self.graph[node].word.is_none() {
-                        trace!("word_create has value, no word found, creating one...");
-                        let word_type = self.graph[node].word_create.clone().unwrap();
-                        let new_gen = self.word_creator_phonology.create_word(&word_type);
-                        if let Some(found_new) = new_gen {
-                            let debug_iter: Vec<String> = found_new.clone().into_iter().collect();
-                            trace!("created new word ({:?}) from phonology rules for ID {}", debug_iter, self.graph[node].id);
-                            self.graph[node].word = Some(found_new);
-                            //continue;
-                        }
-                    }
+            // the word is updated, "trickle down" to outgoing edges
+            let mut edges = self.graph.neighbors_directed(node, Outgoing).detach();
+            while let Some(edge) = edges.next_edge(&self.graph) {
+                if self.graph[edge].intermediate_word.is_some(){
+                    continue
+                }
+                let mut temp_ref = self.graph[node].clone();
+                let steps = self.edge_steps(edge, &mut temp_ref)?;
+                self.graph[edge].applied = steps.into_iter().filter(|s| s.applied && s.input != s.output)
+                .map(|s| AppliedRule { layer: s.layer, name: s.transform, etymon: Some(self.graph[node].id.clone()) }).collect();
+                trace!("updated edge with word {:?}", temp_ref.word);
+
+                // only pass along a noun class if a transform changed it
+                if temp_ref.noun_class != self.graph[node].noun_class {
+                    self.graph[edge].intermediate_class = temp_ref.noun_class;
+                }
+                self.graph[edge].intermediate_word = temp_ref.word;
 
-                    let mut etymons_in_lex = 0;
-                    for edge in self.graph.edges_directed(node, petgraph::Direction::Incoming){
-                        etymons_in_lex += 1;
-                        if edge.weight().intermediate_word.is_none(){
-                            // word still has unpopulated edges, give up
-                            is_ready = false;
-                            break;
-                            
-                        }
-                        // add our populated edge to the list, be prepared to use it
-                        let order = edge.weight().agglutination_order.unwrap_or(0);
-                        upstreams.push((order, edge.weight().intermediate_word.clone().unwrap()));
+                if let Some((_, derived)) = self.graph.edge_endpoints(edge) {
+                    waiting[derived.index()] -= 1;
+                    if waiting[derived.index()] == 0 {
+                        ready.push_back(derived);
                     }
+                }
+            }
+        }
+        self.apply_orthographies();
+        // words that could never be computed are counted as done
+        self.report_progress(total, total);
+        Ok(())
+    }
 
-                    // word has all populated upstream edges, add to tree proper
-                    if etymons_in_lex > 0 && is_ready{
-                        changes+=1;
-                        let rendered_word = join_string_vectors(&mut upstreams);
-
-                        trace!("updated node {} with word: {:?}", self.graph[node].id, rendered_word);
-                        self.graph[node].word = Some(rendered_word);
-                        updated.insert(node, true);
-
-
-                        // merge upstream historical metadata
-                        self.combine_maps_for_lex_idx(&node);
-                        // check global transforms
-                        if let Some(gt) = &self.global_transforms  {
-                            let mut updating = self.graph[node].clone();
-                            let etys: Vec<&Lexis> = self.graph.neighbors_directed(node, Direction::Incoming).map(|e| &self.graph[e]).collect();
-                            for trans in gt {
-                                // collect the upstream etymons
-                                trans.transform(&mut updating, Some(&etys))?;
-                                trace!("updated word {:?} with global transform ", self.graph[node].id);
-                            }
-                            self.graph[node] = updating;
+    /// create a word for the lexis from the phonology rules
+    fn create_word(&mut self, node: NodeIndex, rng: &mut StdRng) {
+        trace!("word_create has value, no word found, creating one...");
+        let lex = &self.graph[node];
+        let word_type = lex.word_create.as_deref().unwrap_or_default();
+        let constraints = &lex.create_constraints;
+        let new_gen = self.word_creator_phonology.create_word_matching_with_rng(word_type, 
+            |w| constraints.as_ref().map(|c| c.matches(w)).unwrap_or(true) && !self.is_blacklisted(w), rng);
+        if new_gen.is_none() && (constraints.is_some() || !self.blacklist.is_empty()) {
+            warn!("could not generate a word for ID {} that matches the constraints and blacklist after {} attempts", 
+            lex.id, lexcreate::MAX_CREATE_ATTEMPTS);
+        }
+        if let Some(found_new) = new_gen {
+            trace!("created new word ({:?}) from phonology rules for ID {}", found_new, lex.id);
+            self.graph[node].word = Some(found_new);
+            self.generated.insert(node);
+        }
+    }
+
+    /// derive the word of a lexis from the words passed down by its etymons, then apply any global transforms and irregular form
+    fn derive_word(&mut self, node: NodeIndex) -> Result<(), TransformError> {
+        let mut upstreams: Vec<(i32, Lemma)> = Vec::new();
+        let mut upstream_class: Option<String> = None;
+        let mut upstream_lang: Option<(i32, NodeIndex)> = None;
+        for edge in self.graph.edges_directed(node, Incoming){
+            let order = edge.weight().agglutination_order.unwrap_or(0);
+            if let Some(word) = &edge.weight().intermediate_word {
+                upstreams.push((order, word.clone()));
+            }
+            if edge.weight().intermediate_class.is_some() {
+                upstream_class.clone_from(&edge.weight().intermediate_class);
+            }
+            // track the language of the first etymon in agglutination order
+            if upstream_lang.map(|(o, _)| order < o).unwrap_or(true) {
+                upstream_lang = Some((order, edge.source()));
+            }
+        }
+
+        if let Some((_, etymon)) = upstream_lang {
+            self.inherit_language(node, self.graph[etymon].language.clone());
+        }
+        let join_rules = self.graph[node].agglutination.as_ref().unwrap_or(&self.agglutination);
+        let seam_rules = if join_rules.sandhi {
+            self.sandhi_rules.get(&self.graph[node].language).map(|r| r.as_slice()).unwrap_or_default()
+        } else {
+            &[]
+        };
+        let rendered_word = join_string_vectors(&mut upstreams, join_rules, seam_rules);
+
+        trace!("updated node {} with word: {:?}", self.graph[node].id, rendered_word);
+        self.graph[node].word = Some(rendered_word);
+        if self.graph[node].noun_class.is_none() {
+            self.graph[node].noun_class = upstream_class;
+        }
+
+        // merge upstream historical metadata
+        self.combine_maps_for_lex_idx(&node);
+        let mut applied: Vec<AppliedRule> = self.graph.edges_directed(node, Incoming)
+        .flat_map(|e| e.weight().applied.iter().cloned()).collect();
+        // check global transforms
+        if let Some(gt) = &self.global_transforms  {
+            if self.transform_precedence == TransformPrecedence::Override && !applied.is_empty() {
+                trace!("skipping global transforms for {}, as a higher-precedence transform was applied", self.graph[node].id);
+            } else {
+                // take the lexis out of the graph while it's transformed, so the etymons can still be borrowed
+                let mut updating = std::mem::take(&mut self.graph[node]);
+                let etys: Vec<&Lexis> = self.graph.neighbors_directed(node, Direction::Incoming).map(|e| &self.graph[e]).collect();
+                let vowels = self.vowels();
+                let mut result = Ok(());
+                for (idx, trans) in gt.iter().enumerate() {
+                    let input = updating.word.clone();
+                    match trans.transform_option(&mut updating, Some(&etys), &vowels).map_err(|e| e.in_transform(&format!("global transform {}", idx))) {
+                        Ok(true) if input != updating.word => {
+                            trace!("updated word {:?} with global transform {}", updating.id, idx);
+                            applied.push(AppliedRule { layer: TransformLayer::Global, name: format!("global transform {}", idx), etymon: None });
+                        },
+                        Ok(_) => {},
+                        Err(e) => {
+                            result = Err(e);
+                            break;
                         }
                     }
-                    // we have a lexis with no upstream edges, but contains a word. mark as updated.
-                    if self.graph[node].word.is_some() && etymons_in_lex == 0 {
-                        trace!("updated node '{}' with no upstreams: {:?}", self.graph[node].id, self.graph[node].word);
-                        changes+=1;
-                        updated.insert(node, true);
-                    }
                 }
+                self.graph[node] = updating;
+                result?;
+            }
+        }
+        self.applied_rules.insert(node, applied);
 
+        if let Some(irregular) = self.graph[node].word_override.clone() {
+            trace!("node {} is irregular: computed word {:?} replaced with {:?}", self.graph[node].id, self.graph[node].word, irregular);
+            self.graph[node].word = Some(irregular);
+        }
+        self.segment_word(node);
+        Ok(())
+    }
 
-                // if a word is updated, "trickle down" to outgoing edges
-                if updated.contains_key(&node){
-                    let mut edges = self.graph.neighbors_directed(node, Outgoing).detach();
-                    while let Some(edge) = edges.next_edge(&self.graph) {
-                         // do we need this check?
-                        if self.graph[edge].intermediate_word.is_some(){
-                            continue
-                        }
-                        let mut temp_ref = self.graph[node].clone();
-                        self.graph[edge].apply_transforms(&mut temp_ref)?;
-                        //self.graph[node] = temp_ref;
-                        trace!("updated edge with word {:?}", temp_ref.word);
+    /// For every edge with a transform that has alternative outcomes, add a variant of the derived lexis for each outcome after the first.
+    /// A variant has the ID `{id}-{transform name}-{outcome index}`, and is connected to the same etymons as the original lexis.
+    /// Returns an error if another word already has the ID of a variant.
+    fn expand_alternatives(&mut self) -> Result<(), TransformError> {
+        // global and language pair transforms aren't attached to a single edge, so there's no derived word to make a variant of
+        let global = self.global_transforms.iter().flatten().enumerate()
+        .filter(|(_, g)| g.transforms.iter().any(|f| matches!(f, TransformFunc::Alternatives { .. })))
+        .map(|(idx, _)| format!("global transform {}", idx));
+        let pairs = self.language_pair_transforms.iter().flat_map(|p| p.transforms.iter())
+        .filter(|t| t.transforms.iter().any(|f| matches!(f, TransformFunc::Alternatives { .. })))
+        .map(|t| format!("language pair transform '{}'", t.name));
+        for name in global.chain(pairs) {
+            warn!("{} has alternative outcomes, but only the first outcome is applied outside of etymology transforms", name);
+        }
 
-                        self.graph[edge].intermediate_word = temp_ref.word;
-                        changes+=1;
+        let edges: Vec<_> = self.graph.edge_indices().collect();
+        for edge in edges {
+            let (etymon, derived) = match self.graph.edge_endpoints(edge) {
+                Some(ends) => ends,
+                None => continue
+            };
+            let transforms = self.graph[edge].transforms.clone();
+            for (trans_idx, trans) in transforms.iter().enumerate() {
+                for (func_idx, func) in trans.transforms.iter().enumerate() {
+                    let TransformFunc::Alternatives { outcomes } = func else {
+                        continue
+                    };
+                    for (outcome_idx, outcome) in outcomes.iter().enumerate().skip(1) {
+                        let variant_id = format!("{}-{}-{}", self.graph[derived].id, trans.name, outcome_idx);
+                        let mut variant_transforms = transforms.clone();
+                        variant_transforms[trans_idx].transforms[func_idx] = TransformFunc::Alternatives { outcomes: vec![outcome.clone()] };
+                        if let Some(existing) = self.graph.node_indices().find(|n| self.graph[*n].id == variant_id) {
+                            // don't create the variant again if the lexicon is computed more than once
+                            let is_variant = self.graph.find_edge(etymon, existing)
+                                .is_some_and(|e| self.graph[e].transforms == variant_transforms);
+                            if is_variant {
+                                continue
+                            }
+                            return Err(TransformError::VariantCollision { id: variant_id, transform: trans.name.clone() })
+                        }
+                        // the override belongs to the original word, so the variant is derived like any other word
+                        let variant = Lexis { id: variant_id, word: None, word_override: None, ..self.graph[derived].clone() };
+                        debug!("adding variant {} from alternative outcome of {}", variant.id, trans.name);
+                        let variant_idx = self.graph.add_node(variant);
+
+                        let incoming: Vec<(NodeIndex, TreeEtymology)> = self.graph.edges_directed(derived, Incoming)
+                        .map(|e| (e.source(), e.weight().clone())).collect();
+                        for (source, weight) in incoming {
+                            let transforms = if source == etymon {variant_transforms.clone()} else {weight.transforms};
+                            self.graph.add_edge(source, variant_idx, TreeEtymology { transforms, intermediate_word: None, 
+                                intermediate_class: None, applied: Vec::new(), ..weight });
+                        }
                     }
-
                 }
             }
-
-            // we iterated the graph without making any changes, consider it done
-            if changes == 0 {
-                incomplete = false;
-            }
-        };
+        }
         Ok(())
     }
 
+    /// render every word into the orthographies and IPA transcription defined for its language
+    fn apply_orthographies(&mut self) {
+        for node in self.graph.node_indices() {
+            let lex = &self.graph[node];
+            let Some(word) = &lex.word else {
+                continue
+            };
+            let ipa = self.ipa.get(&lex.language).map(|table| word.transliterate(table));
+            let rendered: HashMap<String, String> = self.orthographies.get(&lex.language)
+            .map(|tables| tables.iter().map(|(name, table)| (name.clone(), word.transliterate(table))).collect())
+            .unwrap_or_default();
+            self.graph[node].orthographies = rendered;
+            self.graph[node].ipa = ipa;
+        }
+    }
+
     fn combine_maps_for_lex_idx(&mut self,  id: &NodeIndex) {
-        let etys: Vec<Lexis> = self.graph.neighbors_directed(*id, Direction::Incoming).map(|e| self.graph[e].clone()).collect();
-        for ety in etys {
-            if !ety.historical_metadata.is_empty(){
-                self.graph[*id].historical_metadata.extend(ety.historical_metadata.iter().map(|(k, v)| (k.clone(), v.clone())));
+        if self.metadata_inheritance == MetadataInheritance::None {
+            return
+        }
+        let mut metadata = std::mem::take(&mut self.graph[*id].historical_metadata);
+        for ety in self.graph.neighbors_directed(*id, Direction::Incoming) {
+            let upstream = &self.graph[ety].historical_metadata;
+            if self.metadata_inheritance == MetadataInheritance::Preserve {
+                for (k, v) in upstream {
+                    metadata.entry(k.clone()).or_insert_with(|| v.clone());
+                }
+            } else {
+                metadata.extend(upstream.iter().map(|(k, v)| (k.clone(), v.clone())));
             }
-          
         }
+        self.graph[*id].historical_metadata = metadata;
     } 
     
 
@@ -380,13 +848,16 @@ impl LanguageTree {
     F: FnMut(&Lexis) -> bool,
     P: FnMut(&Lexis) -> Lexis,
     {
+        let total = self.graph.node_count();
+        let vowels = self.vowels();
         for node in self.graph.node_indices() {
+            self.report_progress(node.index(), total);
             if apply_to(&self.graph[node]) {
                 debug!("Created daughter word from {}", &self.graph[node].id);
                 let mut applied_transforms: Vec<Transform> = Vec::new();
                 let mut found_updated: Lexis = self.graph[node].clone();
                 for trans in &daughter_transforms {
-                    let updated = trans.transform_option(&mut found_updated)?;
+                    let updated = trans.transform_option(&mut found_updated, &vowels)?;
                     if updated {
                         applied_transforms.push(trans.clone());
                         //found_updated = upd;
@@ -401,6 +872,7 @@ impl LanguageTree {
                 
             }
         };
+        self.report_progress(total, total);
         Ok(())
     }
 
@@ -408,16 +880,49 @@ impl LanguageTree {
 
     /// Reduce the language graph to a vector of words.
     pub fn to_vec(&self) -> Vec<Lexis>{
-        let mut dict: Vec<Lexis> = Vec::new();
-        for node in self.graph.node_indices(){
-            if self.graph[node].word.is_some() {
-                dict.push(self.graph[node].clone());
-            }
-        }
-        dict.sort_by_key(|k| k.word.clone().unwrap());
+        let mut dict: Vec<Lexis> = self.iter().filter(|l| l.word.is_some()).cloned().collect();
+        self.sort_lexicon(&mut dict);
         dict
     }
 
+    /// sort words the same way as to_vec(), using the collation of the tree if there is one.
+    /// Every word should have a computed word.
+    pub(crate) fn sort_lexicon(&self, dict: &mut [Lexis]) {
+        if self.collation.is_some() || !self.language_collation.is_empty() {
+            let default_alphabet = self.collation.clone().unwrap_or_default();
+            dict.sort_by_cached_key(|k| {
+                let alphabet = self.language_collation.get(&k.language).unwrap_or(&default_alphabet);
+                k.word.as_ref().unwrap().sort_key(alphabet)
+            });
+        } else {
+            dict.sort_by_key(|k| k.word.clone().unwrap())
+        }
+    }
+
+    /// Iterate over every lexis in the tree without cloning it, in the order the words were added.
+    /// Unlike to_vec(), words without a computed word are included, and the words aren't sorted.
+    pub fn iter(&self) -> impl Iterator<Item = &Lexis> {
+        self.graph.node_weights()
+    }
+
+    /// Iterate over every lexis in the tree mutably, in the order the words were added.
+    /// Changes to the IDs of words will break lookups such as get_by_id(),
+    /// and changes to the words themselves are replaced the next time compute_lexicon() runs.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Lexis> {
+        self.graph.node_weights_mut()
+    }
+
+    /// Iterate over every etymological link in the tree, as the etymon, the lexis derived from it, and the etymology connecting them
+    pub fn iter_edges(&self) -> impl Iterator<Item = (&Lexis, &Lexis, &TreeEtymology)> {
+        self.graph.edge_references().map(|e| (&self.graph[e.source()], &self.graph[e.target()], e.weight()))
+    }
+
+    /// The letters treated as vowels when transforms find the syllables of a word:
+    /// the `V` group of the phonetic rules, or the default vowels if there isn't one
+    pub fn vowels(&self) -> Vec<String> {
+        self.word_creator_phonology.vowels()
+    }
+
     /// Get a Lemma entry by the ID value
     pub fn get_by_id(&self, id: &str) -> Option<Lexis> {
         for node in self.graph.node_indices(){ 
@@ -429,6 +934,268 @@ impl LanguageTree {
     }
 
 
+    /// split the word of a lexis into letters, using the grapheme inventory of its language
+    fn segment_word(&mut self, node: NodeIndex) {
+        if let (Some(inventory), Some(word)) = (self.graphemes.get(&self.graph[node].language), &self.graph[node].word) {
+            let segmented = word.segment(inventory);
+            self.graph[node].word = Some(segmented);
+        }
+    }
+
+    /// set the language of a lexis from its etymon, if the lexis doesn't already have one
+    fn inherit_language(&mut self, node: NodeIndex, etymon_lang: String) {
+        if !self.graph[node].language.is_empty() || etymon_lang.is_empty() {
+            return
+        }
+        let inherited = self.derived_language(node, &etymon_lang);
+        if inherited.is_empty() {
+            return
+        }
+        trace!("lexis {} inherited language {}", self.graph[node].id, inherited);
+        self.graph[node].language = inherited;
+    }
+
+    /// Return the language a lexis has, or will inherit from an etymon of the given language
+    fn derived_language(&self, node: NodeIndex, etymon_lang: &str) -> String {
+        if !self.graph[node].language.is_empty() || etymon_lang.is_empty() {
+            return self.graph[node].language.clone()
+        }
+        match self.language_inheritance {
+            LanguageInheritance::None => String::new(),
+            LanguageInheritance::Etymon => etymon_lang.to_string(),
+            LanguageInheritance::Daughter => self.language_daughters.get(etymon_lang).cloned().unwrap_or(etymon_lang.to_string())
+        }
+    }
+
+    /// Apply the transforms between an etymon and a derived word, returning a step for every transform that was checked.
+    /// The transforms of the edge are applied first, followed by the transforms for the languages of the two words,
+    /// unless the word was borrowed from the etymon.
+    fn edge_steps(&self, edge: EdgeIndex, working: &mut Lexis) -> Result<Vec<DerivationStep>, TransformError> {
+        let (etymon_id, derived_id) = match self.graph.edge_endpoints(edge) {
+            Some((etymon, derived)) => (self.graph[etymon].id.as_str(), self.graph[derived].id.as_str()),
+            None => ("", "")
+        };
+        let vowels = self.vowels();
+        let run = |trans: &Transform, layer: TransformLayer, working: &mut Lexis| -> Result<DerivationStep, TransformError> {
+            let input = working.word.clone();
+            let (applied, functions) = trans.transform_traced(working, &vowels).map_err(|e| e.for_word(derived_id, etymon_id))?;
+            Ok(DerivationStep { transform: trans.name.clone(), layer, applied, input, output: working.word.clone(), functions })
+        };
+        let mut steps: Vec<DerivationStep> = Vec::new();
+        for trans in &self.graph[edge].transforms {
+            steps.push(run(trans, TransformLayer::Edge, working)?);
+        }
+        // a loanword didn't go through the sound changes between the two languages
+        if self.graph[edge].borrowed {
+            return Ok(steps);
+        }
+        if let Some((etymon, derived)) = self.graph.edge_endpoints(edge) {
+            let from = &self.graph[etymon].language;
+            let to = self.derived_language(derived, from);
+            let edge_applied = steps.iter().any(|s| s.applied && s.input != s.output);
+            for pair in self.language_pair_transforms.iter().filter(|p| &p.from == from && p.to == to) {
+                if self.transform_precedence == TransformPrecedence::Override && edge_applied {
+                    trace!("skipping transforms from {} to {}, as an edge transform was applied", from, to);
+                    break;
+                }
+                for trans in &pair.transforms {
+                    steps.push(run(trans, TransformLayer::LanguagePair, working)?);
+                }
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Apply the transforms between an etymon and a word derived from it to the given lexis, without changing the tree,
+    /// returning a step for every transform that was checked. Returns None if the word is not derived from the etymon.
+    pub fn apply_edge(&self, etymon_id: &str, id: &str, working: &mut Lexis) -> Result<Option<Vec<DerivationStep>>, TransformError> {
+        let find = |id: &str| self.graph.node_indices().find(|n| self.graph[*n].id == id);
+        let edge = match (find(etymon_id), find(id)) {
+            (Some(etymon), Some(derived)) => self.graph.find_edge(etymon, derived),
+            _ => None
+        };
+        match edge {
+            Some(edge) => Ok(Some(self.edge_steps(edge, working)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Return the transforms that changed the lexis with the given ID, and the layer each came from.
+    /// This should be called after compute_lexicon().
+    pub fn applied_rules(&self, id: &str) -> Vec<AppliedRule> {
+        self.graph.node_indices().find(|n| self.graph[*n].id == id)
+        .and_then(|n| self.applied_rules.get(&n).cloned()).unwrap_or_default()
+    }
+
+    /// Return the words directly derived from the lexis with the given ID
+    pub fn descendants(&self, id: &str) -> Vec<Lexis> {
+        match self.graph.node_indices().find(|n| self.graph[*n].id == id) {
+            Some(node) => self.descendants_of(node),
+            None => Vec::new()
+        }
+    }
+
+    fn descendants_of(&self, node: NodeIndex) -> Vec<Lexis> {
+        self.graph.neighbors_directed(node, Outgoing).map(|n| self.graph[n].clone()).collect()
+    }
+
+    /// Returns true if the word matches any pattern in the blacklist
+    pub fn is_blacklisted(&self, word: &Lemma) -> bool {
+        let raw = word.string_without_sep();
+        self.blacklist.iter().any(|b| b.is_match(&raw))
+    }
+
+    /// Return the direct etymons of the lexis with the given ID, in agglutination order.
+    pub fn etymons(&self, id: &str) -> Vec<Lexis> {
+        match self.graph.node_indices().find(|n| self.graph[*n].id == id) {
+            Some(node) => self.etymons_of(node),
+            None => Vec::new()
+        }
+    }
+
+    fn etymons_of(&self, node: NodeIndex) -> Vec<Lexis> {
+        let mut edges: Vec<(Option<i32>, NodeIndex)> = self.graph.edges_directed(node, Incoming)
+        .map(|e| (e.weight().agglutination_order, e.source())).collect();
+        edges.sort_by_key(|(order, n)| (*order, self.graph[*n].id.clone()));
+        edges.into_iter().map(|(_, n)| self.graph[n].clone()).collect()
+    }
+
+    /// Return the earliest ancestors of the lexis with the given ID; that is, any upstream words that have no etymons of their own.
+    /// A word with no etymology will return an empty vector.
+    pub fn root_ancestors(&self, id: &str) -> Vec<Lexis> {
+        match self.graph.node_indices().find(|n| self.graph[*n].id == id) {
+            Some(node) => self.root_ancestors_of(node),
+            None => Vec::new()
+        }
+    }
+
+    fn root_ancestors_of(&self, start: NodeIndex) -> Vec<Lexis> {
+        let mut roots: Vec<NodeIndex> = Vec::new();
+        let mut seen: Vec<NodeIndex> = vec![start];
+        let mut pending: Vec<NodeIndex> = self.graph.neighbors_directed(start, Incoming).collect();
+        while let Some(node) = pending.pop() {
+            if seen.contains(&node) {
+                continue;
+            }
+            seen.push(node);
+            let upstream: Vec<NodeIndex> = self.graph.neighbors_directed(node, Incoming).collect();
+            if upstream.is_empty() {
+                roots.push(node);
+            }
+            pending.extend(upstream);
+        }
+        roots.into_iter().map(|n| self.graph[n].clone()).collect()
+    }
+
+    /// Return the number of etymological steps between the lexis with the given ID and its most distant ancestor.
+    /// A word with no etymology has a depth of 0.
+    pub fn derivation_depth(&self, id: &str) -> usize {
+        match self.graph.node_indices().find(|n| self.graph[*n].id == id) {
+            Some(node) => self.depth_from_node(node, &mut Vec::new(), &mut HashMap::new()),
+            None => 0
+        }
+    }
+
+    /// Index the words of the tree by ID, for looking up the etymology of many words without searching the whole tree for each one.
+    pub fn index(&self) -> WordIndex<'_> {
+        let nodes = self.graph.node_indices().map(|n| (self.graph[n].id.as_str(), n)).collect();
+        WordIndex { tree: self, nodes, depths: RefCell::new(HashMap::new()) }
+    }
+
+    /// Return the IDs of words that have no word, and never will: words without a word, a generate key or an etymology,
+    /// along with words whose etymons have no words of their own. This should be called after compute_lexicon().
+    pub fn orphans(&self) -> Vec<String> {
+        let mut found: Vec<String> = self.graph.node_indices()
+        .filter(|n| self.graph[*n].word.is_none())
+        .map(|n| self.graph[n].id.clone()).collect();
+        found.sort();
+        found
+    }
+
+    /// Return the IDs of words whose word was created from the phonology rules, rather than written or derived from an etymon.
+    /// This should be called after compute_lexicon().
+    pub fn generated(&self) -> Vec<String> {
+        let mut found: Vec<String> = self.generated.iter()
+        .filter(|n| self.graph.neighbors_directed(**n, Incoming).next().is_none())
+        .map(|n| self.graph[*n].id.clone()).collect();
+        found.sort();
+        found
+    }
+
+    /// Return the IDs of words with no etymology, that no other word is derived from.
+    pub fn unused_roots(&self) -> Vec<String> {
+        let mut found: Vec<String> = self.graph.node_indices()
+        .filter(|n| self.graph.neighbors_directed(*n, Incoming).next().is_none() && self.graph.neighbors_directed(*n, Outgoing).next().is_none())
+        .map(|n| self.graph[n].id.clone()).collect();
+        found.sort();
+        found
+    }
+
+    /// Print a graphviz representation of the language, filling the nodes of the given word IDs with the matching color.
+    pub fn graphviz_highlighted(&self, colors: &HashMap<String, String>) -> String {
+        let edge_attrs = |_, edge: EdgeReference<TreeEtymology>| edge_label(edge.weight());
+        let node_attrs = |_, (_, lex): (NodeIndex, &Lexis)| match colors.get(&lex.id) {
+            Some(color) => format!("style=filled fillcolor=\"{}\"", color),
+            None => String::new()
+        };
+        format!("{:?}", Dot::with_attr_getters(&self.graph, &[Config::EdgeNoLabel], &edge_attrs, &node_attrs))
+    }
+
+    /// the depth of a node, reusing the depths of any ancestors that were already found
+    fn depth_from_node(&self, node: NodeIndex, path: &mut Vec<NodeIndex>, depths: &mut HashMap<NodeIndex, usize>) -> usize {
+        if let Some(depth) = depths.get(&node) {
+            return *depth
+        }
+        // guard against cycles in the graph
+        if path.contains(&node) {
+            return 0
+        }
+        path.push(node);
+        let depth = self.graph.neighbors_directed(node, Incoming)
+        .map(|n| self.depth_from_node(n, path, depths) + 1)
+        .max().unwrap_or(0);
+        path.pop();
+        depths.insert(node, depth);
+        depth
+    }
+
+    /// Trace how the lexis with the given ID was derived, including each etymon, and the input and output of every transform between them.
+    /// This should be called after compute_lexicon(). Returns None if no lexis with the ID exists.
+    pub fn explain(&self, id: &str) -> Result<Option<Derivation>, TransformError> {
+        match self.graph.node_indices().find(|n| self.graph[*n].id == id) {
+            Some(node) => Ok(Some(self.explain_node(node, &mut Vec::new())?)),
+            None => Ok(None)
+        }
+    }
+
+    fn explain_node(&self, node: NodeIndex, path: &mut Vec<NodeIndex>) -> Result<Derivation, TransformError> {
+        let lex = &self.graph[node];
+        let mut derivation = Derivation { id: lex.id.clone(), language: lex.language.clone(), 
+            word: lex.word.clone(), word_override: lex.word_override.clone(),
+            definition: Some(lex.definition.clone()).filter(|d| !d.is_empty()), reconstructed: lex.reconstructed, etymons: Vec::new(), 
+            applied: self.applied_rules.get(&node).cloned().unwrap_or_default() };
+        // guard against cycles in the graph
+        if path.contains(&node) {
+            return Ok(derivation)
+        }
+        path.push(node);
+        for edge in self.graph.edges_directed(node, Incoming) {
+            let mut working = self.graph[edge.source()].clone();
+            let steps = self.edge_steps(edge.id(), &mut working)?;
+            derivation.etymons.push(DerivationEdge { 
+                etymon: self.explain_node(edge.source(), path)?, 
+                agglutination_order: edge.weight().agglutination_order, 
+                input: self.graph[edge.source()].word.clone(), 
+                output: working.word, 
+                semantic_shift: edge.weight().semantic_shift.clone(),
+                steps 
+            });
+        }
+        path.pop();
+        derivation.etymons.sort_by_key(|e| (e.agglutination_order, e.etymon.id.clone()));
+        Ok(derivation)
+    }
+
     /// Reduce the language graph to a vector of words that match the provided function. 
     /// Returns a vector of tuples for each matching word and any associated etymological data.
     pub fn to_vec_etymons<F>(&self, filter: F) -> Vec<(Lexis, Etymology)> 
@@ -442,13 +1209,23 @@ impl LanguageTree {
                     for etymon in self.graph.neighbors_directed(node, Incoming){
                         let ety_link: Vec<EdgeReference<TreeEtymology>> = self.graph.edges_connecting(etymon, node).collect();
                         let mut transform_name: Vec<String> = Vec::new();
+                        let mut resolved: Vec<Transform> = Vec::new();
                         let mut agg_order: Option<i32> = None;
-                        if let Some(trans_link) = ety_link.get(0){
+                        let mut borrowed = false;
+                        let mut semantic_shift: Option<SemanticShift> = None;
+                        let mut metadata: Option<HashMap<String, String>> = None;
+                        if let Some(trans_link) = ety_link.first(){
                             let trans_data =  trans_link.weight();
                             transform_name =  trans_data.names();
+                            resolved = trans_data.transforms.clone();
                             agg_order = trans_data.agglutination_order;
+                            borrowed = trans_data.borrowed;
+                            semantic_shift = trans_data.semantic_shift.clone();
+                            metadata = Some(trans_data.metadata.clone()).filter(|m| !m.is_empty());
                         }
-                        etymon_list.push(Edge{etymon: self.graph[etymon].id.clone(), transforms: Some(transform_name), agglutination_order: agg_order});
+                        etymon_list.push(Edge{etymon: self.graph[etymon].id.clone(), transforms: Some(transform_name), 
+                            agglutination_order: agg_order, resolved_transforms: Some(resolved), borrowed: Some(true).filter(|_| borrowed),
+                            semantic_shift, metadata});
                     }
                     word_vec.push((self.graph[node].clone(), Etymology{etymons: etymon_list}));
             }
@@ -461,10 +1238,74 @@ impl LanguageTree {
 }
 
 
-fn join_string_vectors(words: &mut [(i32, Lemma)]) -> Lemma{
+/// join the upstream morphemes in agglutination order. Any joiner is treated as the start of the right-hand morpheme when applying sandhi rules.
+fn join_string_vectors(words: &mut [(i32, Lemma)], rules: &Agglutination, seam_rules: &[SandhiRule]) -> Lemma{
     words.sort_by_key(|k| k.0);
-    let merged: Vec<String> = words.iter().flat_map(|s| s.1.clone().chars()).collect();
-    merged.into()
+    let mut merged = Lemma::default();
+    for (count, (_, morpheme)) in words.iter().enumerate() {
+        if count == 0 {
+            merged = morpheme.clone();
+            continue;
+        }
+        let mut right = Lemma::default();
+        if let Some(joiner) = &rules.joiner {
+            right.push(joiner.clone());
+        }
+        right.push(morpheme.clone());
+        merged = sandhi::join(merged, right, seam_rules);
+    }
+    merged
+}
+
+/// label a graphviz edge with the metadata of the etymology, one `key: value` line per field, sorted by key
+fn edge_label(etymology: &TreeEtymology) -> String {
+    if etymology.metadata.is_empty() {
+        return String::new();
+    }
+    let mut fields: Vec<(&String, &String)> = etymology.metadata.iter().collect();
+    fields.sort();
+    let label: Vec<String> = fields.into_iter().map(|(k, v)| format!("{}: {}", k, v).replace('\\', "\\\\").replace('"', "\\\"")).collect();
+    format!("label=\"{}\"", label.join("\\n"))
+}
+
+/// The words of a language tree indexed by ID, returned by LanguageTree::index().
+/// Lookups take constant time instead of searching the tree, and derivation depths are remembered once they're found,
+/// so renderers can look up the etymology of every word in a large tree.
+pub struct WordIndex<'a> {
+    tree: &'a LanguageTree,
+    nodes: HashMap<&'a str, NodeIndex>,
+    depths: RefCell<HashMap<NodeIndex, usize>>
+}
+
+impl WordIndex<'_> {
+    /// The same as LanguageTree::derivation_depth()
+    pub fn derivation_depth(&self, id: &str) -> usize {
+        match self.nodes.get(id) {
+            Some(node) => self.tree.depth_from_node(*node, &mut Vec::new(), &mut self.depths.borrow_mut()),
+            None => 0
+        }
+    }
+
+    /// The same as LanguageTree::etymons()
+    pub fn etymons(&self, id: &str) -> Vec<Lexis> {
+        self.nodes.get(id).map(|node| self.tree.etymons_of(*node)).unwrap_or_default()
+    }
+
+    /// The same as LanguageTree::descendants()
+    pub fn descendants(&self, id: &str) -> Vec<Lexis> {
+        self.nodes.get(id).map(|node| self.tree.descendants_of(*node)).unwrap_or_default()
+    }
+
+    /// The same as LanguageTree::root_ancestors()
+    pub fn root_ancestors(&self, id: &str) -> Vec<Lexis> {
+        self.nodes.get(id).map(|node| self.tree.root_ancestors_of(*node)).unwrap_or_default()
+    }
+
+    /// The same as LanguageTree::edge()
+    pub fn edge(&self, etymon_id: &str, id: &str) -> Option<&TreeEtymology> {
+        let edge = self.tree.graph.find_edge(*self.nodes.get(etymon_id)?, *self.nodes.get(id)?)?;
+        self.tree.graph.edge_weight(edge)
+    }
 }
 
 #[cfg(test)]
@@ -473,8 +1314,9 @@ mod tests {
     use std::collections::HashMap;
 
     use log::LevelFilter;
-    use crate::{kirum::{LanguageTree, Lexis}, transforms::{Transform, LetterArrayValues, TransformFunc, self, LetterValues, GlobalTransform}, matching::{LexisMatch, Value, ValueMatch, EqualValue}, lexcreate::LexPhonology, lemma::Lemma};
+    use crate::{errors::TransformError, kirum::{LanguageTree, Lexis, Agglutination, MetadataInheritance, LanguageInheritance, TransformPrecedence}, transforms::{Transform, LetterArrayValues, TransformFunc, self, LetterValues, GlobalTransform, LanguagePairTransform}, word::TransformLayer, matching::{LexisMatch, Value, ValueMatch, EqualValue}, lexcreate::LexPhonology, lemma::Lemma, sandhi::SandhiRule};
     use env_logger::Builder;
+    use regex::Regex;
 
 
     fn create_basic_words() -> LanguageTree {
@@ -529,10 +1371,77 @@ mod tests {
          vec![Transform{name: "test".to_string(), lex_match: None, transforms: vec![TransformFunc::Prefix { value: Lemma::from("sur") }]}], None);
 
         test_tree.compute_lexicon().unwrap();
-        let test_word = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet".to_string());
+        let test_word = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet");
         assert_eq!(test_word[0].0.word.clone().unwrap(), Lemma::from("kasurauwarh"))
     }
 
+    #[test]
+    fn test_language_pair_precedence() {
+        let mut test_tree = create_basic_with_globals();
+        let derivative_lang = Lexis{id: "derivative_lang".to_string(), 
+            word: None, lexis_type: "word".to_string(), language: "New Gauntlet".to_string(), ..Default::default()};
+        test_tree.connect_etymology_id(derivative_lang, "derivative_two".to_string(),
+         vec![Transform{name: "loanword".to_string(), lex_match: None, transforms: vec![TransformFunc::Loanword]}], None);
+        test_tree.language_pair_transforms = vec![LanguagePairTransform{from: "gauntlet".to_string(), to: "New Gauntlet".to_string(), 
+            transforms: vec![Transform{name: "pair".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: "i".into() }]}]}];
+
+        // by default, every layer applies
+        let mut stacked = test_tree.clone();
+        stacked.compute_lexicon().unwrap();
+        assert_eq!(stacked.get_by_id("derivative_lang").unwrap().word, Some(Lemma::from("kaauwarhi")));
+        let layers: Vec<TransformLayer> = stacked.applied_rules("derivative_lang").into_iter().map(|r| r.layer).collect();
+        assert_eq!(layers, vec![TransformLayer::LanguagePair, TransformLayer::Global]);
+
+        // the language pair transform changed the word, so the global transform is skipped
+        test_tree.transform_precedence = TransformPrecedence::Override;
+        test_tree.compute_lexicon().unwrap();
+        assert_eq!(test_tree.get_by_id("derivative_lang").unwrap().word, Some(Lemma::from("auwarhi")));
+        let trace = test_tree.explain("derivative_lang").unwrap().unwrap();
+        assert_eq!(trace.etymons[0].steps[1].layer, TransformLayer::LanguagePair);
+    }
+
+    #[test]
+    fn test_borrowed_skips_language_pair() {
+        let mut test_tree = create_basic_with_globals();
+        let inherited = Lexis{id: "inherited".to_string(), language: "New Gauntlet".to_string(), ..Default::default()};
+        let loanword = Lexis{id: "loanword".to_string(), language: "New Gauntlet".to_string(), ..Default::default()};
+        test_tree.connect_etymology_id(inherited, "derivative_two".to_string(), Vec::new(), None);
+        test_tree.connect_etymology_id(loanword, "derivative_two".to_string(),
+            vec![Transform{name: "adapt".to_string(), lex_match: None, transforms: vec![TransformFunc::Prefix { value: "o".into() }]}], None);
+        let loan_edge = test_tree.graph.edge_indices().find(|e| test_tree.graph[test_tree.graph.edge_endpoints(*e).unwrap().1].id == "loanword").unwrap();
+        test_tree.graph[loan_edge].borrowed = true;
+        test_tree.language_pair_transforms = vec![LanguagePairTransform{from: "gauntlet".to_string(), to: "New Gauntlet".to_string(),
+            transforms: vec![Transform{name: "pair".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: "i".into() }]}]}];
+
+        test_tree.compute_lexicon().unwrap();
+        assert_eq!(test_tree.get_by_id("inherited").unwrap().word, Some(Lemma::from("kaauwarhi")));
+        assert_eq!(test_tree.get_by_id("loanword").unwrap().word, Some(Lemma::from("kaoauwarh")));
+        let layers: Vec<TransformLayer> = test_tree.applied_rules("loanword").into_iter().map(|r| r.layer).collect();
+        assert!(!layers.contains(&TransformLayer::LanguagePair));
+    }
+
+    #[test]
+    fn test_orphans_and_unused_roots() {
+        let mut test_tree = create_basic_words();
+        let orphan = Lexis{id: "orphan".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        let orphan_child = Lexis{id: "orphan_child".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        let unused = Lexis{id: "unused".to_string(), word: Some("kir".into()), language: "gauntlet".to_string(), ..Default::default()};
+        test_tree.connect_etymology(orphan_child, orphan, vec![Transform{name: "test".to_string(), lex_match: None, 
+            transforms: vec![TransformFunc::Prefix { value: "a".into() }]}], None);
+        test_tree.add_lexis(unused);
+        test_tree.compute_lexicon().unwrap();
+
+        assert_eq!(test_tree.orphans(), vec!["orphan".to_string(), "orphan_child".to_string()]);
+        assert_eq!(test_tree.unused_roots(), vec!["unused".to_string()]);
+
+        let dot = test_tree.graphviz_highlighted(&HashMap::from([("unused".to_string(), "gray".to_string())]));
+        assert!(dot.contains("fillcolor=\"gray\""));
+
+        let edge = test_tree.edge_mut("orphan", "orphan_child").unwrap();
+        edge.metadata = HashMap::from([("source".to_string(), "Glossary \"B\"".to_string()), ("date".to_string(), "1100".to_string())]);
+        assert!(test_tree.graphviz().contains("label=\"date: 1100\\nsource: Glossary \\\"B\\\"\""), "{}", test_tree.graphviz());
+    }
+
     #[test]
     fn test_metadata_derives(){
         let mut test_tree = create_basic_with_globals();
@@ -561,7 +1470,7 @@ mod tests {
                 "derivative_two" => {
                     assert_eq!(HashMap::from([("test".to_string(), "t".to_string()), ("derivative".to_string(), "one".to_string())]), word.historical_metadata)
                 }
-                _ => {assert!(false, "bad map value in test")}
+                _ => {panic!("bad map value in test")}
             }
         }
     }
@@ -610,7 +1519,7 @@ mod tests {
          vec![Transform{name: "test".to_string(), lex_match: None, transforms: vec![TransformFunc::Loanword]}], None);
 
         test_tree.compute_lexicon().unwrap();
-        let test_word = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet".to_string());
+        let test_word = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet");
         assert_eq!(test_word[0].0.word.clone().unwrap(), Lemma::from("kaauwarh"))
     }
 
@@ -636,11 +1545,11 @@ mod tests {
         None);
 
         test_tree.compute_lexicon().unwrap();
-        let test_words = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet".to_string());
-        assert_eq!(test_words.iter().find(|e| e.0.word == Some(Lemma::from("kaauwarh"))).is_some(), true);
+        let test_words = test_tree.to_vec_etymons(|f| f.language == "New Gauntlet");
+        assert!(test_words.iter().find(|e| e.0.word == Some(Lemma::from("kaauwarh"))).is_some());
         
 
-        assert_eq!(test_words.iter().find(|e| e.0.word == Some(Lemma::from("kaauwarh`sh"))).is_some(), true);
+        assert!(test_words.iter().find(|e| e.0.word == Some(Lemma::from("kaauwarh`sh"))).is_some());
        
     }
 
@@ -669,6 +1578,7 @@ mod tests {
                     "CCC".try_into().unwrap()
                 ])
             ]),
+            fallbacks: HashMap::new()
         };
         let parent = Lexis{id: "parent".to_string(), word:None, 
         language: "gauntlet".to_string(), lexis_type: "root".to_string(), word_create: Some("root".to_string()), ..Default::default()};
@@ -704,6 +1614,29 @@ mod tests {
         assert_eq!(der_two.unwrap().word.unwrap().string_without_sep(), reconstructed);
     }
 
+    #[test]
+    fn test_generated() {
+        let mut tree = LanguageTree::new();
+        tree.word_creator_phonology = LexPhonology{
+            groups: HashMap::from([('C', vec!["k".try_into().unwrap()]), ('V', vec!["a".try_into().unwrap()])]),
+            lexis_types: HashMap::from([("root".to_string(), vec!["CV".try_into().unwrap()])]),
+            fallbacks: HashMap::new()
+        };
+        let generated = Lexis{id: "generated".to_string(), word_create: Some("root".to_string()), ..Default::default()};
+        let written = Lexis{id: "written".to_string(), word: Some("ri".into()), word_create: Some("root".to_string()), ..Default::default()};
+        tree.connect_etymology(Lexis{id: "derived".to_string(), ..Default::default()}, generated, Vec::new(), None);
+        tree.add_lexis(written);
+        tree.compute_lexicon().unwrap();
+        assert_eq!(tree.generated(), vec!["generated".to_string()]);
+        // computing again doesn't forget which words were generated
+        tree.compute_lexicon().unwrap();
+        assert_eq!(tree.generated(), vec!["generated".to_string()]);
+
+        let mut restored = LanguageTree::new();
+        assert!(restored.restore_computed(tree.computed_graph()));
+        assert_eq!(restored.generated(), vec!["generated".to_string()]);
+    }
+
     #[test]
     fn test_basic_tree(){
         let mut tree = create_basic_words();
@@ -714,9 +1647,9 @@ mod tests {
         println!("got words: {:?}", out);
         let out_words: Vec<String> = out.into_iter().map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
 
-        assert_eq!(out_words.contains(&"wrh".to_string()), true);
-        assert_eq!(out_words.contains(&"warh".to_string()), true);
-        assert_eq!(out_words.contains(&"auwarh".to_string()), true);
+        assert!(out_words.contains(&"wrh".to_string()));
+        assert!(out_words.contains(&"warh".to_string()));
+        assert!(out_words.contains(&"auwarh".to_string()));
         
     }
 
@@ -736,10 +1669,446 @@ mod tests {
         println!("got words: {:?}", out);
         let out_words: Vec<String> = out.into_iter().map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
 
-        assert_eq!(out_words.contains(&"maarkwarh".to_string()), true);
+        assert!(out_words.contains(&"maarkwarh".to_string()));
        // tree.connect_etymology(lex, etymon, trans, agglutination_order)
     }
 
+    #[test]
+    fn test_agglutination_joiner(){
+        let mut tree = create_basic_words();
+        let parent_part = Lexis{id: "parent_part".to_string(), word: Some("maark".into()), language: "gauntlet".to_string(), lexis_type: "word".to_string(), ..Default::default()};
+        let hyphenated = Lexis{id: "hyphenated".to_string(), word: None, ..parent_part.clone()};
+        let linked = Lexis{id: "linked".to_string(), word: None,
+            agglutination: Some(Agglutination { joiner: Some("o".into()), sandhi: true }), ..parent_part.clone()};
+
+        let agg_transform = vec![Transform{name: "agg_transform".to_string(), lex_match: None, transforms: vec![TransformFunc::Loanword]}];
+
+        tree.agglutination = Agglutination { joiner: Some("-".into()), sandhi: true };
+        tree.connect_etymology(hyphenated.clone(), parent_part.clone(), agg_transform.clone(), Some(0));
+        tree.connect_etymology_id(hyphenated, "derivative_one".to_string(), agg_transform.clone(), Some(1));
+        tree.connect_etymology(linked.clone(), parent_part, agg_transform.clone(), Some(0));
+        tree.connect_etymology_id(linked, "derivative_one".to_string(), agg_transform, Some(1));
+
+        tree.compute_lexicon().unwrap();
+        let out_words: Vec<String> = tree.to_vec().into_iter().map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
+
+        assert!(out_words.contains(&"maark-warh".to_string()));
+        assert!(out_words.contains(&"maarkowarh".to_string()));
+    }
+
+    #[test]
+    fn test_agglutination_sandhi(){
+        let mut tree = create_basic_words();
+        let parent_part = Lexis{id: "parent_part".to_string(), word: Some("maarw".into()), language: "gauntlet".to_string(), lexis_type: "word".to_string(), ..Default::default()};
+        let combined = Lexis{id: "combined".to_string(), word: None, ..parent_part.clone()};
+        let no_sandhi = Lexis{id: "no_sandhi".to_string(), word: None, 
+            agglutination: Some(Agglutination { joiner: None, sandhi: false }), ..parent_part.clone()};
+
+        let agg_transform = vec![Transform{name: "agg_transform".to_string(), lex_match: None, transforms: vec![TransformFunc::Loanword]}];
+        tree.sandhi_rules.insert("gauntlet".to_string(), vec![SandhiRule{left: "w".into(), right: "w".into(), replace: "v".into()}]);
+
+        tree.connect_etymology(combined.clone(), parent_part.clone(), agg_transform.clone(), Some(0));
+        tree.connect_etymology_id(combined, "derivative_one".to_string(), agg_transform.clone(), Some(1));
+        tree.connect_etymology(no_sandhi.clone(), parent_part, agg_transform.clone(), Some(0));
+        tree.connect_etymology_id(no_sandhi, "derivative_one".to_string(), agg_transform, Some(1));
+
+        tree.compute_lexicon().unwrap();
+        assert_eq!(tree.get_by_id("combined").unwrap().word.unwrap(), Lemma::from("maarvarh"));
+        assert_eq!(tree.get_by_id("no_sandhi").unwrap().word.unwrap(), Lemma::from("maarwwarh"));
+    }
+
+    #[test]
+    fn test_metadata_preserve() {
+        let mut test_tree = create_basic_words();
+        test_tree.metadata_inheritance = MetadataInheritance::Preserve;
+        let override_word = Lexis{id: "override".to_string(), word: None, language: "gauntlet".to_string(),
+            historical_metadata: HashMap::from([("test".to_string(), "own".to_string())]), ..Default::default()};
+        test_tree.connect_etymology_id(override_word, "parent".to_string(), vec![], None);
+        test_tree.compute_lexicon().unwrap();
+
+        assert_eq!(test_tree.get_by_id("override").unwrap().historical_metadata.get("test"), Some(&"own".to_string()));
+        assert_eq!(test_tree.get_by_id("derivative_one").unwrap().historical_metadata.get("test"), Some(&"t".to_string()));
+    }
+
+    #[test]
+    fn test_metadata_no_inheritance() {
+        let mut test_tree = create_basic_words();
+        test_tree.metadata_inheritance = MetadataInheritance::None;
+        let no_metadata = Lexis{id: "no_metadata".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        test_tree.connect_etymology_id(no_metadata, "parent".to_string(), vec![], None);
+        test_tree.compute_lexicon().unwrap();
+
+        assert!(test_tree.get_by_id("no_metadata").unwrap().historical_metadata.is_empty());
+    }
+
+    #[test]
+    fn test_collation_sort() {
+        let mut tree = LanguageTree::new();
+        for (id, word) in [("one", "ba"), ("two", "ab"), ("three", "cb")] {
+            tree.add_lexis(Lexis{id: id.to_string(), word: Some(word.into()), ..Default::default()});
+        }
+        tree.collation = Some(vec!["c".to_string(), "b".to_string(), "a".to_string()]);
+        tree.compute_lexicon().unwrap();
+
+        let sorted: Vec<String> = tree.to_vec().into_iter().map(|l| l.id).collect();
+        assert_eq!(sorted, vec!["three".to_string(), "one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    fn test_language_inheritance() {
+        let parent = Lexis{id: "parent".to_string(), word: Some("wrh".into()), language: "gauntlet".to_string(), ..Default::default()};
+        let child = Lexis{id: "child".to_string(), ..Default::default()};
+        let grandchild = Lexis{id: "grandchild".to_string(), ..Default::default()};
+        let explicit = Lexis{id: "explicit".to_string(), language: "fluent".to_string(), ..Default::default()};
+
+        let mut tree = LanguageTree::new();
+        tree.connect_etymology(child.clone(), parent.clone(), Vec::new(), None);
+        tree.connect_etymology(grandchild.clone(), child.clone(), Vec::new(), None);
+        tree.connect_etymology(explicit.clone(), parent.clone(), Vec::new(), None);
+        tree.language_inheritance = LanguageInheritance::Etymon;
+        tree.compute_lexicon().unwrap();
+        assert_eq!(tree.get_by_id("grandchild").unwrap().language, "gauntlet");
+        assert_eq!(tree.get_by_id("explicit").unwrap().language, "fluent");
+
+        let mut daughter_tree = LanguageTree::new();
+        daughter_tree.connect_etymology(child, parent, Vec::new(), None);
+        daughter_tree.connect_etymology(grandchild, Lexis{id: "child".to_string(), ..Default::default()}, Vec::new(), None);
+        daughter_tree.language_inheritance = LanguageInheritance::Daughter;
+        daughter_tree.language_daughters.insert("gauntlet".to_string(), "new gauntlet".to_string());
+        daughter_tree.compute_lexicon().unwrap();
+        assert_eq!(daughter_tree.get_by_id("child").unwrap().language, "new gauntlet");
+        // new gauntlet has no daughter, so the language stays the same
+        assert_eq!(daughter_tree.get_by_id("grandchild").unwrap().language, "new gauntlet");
+    }
+
+    #[test]
+    fn test_grapheme_segmentation() {
+        let parent = Lexis{id: "parent".to_string(), word: Some("chak".into()), language: "gauntlet".to_string(), ..Default::default()};
+        let child = Lexis{id: "child".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        // without the grapheme inventory, "ch" would not be a single letter, and could not be doubled
+        let transform = Transform{name: "double-ch".to_string(), lex_match: None, 
+        transforms: vec![TransformFunc::Double { letter: "ch".to_string(), position: transforms::LetterPlaceType::All }]};
+
+        let mut tree = LanguageTree::new();
+        tree.connect_etymology(child, parent, vec![transform], None);
+        tree.graphemes.insert("gauntlet".to_string(), vec!["ch".to_string()]);
+        tree.compute_lexicon().unwrap();
+
+        let parent_word = tree.get_by_id("parent").unwrap().word.unwrap();
+        assert_eq!(parent_word.chars(), vec!["ch", "a", "k"]);
+        let child_word = tree.get_by_id("child").unwrap().word.unwrap();
+        assert_eq!(child_word.string_without_sep(), "chchak");
+    }
+
+    #[test]
+    fn test_language_collation_sort() {
+        let mut tree = LanguageTree::new();
+        for (id, word, lang) in [("one", "ka", "Old Exemplum"), ("two", "cha", "Old Exemplum"), ("three", "ca", "Old Exemplum")] {
+            tree.add_lexis(Lexis{id: id.to_string(), word: Some(word.into()), language: lang.to_string(), ..Default::default()});
+        }
+        tree.language_collation.insert("Old Exemplum".to_string(), vec!["c".to_string(), "k".to_string(), "ch".to_string(), "a".to_string()]);
+        tree.compute_lexicon().unwrap();
+
+        let sorted: Vec<String> = tree.to_vec().into_iter().map(|l| l.id).collect();
+        assert_eq!(sorted, vec!["three".to_string(), "one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    fn test_seeded_word_create() {
+        let phonology = LexPhonology{
+            groups: HashMap::from([
+                ('C', vec!["h".try_into().unwrap(), "r".try_into().unwrap(), "x".try_into().unwrap(), "k".try_into().unwrap()]),
+            ]),
+            lexis_types: HashMap::from([
+                ("root".to_string(), vec!["CCCCCC".try_into().unwrap()])
+            ]),
+            fallbacks: HashMap::new()
+        };
+        let mut words: Vec<Lemma> = Vec::new();
+        for _ in 0..2 {
+            let mut tree = LanguageTree::new();
+            tree.word_creator_phonology = phonology.clone();
+            tree.seed = Some(42);
+            tree.add_lexis(Lexis{id: "generated".to_string(), word_create: Some("root".to_string()), ..Default::default()});
+            tree.compute_lexicon().unwrap();
+            words.push(tree.get_by_id("generated").unwrap().word.unwrap());
+        }
+        assert_eq!(words[0], words[1]);
+    }
+
+    #[test]
+    fn test_noun_class_from_transform() {
+        let mut tree = create_basic_words();
+        let abstract_noun = Lexis{id: "abstract".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        let explicit_class = Lexis{id: "explicit".to_string(), word: None, language: "gauntlet".to_string(), noun_class: Some("I".to_string()), ..Default::default()};
+        let abstract_transform = vec![Transform{name: "abstract".to_string(), lex_match: None, 
+            transforms: vec![TransformFunc::Postfix { value: "ut".into() }, TransformFunc::NounClass { value: "III".to_string() }]}];
+
+        tree.connect_etymology_id(abstract_noun, "parent".to_string(), abstract_transform.clone(), None);
+        tree.connect_etymology_id(explicit_class, "parent".to_string(), abstract_transform, None);
+        tree.compute_lexicon().unwrap();
+
+        assert_eq!(tree.get_by_id("abstract").unwrap().noun_class, Some("III".to_string()));
+        assert_eq!(tree.get_by_id("explicit").unwrap().noun_class, Some("I".to_string()));
+        assert_eq!(tree.get_by_id("derivative_one").unwrap().noun_class, None);
+    }
+
+    #[test]
+    fn test_word_override() {
+        let mut tree = create_basic_words();
+        let irregular = Lexis{id: "irregular".to_string(), word: None, language: "gauntlet".to_string(), 
+            word_override: Some("went".into()), ..Default::default()};
+        let downstream = Lexis{id: "downstream".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+
+        tree.connect_etymology_id(irregular, "derivative_one".to_string(), 
+        vec![Transform{name: "test".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: "ed".into() }]}], None);
+        tree.connect_etymology_id(downstream, "irregular".to_string(), 
+        vec![Transform{name: "test".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: "s".into() }]}], None);
+        tree.compute_lexicon().unwrap();
+
+        assert_eq!(tree.get_by_id("irregular").unwrap().word.unwrap(), Lemma::from("went"));
+        assert_eq!(tree.get_by_id("downstream").unwrap().word.unwrap(), Lemma::from("wents"));
+        let etymons = tree.to_vec_etymons(|l| l.id == "irregular");
+        assert_eq!(etymons[0].1.etymons[0].etymon, "derivative_one");
+    }
+
+    #[test]
+    fn test_borrowing_iterators() {
+        let mut tree = create_basic_words();
+        tree.compute_lexicon().unwrap();
+        let ids: Vec<&str> = tree.iter().map(|l| l.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&"parent") && ids.contains(&"derivative_two"));
+        assert_eq!((&tree).into_iter().count(), 3);
+
+        let edges: Vec<(&str, &str)> = tree.iter_edges().map(|(etymon, lex, _)| (etymon.id.as_str(), lex.id.as_str())).collect();
+        assert!(edges.contains(&("parent", "derivative_one")));
+        assert!(edges.contains(&("derivative_one", "derivative_two")));
+
+        for lex in tree.iter_mut() {
+            lex.tags.push("checked".to_string());
+        }
+        assert!(tree.to_vec().iter().all(|l| l.tags.contains(&"checked".to_string())));
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut tree = create_basic_words();
+        let reported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_reported = reported.clone();
+        tree.on_progress(move |done, total| hook_reported.lock().unwrap().push((done, total)));
+        tree.compute_lexicon().unwrap();
+        assert_eq!(*reported.lock().unwrap(), vec![(0, 3), (1, 3), (2, 3), (3, 3)]);
+
+        reported.lock().unwrap().clear();
+        tree.generate_daughter_language("daughter".to_string(), Vec::new(), |_| true, |l| Lexis{id: format!("d-{}", l.id), ..l.clone()}).unwrap();
+        assert_eq!(reported.lock().unwrap().last(), Some(&(3, 3)));
+        assert_eq!(tree.len(), 6);
+    }
+
+    #[test]
+    fn test_apply_edge() {
+        let mut tree = create_basic_words();
+        tree.compute_lexicon().unwrap();
+        let mut working = Lexis{id: "test".to_string(), word: Some("krt".into()), ..Default::default()};
+        let steps = tree.apply_edge("parent", "derivative_one", &mut working).unwrap().unwrap();
+        assert_eq!(working.word, Some("kart".into()));
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].transform, "first_transform");
+        assert_eq!(steps[0].input, Some("krt".into()));
+        assert_eq!(tree.get_by_id("derivative_one").unwrap().word, Some("warh".into()));
+        assert!(tree.apply_edge("parent", "derivative_two", &mut working).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_transform_error_location() {
+        let mut tree = create_basic_words();
+        let broken = Transform{name: "broken_transform".to_string(), lex_match: None,
+            transforms: vec![TransformFunc::Prefix { value: "t".into() }, TransformFunc::RhaiScript { file: "testfiles/missing.rhai".to_string() }]};
+        let broken_derivative = Lexis{id: "broken_derivative".to_string(), word: None, ..Default::default()};
+        tree.connect_etymology_id(broken_derivative, "parent".to_string(), vec![broken], None);
+
+        let err = tree.compute_lexicon().unwrap_err();
+        assert_eq!(err.to_string(), "error applying transforms[1] of 'broken_transform' to 'broken_derivative' from 'parent'");
+        match err {
+            TransformError::Transform { source, .. } => assert!(matches!(*source, TransformError::EvalError { file, .. } if file == "testfiles/missing.rhai")),
+            other => panic!("unexpected error: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_restore_computed() {
+        let mut computed = create_basic_words();
+        computed.compute_lexicon().unwrap();
+        let snapshot = serde_json::to_string(&computed.computed_graph()).unwrap();
+
+        let mut restored = create_basic_words();
+        assert!(restored.restore_computed(serde_json::from_str(&snapshot).unwrap()));
+        assert_eq!(restored.to_vec(), computed.to_vec());
+        assert_eq!(restored.etymons("derivative_two")[0].id, "derivative_one");
+        assert_eq!(restored.applied_rules("derivative_one"), computed.applied_rules("derivative_one"));
+
+        let mut other = LanguageTree::new();
+        other.add_lexis(Lexis{id: "unrelated".to_string(), ..Default::default()});
+        assert!(!other.restore_computed(serde_json::from_str(&snapshot).unwrap()));
+        assert_eq!(other.len(), 1);
+    }
+
+    #[test]
+    fn test_graph_relations() {
+        let mut tree = create_basic_words();
+        tree.compute_lexicon().unwrap();
+
+        let etymons: Vec<String> = tree.etymons("derivative_two").into_iter().map(|l| l.id).collect();
+        assert_eq!(etymons, vec!["derivative_one".to_string()]);
+        assert!(tree.etymons("parent").is_empty());
+
+        let desc: Vec<String> = tree.descendants("parent").into_iter().map(|l| l.id).collect();
+        assert!(desc.contains(&"derivative_one".to_string()));
+        assert!(!desc.contains(&"derivative_two".to_string()));
+
+        let roots: Vec<String> = tree.root_ancestors("derivative_two").into_iter().map(|l| l.id).collect();
+        assert_eq!(roots, vec!["parent".to_string()]);
+        assert!(tree.root_ancestors("parent").is_empty());
+
+        assert_eq!(tree.derivation_depth("parent"), 0);
+        assert_eq!(tree.derivation_depth("derivative_one"), 1);
+        assert_eq!(tree.derivation_depth("derivative_two"), 2);
+    }
+
+    #[test]
+    fn test_word_index() {
+        let mut tree = create_basic_words();
+        tree.compute_lexicon().unwrap();
+        let index = tree.index();
+        for id in ["parent", "derivative_one", "derivative_two", "missing"] {
+            assert_eq!(index.etymons(id), tree.etymons(id));
+            assert_eq!(index.descendants(id), tree.descendants(id));
+            assert_eq!(index.root_ancestors(id), tree.root_ancestors(id));
+            assert_eq!(index.derivation_depth(id), tree.derivation_depth(id));
+        }
+        // the depth of derivative_two is found from the remembered depth of derivative_one
+        assert_eq!(index.derivation_depth("derivative_one"), 1);
+        assert_eq!(index.derivation_depth("derivative_two"), 2);
+        assert!(index.edge("parent", "derivative_one").is_some());
+        assert!(index.edge("parent", "derivative_two").is_none());
+    }
+
+    #[test]
+    fn test_alternative_outcomes() {
+        let parent = Lexis{id: "parent".to_string(), word: Some("wrh".into()), language: "gauntlet".to_string(), ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), word: None, language: "gauntlet".to_string(), ..Default::default()};
+        let raw = r#"[{"alternatives": {"outcomes": [[{"prefix": {"value": "au"}}], [{"postfix": {"value": "a"}}], []]}}]"#;
+        let transform = Transform{name: "split".to_string(), lex_match: None, transforms: serde_json::from_str(raw).unwrap()};
+        let mut tree = LanguageTree::new();
+        tree.connect_etymology(derived, parent, vec![transform], None);
+        tree.compute_lexicon().unwrap();
+        // computing again shouldn't add more variants
+        tree.compute_lexicon().unwrap();
+
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.get_by_id("derived").unwrap().word, Some("auwrh".into()));
+        assert_eq!(tree.get_by_id("derived-split-1").unwrap().word, Some("wrha".into()));
+        assert_eq!(tree.get_by_id("derived-split-2").unwrap().word, Some("wrh".into()));
+        let variant_etymons: Vec<String> = tree.etymons("derived-split-1").into_iter().map(|l| l.id).collect();
+        assert_eq!(variant_etymons, vec!["parent".to_string()]);
+    }
+
+    #[test]
+    fn test_alternative_variant_override() {
+        let parent = Lexis{id: "parent".to_string(), word: Some("wrh".into()), ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), word_override: Some("wrah".into()), ..Default::default()};
+        let raw = r#"[{"alternatives": {"outcomes": [[{"prefix": {"value": "au"}}], [{"postfix": {"value": "a"}}]]}}]"#;
+        let transform = Transform{name: "split".to_string(), lex_match: None, transforms: serde_json::from_str(raw).unwrap()};
+        let mut tree = LanguageTree::new();
+        tree.connect_etymology(derived, parent, vec![transform], None);
+        tree.compute_lexicon().unwrap();
+
+        assert_eq!(tree.get_by_id("derived").unwrap().word, Some("wrah".into()));
+        let variant = tree.get_by_id("derived-split-1").unwrap();
+        assert_eq!(variant.word_override, None);
+        assert_eq!(variant.word, Some("wrha".into()));
+    }
+
+    #[test]
+    fn test_alternative_variant_collision() {
+        let parent = Lexis{id: "parent".to_string(), word: Some("wrh".into()), ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), ..Default::default()};
+        let raw = r#"[{"alternatives": {"outcomes": [[{"prefix": {"value": "au"}}], [{"postfix": {"value": "a"}}]]}}]"#;
+        let transform = Transform{name: "split".to_string(), lex_match: None, transforms: serde_json::from_str(raw).unwrap()};
+        let mut tree = LanguageTree::new();
+        tree.connect_etymology(derived, parent, vec![transform], None);
+        tree.add_lexis(Lexis{id: "derived-split-1".to_string(), word: Some("kira".into()), ..Default::default()});
+
+        let err = tree.compute_lexicon().unwrap_err();
+        assert!(matches!(err, TransformError::VariantCollision { id, transform } if id == "derived-split-1" && transform == "split"));
+        assert_eq!(tree.get_by_id("derived-split-1").unwrap().word, Some("kira".into()));
+    }
+
+    #[test]
+    fn test_blacklist_regenerates() {
+        let phonology = LexPhonology{
+            groups: HashMap::from([
+                ('C', vec!["k".try_into().unwrap(), "r".try_into().unwrap()]),
+                ('V', vec!["a".try_into().unwrap(), "i".try_into().unwrap()]),
+            ]),
+            lexis_types: HashMap::from([
+                ("root".to_string(), vec!["CV".try_into().unwrap()])
+            ]),
+            fallbacks: HashMap::new()
+        };
+        let blacklist = vec![Regex::new("^k").unwrap(), Regex::new("a").unwrap()];
+        for seed in 0..10 {
+            let mut tree = LanguageTree::new();
+            tree.word_creator_phonology = phonology.clone();
+            tree.seed = Some(seed);
+            tree.blacklist = blacklist.clone();
+            tree.add_lexis(Lexis{id: "generated".to_string(), word_create: Some("root".to_string()), ..Default::default()});
+            tree.compute_lexicon().unwrap();
+            assert_eq!(tree.get_by_id("generated").unwrap().word, Some("ri".into()));
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut tree = create_basic_words();
+        tree.compute_lexicon().unwrap();
+
+        let trace = tree.explain("derivative_two").unwrap().unwrap();
+        assert_eq!(trace.word, Some("auwarh".into()));
+        assert_eq!(trace.etymons.len(), 1);
+        let edge = &trace.etymons[0];
+        assert_eq!(edge.input, Some("warh".into()));
+        assert_eq!(edge.output, Some("auwarh".into()));
+        assert_eq!(edge.steps[0].transform, "second_transform");
+        assert!(edge.steps[0].applied);
+
+        let parent_edge = &edge.etymon.etymons[0];
+        assert_eq!(parent_edge.etymon.id, "parent");
+        assert_eq!(parent_edge.input, Some("wrh".into()));
+        assert_eq!(parent_edge.steps[0].output, Some("warh".into()));
+        assert!(parent_edge.etymon.etymons.is_empty());
+
+        assert!(tree.explain("not_a_word").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_gloss_fallback() {
+        let glosses: HashMap<String, String> = [("en".to_string(), "garden".to_string()), ("de".to_string(), "Garten".to_string())].into_iter().collect();
+        let multi = Lexis{id: "multi".to_string(), glosses: glosses.clone(), ..Default::default()};
+        assert_eq!(multi.gloss(Some("en")), "garden");
+        // no french gloss, fall back to the first language alphabetically
+        assert_eq!(multi.gloss(Some("fr")), "Garten");
+        assert_eq!(multi.gloss(None), "Garten");
+
+        let with_default = Lexis{id: "with_default".to_string(), definition: "orchard".to_string(), glosses, ..Default::default()};
+        assert_eq!(with_default.gloss(Some("de")), "Garten");
+        assert_eq!(with_default.gloss(Some("fr")), "orchard");
+
+        let single = Lexis{id: "single".to_string(), definition: "orchard".to_string(), ..Default::default()};
+        assert_eq!(single.gloss(Some("en")), "orchard");
+    }
+
     #[test]
     fn test_lexis_overwrite() {
         let proto_word = Lexis{id: "proto_word".to_string(), word: Some("vrh".into()), language: "proto-gauntlet".to_string(), lexis_type: "stem".to_string(), ..Default::default()};
@@ -758,9 +2127,9 @@ mod tests {
 
         println!("{}", tree.graphviz());
 
-        assert_eq!(out_words.contains(&"vrh".to_string()), true);
-        assert_eq!(out_words.contains(&"varh".to_string()), true);
-        assert_eq!(out_words.contains(&"auvarh".to_string()), true);
+        assert!(out_words.contains(&"vrh".to_string()));
+        assert!(out_words.contains(&"varh".to_string()));
+        assert!(out_words.contains(&"auvarh".to_string()));
     }
 
     #[test]
@@ -775,9 +2144,9 @@ mod tests {
         println!("got words: {:?}", out);
         let out_words: Vec<String> = out.into_iter().map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
 
-        assert_eq!(out_words.contains(&"wrh".to_string()), true);
-        assert_eq!(out_words.contains(&"warh".to_string()), true);
-        assert_eq!(out_words.contains(&"auwarh".to_string()), true);
+        assert!(out_words.contains(&"wrh".to_string()));
+        assert!(out_words.contains(&"warh".to_string()));
+        assert!(out_words.contains(&"auwarh".to_string()));
 
     }
 
@@ -797,9 +2166,9 @@ mod tests {
 
         let out_words: Vec<String> = out.into_iter().map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
 
-        assert_eq!(out_words.contains(&"tree".to_string()), true);
-        assert_eq!(out_words.contains(&"frost".to_string()), true);
-        assert_eq!(out_words.contains(&"rain".to_string()), true);
+        assert!(out_words.contains(&"tree".to_string()));
+        assert!(out_words.contains(&"frost".to_string()));
+        assert!(out_words.contains(&"rain".to_string()));
     }
 
     #[test]
@@ -815,7 +2184,8 @@ mod tests {
                 pos: None,
                 lexis_type: Some(Value::Match(crate::matching::ValueMatch::Equals(crate::matching::EqualValue::String("word".to_string())))),
                 archaic: None,
-                tags: None
+                tags: None,
+                noun_class: None
             }),
             transforms: vec![
                 TransformFunc::LetterReplace { letter: LetterValues { old: "w".to_string(), new: "k".to_string() }, replace: transforms::LetterPlaceType::All },
@@ -826,13 +2196,13 @@ mod tests {
         tree.compute_lexicon().unwrap();
 
         tree.generate_daughter_language("High Gauntlet".to_string(), 
-        daughter_transforms, |lex|lex.language == "gauntlet".to_string(), |lex| Lexis {tags: vec!["tested".to_string()], ..lex.clone() }).unwrap();
+        daughter_transforms, |lex|lex.language == "gauntlet", |lex| Lexis {tags: vec!["tested".to_string()], ..lex.clone() }).unwrap();
 
         let out = tree.to_vec();
         println!("got words: {:?}", out);
-        let out_words: Vec<String> = out.into_iter().filter(|lex|lex.tags.len() > 0).map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
+        let out_words: Vec<String> = out.into_iter().filter(|lex|!lex.tags.is_empty()).map(|l| l.word.unwrap_or_default().string_without_sep()).collect();
 
-        assert_eq!(out_words.contains(&"karh".to_string()), true);
-        assert_eq!(out_words.contains(&"akarh".to_string()), true);
+        assert!(out_words.contains(&"karh".to_string()));
+        assert!(out_words.contains(&"akarh".to_string()));
     }
 }
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use rhai::{Array, Dynamic};
 use serde::{Serialize, Deserialize, de::Visitor};
+use schemars::{JsonSchema, SchemaGenerator, Schema, json_schema};
 use unicode_segmentation::UnicodeSegmentation;
 use crate::{errors::LemmaFromError, transforms::{LetterArrayValues, LetterPlaceType}};
 use regex::Regex;
@@ -41,6 +44,22 @@ impl<'de> Deserialize<'de> for Lemma {
     }
 }
 
+impl JsonSchema for Lemma {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Lemma".into()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A word, either as a string, or as an array of letters for letters made of multiple characters",
+            "anyOf": [
+                {"type": "string"},
+                {"type": "array", "items": {"type": "string"}}
+            ]
+        })
+    }
+}
+
 struct LemmaVisitor;
 
 impl<'de> Visitor<'de> for LemmaVisitor {
@@ -121,9 +140,9 @@ impl From<&'static str> for Lemma {
     }
 }
 
-impl std::string::ToString for Lemma {
-    fn to_string(&self) -> String {
-        self.value.clone()
+impl std::fmt::Display for Lemma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.value)
     }
 }
 
@@ -212,11 +231,89 @@ impl Lemma {
         self.value.replace(&rep, "")
     }
 
+    /// Create a key for sorting the lemma according to the given alphabet, where each value is a letter or multi-letter grapheme.
+    /// The lemma is split into letters by matching the longest alphabet entry at each position, so digraphs such as "ch" sort as a single letter.
+    /// Letters not in the alphabet sort after all letters in the alphabet.
+    pub fn sort_key(&self, alphabet: &[String]) -> Vec<(usize, String)> {
+        let raw = self.string_without_sep();
+        let mut key: Vec<(usize, String)> = Vec::new();
+        let mut rest = raw.as_str();
+        while !rest.is_empty() {
+            let found = alphabet.iter().enumerate()
+            .filter(|(_, letter)| !letter.is_empty() && rest.starts_with(letter.as_str()))
+            .max_by_key(|(_, letter)| letter.len());
+            match found {
+                Some((pos, letter)) => {
+                    key.push((pos, String::new()));
+                    rest = &rest[letter.len()..];
+                },
+                None => {
+                    let grapheme = rest.graphemes(true).next().unwrap_or_default();
+                    key.push((alphabet.len(), grapheme.to_string()));
+                    rest = &rest[grapheme.len()..];
+                }
+            }
+        }
+        key
+    }
+
+    /// Re-split the lemma into letters using the given inventory of multi-character letters, such as "ch" or "ng",
+    /// by matching the longest inventory entry at each position. Any text that doesn't match the inventory is split into single characters.
+    /// A lemma that already contains multi-character letters was segmented explicitly, and is returned unchanged.
+    pub fn segment(&self, inventory: &[String]) -> Lemma {
+        if self.clone().into_iter().any(|letter| letter.graphemes(true).count() > 1) {
+            return self.clone()
+        }
+        let raw = self.string_without_sep();
+        let mut letters: Vec<String> = Vec::new();
+        let mut rest = raw.as_str();
+        while !rest.is_empty() {
+            let found = inventory.iter()
+            .filter(|letter| !letter.is_empty() && rest.starts_with(letter.as_str()))
+            .max_by_key(|letter| letter.len())
+            .map(|letter| letter.as_str())
+            .unwrap_or_else(|| rest.graphemes(true).next().unwrap_or_default());
+            letters.push(found.to_string());
+            rest = &rest[found.len()..];
+        }
+        letters.into()
+    }
+
+    /// Transliterate the lemma using a table of letters, by matching the longest key in the table at each position.
+    /// Any text that doesn't match a key in the table is copied unchanged.
+    pub fn transliterate(&self, table: &HashMap<String, String>) -> String {
+        let raw = self.string_without_sep();
+        let mut out = String::new();
+        let mut rest = raw.as_str();
+        while !rest.is_empty() {
+            let found = table.iter()
+            .filter(|(letter, _)| !letter.is_empty() && rest.starts_with(letter.as_str()))
+            .max_by_key(|(letter, _)| letter.len());
+            match found {
+                Some((letter, replace)) => {
+                    out.push_str(replace);
+                    rest = &rest[letter.len()..];
+                },
+                None => {
+                    let grapheme = rest.graphemes(true).next().unwrap_or_default();
+                    out.push_str(grapheme);
+                    rest = &rest[grapheme.len()..];
+                }
+            }
+        }
+        out
+    }
+
     /// Turn the Lemma into a vector of characters
     pub fn chars(self) -> Vec<String> {
         self.into_iter().collect()
     }
 
+    /// Iterate over the characters of the Lemma without copying them
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.value.split(WORD_SEP).filter(|c| !c.is_empty())
+    }
+
     /// Removes the given character from the Lemma
     pub fn remove_char(&mut self, char: &str, remove_type: &LetterPlaceType) {
         self.replace_str(char, "", remove_type);
@@ -233,6 +330,15 @@ impl Lemma {
         self.value = format!("{}{}", prefix.value, self.value)
     }
 
+    /// Inserts a value before the letter at the given index. An index past the end of the Lemma adds the value to the end.
+    pub fn insert(&mut self, idx: usize, inserted: &Lemma) {
+        let mut letters: Vec<String> = self.clone().into();
+        let idx = idx.min(letters.len());
+        let new_letters: Vec<String> = inserted.clone().into();
+        letters.splice(idx..idx, new_letters);
+        self.value = Lemma::from(letters).value
+    }
+
     /// Adds the postfix to the given Lemma
     pub fn add_postfix(&mut self, postfix: &Lemma) {
         self.value = format!("{}{}", self.value, postfix.value)
@@ -394,8 +500,47 @@ fn double_vec(current: Vec<String>, letter: &str, found_pos: Option<usize>, reve
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use crate::{lemma::Lemma, transforms::{LetterPlaceType, LetterArrayValues}};
 
+    #[test]
+    fn test_transliterate() {
+        let table: HashMap<String, String> = [("k", "к"), ("i", "и"), ("r", "р"), ("u", "у"), ("ch", "ч")].iter()
+        .map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let word: Lemma = "kiruchm".into();
+        assert_eq!(word.transliterate(&table), "киручm");
+    }
+
+    #[test]
+    fn test_segment() {
+        let inventory: Vec<String> = ["ch", "ts'", "ng"].iter().map(|l| l.to_string()).collect();
+        let word: Lemma = "chats'ang".into();
+        assert_eq!(word.segment(&inventory).chars(), vec!["ch", "a", "ts'", "a", "ng"]);
+        // explicitly segmented words are left alone
+        let explicit: Lemma = vec!["n", "g", "ch", "a"].into();
+        assert_eq!(explicit.segment(&inventory), explicit);
+    }
+
+    #[test]
+    fn test_iter() {
+        let word: Lemma = vec!["ch", "a", "ts'"].into();
+        assert_eq!(word.iter().collect::<Vec<&str>>(), vec!["ch", "a", "ts'"]);
+        assert_eq!(Lemma::default().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_sort_key_digraphs() {
+        let alphabet: Vec<String> = ["a", "c", "ch", "h", "k"].iter().map(|l| l.to_string()).collect();
+        let word: Lemma = "chak".into();
+        assert_eq!(word.sort_key(&alphabet), vec![(2, String::new()), (0, String::new()), (4, String::new())]);
+        // "ch" sorts after every word starting with "c"
+        let c_word: Lemma = "cak".into();
+        assert!(c_word.sort_key(&alphabet) < word.sort_key(&alphabet));
+        // unknown letters sort last
+        let unknown: Lemma = "xa".into();
+        assert_eq!(unknown.sort_key(&alphabet)[0], (5, "x".to_string()));
+    }
+
     #[test]
     fn test_char_array() {
         let mut vec_word: Lemma = vec!["k", "i", "r", "u", "m"].into();
@@ -429,7 +574,7 @@ mod tests {
         assert_eq!(vec_word.string_without_sep(), "kerum".to_string());
 
         let mut str_word: Lemma = "kirum".into();
-        str_word.replace("i".into(), "e".into(), &LetterPlaceType::All);
+        str_word.replace("i", "e", &LetterPlaceType::All);
 
         assert_eq!(str_word.string_without_sep(), "kerum".to_string());
     }
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
-use rand::seq::SliceRandom;
-use crate::{lemma::Lemma, errors::{self, PhoneticParsingError}};
+use rand::{seq::SliceRandom, Rng};
+use crate::{lemma::Lemma, errors::{self, PhoneticParsingError}, sorted::sorted_map, xsampa};
 use serde::{Deserialize, Serialize, de::{Visitor, self, Unexpected}};
+use serde_with::{serde_as, skip_serializing_none, OneOrMany};
+use schemars::JsonSchema;
 
 /// Carries a set of phonological and letter groupings that taken together, can generate random words
 /// that match the given phonetics
-#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug, JsonSchema)]
+#[serde(try_from = "RawLexPhonology")]
 pub struct LexPhonology {
     /// May contain a map of any kind of phonetic value, syllables, phonemes, etc.
     /// The keys of the hashmap are referenced in the following lexis_types below.
@@ -15,13 +18,106 @@ pub struct LexPhonology {
     /// C = v b r t h # The available consonants
     /// V = i u o y e # The available vowels
     /// S = CVC CVV VVC # The possible syllable structures
+    #[serde(serialize_with = "sorted_map")]
     pub groups: HashMap<char, Vec<PhoneticReference>>,
     /// A map of `groups` keys or PhoneticReferences. A key value in the map can be referenced
     /// in the `create` field of a Lexis to generate a word.
     /// Expanding on the above example: 
     /// word = S SS SuiS
     /// prefix = S uS Su
-    pub lexis_types: HashMap<String, Vec<PhoneticReference>>
+    /// A lexis type can also reference other lexis types by name, wrapped in angle brackets:
+    /// compound = <word><word> <prefix><word>
+    #[serde(serialize_with = "sorted_map")]
+    pub lexis_types: HashMap<String, Vec<PhoneticReference>>,
+    /// An ordered list of lexis types to try, in order, when a word can't be generated from the given lexis type,
+    /// either because the type doesn't exist, or no generated word met the supplied constraints.
+    /// Fallback types can have fallbacks of their own. Fallbacks aren't weighted, and are always tried in the listed order;
+    /// to weight the patterns of a lexis type, list a pattern more than once.
+    /// For example:
+    /// compound-root = root-cluster root
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted_map")]
+    pub fallbacks: HashMap<String, Vec<String>>
+}
+
+/// The notation used to write the phonemes of a phonetic rule set
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Default, Debug, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Notation {
+    /// Phonemes are written as-is
+    #[default]
+    Unicode,
+    /// Phonemes are written in X-SAMPA, and converted to IPA when the rules are read.
+    /// Since X-SAMPA uses upper-case letters, an upper-case letter is only treated as a reference if it is a key in `groups`.
+    XSampa
+}
+
+/// The serialized form of LexPhonology, before phonetic references are parsed
+#[derive(Deserialize, JsonSchema)]
+struct RawLexPhonology {
+    #[serde(default)]
+    groups: HashMap<char, Vec<String>>,
+    #[serde(default)]
+    lexis_types: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    fallbacks: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    notation: Notation
+}
+
+impl TryFrom<RawLexPhonology> for LexPhonology {
+    type Error = PhoneticParsingError;
+    fn try_from(value: RawLexPhonology) -> Result<Self, Self::Error> {
+        let keys: Vec<char> = value.groups.keys().copied().collect();
+        let parse = |raw: Vec<String>| -> Result<Vec<PhoneticReference>, PhoneticParsingError> {
+            raw.iter().map(|r| match value.notation {
+                Notation::Unicode => PhoneticReference::from_unicode(r, &keys),
+                Notation::XSampa => PhoneticReference::from_xsampa(r, &keys)
+            }).collect()
+        };
+        let mut groups = HashMap::new();
+        for (key, raw) in value.groups {
+            groups.insert(key, parse(raw)?);
+        }
+        let mut lexis_types = HashMap::new();
+        for (key, raw) in value.lexis_types {
+            lexis_types.insert(key, parse(raw)?);
+        }
+        Ok(LexPhonology { groups, lexis_types, fallbacks: value.fallbacks })
+    }
+}
+
+/// The number of times create_constrained_word_with_rng will try to generate a word before giving up
+pub const MAX_CREATE_ATTEMPTS: usize = 1000;
+
+/// Per-word constraints on a word generated from phonetic rules
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug, JsonSchema)]
+pub struct CreateConstraints {
+    /// Letter sequences that must appear in the word
+    #[serde_as(as = "OneOrMany<_>")]
+    #[serde(default)]
+    pub must_contain: Vec<String>,
+    /// Letter sequences that must not appear in the word
+    #[serde_as(as = "OneOrMany<_>")]
+    #[serde(default)]
+    pub forbid: Vec<String>,
+    /// Minimum length of the word, in letters
+    pub min_len: Option<usize>,
+    /// Maximum length of the word, in letters
+    pub max_len: Option<usize>
+}
+
+impl CreateConstraints {
+    /// returns true if the word meets all the constraints
+    pub fn matches(&self, word: &Lemma) -> bool {
+        let raw = word.string_without_sep();
+        let len = word.len();
+        self.must_contain.iter().all(|c| raw.contains(c.as_str())) &&
+        !self.forbid.iter().any(|f| raw.contains(f.as_str())) &&
+        self.min_len.map(|m| len >= m).unwrap_or(true) &&
+        self.max_len.map(|m| len <= m).unwrap_or(true)
+    }
 }
 
 /// A single "reference" to a phonetic value used to generate words.
@@ -32,7 +128,7 @@ impl Serialize for PhoneticReference{
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
-        serializer.serialize_str(&self.to_string())
+        serializer.serialize_str(&self.to_rule_string())
     }
 }
 
@@ -44,6 +140,19 @@ impl<'de> Deserialize<'de> for PhoneticReference {
     }
 }
 
+impl JsonSchema for PhoneticReference {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "PhoneticReference".into()
+    }
+
+    fn json_schema(_gen: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A string of phonemes and upper-case references to groups, i.e. `CVC` or `iSS`",
+            "type": "string"
+        })
+    }
+}
+
 struct PhoneticReferenceVisitor;
 
 impl<'de> Visitor<'de> for PhoneticReferenceVisitor {
@@ -68,18 +177,26 @@ impl<'de> Visitor<'de> for PhoneticReferenceVisitor {
 // the PhoneticReference can be formatted one of two ways:
 // CCCC
 // C C C C
-// the latter helps for cases where we've inserted a weird character that's more than one unicode character
+// the latter helps for cases where we've inserted a weird character that's more than one unicode character.
+// A single phoneme of more than one character is written with a trailing space: `aː `
+// in either format, a lexis type can be referenced by wrapping its name in angle brackets: <root>
 impl TryFrom<&str> for PhoneticReference{
     type Error = PhoneticParsingError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let mut phon_vec: Vec<CreateValue> = Vec::new();
-        if value.matches(' ').count() > 1{
+        if value.contains(' ') {
             for char in value.split_whitespace(){
                 phon_vec.push(char.try_into()?)
             }
         } else {
-            for char in value.chars(){ 
-                phon_vec.push(char.into())
+            let mut chars = value.chars();
+            while let Some(char) = chars.next() {
+                if char == '<' {
+                    let name: String = chars.by_ref().take_while(|c| *c != '>').collect();
+                    phon_vec.push(format!("<{}>", name).as_str().try_into()?)
+                } else {
+                    phon_vec.push(char.into())
+                }
             }
         }
 
@@ -88,13 +205,79 @@ impl TryFrom<&str> for PhoneticReference{
 
 }
 
-impl ToString for PhoneticReference{
-    fn to_string(&self) -> String {
-        let mut acc = String::new();
+impl PhoneticReference {
+    /// Parse a phonetic reference written in unicode, where `keys` are the keys of the groups in the rule set.
+    /// This is the same as parsing the reference on its own, except that a character that's neither lower nor upper case,
+    /// such as the IPA length mark `ː`, is a phoneme unless it's a key in `groups`.
+    pub fn from_unicode(value: &str, keys: &[char]) -> Result<Self, PhoneticParsingError> {
+        let PhoneticReference(parts) = value.try_into()?;
+        Ok(PhoneticReference(parts.into_iter().map(|part| match part {
+            CreateValue::Reference(r) if !r.is_uppercase() && !keys.contains(&r) => CreateValue::Phoneme(r.to_string()),
+            other => other
+        }).collect()))
+    }
+
+    /// Format the reference so it's parsed back into the same values: if any phoneme is more than one character,
+    /// the values are separated with spaces, so they aren't split into a phoneme for each character.
+    pub fn to_rule_string(&self) -> String {
+        if !self.0.iter().any(|p| matches!(p, CreateValue::Phoneme(p) if p.chars().count() > 1)) {
+            return self.to_string()
+        }
+        let parts: Vec<String> = self.0.iter().map(|p| p.to_string()).collect();
+        if parts.len() == 1 {
+            format!("{} ", parts[0])
+        } else {
+            parts.join(" ")
+        }
+    }
+
+    /// Parse a phonetic reference written in X-SAMPA, where `keys` are the keys of the groups in the rule set.
+    /// Any group key or <lexis_type> is treated as a reference, and the text between references is converted to a single IPA phoneme.
+    /// As with unicode phonetic references, values can also be separated with spaces.
+    pub fn from_xsampa(value: &str, keys: &[char]) -> Result<Self, PhoneticParsingError> {
+        let mut phon_vec: Vec<CreateValue> = Vec::new();
+        if value.contains(' ') {
+            for token in value.split_whitespace() {
+                let mut single = token.chars();
+                match (single.next(), single.next()) {
+                    (Some(key), None) if keys.contains(&key) => phon_vec.push(CreateValue::Reference(key)),
+                    _ if token.starts_with('<') => phon_vec.push(token.try_into()?),
+                    _ => phon_vec.push(CreateValue::Phoneme(xsampa::to_ipa(token)))
+                }
+            }
+            return Ok(PhoneticReference(phon_vec))
+        }
+        let mut phoneme = String::new();
+        let mut chars = value.chars();
+        while let Some(char) = chars.next() {
+            if keys.contains(&char) || char == '<' {
+                if !phoneme.is_empty() {
+                    phon_vec.push(CreateValue::Phoneme(xsampa::to_ipa(&phoneme)));
+                    phoneme.clear();
+                }
+                if char == '<' {
+                    let name: String = chars.by_ref().take_while(|c| *c != '>').collect();
+                    phon_vec.push(format!("<{}>", name).as_str().try_into()?)
+                } else {
+                    phon_vec.push(CreateValue::Reference(char))
+                }
+            } else {
+                phoneme.push(char)
+            }
+        }
+        if !phoneme.is_empty() {
+            phon_vec.push(CreateValue::Phoneme(xsampa::to_ipa(&phoneme)));
+        }
+        Ok(PhoneticReference(phon_vec))
+    }
+}
+
+impl std::fmt::Display for PhoneticReference{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for part in &self.0{
-            acc.push_str(&part.to_string())
+            write!(f, "{}", part)?;
         }
-        acc
+        Ok(())
     }
 }
 
@@ -102,14 +285,17 @@ impl ToString for PhoneticReference{
 #[derive(Clone, PartialEq, PartialOrd, Eq, Ord, Debug)]
 pub enum CreateValue {
     Phoneme(String),
-    Reference(char)
+    Reference(char),
+    /// A reference to another lexis type
+    LexisType(String)
 }
 
-impl ToString for CreateValue{
-    fn to_string(&self) -> String {
+impl std::fmt::Display for CreateValue{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Phoneme(p) => p.to_string(),
-            Self::Reference(r) => r.to_string()
+            Self::Phoneme(p) => write!(f, "{}", p),
+            Self::Reference(r) => write!(f, "{}", r),
+            Self::LexisType(t) => write!(f, "<{}>", t)
         }
     }
 }
@@ -117,6 +303,12 @@ impl ToString for CreateValue{
 impl TryFrom<&str> for CreateValue{
     type Error = errors::PhoneticParsingError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Some(lexis_type) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+            if lexis_type.is_empty() {
+                return Err(PhoneticParsingError{msg: "a lexis type reference must have a name", found: value.to_string()})
+            }
+            return Ok(CreateValue::LexisType(lexis_type.to_string()))
+        }
         let found_uppercase = value.chars().filter(|c| c.is_uppercase()).count();
         if found_uppercase == value.len() && value.len() == 1 {
             let raw: char = value.chars().next()
@@ -193,28 +385,96 @@ impl<'de> Visitor<'de> for CreateValueVisitor {
 
 impl LexPhonology {
 
+    /// The letters of the `V` group, which transforms treat as vowels, or the default vowels if there's no `V` group
+    pub fn vowels(&self) -> Vec<String> {
+        match self.groups.get(&'V') {
+            Some(refs) => refs.iter().map(|r| r.to_string()).collect(),
+            None => crate::transforms::default_vowels()
+        }
+    }
+
     /// Creates a new random word based on the applied phonetic rules
     pub fn create_word(&self, lexis_type: &str) -> Option<Lemma> {
-        if let Some(found_type_list) = self.lexis_types.get(lexis_type) {
-            if let Some(selected_phon) = found_type_list.choose(&mut rand::thread_rng()) {
-                return self.resolve_phonetic_reference(selected_phon)
+        self.create_word_with_rng(lexis_type, &mut rand::thread_rng())
+    }
+
+    /// The same as create_word, but uses the supplied random number generator.
+    /// This can be used with a seeded generator to create reproducible words.
+    /// If no word can be created from the lexis type, any fallback types are tried in order.
+    pub fn create_word_with_rng<R: Rng>(&self, lexis_type: &str, rng: &mut R) -> Option<Lemma> {
+        self.fallback_chain(lexis_type).iter().find_map(|t| self.create_single_type(t, rng, &mut Vec::new()))
+    }
+
+    /// The same as create_word_with_rng, but will repeatedly generate words until one meets the supplied constraints.
+    /// If no matching word could be generated after MAX_CREATE_ATTEMPTS tries, any fallback types are tried in order.
+    /// Returns None if no lexis type in the fallback chain could produce a matching word.
+    pub fn create_constrained_word_with_rng<R: Rng>(&self, lexis_type: &str, constraints: &CreateConstraints, rng: &mut R) -> Option<Lemma> {
+        self.create_word_matching_with_rng(lexis_type, |w| constraints.matches(w), rng)
+    }
+
+    /// The same as create_word_with_rng, but will repeatedly generate words until one is accepted by the supplied function.
+    /// If no word is accepted after MAX_CREATE_ATTEMPTS tries, any fallback types are tried in order.
+    pub fn create_word_matching_with_rng<R: Rng, F: Fn(&Lemma) -> bool>(&self, lexis_type: &str, accept: F, rng: &mut R) -> Option<Lemma> {
+        for current in self.fallback_chain(lexis_type) {
+            for _ in 0..MAX_CREATE_ATTEMPTS {
+                let created = match self.create_single_type(&current, rng, &mut Vec::new()) {
+                    Some(c) => c,
+                    None => break
+                };
+                if accept(&created) {
+                    return Some(created)
+                }
             }
         }
-
         None
     }
 
-    fn resolve_phonetic_reference(&self, pref: &PhoneticReference) -> Option<Lemma> {
+    /// Return the lexis type, followed by its fallbacks, in the order they should be tried
+    pub fn fallback_chain(&self, lexis_type: &str) -> Vec<String> {
+        let mut chain = vec![lexis_type.to_string()];
+        let mut idx = 0;
+        while idx < chain.len() {
+            if let Some(fallbacks) = self.fallbacks.get(&chain[idx]) {
+                let mut insert_at = idx + 1;
+                for fallback in fallbacks {
+                    // a fallback already in the chain would create a cycle
+                    if !chain.contains(fallback) {
+                        chain.insert(insert_at, fallback.clone());
+                        insert_at += 1;
+                    }
+                }
+            }
+            idx += 1;
+        }
+        chain
+    }
+
+    // visiting tracks the lexis types currently being resolved, so types that reference each other can't recurse forever
+    fn create_single_type<R: Rng>(&self, lexis_type: &str, rng: &mut R, visiting: &mut Vec<String>) -> Option<Lemma> {
+        if visiting.iter().any(|v| v == lexis_type) {
+            return None
+        }
+        let selected_phon = self.lexis_types.get(lexis_type)?.choose(rng)?;
+        visiting.push(lexis_type.to_string());
+        let created = self.resolve_phonetic_reference(selected_phon, rng, visiting);
+        visiting.pop();
+        created
+    }
+
+    fn resolve_phonetic_reference<R: Rng>(&self, pref: &PhoneticReference, rng: &mut R, visiting: &mut Vec<String>) -> Option<Lemma> {
         let mut phonetic_acc = Lemma::default();
         for phon in &pref.0 {
             match phon {
                 CreateValue::Phoneme(p) => {phonetic_acc.push_char(p)},
                 CreateValue::Reference(single_ref) => {
-                    if let Some(found_ref) =  self.random_phoneme(single_ref) {
+                    if let Some(found_ref) =  self.random_phoneme(single_ref, rng, visiting) {
                         phonetic_acc.push(found_ref)
                     } else {
                         return None
                     }
+                },
+                CreateValue::LexisType(lexis_type) => {
+                    phonetic_acc.push(self.create_single_type(lexis_type, rng, visiting)?)
                 }
             }
         }
@@ -227,11 +487,11 @@ impl LexPhonology {
         
     }
 
-    fn random_phoneme(&self, phoneme_key: &char) -> Option<Lemma> {
+    fn random_phoneme<R: Rng>(&self, phoneme_key: &char, rng: &mut R, visiting: &mut Vec<String>) -> Option<Lemma> {
         if let Some(type_val) = self.groups.get(phoneme_key) {
-            let picked_from = type_val.choose(&mut rand::thread_rng());
+            let picked_from = type_val.choose(rng);
             if let Some(picked) = picked_from {
-                return self.resolve_phonetic_reference(picked)
+                return self.resolve_phonetic_reference(picked, rng, visiting)
             }
         }
 
@@ -245,7 +505,135 @@ impl LexPhonology {
 mod tests {
     use std::collections::HashMap;
     use crate::{lexcreate::PhoneticReference, errors::PhoneticParsingError};
-    use super::{LexPhonology, CreateValue};
+    use rand::{SeedableRng, rngs::StdRng};
+    use super::{LexPhonology, CreateValue, CreateConstraints};
+
+    #[test]
+    fn test_constrained_create() {
+        let phonology = LexPhonology{
+            groups: HashMap::from([
+                ('C', vec!["r".try_into().unwrap(), "k".try_into().unwrap(), "t".try_into().unwrap()]),
+                ('V', vec!["i".try_into().unwrap(), "a".try_into().unwrap()]),
+            ]),
+            lexis_types: HashMap::from([
+                ("root".to_string(), vec!["CVCV".try_into().unwrap(), "CVCVCV".try_into().unwrap()])
+            ]),
+            fallbacks: HashMap::new(),
+        };
+        let constraints: CreateConstraints = serde_json::from_str(r#"{"must_contain": "r", "forbid": ["ii"], "min_len": 5}"#).unwrap();
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..20 {
+            let word = phonology.create_constrained_word_with_rng("root", &constraints, &mut rng).unwrap();
+            let raw = word.string_without_sep();
+            assert!(raw.contains('r'));
+            assert!(!raw.contains("ii"));
+            assert_eq!(word.len(), 6);
+        }
+
+        let impossible = CreateConstraints{must_contain: vec!["x".to_string()], ..Default::default()};
+        assert!(phonology.create_constrained_word_with_rng("root", &impossible, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_lexis_type_references_and_fallbacks() {
+        let phonology: LexPhonology = serde_json::from_str(r#"{
+            "groups": {"C": ["r", "k"], "V": ["i", "a"]},
+            "lexis_types": {
+                "root": ["CV"],
+                "compound-root": ["<root>x<root>"],
+                "loop": ["a<loop>"]
+            },
+            "fallbacks": {
+                "long-root": ["compound-root", "root"],
+                "compound-root": ["root"]
+            }
+        }"#).unwrap();
+        assert_eq!(phonology.lexis_types["compound-root"][0], PhoneticReference(vec![
+            CreateValue::LexisType("root".to_string()), CreateValue::Phoneme("x".to_string()), CreateValue::LexisType("root".to_string())
+        ]));
+        assert_eq!(phonology.fallback_chain("long-root"), vec!["long-root", "compound-root", "root"]);
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let compound = phonology.create_word_with_rng("compound-root", &mut rng).unwrap();
+        assert_eq!(compound.len(), 5);
+        // no long-root type exists, so fall back to compound-root
+        assert_eq!(phonology.create_word_with_rng("long-root", &mut rng).unwrap().len(), 5);
+        // compound-root can never satisfy this, so fall back to root
+        let short = CreateConstraints{max_len: Some(2), ..Default::default()};
+        assert_eq!(phonology.create_constrained_word_with_rng("compound-root", &short, &mut rng).unwrap().len(), 2);
+        // a type that references itself can't be resolved
+        assert!(phonology.create_word_with_rng("loop", &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_xsampa_notation() {
+        let phonology: LexPhonology = serde_json::from_str(r#"{
+            "notation": "x-sampa",
+            "groups": {"C": ["tS", "N", "S"], "V": ["a:", "E"]},
+            "lexis_types": {"root": ["CV", "CVN", "C E C"]}
+        }"#).unwrap();
+        assert_eq!(phonology.groups[&'C'], vec![
+            PhoneticReference(vec![CreateValue::Phoneme("tʃ".to_string())]),
+            PhoneticReference(vec![CreateValue::Phoneme("ŋ".to_string())]),
+            PhoneticReference(vec![CreateValue::Phoneme("ʃ".to_string())]),
+        ]);
+        assert_eq!(phonology.lexis_types["root"][1], PhoneticReference(vec![
+            CreateValue::Reference('C'), CreateValue::Reference('V'), CreateValue::Phoneme("ŋ".to_string())
+        ]));
+        assert_eq!(phonology.lexis_types["root"][2], PhoneticReference(vec![
+            CreateValue::Reference('C'), CreateValue::Phoneme("ɛ".to_string()), CreateValue::Reference('C')
+        ]));
+
+        // once converted, rules are written out as unicode, and can be read back in
+        let written = serde_json::to_string(&phonology).unwrap();
+        assert!(!written.contains("notation"));
+        let read: LexPhonology = serde_json::from_str(&written).unwrap();
+        assert_eq!(read, phonology);
+        // multi-character phonemes aren't split into a phoneme for each character
+        assert_eq!(read.groups[&'V'][0], PhoneticReference(vec![CreateValue::Phoneme("aː".to_string())]));
+        assert_eq!(read.groups[&'C'][0], PhoneticReference(vec![CreateValue::Phoneme("tʃ".to_string())]));
+    }
+
+    #[test]
+    fn test_rule_string_round_trip() {
+        let single = PhoneticReference(vec![CreateValue::Phoneme("aː".to_string())]);
+        assert_eq!(single.to_rule_string(), "aː ");
+        let mixed = PhoneticReference(vec![CreateValue::Reference('C'), CreateValue::Phoneme("tʃ".to_string()), CreateValue::LexisType("root".to_string())]);
+        assert_eq!(mixed.to_rule_string(), "C tʃ <root>");
+        let plain = PhoneticReference(vec![CreateValue::Reference('C'), CreateValue::Phoneme("a".to_string())]);
+        assert_eq!(plain.to_rule_string(), "Ca");
+        for reference in [single, mixed, plain] {
+            assert_eq!(PhoneticReference::try_from(reference.to_rule_string().as_str()).unwrap(), reference);
+        }
+    }
+
+    #[test]
+    fn test_unicode_references() {
+        // without the group keys, anything that isn't lower case is a reference, as before
+        assert_eq!(CreateValue::from('ː'), CreateValue::Reference('ː'));
+        assert_eq!(CreateValue::from('C'), CreateValue::Reference('C'));
+        assert_eq!(CreateValue::from('ʃ'), CreateValue::Phoneme("ʃ".to_string()));
+
+        let phonology: LexPhonology = serde_json::from_str(r#"{
+            "groups": {"C": ["k", "ʃ"], "V": ["a", "i"], "ː": ["ː"]},
+            "lexis_types": {"root": ["CVː", "CVX"]}
+        }"#).unwrap();
+        assert_eq!(phonology.lexis_types["root"][0], PhoneticReference(vec![
+            CreateValue::Reference('C'), CreateValue::Reference('V'), CreateValue::Reference('ː')
+        ]));
+        // an undeclared upper-case letter is still a reference
+        assert_eq!(phonology.lexis_types["root"][1], PhoneticReference(vec![
+            CreateValue::Reference('C'), CreateValue::Reference('V'), CreateValue::Reference('X')
+        ]));
+
+        let undeclared: LexPhonology = serde_json::from_str(r#"{
+            "groups": {"C": ["k"], "V": ["a"]},
+            "lexis_types": {"root": ["CVː"]}
+        }"#).unwrap();
+        assert_eq!(undeclared.lexis_types["root"][0], PhoneticReference(vec![
+            CreateValue::Reference('C'), CreateValue::Reference('V'), CreateValue::Phoneme("ː".to_string())
+        ]));
+    }
 
     #[test]
     fn test_bad_phonetic_input(){
@@ -329,12 +717,13 @@ mod tests {
                     ])
                 ])
             ]),
+            fallbacks: HashMap::new(),
         };
 
         let res = test_phon.create_word("words");
-        assert_eq!(true, res.is_some());
-        assert!(res.clone().unwrap().len() > 0);
-        println!("got: {}", res.unwrap().to_string());
+        assert!(res.is_some());
+        assert!(!res.clone().unwrap().is_empty());
+        println!("got: {}", res.unwrap());
     }
 
     
@@ -0,0 +1,184 @@
+use std::fmt::Display;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+use crate::errors::TransformError;
+use crate::kirum::LanguageTree;
+use log::{debug, warn};
+
+/// A read-only snapshot of a computed language tree, which can be shared between threads.
+/// The methods of the underlying LanguageTree that take &self are available through Deref.
+pub struct ComputedLexicon {
+    tree: LanguageTree
+}
+
+impl ComputedLexicon {
+    /// Compute the lexicon of the tree, and wrap the result in a snapshot.
+    pub fn new(mut tree: LanguageTree) -> Result<Self, TransformError> {
+        tree.compute_lexicon()?;
+        Ok(ComputedLexicon { tree })
+    }
+
+    /// Wrap a tree that has already been computed, such as one restored with restore_computed(), without computing it again.
+    pub fn from_computed(tree: LanguageTree) -> Self {
+        ComputedLexicon { tree }
+    }
+}
+
+impl Deref for ComputedLexicon {
+    type Target = LanguageTree;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree
+    }
+}
+
+/// A handle to the current snapshot of a lexicon, for long-running commands that answer queries while the project changes.
+/// Readers hold on to the snapshot returned by snapshot() for as long as they need it,
+/// while a new snapshot is computed in the background and swapped in. Cloning the handle shares the same snapshot.
+#[derive(Clone)]
+pub struct SharedLexicon {
+    /// the current snapshot, and the generation it was started in
+    current: Arc<RwLock<(u64, Arc<ComputedLexicon>)>>,
+    /// the last generation handed out to replace() or recompute()
+    generation: Arc<AtomicU64>
+}
+
+impl SharedLexicon {
+    pub fn new(lexicon: ComputedLexicon) -> Self {
+        SharedLexicon { current: Arc::new(RwLock::new((0, Arc::new(lexicon)))), generation: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Return the current snapshot. The snapshot doesn't change if a new one is swapped in while it's in use.
+    pub fn snapshot(&self) -> Arc<ComputedLexicon> {
+        // the lock only guards a pointer swap, so a poisoned lock still holds a complete snapshot
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        current.1.clone()
+    }
+
+    /// Replace the current snapshot. Readers that already hold the old snapshot keep using it.
+    /// A recompute that was started before the replace is discarded when it finishes.
+    pub fn replace(&self, lexicon: ComputedLexicon) {
+        let generation = self.next_generation();
+        self.swap(generation, lexicon);
+    }
+
+    /// Compute a new snapshot on a background thread, swapping it in once it's complete.
+    /// If the snapshot can't be computed, the error is logged and returned from the thread, and the current snapshot is kept.
+    /// If recomputes overlap, only the snapshot of the one started last is kept, whichever order they finish in.
+    pub fn recompute<F, E>(&self, compute: F) -> JoinHandle<Result<(), E>>
+    where
+    F: FnOnce() -> Result<ComputedLexicon, E> + Send + 'static,
+    E: Display + Send + 'static,
+    {
+        let shared = self.clone();
+        let generation = self.next_generation();
+        std::thread::spawn(move || {
+            match compute() {
+                Ok(lexicon) => {
+                    debug!("recomputed lexicon with {} words", lexicon.len());
+                    shared.swap(generation, lexicon);
+                    Ok(())
+                },
+                Err(e) => {
+                    warn!("could not recompute lexicon, keeping the current snapshot: {:#}", e);
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn next_generation(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// swap in the snapshot, unless a newer generation is already in place
+    fn swap(&self, generation: u64, lexicon: ComputedLexicon) {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        if generation < current.0 {
+            debug!("discarding lexicon from generation {}, generation {} is newer", generation, current.0);
+            return;
+        }
+        *current = (generation, Arc::new(lexicon));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use crate::kirum::{LanguageTree, Lexis};
+    use crate::lemma::Lemma;
+    use crate::transforms::{Transform, TransformFunc};
+    use super::{ComputedLexicon, SharedLexicon};
+
+    fn test_tree(postfix: &str) -> LanguageTree {
+        let mut tree = LanguageTree::new();
+        let root = Lexis{id: "root".to_string(), word: Some("kira".into()), ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), ..Default::default()};
+        tree.connect_etymology(derived, root,
+            vec![Transform{name: "suffix".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: postfix.to_string().into() }]}], None);
+        tree
+    }
+
+    #[test]
+    fn test_snapshot_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ComputedLexicon>();
+        assert_send_sync::<SharedLexicon>();
+    }
+
+    #[test]
+    fn test_recompute() {
+        let shared = SharedLexicon::new(ComputedLexicon::new(test_tree("m")).unwrap());
+        let before = shared.snapshot();
+        assert_eq!(before.get_by_id("derived").unwrap().word, Some(Lemma::from("kiram")));
+
+        // readers on other threads can query the snapshot while the recompute runs
+        let reader = {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.snapshot().get_by_id("root").unwrap().word)
+        };
+        shared.recompute(|| ComputedLexicon::new(test_tree("n"))).join().unwrap().unwrap();
+        assert_eq!(reader.join().unwrap(), Some(Lemma::from("kira")));
+
+        assert_eq!(shared.snapshot().get_by_id("derived").unwrap().word, Some(Lemma::from("kiran")));
+        // the old snapshot is unchanged
+        assert_eq!(before.get_by_id("derived").unwrap().word, Some(Lemma::from("kiram")));
+    }
+
+    #[test]
+    fn test_failed_recompute() {
+        let shared = SharedLexicon::new(ComputedLexicon::new(test_tree("m")).unwrap());
+        let res = shared.recompute(|| Err::<ComputedLexicon, String>(String::from("not found"))).join().unwrap();
+        assert_eq!(res, Err(String::from("not found")));
+        assert_eq!(shared.snapshot().get_by_id("derived").unwrap().word, Some(Lemma::from("kiram")));
+    }
+
+    #[test]
+    fn test_overlapping_recompute() {
+        let shared = SharedLexicon::new(ComputedLexicon::new(test_tree("m")).unwrap());
+        // the first recompute is still running when the second one finishes
+        let (release, wait) = mpsc::channel::<()>();
+        let slow = shared.recompute(move || {
+            wait.recv().unwrap();
+            ComputedLexicon::new(test_tree("n"))
+        });
+        shared.recompute(|| ComputedLexicon::new(test_tree("s"))).join().unwrap().unwrap();
+        release.send(()).unwrap();
+        slow.join().unwrap().unwrap();
+        assert_eq!(shared.snapshot().get_by_id("derived").unwrap().word, Some(Lemma::from("kiras")));
+
+        // a replace is newer than any recompute started before it
+        let (release, wait) = mpsc::channel::<()>();
+        let slow = shared.recompute(move || {
+            wait.recv().unwrap();
+            ComputedLexicon::new(test_tree("n"))
+        });
+        shared.replace(ComputedLexicon::new(test_tree("t")).unwrap());
+        release.send(()).unwrap();
+        slow.join().unwrap().unwrap();
+        assert_eq!(shared.snapshot().get_by_id("derived").unwrap().word, Some(Lemma::from("kirat")));
+    }
+}
@@ -6,3 +6,8 @@ pub mod kirum;
 pub mod matching;
 pub mod lemma;
 pub mod lexcreate;
+pub mod sandhi;
+pub mod xsampa;
+pub mod lexicon;
+pub mod query;
+pub mod sorted;
\ No newline at end of file
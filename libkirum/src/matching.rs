@@ -1,11 +1,15 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::errors::LangError;
 use crate::kirum::Lexis;
 use crate::lemma::Lemma;
 use crate::word::PartOfSpeech;
 
 
 /// A match value that can be used to evaluate if a given Lexis field matches a predicate.
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum Value{
     #[serde(rename="not")]
     Not(ValueMatch),
@@ -20,6 +24,14 @@ impl From<String> for Value{
 }
 
 impl Value{
+    /// returns the underlying ValueMatch, regardless of if the value is negated
+    pub fn inner(&self) -> &ValueMatch {
+        match self {
+            Self::Match(v) => v,
+            Self::Not(v) => v
+        }
+    }
+
     pub fn is_true<T>(&self, val: &T)-> bool
     where
     ValueMatch: PartialEq<T>
@@ -36,14 +48,14 @@ impl Value{
 }
 
 /// Defines an equality in a match statement
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(untagged)]
 pub enum EqualValue{
     String(String),
     Vector(Vec<String>)
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum ValueMatch{
     #[serde(rename="equals")]
     Equals(EqualValue),
@@ -86,7 +98,13 @@ impl PartialEq<Lemma> for ValueMatch{
 
 impl PartialEq<PartOfSpeech> for ValueMatch{
     fn eq(&self, other: &PartOfSpeech) -> bool {
-        *self == other.to_string()
+        // compare the parsed values, so abbreviations such as "adj" match
+        let same = |s: &String| PartOfSpeech::from_str(s).is_ok_and(|p| p == *other);
+        match self {
+            Self::Equals(EqualValue::String(s)) => same(s),
+            Self::Equals(EqualValue::Vector(_)) => false,
+            Self::OneOf(a) => a.iter().any(same)
+        }
     }
 }
 
@@ -108,7 +126,7 @@ impl PartialEq<Vec<std::string::String>> for ValueMatch{
 }
 
 /// A matching object that can be used to evaluate if the selected predicates match a supplied Lexis
-#[derive(Serialize, Default, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Default, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct LexisMatch{
     pub id: Option<Value>,
     pub word: Option<Value>,
@@ -117,7 +135,9 @@ pub struct LexisMatch{
     #[serde(alias="type")]
     pub lexis_type: Option<Value>,
     pub archaic: Option<bool>,
-    pub tags: Option<Value>
+    pub tags: Option<Value>,
+    #[serde(alias="gender")]
+    pub noun_class: Option<Value>
 }
 
 impl LexisMatch {
@@ -125,6 +145,32 @@ impl LexisMatch {
     pub fn matches(&self, lex: &Lexis) -> bool{
             self == lex
     }
+
+    /// Check the match statement for predicates that can never evaluate to true, 
+    /// such as a list equality on a single-value field, or an unknown part of speech.
+    pub fn validate(&self) -> Result<(), LangError> {
+        let single_fields = [("id", &self.id), ("word", &self.word), ("language", &self.language), 
+        ("pos", &self.pos), ("lexis_type", &self.lexis_type), ("noun_class", &self.noun_class)];
+        for (name, field) in single_fields {
+            if let Some(ValueMatch::Equals(EqualValue::Vector(v))) = field.as_ref().map(|f| f.inner()) {
+                return Err(LangError::ValidationError(format!("field '{}' can only be compared to a single value, found list {:?}", name, v)));
+            }
+        }
+        if let Some(ValueMatch::Equals(EqualValue::String(s))) = self.tags.as_ref().map(|f| f.inner()) {
+            return Err(LangError::ValidationError(format!("field 'tags' must be compared to a list of values, found single value '{}'", s)));
+        }
+        if let Some(pos) = &self.pos {
+            let values = match pos.inner() {
+                ValueMatch::Equals(EqualValue::String(s)) => vec![s.clone()],
+                ValueMatch::OneOf(lst) => lst.clone(),
+                ValueMatch::Equals(EqualValue::Vector(lst)) => lst.clone(),
+            };
+            for val in values {
+                PartOfSpeech::from_str(&val).map_err(|e| LangError::ValidationError(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
 }
 
 fn value_matches<T>(val: &Option<Value>, to_match: &T) -> bool
@@ -143,15 +189,16 @@ impl PartialEq<Lexis> for LexisMatch{
         value_matches(&self.tags, &other.tags) &
         if let Some(word) = &other.word{value_matches(&self.word, word)} else{true} &
         value_matches(&self.language, &other.language) &
-        if let Some(pos) = other.pos{value_matches(&self.pos, &pos)} else{true} &
+        if let Some(pos) = &other.pos{value_matches(&self.pos, pos)} else{true} &
         value_matches(&self.lexis_type, &other.lexis_type) &
-        if let Some(a) = self.archaic{a == other.archaic} else{true}
+        if let Some(a) = self.archaic{a == other.archaic} else{true} &
+        if let Some(class) = &other.noun_class{value_matches(&self.noun_class, class)} else{true}
         
     }
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum EtymonMatch{
     #[serde(rename="all")]
     All(LexisMatch),
@@ -173,7 +220,7 @@ impl PartialEq<Vec<Lexis>> for EtymonMatch {
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum WhenMatch{
     /// Before will match a lexis before it has been transformed by any other non-global transforms
     #[serde(alias="before")]
@@ -191,6 +238,7 @@ mod tests {
 
     use crate::errors::LangError;
     use crate::kirum::Lexis;
+    use crate::word::PartOfSpeech;
     use crate::matching::{Value, ValueMatch, LexisMatch, EqualValue};
 
 
@@ -203,10 +251,22 @@ mod tests {
         language: "Old Babylonian".to_string(),
         pos: None,
         definition: "".to_string(),
+        glosses: HashMap::new(),
+        examples: Vec::new(),
+        orthographies: HashMap::new(),
+        ipa: None,
+        semantic_field: None,
         archaic: false,
+        reconstructed: false,
         tags: vec!["tag1".to_string(), "tag2".to_string()],
         historical_metadata: HashMap::new(),
-        word_create: None
+        word_create: None,
+        create_constraints: None,
+        agglutination: None,
+        noun_class: None,
+        word_override: None,
+        notes: None,
+        status: None
         }; 
 
         let test_match = LexisMatch{
@@ -216,9 +276,10 @@ mod tests {
             pos: None,
             archaic: Some(false),
             lexis_type: None,
-            tags: Some(Value::Match(ValueMatch::OneOf(vec!["tag1".to_string(), "tag3".to_string()])))
+            tags: Some(Value::Match(ValueMatch::OneOf(vec!["tag1".to_string(), "tag3".to_string()]))),
+            noun_class: None
         };
-        assert_eq!(test_match == test_lexis, true);
+        assert!(test_match == test_lexis);
         Ok(())
     }
     #[test]
@@ -228,19 +289,80 @@ mod tests {
             tags: Some(Value::Match(ValueMatch::Equals(EqualValue::Vector(vec!["tag1".to_string(), "tag2".to_string()])))),
             ..Default::default()
         };
-        assert_eq!(tags_all == test_lexis, true);
+        assert!(tags_all == test_lexis);
 
         let tags_not_all = LexisMatch{
             tags: Some(Value::Not(ValueMatch::Equals(EqualValue::Vector(vec!["tag3".to_string(), "tag4".to_string()])))),
             ..Default::default()
         };
-        assert_eq!(tags_not_all == test_lexis, true);
+        assert!(tags_not_all == test_lexis);
 
         let tags_not_oneof = LexisMatch{
             tags: Some(Value::Not(ValueMatch::OneOf(vec!["tag3".to_string(), "tag4".to_string()]))),
             ..Default::default()
         };
-        assert_eq!(tags_not_oneof == test_lexis, true);
+        assert!(tags_not_oneof == test_lexis);
         Ok(())
     }
+
+    #[test]
+    fn test_validate() {
+        let good = LexisMatch{
+            language: Some(Value::Match(ValueMatch::Equals(EqualValue::String("Old Babylonian".to_string())))),
+            pos: Some(Value::Not(ValueMatch::OneOf(vec!["noun".to_string(), "verb".to_string()]))),
+            tags: Some(Value::Match(ValueMatch::Equals(EqualValue::Vector(vec!["tag1".to_string()])))),
+            ..Default::default()
+        };
+        assert!(good.validate().is_ok());
+
+        let list_on_single = LexisMatch{
+            language: Some(Value::Match(ValueMatch::Equals(EqualValue::Vector(vec!["Old Babylonian".to_string()])))),
+            ..Default::default()
+        };
+        assert!(list_on_single.validate().is_err());
+
+        let single_on_tags = LexisMatch{
+            tags: Some(Value::Not(ValueMatch::Equals(EqualValue::String("tag1".to_string())))),
+            ..Default::default()
+        };
+        assert!(single_on_tags.validate().is_err());
+
+        let bad_pos = LexisMatch{
+            pos: Some(Value::Match(ValueMatch::Equals(EqualValue::String(" ".to_string())))),
+            ..Default::default()
+        };
+        assert!(bad_pos.validate().is_err());
+    }
+
+    #[test]
+    fn test_lexis_noun_class() {
+        let test_lexis = Lexis{noun_class: Some("III".to_string()), ..Default::default()};
+        let class_match = LexisMatch{
+            noun_class: Some(Value::Match(ValueMatch::OneOf(vec!["II".to_string(), "III".to_string()]))),
+            ..Default::default()
+        };
+        assert!(class_match == test_lexis);
+
+        let class_not = LexisMatch{
+            noun_class: Some(Value::Not(ValueMatch::Equals(EqualValue::String("III".to_string())))),
+            ..Default::default()
+        };
+        assert!(class_not != test_lexis);
+    }
+
+    #[test]
+    fn test_lexis_pos() {
+        let adverb = Lexis{pos: Some(PartOfSpeech::Adverb), ..Default::default()};
+        let classifier = Lexis{pos: Some("classifier".parse().unwrap()), ..Default::default()};
+        let pos_match = LexisMatch{
+            pos: Some(Value::Match(ValueMatch::OneOf(vec!["ADV".to_string(), "classifier".to_string()]))),
+            ..Default::default()
+        };
+        assert!(pos_match.validate().is_ok());
+        assert!(pos_match == adverb);
+        assert!(pos_match == classifier);
+        // parts of speech defined by the project aren't case sensitive
+        assert!(pos_match == Lexis{pos: Some("Classifier".parse().unwrap()), ..Default::default()});
+        assert!(pos_match != Lexis{pos: Some(PartOfSpeech::Adjective), ..Default::default()});
+    }
 }
\ No newline at end of file
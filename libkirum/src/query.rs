@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use crate::kirum::{LanguageTree, Lexis};
+use crate::word::{Etymology, PartOfSpeech};
+
+/// A field of a lexis that can be compared in a query
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryField {
+    Id,
+    Word,
+    Language,
+    Pos,
+    LexisType,
+    /// The definition, along with any glosses in other metalanguages
+    Definition,
+    Tags,
+    SemanticField,
+    NounClass,
+    Archaic,
+    Reconstructed,
+    Status,
+    Ipa,
+    /// A key of the historical metadata
+    Metadata(String)
+}
+
+impl QueryField {
+    /// the values of the field for a lexis. Fields that aren't set have no values, and list fields can have many.
+    pub fn values(&self, lex: &Lexis) -> Vec<String> {
+        let single = |val: Option<String>| val.into_iter().filter(|v| !v.is_empty()).collect();
+        match self {
+            QueryField::Id => vec![lex.id.clone()],
+            QueryField::Word => single(lex.word.as_ref().map(|w| w.string_without_sep())),
+            QueryField::Language => single(Some(lex.language.clone())),
+            QueryField::Pos => single(lex.pos.as_ref().map(|p| p.to_string())),
+            QueryField::LexisType => single(Some(lex.lexis_type.clone())),
+            QueryField::Definition => {
+                let mut defs: Vec<String> = single(Some(lex.definition.clone()));
+                defs.extend(lex.glosses.values().cloned());
+                defs
+            },
+            QueryField::Tags => lex.tags.clone(),
+            QueryField::SemanticField => single(lex.semantic_field.clone()),
+            QueryField::NounClass => single(lex.noun_class.clone()),
+            QueryField::Archaic => vec![lex.archaic.to_string()],
+            QueryField::Reconstructed => vec![lex.reconstructed.to_string()],
+            QueryField::Status => single(lex.status.map(|s| s.to_string())),
+            QueryField::Ipa => single(lex.ipa.clone()),
+            QueryField::Metadata(key) => single(lex.historical_metadata.get(key).cloned())
+        }
+    }
+}
+
+/// A comparison between the value of a field and a user-supplied value
+#[derive(Clone, Debug)]
+pub enum Comparison {
+    Equals(String),
+    NotEquals(String),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Matches(Regex)
+}
+
+impl Comparison {
+    fn check(&self, value: &str) -> bool {
+        match self {
+            Comparison::Equals(v) | Comparison::NotEquals(v) => value == v,
+            Comparison::Contains(v) => value.contains(v.as_str()),
+            Comparison::StartsWith(v) => value.starts_with(v.as_str()),
+            Comparison::EndsWith(v) => value.ends_with(v.as_str()),
+            Comparison::Matches(re) => re.is_match(value)
+        }
+    }
+}
+
+/// A condition on a lexis, which can be combined with other conditions
+#[derive(Clone, Debug)]
+pub enum Condition {
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(QueryField, Comparison)
+}
+
+impl Condition {
+    /// check if the lexis meets the condition.
+    /// For fields with more than one value, such as tags, a comparison matches if any value matches,
+    /// and NotEquals matches if no value is equal.
+    pub fn matches(&self, lex: &Lexis) -> bool {
+        match self {
+            Condition::And(left, right) => left.matches(lex) && right.matches(lex),
+            Condition::Or(left, right) => left.matches(lex) || right.matches(lex),
+            Condition::Not(inner) => !inner.matches(lex),
+            Condition::Compare(field, cmp @ Comparison::NotEquals(_)) => !field.values(lex).iter().any(|v| cmp.check(v)),
+            Condition::Compare(field, cmp) => field.values(lex).iter().any(|v| cmp.check(v))
+        }
+    }
+}
+
+/// A lexis found by a query
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryMatch {
+    pub lexis: Lexis,
+    /// The etymons of the lexis, if the query was created with with_etymology()
+    pub etymology: Option<Etymology>
+}
+
+/// A set of conditions that are evaluated against the words of a computed LanguageTree.
+/// A lexis matches the query if it meets every condition, i.e.
+/// `Query::new().language("Latin").tag("archaic").word_matches(Regex::new("um$")?)`
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    conditions: Vec<Condition>,
+    etymology: bool
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Add any condition to the query, including conditions built from And, Or and Not
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Add a comparison on a single field
+    pub fn field(self, field: QueryField, cmp: Comparison) -> Self {
+        self.condition(Condition::Compare(field, cmp))
+    }
+
+    pub fn id(self, id: &str) -> Self {
+        self.field(QueryField::Id, Comparison::Equals(id.to_string()))
+    }
+
+    pub fn language(self, language: &str) -> Self {
+        self.field(QueryField::Language, Comparison::Equals(language.to_string()))
+    }
+
+    pub fn lexis_type(self, lexis_type: &str) -> Self {
+        self.field(QueryField::LexisType, Comparison::Equals(lexis_type.to_string()))
+    }
+
+    pub fn pos(self, pos: PartOfSpeech) -> Self {
+        self.field(QueryField::Pos, Comparison::Equals(pos.to_string()))
+    }
+
+    /// Match words with the given tag
+    pub fn tag(self, tag: &str) -> Self {
+        self.field(QueryField::Tags, Comparison::Equals(tag.to_string()))
+    }
+
+    /// Match words whose historical metadata has the given value for the key
+    pub fn metadata(self, key: &str, value: &str) -> Self {
+        self.field(QueryField::Metadata(key.to_string()), Comparison::Equals(value.to_string()))
+    }
+
+    pub fn word_matches(self, re: Regex) -> Self {
+        self.field(QueryField::Word, Comparison::Matches(re))
+    }
+
+    pub fn definition_matches(self, re: Regex) -> Self {
+        self.field(QueryField::Definition, Comparison::Matches(re))
+    }
+
+    /// Include the etymons of every matching lexis in the results
+    pub fn with_etymology(mut self) -> Self {
+        self.etymology = true;
+        self
+    }
+
+    /// check if the lexis meets every condition of the query
+    pub fn matches(&self, lex: &Lexis) -> bool {
+        self.conditions.iter().all(|c| c.matches(lex))
+    }
+
+    /// Return the words in the tree that match the query, in the same order as LanguageTree::to_vec().
+    /// Like to_vec(), words without a computed word are skipped, so the tree should be computed first.
+    pub fn run(&self, tree: &LanguageTree) -> Vec<QueryMatch> {
+        let mut etymologies: HashMap<String, Etymology> = if self.etymology {
+            tree.to_vec_etymons(|l| self.matches(l)).into_iter().map(|(lex, ety)| (lex.id, ety)).collect()
+        } else {
+            HashMap::new()
+        };
+        // only the matching words are cloned
+        let mut found: Vec<Lexis> = tree.iter().filter(|l| l.word.is_some() && self.matches(l)).cloned().collect();
+        tree.sort_lexicon(&mut found);
+        found.into_iter().map(|lexis| {
+            let etymology = etymologies.remove(&lexis.id);
+            QueryMatch { lexis, etymology }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use regex::Regex;
+    use crate::kirum::{LanguageTree, Lexis};
+    use crate::transforms::{Transform, TransformFunc};
+    use crate::word::PartOfSpeech;
+    use super::{Comparison, Condition, Query, QueryField};
+
+    fn test_tree() -> LanguageTree {
+        let mut tree = LanguageTree::new();
+        let root = Lexis{id: "root".to_string(), word: Some("kira".into()), language: "Old Lang".to_string(), pos: Some(PartOfSpeech::Verb),
+            tags: vec!["archaic".to_string()], historical_metadata: HashMap::from([("era".to_string(), "early".to_string())]), ..Default::default()};
+        let derived = Lexis{id: "derived".to_string(), language: "New Lang".to_string(), pos: Some(PartOfSpeech::Noun), ..Default::default()};
+        tree.connect_etymology(derived, root,
+            vec![Transform{name: "suffix".to_string(), lex_match: None, transforms: vec![TransformFunc::Postfix { value: "um".into() }]}], None);
+        tree.compute_lexicon().unwrap();
+        tree
+    }
+
+    fn ids(query: &Query, tree: &LanguageTree) -> Vec<String> {
+        query.run(tree).into_iter().map(|m| m.lexis.id).collect()
+    }
+
+    #[test]
+    fn test_query_builder() {
+        let tree = test_tree();
+        assert_eq!(ids(&Query::new().language("Old Lang").tag("archaic"), &tree), vec!["root"]);
+        assert_eq!(ids(&Query::new().word_matches(Regex::new("um$").unwrap()), &tree), vec!["derived"]);
+        assert_eq!(ids(&Query::new().metadata("era", "early"), &tree), vec!["root", "derived"]);
+        assert_eq!(ids(&Query::new().pos(PartOfSpeech::Noun).language("Old Lang"), &tree), Vec::<String>::new());
+        assert_eq!(ids(&Query::new(), &tree).len(), 2);
+    }
+
+    #[test]
+    fn test_query_conditions() {
+        let tree = test_tree();
+        let either = Condition::Or(Box::new(Condition::Compare(QueryField::Tags, Comparison::Contains("arch".to_string()))),
+            Box::new(Condition::Compare(QueryField::Word, Comparison::StartsWith("kiraum".to_string()))));
+        assert_eq!(ids(&Query::new().condition(either), &tree), vec!["root", "derived"]);
+        let untagged = Query::new().field(QueryField::Tags, Comparison::NotEquals("archaic".to_string()));
+        assert_eq!(ids(&untagged, &tree), vec!["derived"]);
+    }
+
+    #[test]
+    fn test_query_etymology() {
+        let tree = test_tree();
+        let found = Query::new().id("derived").with_etymology().run(&tree);
+        assert_eq!(found[0].etymology.as_ref().unwrap().etymons[0].etymon, "root");
+        assert!(Query::new().id("derived").run(&tree)[0].etymology.is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::lemma::Lemma;
+use log::trace;
+
+/// A SandhiRule is applied only at the boundary where two morphemes meet during agglutination.
+/// If the left-hand morpheme ends with `left` and the right-hand morpheme begins with `right`,
+/// both matched sequences are replaced with `replace`.
+/// For example, a degemination rule could be left=t, right=t, replace=t,
+/// and a vowel coalescence rule could be left=a, right=i, replace=e.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+pub struct SandhiRule {
+    /// The letters that must end the left-hand morpheme. An empty value will match any morpheme.
+    #[serde(default)]
+    pub left: Lemma,
+    /// The letters that must begin the right-hand morpheme. An empty value will match any morpheme.
+    #[serde(default)]
+    pub right: Lemma,
+    /// The letters that will replace both matched sequences.
+    #[serde(default)]
+    pub replace: Lemma,
+}
+
+impl SandhiRule {
+    /// Returns true if the rule applies to the seam between the two morphemes
+    pub fn matches(&self, left: &[String], right: &[String]) -> bool {
+        let left_match: Vec<String> = self.left.clone().chars();
+        let right_match: Vec<String> = self.right.clone().chars();
+        left.ends_with(&left_match) && right.starts_with(&right_match)
+    }
+}
+
+/// Join two morphemes, applying the first matching sandhi rule at the seam.
+pub fn join(left: Lemma, right: Lemma, rules: &[SandhiRule]) -> Lemma {
+    let mut left_chars = left.chars();
+    let right_chars = right.chars();
+    let found = rules.iter().find(|r| r.matches(&left_chars, &right_chars));
+    match found {
+        Some(rule) => {
+            trace!("applying sandhi rule {:?} at seam of {:?} and {:?}", rule, left_chars, right_chars);
+            left_chars.truncate(left_chars.len() - rule.left.len());
+            left_chars.extend(rule.replace.clone().chars());
+            left_chars.extend(right_chars.into_iter().skip(rule.right.len()));
+        },
+        None => {
+            left_chars.extend(right_chars);
+        }
+    }
+    left_chars.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lemma::Lemma;
+    use super::{SandhiRule, join};
+
+    #[test]
+    fn test_degemination() {
+        let rules = vec![SandhiRule{left: "t".into(), right: "t".into(), replace: "t".into()}];
+        let joined = join("kat".into(), "tum".into(), &rules);
+        assert_eq!(joined, Lemma::from("katum"));
+    }
+
+    #[test]
+    fn test_coalescence_first_rule_wins() {
+        let rules = vec![
+            SandhiRule{left: "a".into(), right: "i".into(), replace: "e".into()},
+            SandhiRule{left: "a".into(), right: "".into(), replace: "o".into()},
+        ];
+        let joined = join("sa".into(), "ik".into(), &rules);
+        assert_eq!(joined, Lemma::from("sek"));
+    }
+
+    #[test]
+    fn test_no_match() {
+        let rules = vec![SandhiRule{left: "t".into(), right: "t".into(), replace: "t".into()}];
+        let joined = join("kas".into(), "tum".into(), &rules);
+        assert_eq!(joined, Lemma::from("kastum"));
+    }
+}
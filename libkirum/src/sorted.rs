@@ -0,0 +1,38 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Serialize, Serializer};
+
+/// Serialize a HashMap with its keys in sorted order, so the output is identical between runs.
+/// Used with `#[serde(serialize_with = "sorted_map")]`
+pub fn sorted_map<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer, K: Ord + Serialize, V: Serialize
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Serialize an optional HashMap with its keys in sorted order
+pub fn sorted_option_map<S, K, V>(map: &Option<HashMap<K, V>>, serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer, K: Ord + Serialize, V: Serialize
+{
+    map.as_ref().map(|m| m.iter().collect::<BTreeMap<_, _>>()).serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use serde::Serialize;
+    use super::sorted_map;
+
+    #[derive(Serialize)]
+    struct Sorted {
+        #[serde(serialize_with = "sorted_map")]
+        map: HashMap<String, u32>
+    }
+
+    #[test]
+    fn test_sorted_map() {
+        let map: HashMap<String, u32> = (0..50).map(|i| (format!("key{:02}", i), i)).collect();
+        let written = serde_json::to_string(&Sorted { map }).unwrap();
+        let expected: Vec<String> = (0..50).map(|i| format!("\"key{:02}\":{}", i, i)).collect();
+        assert_eq!(written, format!("{{\"map\":{{{}}}}}", expected.join(",")));
+    }
+}
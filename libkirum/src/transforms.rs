@@ -2,7 +2,8 @@ use std::fmt::Display;
 
 use rhai::{Dynamic, Scope};
 use serde::{Deserialize, Serialize};
-use crate::{errors::TransformError, kirum::Lexis, lemma::Lemma, matching::LexisMatch};
+use schemars::JsonSchema;
+use crate::{errors::TransformError, kirum::Lexis, lemma::Lemma, matching::LexisMatch, word::FunctionStep};
 use log::{debug, trace};
 
 /// Specifies a transform at a global level. Global transforms don't have a name, but can be matched to both the target lexis, and the etymon.
@@ -17,8 +18,15 @@ pub struct GlobalTransform {
 }
 
 impl GlobalTransform {
-    ///  Transform the given lexis, or return the original unaltered lexis if the specified lexii don't meet the match statements
-    pub fn transform(&self,  lex: &mut Lexis, etymon: Option<&Vec<&Lexis>>) -> Result<(), TransformError> {
+    ///  Transform the given lexis, or return the original unaltered lexis if the specified lexii don't meet the match statements.
+    /// The vowels are the letters treated as vowels by functions that find the syllables of a word.
+    pub fn transform(&self,  lex: &mut Lexis, etymon: Option<&Vec<&Lexis>>, vowels: &[String]) -> Result<(), TransformError> {
+        self.transform_option(lex, etymon, vowels)?;
+        Ok(())
+    }
+
+    /// Transform the given lexis, returning false if the lexii don't meet the match statements
+    pub fn transform_option(&self,  lex: &mut Lexis, etymon: Option<&Vec<&Lexis>>, vowels: &[String]) -> Result<bool, TransformError> {
         // check to see if the etymon should allow us to transform
         let should_trans = if let Some(ety) = etymon  {
             if let Some(ety_match) = &self.etymon_match  {
@@ -33,18 +41,32 @@ impl GlobalTransform {
         trace!("checking global transforms for {}", lex.id);
         if self.lex_match.matches(lex) && should_trans{
             trace!("applying global transforms to {}", lex.id);
-            for trans in &self.transforms {
-                trans.transform(lex)?
+            for (step, trans) in self.transforms.iter().enumerate() {
+                trans.transform(lex, vowels).map_err(|e| e.in_step(&lex.id, "global transform", step))?
             }
+            return Ok(true)
         };
-        Ok(())
+        Ok(false)
     }
 }
 
+/// A list of transforms applied to every word derived from a word of one language into another,
+/// such as the regular sound changes between Latin and Old French.
+#[derive(Clone, Debug, Default)]
+pub struct LanguagePairTransform {
+    /// The language of the etymon
+    pub from: String,
+    /// The language of the derived word
+    pub to: String,
+    pub transforms: Vec<Transform>
+}
+
 /// Defines a series of transforms that are applied to a lexis.
-#[derive(Clone, Default)]
+/// When serialized, a transform uses the same fields as the transforms in an etymology file, along with the name of the transform.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Transform {
     pub name: String,
+    #[serde(rename="conditional", default, skip_serializing_if = "Option::is_none")]
     pub lex_match: Option<LexisMatch>,
     pub transforms: Vec<TransformFunc>,
    //pub agglutination_order: Option<i32>,
@@ -58,25 +80,43 @@ impl std::fmt::Debug for Transform {
 
 impl Transform{
     /// Transform the given lexis, or return the original unaltered lexis if the lex_match resolves to false.
-    pub fn transform(&self, etymon: &mut Lexis) -> Result<(), TransformError> {
-        self.transform_option(etymon)?;
+    /// The vowels are the letters treated as vowels by functions that find the syllables of a word.
+    pub fn transform(&self, etymon: &mut Lexis, vowels: &[String]) -> Result<(), TransformError> {
+        self.transform_option(etymon, vowels)?;
         Ok(())
     }
 
     // Transform the given lexis, or return None if the lex_match condition evaluates to false
-    pub fn transform_option(&self, etymon: &mut Lexis) -> Result<bool, TransformError> {
+    pub fn transform_option(&self, etymon: &mut Lexis, vowels: &[String]) -> Result<bool, TransformError> {
+        self.apply(etymon, vowels, None)
+    }
+
+    /// Like transform_option(), but also return the word after each function of the transform,
+    /// for debugging why a transform did or didn't change a word.
+    pub fn transform_traced(&self, etymon: &mut Lexis, vowels: &[String]) -> Result<(bool, Vec<FunctionStep>), TransformError> {
+        let mut functions = Vec::new();
+        let applied = self.apply(etymon, vowels, Some(&mut functions))?;
+        Ok((applied, functions))
+    }
+
+    fn apply(&self, etymon: &mut Lexis, vowels: &[String], mut functions: Option<&mut Vec<FunctionStep>>) -> Result<bool, TransformError> {
         let can_transform = if let Some(lex_match) = &self.lex_match{
             lex_match.matches(etymon)
         } else {
             true
         };
-        //let mut updated = etymon.clone();
         if can_transform{
-            for transform in &self.transforms {
-                transform.transform(etymon)?; 
+            for (step, transform) in self.transforms.iter().enumerate() {
+                transform.transform(etymon, vowels).map_err(|e| e.in_step(&etymon.id, &self.name, step))?; 
+                trace!("{} transforms[{}] {} for {}: {}", self.name, step, transform, etymon.id, 
+                    etymon.word.as_ref().map(|w| w.string_without_sep()).unwrap_or_default());
+                if let Some(functions) = functions.as_deref_mut() {
+                    functions.push(FunctionStep { function: transform.to_string(), output: etymon.word.clone() });
+                }
             };
             Ok(true)
         } else{
+            trace!("skipping {} for {}, conditional did not match", self.name, etymon.id);
             Ok(false)
         }
     }
@@ -84,7 +124,7 @@ impl Transform{
 
  
  /// Defines all the possible transforms that can be applied to a Lexis
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum TransformFunc {
     /// replaces one specified letter with another
     #[serde(rename="letter_replace")]
@@ -116,11 +156,24 @@ pub enum TransformFunc {
     /// replace a matching substring
     #[serde(rename="match_replace")]
     MatchReplace{old: Lemma, new: Lemma},
+    /// Insert a value inside a word, such as the Tagalog -um- in sulat > sumulat
+    #[serde(rename="infix")]
+    Infix{value: Lemma, position: InfixPosition},
+    /// Remove the vowels at the given position from inside a word, such as Latin calidus > caldus
+    #[serde(rename="syncope")]
+    Syncope{position: SyncopePosition},
 
     /// Transform a word using an rhai file.
     /// The rhai script should return a string of the updated word
     #[serde(rename="rhai_script")]
-    RhaiScript{file: String}
+    RhaiScript{file: String},
+    /// Set the noun class or grammatical gender of the word
+    #[serde(rename="noun_class")]
+    NounClass{value: String},
+    /// Competing outcomes of a single change, each a list of transforms. The first outcome is applied to the derived word,
+    /// and each other outcome creates a variant of the derived word from the same etymon. An empty outcome leaves the word unchanged, creating a doublet.
+    #[serde(rename="alternatives")]
+    Alternatives{outcomes: Vec<Vec<TransformFunc>>}
 }
 
 impl Display for TransformFunc {
@@ -130,10 +183,10 @@ impl Display for TransformFunc {
                 write!(f, "LetterReplace ({:?})", letter)
             },
             TransformFunc::Postfix { value } => {
-                write!(f, "Postfix ({})", value.to_string())
+                write!(f, "Postfix ({})", value)
             },
             TransformFunc::Prefix { value } => {
-                write!(f, "Prefix ({})", value.to_string())
+                write!(f, "Prefix ({})", value)
             },
             TransformFunc::Loanword => {
                 write!(f, "Loanword")
@@ -148,13 +201,25 @@ impl Display for TransformFunc {
                 write!(f, "DeDouble ({})", letter)
             },
             TransformFunc::MatchReplace { old, new } => {
-                write!(f, "MatchReplace ({} > {})", old.to_string(), new.to_string())
+                write!(f, "MatchReplace ({} > {})", old, new)
+            },
+            TransformFunc::Infix { value, position } => {
+                write!(f, "Infix ({} at {})", value, position)
+            },
+            TransformFunc::Syncope { position } => {
+                write!(f, "Syncope ({})", position)
             },
             TransformFunc::RhaiScript { file } => {
                 write!(f, "RhaiScript ({})", file)
             },
             TransformFunc::LetterArray { letters } => {
                 write!(f, "LetterArray ({:?})", letters)
+            },
+            TransformFunc::NounClass { value } => {
+                write!(f, "NounClass ({})", value)
+            },
+            TransformFunc::Alternatives { outcomes } => {
+                write!(f, "Alternatives ({} outcomes)", outcomes.len())
             }
         }
     }
@@ -162,10 +227,19 @@ impl Display for TransformFunc {
 
 
 impl TransformFunc{
-    pub fn transform(&self, current_word: &mut Lexis) -> Result<(), TransformError> {
+    /// Apply the function to a lexis. The vowels are the letters treated as vowels when finding the syllables of the word,
+    /// usually the `V` group of the phonetic rules, or [default_vowels].
+    pub fn transform(&self, current_word: &mut Lexis, vowels: &[String]) -> Result<(), TransformError> {
         if current_word.word.is_none(){
             return Ok(())
         }
+        if let TransformFunc::Alternatives { outcomes } = self {
+            debug!("got Alternatives for {}", current_word.id);
+            for func in outcomes.first().map(|o| o.as_slice()).unwrap_or_default() {
+                func.transform(current_word, vowels)?;
+            }
+            return Ok(())
+        }
         if let Some(current) = current_word.word.as_mut() {
             match self {
                 TransformFunc::LetterReplace{ letter, replace } => {
@@ -203,6 +277,16 @@ impl TransformFunc{
                 TransformFunc::MatchReplace { old, new } => {
                     current.match_replace(old, new)
                 },
+                TransformFunc::Infix { value, position } => {
+                    debug!("got Infix ({:?}) for {}", position, current_word.id);
+                    if let Some(idx) = position.index(current, vowels) {
+                        current.insert(idx, value)
+                    }
+                },
+                TransformFunc::Syncope { position } => {
+                    debug!("got Syncope ({:?}) for {}", position, current_word.id);
+                    position.apply(current, vowels)
+                },
                 TransformFunc::RhaiScript { file } => {
                     let engine = rhai::Engine::new();
                     let mut scope = Scope::new();
@@ -214,13 +298,22 @@ impl TransformFunc{
                     scope.push("language", current_word.language.clone());
                     scope.push("tags", tags_array);
                     scope.push("metadata", metadata_object);
-                    scope.push("pos", current_word.pos.unwrap_or_default().to_string());
+                    scope.push("pos", current_word.pos.clone().unwrap_or_default().to_string());
+                    scope.push("noun_class", current_word.noun_class.clone().unwrap_or_default());
                     scope.push("lemma_array", lemma_array);
                     scope.push("lemma_string", current.clone().string_without_sep());
 
-                    let updated: Lemma = engine.eval_file_with_scope::<Dynamic>(&mut scope, file.into())?.try_into()?;
-                    *current = updated.into();
-                }
+                    let updated: Lemma = engine.eval_file_with_scope::<Dynamic>(&mut scope, file.into())
+                    .map_err(|source| TransformError::EvalError { file: file.clone(), source })?
+                    .try_into().map_err(|source| TransformError::ScriptReturnValueError { file: file.clone(), source })?;
+                    *current = updated;
+                },
+                TransformFunc::NounClass { value } => {
+                    debug!("got NounClass for {}", current_word.id);
+                    current_word.noun_class = Some(value.clone());
+                },
+                // handled above, as the outcomes need access to the entire lexis
+                TransformFunc::Alternatives { .. } => {}
             };
         };
         Ok(())
@@ -229,14 +322,14 @@ impl TransformFunc{
 }
 
 /// Specifies the old and new letters to replace.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct LetterValues{
     pub old: String,
     pub new: String,
 }
 
 /// Determines where a letter should be replaced.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum LetterPlaceType {
     #[serde(rename="first")]
     First,
@@ -246,17 +339,152 @@ pub enum LetterPlaceType {
     Last,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(untagged)]
 pub enum LetterArrayValues{
     Char(String),
     Place(i32)
 }
 
+/// Letters treated as vowels when finding the vowels and consonants of a word, if the project doesn't define a `V` group.
+/// Any other letter is a consonant.
+pub const DEFAULT_VOWELS: &[&str] = &["a", "e", "i", "o", "u", "y", "á", "é", "í", "ó", "ú", "à", "è", "ì", "ò", "ù",
+"â", "ê", "î", "ô", "û", "ä", "ë", "ï", "ö", "ü", "ā", "ē", "ī", "ō", "ū"];
+
+/// the default vowels, for passing to transforms
+pub fn default_vowels() -> Vec<String> {
+    DEFAULT_VOWELS.iter().map(|v| v.to_string()).collect()
+}
+
+fn is_vowel(letter: &str, vowels: &[String]) -> bool {
+    let lower = letter.to_lowercase();
+    vowels.iter().any(|v| v.to_lowercase() == lower)
+}
+
+/// the start and end of each group of adjacent vowels in a word
+fn vowel_groups(letters: &[String], vowels: &[String]) -> Vec<(usize, usize)> {
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    let mut idx = 0;
+    while idx < letters.len() {
+        if !is_vowel(&letters[idx], vowels) {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < letters.len() && is_vowel(&letters[idx], vowels) {
+            idx += 1;
+        }
+        groups.push((start, idx));
+    }
+    groups
+}
+
+/// Determines where an infix is inserted. Either one of the named positions, or the number of letters before the infix.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum InfixPosition {
+    After(InfixAfter),
+    Index(usize)
+}
+
+/// The named positions of an infix
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub enum InfixAfter {
+    /// After the first consonant of the word, or at the start of a word that begins with a vowel, as in Tagalog aral > umaral
+    #[serde(rename="first_consonant")]
+    FirstConsonant,
+    /// After the first vowel of the word
+    #[serde(rename="first_vowel")]
+    FirstVowel,
+    /// After the first group of adjacent vowels, which is the nucleus of the first syllable
+    #[serde(rename="first_syllable")]
+    FirstSyllable,
+}
+
+impl Display for InfixPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InfixPosition::Index(idx) => write!(f, "{}", idx),
+            InfixPosition::After(InfixAfter::FirstConsonant) => write!(f, "first_consonant"),
+            InfixPosition::After(InfixAfter::FirstVowel) => write!(f, "first_vowel"),
+            InfixPosition::After(InfixAfter::FirstSyllable) => write!(f, "first_syllable")
+        }
+    }
+}
+
+impl InfixPosition {
+    /// The index in the word the infix is inserted at, or None if the word has no letter at the position.
+    /// An index past the end of the word adds the infix to the end.
+    pub fn index(&self, word: &Lemma, vowels: &[String]) -> Option<usize> {
+        let letters: Vec<&str> = word.iter().collect();
+        let is_vowel = |letter: &&str| is_vowel(letter, vowels);
+        match self {
+            InfixPosition::Index(idx) => Some(*idx),
+            InfixPosition::After(InfixAfter::FirstConsonant) => match letters.first() {
+                Some(first) if is_vowel(first) => Some(0),
+                _ => letters.iter().position(|l| !is_vowel(l)).map(|i| i + 1)
+            },
+            InfixPosition::After(InfixAfter::FirstVowel) => letters.iter().position(is_vowel).map(|i| i + 1),
+            InfixPosition::After(InfixAfter::FirstSyllable) => {
+                let start = letters.iter().position(is_vowel)?;
+                Some(start + letters[start..].iter().take_while(|l| is_vowel(l)).count())
+            }
+        }
+    }
+}
+
+
+/// Determines which vowels are removed by syncope. Each group of adjacent vowels is the nucleus of a syllable.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub enum SyncopePosition {
+    /// Remove every vowel that isn't in the first or last syllable, whether or not it's stressed
+    #[serde(rename="medial")]
+    Medial,
+    /// Remove the vowels of the second-to-last syllable, in words of at least three syllables,
+    /// so the first syllable of a word is never removed
+    #[serde(rename="penultimate")]
+    Penultimate,
+    /// Remove any single vowel with a consonant on both sides. If consonants isn't empty, both must be one of the listed letters.
+    #[serde(rename="between")]
+    Between{#[serde(default)] consonants: Vec<String>}
+}
+
+impl Display for SyncopePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncopePosition::Medial => write!(f, "medial"),
+            SyncopePosition::Penultimate => write!(f, "penultimate"),
+            SyncopePosition::Between { consonants } if consonants.is_empty() => write!(f, "between"),
+            SyncopePosition::Between { consonants } => write!(f, "between {}", consonants.join(", "))
+        }
+    }
+}
+
+impl SyncopePosition {
+    /// Remove the vowels at the position from the word
+    pub fn apply(&self, word: &mut Lemma, vowels: &[String]) {
+        let letters: Vec<String> = word.clone().into();
+        let groups = vowel_groups(&letters, vowels);
+        let removed: Vec<(usize, usize)> = match self {
+            SyncopePosition::Medial => groups.get(1..groups.len().saturating_sub(1)).unwrap_or_default().to_vec(),
+            SyncopePosition::Penultimate if groups.len() >= 3 => vec![groups[groups.len() - 2]],
+            SyncopePosition::Penultimate => Vec::new(),
+            SyncopePosition::Between { consonants } => {
+                let flanking = |letter: &String| !is_vowel(letter, vowels) && (consonants.is_empty() || consonants.contains(letter));
+                (1..letters.len().saturating_sub(1))
+                .filter(|idx| is_vowel(&letters[*idx], vowels) && flanking(&letters[idx - 1]) && flanking(&letters[idx + 1]))
+                .map(|idx| (idx, idx + 1)).collect()
+            }
+        };
+        *word = letters.into_iter().enumerate()
+            .filter(|(idx, _)| !removed.iter().any(|(start, end)| idx >= start && idx < end))
+            .map(|(_, letter)| letter).collect();
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::transforms::{TransformFunc, LetterValues, LetterPlaceType, LetterArrayValues};
+    use crate::transforms::{default_vowels, TransformFunc, LetterValues, LetterPlaceType, LetterArrayValues, InfixPosition, InfixAfter, SyncopePosition};
     use crate::kirum::Lexis;
     use crate::word::PartOfSpeech;
     use super::Transform;
@@ -271,6 +499,28 @@ mod tests {
             ..Default::default()}
     }
 
+    #[test]
+    fn test_transform_traced() {
+        let mut word = rhai_setup();
+        let mut transform = Transform{
+            name: "test".to_string(),
+            lex_match: None,
+            transforms: vec![TransformFunc::Prefix { value: "pre".into() }, TransformFunc::Postfix { value: "s".into() }]
+        };
+        let (applied, functions) = transform.transform_traced(&mut word, &default_vowels()).unwrap();
+        assert!(applied);
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].output, Some("preexample".into()));
+        assert_eq!(functions[1].output, Some("preexamples".into()));
+        assert!(functions[1].function.starts_with("Postfix"));
+
+        transform.lex_match = Some(serde_json::from_str(r#"{"pos": {"match": {"equals": "verb"}}}"#).unwrap());
+        let (applied, functions) = transform.transform_traced(&mut word, &default_vowels()).unwrap();
+        assert!(!applied);
+        assert!(functions.is_empty());
+        assert_eq!(word.word, Some("preexamples".into()));
+    }
+
     #[test]
     fn test_rhai_script_metadata_tags() {
         let mut word = rhai_setup();
@@ -281,7 +531,7 @@ mod tests {
                 TransformFunc::RhaiScript { file: "testfiles/basic.rhai".to_string() }
             ]
         };
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
         assert_eq!(word.word.unwrap().string_without_sep(), "example-test&map:true".to_string())
     }
 
@@ -295,7 +545,7 @@ mod tests {
                 TransformFunc::RhaiScript { file: "testfiles/return_array.rhai".to_string() }
             ]
         };
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
         assert_eq!(word.word.unwrap().string_without_sep(), "+e+x+a+m+p+l+e".to_string())
     }
 
@@ -309,7 +559,7 @@ mod tests {
                 TransformFunc::RhaiScript { file: "testfiles/pos.rhai".to_string() }
             ]
         };
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
         assert_eq!(word.word.unwrap().string_without_sep(), "example-noun".to_string())
     }
 
@@ -323,7 +573,7 @@ mod tests {
                 TransformFunc::RhaiScript { file: "testfiles/language.rhai".to_string() }
             ]
         };
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
         assert_eq!(word.word.unwrap().string_without_sep(), "example-testlang".to_string())
     }
 
@@ -345,7 +595,7 @@ mod tests {
             ]
         };
 
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
         assert_eq!(word.word.unwrap().string_without_sep(), "hanʷ".to_string())
     }
 
@@ -361,7 +611,7 @@ mod tests {
             ]
         };
 
-        transform.transform(&mut word).unwrap();
+        transform.transform(&mut word, &default_vowels()).unwrap();
 
         assert_eq!(word.word.unwrap().string_without_sep(), "oirun".to_string())
     }
@@ -372,7 +622,7 @@ mod tests {
         let test_transform = TransformFunc::LetterReplace { letter: letter_logic, replace:  LetterPlaceType::All};
         let mut old_word = Lexis{word: Some("kurum".into()), ..Default::default() };
         
-        test_transform.transform(&mut old_word).unwrap();
+        test_transform.transform(&mut old_word, &default_vowels()).unwrap();
         //let compare: Word = "karam".into();
         assert_eq!("karam".to_string(), old_word.word.unwrap().string_without_sep());
     }
@@ -382,7 +632,7 @@ mod tests {
         let test_transform = TransformFunc::LetterArray { letters: vec![LetterArrayValues::Place(0), LetterArrayValues::Place(1),  LetterArrayValues::Char("u".to_string())] };
         let mut old_word =  Lexis{word: Some("krm".into()), ..Default::default() };
 
-        test_transform.transform(&mut old_word).unwrap();
+        test_transform.transform(&mut old_word, &default_vowels()).unwrap();
         assert_eq!("kru".to_string(), old_word.word.unwrap().string_without_sep());
 
     }
@@ -392,7 +642,7 @@ mod tests {
         let test_transform = TransformFunc::Postfix { value: "uh".into() };
         let mut old_word = Lexis{word: Some("kurum".into()), ..Default::default()};
 
-        test_transform.transform(&mut old_word).unwrap();
+        test_transform.transform(&mut old_word, &default_vowels()).unwrap();
         assert_eq!("kurumuh".to_string(), old_word.word.unwrap().string_without_sep())
     }
 
@@ -401,10 +651,73 @@ mod tests {
         let test_transform = TransformFunc::Prefix { value: "tur".into() };
         let mut old_word = Lexis{word: Some("kurum".into()), ..Default::default()};
 
-        test_transform.transform(&mut old_word).unwrap();
+        test_transform.transform(&mut old_word, &default_vowels()).unwrap();
         assert_eq!("turkurum".to_string(), old_word.word.unwrap().string_without_sep());
     }
 
+    #[test]
+    fn test_infix(){
+        let infix = |word: &'static str, position: InfixPosition| {
+            let mut lex = Lexis{word: Some(word.into()), ..Default::default()};
+            TransformFunc::Infix { value: "um".into(), position }.transform(&mut lex, &default_vowels()).unwrap();
+            lex.word.unwrap().string_without_sep()
+        };
+        assert_eq!(infix("sulat", InfixPosition::After(InfixAfter::FirstConsonant)), "sumulat");
+        assert_eq!(infix("aral", InfixPosition::After(InfixAfter::FirstConsonant)), "umaral");
+        assert_eq!(infix("kaira", InfixPosition::After(InfixAfter::FirstVowel)), "kaumira");
+        assert_eq!(infix("kaira", InfixPosition::After(InfixAfter::FirstSyllable)), "kaiumra");
+        assert_eq!(infix("kira", InfixPosition::Index(0)), "umkira");
+        assert_eq!(infix("kira", InfixPosition::Index(2)), "kiumra");
+        assert_eq!(infix("kira", InfixPosition::Index(10)), "kiraum");
+        // no vowel to insert after, so the word is unchanged
+        assert_eq!(infix("krt", InfixPosition::After(InfixAfter::FirstVowel)), "krt");
+
+        let parsed: Vec<TransformFunc> = serde_json::from_str(r#"[{"infix": {"value": "in", "position": "first_consonant"}}, {"infix": {"value": "in", "position": 3}}]"#).unwrap();
+        assert_eq!(parsed[0], TransformFunc::Infix { value: "in".into(), position: InfixPosition::After(InfixAfter::FirstConsonant) });
+        assert_eq!(parsed[1], TransformFunc::Infix { value: "in".into(), position: InfixPosition::Index(3) });
+        assert!(parsed[0].to_string().ends_with(" at first_consonant)"));
+    }
+
+    #[test]
+    fn test_project_vowels(){
+        // y is a consonant in this language, so it isn't in the vowels
+        let vowels: Vec<String> = ["a", "e", "i", "o", "u"].iter().map(|v| v.to_string()).collect();
+        let mut lex = Lexis{word: Some("yuka".into()), ..Default::default()};
+        let infix = TransformFunc::Infix { value: "um".into(), position: InfixPosition::After(InfixAfter::FirstConsonant) };
+        infix.transform(&mut lex, &vowels).unwrap();
+        assert_eq!(lex.word.unwrap().string_without_sep(), "yumuka");
+
+        let mut lex = Lexis{word: Some("kayata".into()), ..Default::default()};
+        let syncope = TransformFunc::Syncope { position: SyncopePosition::Medial };
+        syncope.transform(&mut lex, &vowels).unwrap();
+        assert_eq!(lex.word.unwrap().string_without_sep(), "kayta");
+        assert_eq!(syncope.to_string(), "Syncope (medial)");
+    }
+
+    #[test]
+    fn test_syncope(){
+        let syncope = |word: &'static str, position: SyncopePosition| {
+            let mut lex = Lexis{word: Some(word.into()), ..Default::default()};
+            TransformFunc::Syncope { position }.transform(&mut lex, &default_vowels()).unwrap();
+            lex.word.unwrap().string_without_sep()
+        };
+        assert_eq!(syncope("calidus", SyncopePosition::Penultimate), "caldus");
+        assert_eq!(syncope("kirumaita", SyncopePosition::Medial), "kirmta");
+        assert_eq!(syncope("kirumaita", SyncopePosition::Penultimate), "kirumta");
+        // the penultimate syllable of a two-syllable word is the first syllable
+        assert_eq!(syncope("kaira", SyncopePosition::Penultimate), "kaira");
+        assert_eq!(syncope("aral", SyncopePosition::Penultimate), "aral");
+        // words without enough syllables are unchanged
+        assert_eq!(syncope("kira", SyncopePosition::Medial), "kira");
+        assert_eq!(syncope("kir", SyncopePosition::Penultimate), "kir");
+        assert_eq!(syncope("tabarata", SyncopePosition::Between { consonants: Vec::new() }), "tbrta");
+        assert_eq!(syncope("tabarata", SyncopePosition::Between { consonants: vec!["r".to_string(), "t".to_string()] }), "tabarta");
+
+        let parsed: Vec<TransformFunc> = serde_json::from_str(r#"[{"syncope": {"position": "medial"}}, {"syncope": {"position": {"between": {"consonants": ["l", "r"]}}}}]"#).unwrap();
+        assert_eq!(parsed[0], TransformFunc::Syncope { position: SyncopePosition::Medial });
+        assert_eq!(parsed[1], TransformFunc::Syncope { position: SyncopePosition::Between { consonants: vec!["l".to_string(), "r".to_string()] } });
+    }
+
 
 
 }
\ No newline at end of file
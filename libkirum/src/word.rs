@@ -1,61 +1,348 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use schemars::{JsonSchema, SchemaGenerator, Schema, json_schema};
 
 use crate::errors;
+use crate::lemma::Lemma;
+use crate::sorted::sorted_option_map;
+use crate::transforms::Transform;
 
-/// The possible Part Of Speech values for a Lexis
-#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq)]
+/// The possible Part Of Speech values for a Lexis.
+/// Parts of speech defined by the project are compared without regard to case, so `Classifier` and `classifier` are the same.
+#[derive(Debug, Default, Clone)]
 pub enum PartOfSpeech {
-    #[serde(rename(deserialize= "none", serialize="none"))]
     #[default]
     None,
-    #[serde(rename(deserialize= "noun", serialize="noun"))]
     Noun,
-    #[serde(rename(deserialize="verb", serialize="verb"))]
     Verb,
-    #[serde(rename(deserialize= "adjective", serialize="adjective"))]
     Adjective,
+    Adverb,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Particle,
+    Numeral,
+    Interjection,
+    /// A part of speech defined by the project, such as a classifier or postposition
+    Other(String),
+}
+
+impl PartOfSpeech {
+    /// The names of the built-in parts of speech
+    pub const BUILTIN: [&'static str; 11] = ["none", "noun", "verb", "adjective", "adverb", "pronoun", "preposition",
+        "conjunction", "particle", "numeral", "interjection"];
+}
+
+impl PartialEq for PartOfSpeech {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Other(a), Self::Other(b)) => a.to_lowercase() == b.to_lowercase(),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b)
+        }
+    }
+}
+
+impl Eq for PartOfSpeech {}
+
+impl std::hash::Hash for PartOfSpeech {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let Self::Other(name) = self {
+            name.to_lowercase().hash(state);
+        }
+    }
 }
 
 impl FromStr for PartOfSpeech {
     type Err = errors::POSFromError;
 
+    /// Parse a part of speech. The built-in names and their abbreviations aren't case sensitive,
+    /// and any other value is kept as a user-defined part of speech.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_ascii_lowercase().as_ref() {
-            "noun" => Ok(Self::Noun),
-            "verb" => Ok(Self::Verb),
-            "adjective" => Ok(Self::Adjective),
-            "adj" => Ok(Self::Adjective),
-            _ => Err(errors::POSFromError { found: s.to_string() })
-        }
+        let pos = match s.trim().to_lowercase().as_ref() {
+            "none" => Self::None,
+            "noun" | "n" => Self::Noun,
+            "verb" | "v" => Self::Verb,
+            "adjective" | "adj" => Self::Adjective,
+            "adverb" | "adv" => Self::Adverb,
+            "pronoun" | "pron" => Self::Pronoun,
+            "preposition" | "prep" => Self::Preposition,
+            "conjunction" | "conj" => Self::Conjunction,
+            "particle" | "ptcl" => Self::Particle,
+            "numeral" | "num" => Self::Numeral,
+            "interjection" | "interj" | "intj" => Self::Interjection,
+            "" => return Err(errors::POSFromError { found: s.to_string() }),
+            _ => Self::Other(s.trim().to_string())
+        };
+        Ok(pos)
     }
 }
 
 
-impl std::string::ToString for PartOfSpeech{
-    fn to_string(&self) -> String {
+impl std::fmt::Display for PartOfSpeech{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self{
-            Self::Adjective => "adjective".to_string(),
-            Self::Noun => "noun".to_string(),
-            Self::Verb => "verb".to_string(),
-            Self::None => "none".to_string()
+            Self::Adjective => write!(f, "adjective"),
+            Self::Noun => write!(f, "noun"),
+            Self::Verb => write!(f, "verb"),
+            Self::Adverb => write!(f, "adverb"),
+            Self::Pronoun => write!(f, "pronoun"),
+            Self::Preposition => write!(f, "preposition"),
+            Self::Conjunction => write!(f, "conjunction"),
+            Self::Particle => write!(f, "particle"),
+            Self::Numeral => write!(f, "numeral"),
+            Self::Interjection => write!(f, "interjection"),
+            Self::Other(name) => write!(f, "{}", name),
+            Self::None => write!(f, "none")
+        }
+    }
+}
+
+impl Serialize for PartOfSpeech {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PartOfSpeech {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for PartOfSpeech {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "PartOfSpeech".into()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A part of speech, either one of the built-in values, or any other value defined by the project",
+            "anyOf": [
+                {"enum": PartOfSpeech::BUILTIN},
+                {"type": "string", "minLength": 1}
+            ]
+        })
+    }
+}
+
+/// The editorial status of a lexis, for projects with more than one contributor
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorialStatus {
+    /// A proposed word, which isn't rendered unless drafts are requested
+    Draft,
+    /// A word that has been checked by another contributor
+    Reviewed,
+    /// A settled part of the language
+    Canon
+}
+
+impl std::fmt::Display for EditorialStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Draft => write!(f, "draft"),
+            Self::Reviewed => write!(f, "reviewed"),
+            Self::Canon => write!(f, "canon")
+        }
+    }
+}
+
+/// The kind of change in meaning between an etymon and a word derived from it
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ShiftKind {
+    /// The derived word has a more specific meaning than the etymon
+    Narrowing,
+    /// The derived word has a more general meaning than the etymon
+    Broadening,
+    /// The meaning was extended through a figurative resemblance
+    Metaphor,
+    /// The meaning was extended to something closely associated with the original
+    Metonymy,
+    /// The meaning became more negative
+    Pejoration,
+    /// The meaning became more positive
+    Amelioration
+}
+
+impl std::fmt::Display for ShiftKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Narrowing => write!(f, "narrowing"),
+            Self::Broadening => write!(f, "broadening"),
+            Self::Metaphor => write!(f, "metaphor"),
+            Self::Metonymy => write!(f, "metonymy"),
+            Self::Pejoration => write!(f, "pejoration"),
+            Self::Amelioration => write!(f, "amelioration")
+        }
+    }
+}
+
+/// Describes how the meaning of a word changed from its etymon
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+pub struct SemanticShift {
+    #[serde(rename = "type")]
+    pub kind: Option<ShiftKind>,
+    /// A free-text description of the change
+    pub description: Option<String>,
+    /// The meaning of the derived word. Used as the definition of a word that doesn't set its own.
+    pub definition: Option<String>
+}
+
+impl std::fmt::Display for SemanticShift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.kind, &self.description) {
+            (Some(kind), Some(desc)) => write!(f, "{}: {}", kind, desc),
+            (Some(kind), None) => write!(f, "{}", kind),
+            (None, Some(desc)) => write!(f, "{}", desc),
+            (None, None) => write!(f, "shifted")
         }
     }
 }
 
+/// An example sentence or phrase that demonstrates the usage of a lexis.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+pub struct Example {
+    /// The example text, in the language of the lexis
+    pub text: String,
+    /// Optional interlinear gloss of the example text
+    pub gloss: Option<String>,
+    /// Optional free translation of the example text
+    pub translation: Option<String>
+}
+
 /// The etymology of a given lexis.
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct Etymology{
     pub etymons: Vec<Edge>,
 }
 
 /// The edge of the tree graph, containing a reference to the upstream word, and other metadata.
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
 pub struct Edge {
     pub etymon: String,
     pub transforms: Option<Vec<String>>,
-    pub agglutination_order: Option<i32>
+    pub agglutination_order: Option<i32>,
+    /// The definition of each transform named in `transforms`, as returned by LanguageTree::to_vec_etymons(),
+    /// so a derivation can be reproduced without the etymology files. This is not read from tree files.
+    pub resolved_transforms: Option<Vec<Transform>>,
+    /// Marks the word as a loanword borrowed from the etymon, rather than inherited from it
+    pub borrowed: Option<bool>,
+    /// How the meaning of the word changed from the etymon
+    pub semantic_shift: Option<SemanticShift>,
+    /// User-defined metadata about the relationship, such as the date of the change, a source, or its certainty
+    #[serde(default, serialize_with = "sorted_option_map")]
+    pub metadata: Option<HashMap<String, String>>
+}
+
+/// A trace of how a lexis was derived from its etymons, as returned by LanguageTree::explain()
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Derivation {
+    pub id: String,
+    pub language: String,
+    /// The final computed form of the word
+    pub word: Option<Lemma>,
+    /// The irregular form that replaced the derived word, if one is set
+    pub word_override: Option<Lemma>,
+    pub definition: Option<String>,
+    /// Set if the word is a reconstructed form
+    #[serde(default)]
+    pub reconstructed: bool,
+    /// The derivation of each etymon, and the transforms that connect it to this word
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub etymons: Vec<DerivationEdge>,
+    /// Every transform that changed the word during compute_lexicon(), and the layer it came from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied: Vec<AppliedRule>,
+}
+
+/// A single edge in a derivation trace
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DerivationEdge {
+    pub etymon: Derivation,
+    pub agglutination_order: Option<i32>,
+    /// The form of the etymon before any transforms are applied
+    pub input: Option<Lemma>,
+    /// The form passed to the derived word after all transforms are applied
+    pub output: Option<Lemma>,
+    /// How the meaning changed along the edge, if set
+    pub semantic_shift: Option<SemanticShift>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<DerivationStep>,
+}
+
+/// A single transform applied along an edge
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DerivationStep {
+    pub transform: String,
+    /// Where the transform came from
+    #[serde(default)]
+    pub layer: TransformLayer,
+    /// false if the transform's conditional didn't match, and the word was left unchanged
+    pub applied: bool,
+    pub input: Option<Lemma>,
+    pub output: Option<Lemma>,
+    /// The word after each function of the transform
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<FunctionStep>,
+}
+
+/// The result of a single function of a transform
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct FunctionStep {
+    pub function: String,
+    pub output: Option<Lemma>,
+}
+
+/// The source of a transform applied to a word. When transforms from multiple layers change a word,
+/// edge transforms take precedence over language pair transforms, which take precedence over global transforms.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransformLayer {
+    /// A transform named in the etymology of the word
+    #[default]
+    #[serde(rename="edge")]
+    Edge,
+    /// A transform applied to every word derived from one language into another
+    #[serde(rename="language_pair")]
+    LanguagePair,
+    /// A global transform
+    #[serde(rename="global")]
+    Global,
+}
+
+impl std::fmt::Display for TransformLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransformLayer::Edge => write!(f, "edge"),
+            TransformLayer::LanguagePair => write!(f, "language pair"),
+            TransformLayer::Global => write!(f, "global"),
+        }
+    }
+}
+
+/// A transform that changed a word during compute_lexicon()
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AppliedRule {
+    pub layer: TransformLayer,
+    /// The name of the transform. Global transforms are unnamed, and use their position in the list of global transforms.
+    pub name: String,
+    /// The ID of the etymon the transform was applied along, for edge and language pair transforms
+    pub etymon: Option<String>,
 }
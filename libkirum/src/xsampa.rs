@@ -0,0 +1,71 @@
+/// X-SAMPA symbols and their IPA equivalents. Each IPA value appears only once, so text can be converted in both directions.
+const SYMBOLS: &[(&str, &str)] = &[
+    // vowels
+    ("{", "æ"), ("6", "ɐ"), ("@", "ə"), ("@\\", "ɘ"), ("3", "ɜ"), ("3\\", "ɞ"), ("E", "ɛ"), ("I", "ɪ"),
+    ("O", "ɔ"), ("U", "ʊ"), ("V", "ʌ"), ("Q", "ɒ"), ("A", "ɑ"), ("Y", "ʏ"), ("2", "ø"), ("9", "œ"),
+    ("1", "ɨ"), ("}", "ʉ"), ("M", "ɯ"), ("7", "ɤ"), ("8", "ɵ"), ("&", "ɶ"),
+    // consonants
+    ("S", "ʃ"), ("Z", "ʒ"), ("T", "θ"), ("D", "ð"), ("N", "ŋ"), ("J", "ɲ"), ("G", "ɣ"), ("X", "χ"),
+    ("R", "ʁ"), ("h\\", "ɦ"), ("?", "ʔ"), ("4", "ɾ"), ("r\\", "ɹ"), ("r\\`", "ɻ"), ("L", "ʎ"), ("5", "ɫ"),
+    ("F", "ɱ"), ("B", "β"), ("P", "ʋ"), ("C", "ç"), ("j\\", "ʝ"), ("K", "ɬ"), ("K\\", "ɮ"), ("s`", "ʂ"),
+    ("z`", "ʐ"), ("t`", "ʈ"), ("d`", "ɖ"), ("n`", "ɳ"), ("l`", "ɭ"), ("r`", "ɽ"), ("s\\", "ɕ"), ("z\\", "ʑ"),
+    ("H", "ɥ"), ("W", "ʍ"), ("X\\", "ħ"), ("?\\", "ʕ"), ("N\\", "ɴ"), ("R\\", "ʀ"), ("G\\", "ɢ"), ("B\\", "ʙ"),
+    ("l\\", "ɺ"), ("M\\", "ɰ"), ("x\\", "ɧ"),
+    // diacritics and suprasegmentals
+    (":", "ː"), ("_h", "ʰ"), ("_w", "ʷ"), ("_j", "ʲ"), ("~", "\u{0303}"), ("=", "\u{0329}"), ("_0", "\u{0325}"),
+    ("\"", "ˈ"), ("%", "ˌ"),
+];
+
+/// Convert a string of X-SAMPA to IPA. Any characters that aren't X-SAMPA symbols, such as plain latin letters, are copied unchanged.
+pub fn to_ipa(xsampa: &str) -> String {
+    convert(xsampa, |(x, i)| (*x, *i))
+}
+
+/// Convert a string of IPA to X-SAMPA. This is the reverse of to_ipa()
+pub fn from_ipa(ipa: &str) -> String {
+    convert(ipa, |(x, i)| (*i, *x))
+}
+
+// convert the string using the longest matching symbol at each position
+fn convert<F>(value: &str, direction: F) -> String
+where F: Fn(&(&'static str, &'static str)) -> (&'static str, &'static str)
+{
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(first) = rest.chars().next() {
+        let found = SYMBOLS.iter().map(&direction)
+        .filter(|(from, _)| rest.starts_with(from))
+        .max_by_key(|(from, _)| from.len());
+        match found {
+            Some((from, to)) => {
+                out.push_str(to);
+                rest = &rest[from.len()..];
+            },
+            None => {
+                out.push(first);
+                rest = &rest[first.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_ipa, from_ipa, SYMBOLS};
+
+    #[test]
+    fn test_to_ipa() {
+        assert_eq!(to_ipa("tSa:N"), "tʃaːŋ");
+        assert_eq!(to_ipa("r\\`o4"), "ɻoɾ");
+        assert_eq!(to_ipa("kirum"), "kirum");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for (xsampa, _) in SYMBOLS {
+            assert_eq!(from_ipa(&to_ipa(xsampa)), *xsampa);
+        }
+        assert_eq!(from_ipa("ʃəˈɹɛm"), "S@\"r\\Em");
+    }
+}